@@ -6,22 +6,80 @@ use clap::Parser;
 use std::process::ExitCode;
 
 pub fn run() -> ExitCode {
+    // Shell completion callbacks (COMPLETE=<shell> vx ...) answer and exit here.
+    crate::completions::handle_env();
+
     let cli = Cli::parse();
 
     let log = Log {
         quiet: cli.quiet,
         verbose: cli.verbose,
+        json: cli.json,
     };
 
+    // `vx config` has to work when the config doesn't parse (to fix it).
+    if let crate::cli::Cmd::Config { cmd } = &cli.cmd {
+        return crate::core::configure::dispatch(&log, cmd);
+    }
+
     // On first run, optionally bootstrap a default config (interactive, once).
     let cfg = match Config::load_or_bootstrap_interactive() {
         Ok(c) => c, // Option<Config>
         Err(e) => {
-            log.error(format!("vx: {e}"));
+            log.error(e);
             return ExitCode::from(2);
         }
     };
 
-    crate::core::dispatch(&log, cli, cfg)
+    // sudo/doas/run0/none: config `privilege.command`, else auto-detected.
+    if let Err(e) = crate::privilege::init(cfg.as_ref().and_then(|c| c.privilege_command.as_deref())) {
+        log.error(e);
+        return ExitCode::from(2);
+    }
+
+    // --root, else config `xbps.root`; passed to xbps tools as `-r <dir>`.
+    if let Err(e) = crate::rootdir::init(
+        cli.root.as_deref(),
+        cfg.as_ref().and_then(|c| c.root.as_deref()),
+    ) {
+        log.error(e);
+        return ExitCode::from(2);
+    }
+
+    // xbps.mirror: the xbps.d repositories, fetched from the user's mirror.
+    if let Some(m) = cfg.as_ref().and_then(|c| c.mirror.as_deref()) {
+        crate::rootdir::init_repositories(crate::core::repo::mirrors::user_repositories(m));
+    }
+
+    // cache.*: sync TTLs, and whether caches are used at all (--fresh skips them).
+    crate::cache::init(match cfg.as_ref() {
+        Some(c) => crate::cache::Policy {
+            repodata_ttl_secs: c.repodata_ttl_secs,
+            git_ttl_secs: c.git_ttl_secs,
+            fresh: cli.fresh || !c.cache_enabled,
+        },
+        None => crate::cache::Policy {
+            fresh: cli.fresh,
+            ..crate::cache::Policy::default()
+        },
+    });
+
+    // One mutating vx at a time: a second one fails here instead of
+    // interleaving its prompts and transactions with the first.
+    let _lock = match crate::core::lock::acquire(&cli.cmd) {
+        Ok(l) => l,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    // base.notify: tell the desktop when a long update or build is done.
+    let watch = crate::core::notify::watch(&cli.cmd, cfg.as_ref());
+    let c = crate::core::dispatch(&log, cli, cfg);
+    if let Some(w) = watch {
+        w.finish(c);
+    }
+    c
 }
 
@@ -1,37 +1,70 @@
 // Author Dustin Pilgrim
 // License: MIT
 
+use crate::{cli::Cli, log::Log};
+use clap::CommandFactory;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
     collections::hash_map::DefaultHasher,
     env,
-    fs,
+    fs::{self, File, TryLockError},
     hash::{Hash, Hasher},
     io,
     path::{Path, PathBuf},
+    sync::OnceLock,
     time::{SystemTime, UNIX_EPOCH},
 };
 
 /// Global default TTL for sync caches (in seconds).
-/// Override with VX_SYNC_TTL_SECS.
+/// Override with `cache.repodata_ttl` / `cache.git_ttl` (VX_SYNC_TTL_SECS sets both).
 pub const DEFAULT_SYNC_TTL_SECS: u64 = 600;
 
-/// Force bypass all caches when set to "1"/"true"/"yes".
-pub fn force_fresh() -> bool {
-    match env::var("VX_FRESH") {
-        Ok(v) => {
-            let v = v.trim().to_ascii_lowercase();
-            v == "1" || v == "true" || v == "yes"
+/// How long syncs stay fresh and whether caches are used at all.
+#[derive(Debug, Clone, Copy)]
+pub struct Policy {
+    pub repodata_ttl_secs: u64,
+    pub git_ttl_secs: u64,
+    /// Bypass every TTL cache: `--fresh`, or `cache.enabled false`.
+    pub fresh: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            repodata_ttl_secs: DEFAULT_SYNC_TTL_SECS,
+            git_ttl_secs: DEFAULT_SYNC_TTL_SECS,
+            fresh: false,
         }
-        Err(_) => false,
     }
 }
 
-/// TTL override for sync caches.
-pub fn sync_ttl_secs() -> u64 {
-    match env::var("VX_SYNC_TTL_SECS") {
-        Ok(v) => v.trim().parse::<u64>().unwrap_or(DEFAULT_SYNC_TTL_SECS),
-        Err(_) => DEFAULT_SYNC_TTL_SECS,
-    }
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// Set the cache policy from the config and `--fresh`.
+/// Call once at startup, before any command runs.
+pub fn init(policy: Policy) {
+    let _ = POLICY.set(policy);
+}
+
+fn policy() -> Policy {
+    *POLICY.get_or_init(Policy::default)
+}
+
+/// Force bypass all caches: `--fresh` or `cache.enabled false` (VX_CACHE=0,
+/// or the older VX_FRESH=1).
+pub fn force_fresh() -> bool {
+    policy().fresh
+}
+
+/// TTL of the repodata sync (`cache.repodata_ttl`).
+pub fn repodata_ttl_secs() -> u64 {
+    policy().repodata_ttl_secs
+}
+
+/// TTL of the void-packages upstream fetch (`cache.git_ttl`).
+pub fn git_ttl_secs() -> u64 {
+    policy().git_ttl_secs
 }
 
 fn now_secs() -> u64 {
@@ -54,7 +87,7 @@ fn xdg_cache_home() -> PathBuf {
 }
 
 /// ~/.cache/vx/...
-fn vx_cache_dir() -> PathBuf {
+pub fn vx_cache_dir() -> PathBuf {
     xdg_cache_home().join("vx")
 }
 
@@ -75,29 +108,200 @@ pub fn is_fresh(key: &str, ttl_secs: u64) -> bool {
         return false;
     }
 
-    let p = key_path(key);
-    let data = match fs::read_to_string(&p) {
-        Ok(s) => s,
-        Err(_) => return false,
-    };
-
-    let last = match data.trim().parse::<u64>() {
-        Ok(v) => v,
-        Err(_) => return false,
+    let Some(e) = entry(key) else {
+        return false;
     };
 
     let now = now_secs();
-    now.saturating_sub(last) <= ttl_secs
+    now.saturating_sub(e.created) <= ttl_secs
+}
+
+/// What a cache key's file (`~/.cache/vx/<hash>.stamp`) holds: when and by
+/// which command it was marked, the TTL it was marked under, and optionally
+/// what was computed then (the last update plan, say).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Entry {
+    /// None for stamps an older vx wrote.
+    #[serde(default)]
+    pub key: Option<String>,
+    pub created: u64,
+    /// Only recorded for `vx cache status`; `is_fresh` is passed its own.
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// The vx invocation that marked it (`vx up -a`).
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// An entry as JSON, or `<secs>\t<ttl>\t<key>` / `<secs>` as older vx wrote it.
+fn parse_entry(text: &str) -> Option<Entry> {
+    if let Ok(e) = serde_json::from_str::<Entry>(text) {
+        return Some(e);
+    }
+    let mut parts = text.trim().splitn(3, '\t');
+    Some(Entry {
+        created: parts.next()?.parse().ok()?,
+        ttl_secs: parts.next().and_then(|t| t.parse().ok()),
+        key: parts.next().map(str::to_string).filter(|k| !k.is_empty()),
+        ..Entry::default()
+    })
+}
+
+/// `vx src up`: the running subcommand without its arguments (which may
+/// name paths or hosts), for `Entry::command`.
+fn invocation() -> String {
+    let root = Cli::command();
+    let mut cmd = root.clone();
+    let mut out = vec!["vx".to_string()];
+    let mut args = env::args().skip(1);
+    while let Some(a) = args.next() {
+        if let Some(opt) = a.strip_prefix('-') {
+            // `--root DIR`: skip the value too, unless given as `--root=DIR`.
+            let takes_value = !opt.contains('=')
+                && [&cmd, &root].iter().flat_map(|c| c.get_arguments()).any(|arg| {
+                    let named = match opt.strip_prefix('-') {
+                        Some(long) => arg.get_long() == Some(long),
+                        None => {
+                            opt.len() == 1 && arg.get_short().is_some_and(|s| opt.starts_with(s))
+                        }
+                    };
+                    named && arg.get_action().takes_values()
+                });
+            if takes_value {
+                args.next();
+            }
+            continue;
+        }
+        let Some(sub) = cmd.find_subcommand(&a).cloned() else {
+            break;
+        };
+        out.push(sub.get_name().to_string());
+        cmd = sub;
+    }
+    out.join(" ")
+}
+
+fn read_entry(path: &Path) -> Option<Entry> {
+    let mut e = parse_entry(&fs::read_to_string(path).ok()?)?;
+    e.path = path.to_path_buf();
+    Some(e)
+}
+
+/// The entry of `key`, whether fresh or not.
+pub fn entry(key: &str) -> Option<Entry> {
+    read_entry(&key_path(key))
 }
 
 /// Mark a cache key as updated "now".
-pub fn mark(key: &str) {
+pub fn mark(key: &str, ttl_secs: u64) {
+    write_entry(key, ttl_secs, None);
+}
+
+/// Mark a cache key and keep `payload` with it, for reading back with `entry`.
+pub fn mark_with(key: &str, ttl_secs: u64, payload: &impl Serialize) {
+    write_entry(key, ttl_secs, serde_json::to_value(payload).ok());
+}
+
+fn write_entry(key: &str, ttl_secs: u64, payload: Option<Value>) {
     let dir = vx_cache_dir();
     if ensure_dir(&dir).is_err() {
         return;
     }
 
-    let p = key_path(key);
-    let _ = fs::write(p, format!("{}", now_secs()));
+    let e = Entry {
+        key: Some(key.to_string()),
+        created: now_secs(),
+        ttl_secs: Some(ttl_secs),
+        command: Some(invocation()),
+        payload,
+        path: PathBuf::new(),
+    };
+    if let Ok(text) = serde_json::to_string(&e) {
+        let _ = write_atomic(&key_path(key), &text);
+    }
+}
+
+/// Every entry, most recently marked first.
+pub fn entries() -> Vec<Entry> {
+    let Ok(rd) = fs::read_dir(vx_cache_dir()) else {
+        return Vec::new();
+    };
+    let mut out: Vec<Entry> = rd
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "stamp"))
+        .filter_map(|p| read_entry(&p))
+        .collect();
+    out.sort_by_key(|e| std::cmp::Reverse(e.created));
+    out
+}
+
+/// ~/.cache/vx/<name>, for cached data that goes with a key.
+pub fn data_path(name: &str) -> PathBuf {
+    vx_cache_dir().join(name)
+}
+
+pub fn write_data(name: &str, text: &str) -> io::Result<()> {
+    ensure_dir(&vx_cache_dir())?;
+    write_atomic(&data_path(name), text)
+}
+
+/// Write through a temporary file and rename it over `path`, so a concurrent
+/// reader sees the old or the new contents, never half of them.
+pub fn write_atomic(path: &Path, text: &str) -> io::Result<()> {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Exclusive advisory lock on ~/.cache/vx/<name>.lock, released on drop.
+pub struct Lock {
+    _file: File,
+}
+
+/// Take the lock guarding a read-modify-write of `name` (the managed manifest,
+/// a cache file). Another vx holding it is waited for, with a note.
+pub fn lock(log: &Log, name: &str) -> io::Result<Lock> {
+    let dir = vx_cache_dir();
+    ensure_dir(&dir)?;
+    let file = File::create(dir.join(format!("{name}.lock")))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            log.info(format!("vx: another vx is running; waiting for it to finish with {name}..."));
+            file.lock()?;
+        }
+        Err(TryLockError::Error(e)) => return Err(e),
+    }
+    Ok(Lock { _file: file })
 }
 
+#[cfg(test)]
+mod tests {
+    use super::parse_entry;
+
+    #[test]
+    fn entries_json_and_older_stamps() {
+        let e = parse_entry(
+            r#"{"key":"xbps.repodata.sync","created":1700000000,"ttl_secs":600,"command":"vx up","payload":[1]}"#,
+        )
+        .unwrap();
+        assert_eq!(e.key.as_deref(), Some("xbps.repodata.sync"));
+        assert_eq!((e.created, e.ttl_secs), (1700000000, Some(600)));
+        assert_eq!(e.command.as_deref(), Some("vx up"));
+        assert!(e.payload.is_some());
+
+        let e = parse_entry("1700000000\t600\tvoid.news").unwrap();
+        assert_eq!((e.created, e.ttl_secs, e.key.as_deref()), (1700000000, Some(600), Some("void.news")));
+        let e = parse_entry("1700000000\n").unwrap();
+        assert_eq!((e.created, e.ttl_secs, e.key), (1700000000, None, None));
+        assert!(parse_entry("garbage").is_none());
+    }
+}
@@ -1,7 +1,9 @@
 // Author Dustin Pilgrim
 // License: MIT
 
+use crate::completions;
 use clap::{ArgAction, Args, Parser, Subcommand};
+use clap_complete::ArgValueCandidates;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -29,6 +31,18 @@ pub struct Cli {
     #[arg(long, global = true, value_name = "PATH")]
     pub voidpkgs: Option<PathBuf>,
 
+    /// Print machine-readable JSON (up/src up --dry-run, status, search, info).
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Manage the system installed under DIR (chroot, container, bootstrap).
+    #[arg(long, global = true, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+
+    /// Ignore cached syncs, fetches and lookups for this run (same as VX_CACHE=0).
+    #[arg(long, global = true)]
+    pub fresh: bool,
+
     #[command(subcommand)]
     pub cmd: Cmd,
 }
@@ -36,38 +50,126 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Cmd {
     /// Show vx status (config + void-packages info).
-    Status,
+    Status {
+        /// Print every setting's effective value and where it came from
+        /// (cli, env, config or default).
+        #[arg(long)]
+        resolved: bool,
+    },
+
+    /// Check the setup source builds need (void-packages, chroot method).
+    Doctor,
 
     /// Search available packages (xbps-query -Rs).
     Search {
+        /// Extra repository URL or path (can be repeated).
+        #[arg(long = "repo", value_name = "URL")]
+        repos: Vec<String>,
+
+        /// Pick matches interactively (fuzzy filter) and install them.
+        #[arg(short = 'p', long)]
+        pick: bool,
+
+        /// Print only package names, one per line.
+        #[arg(long, conflicts_with = "pick")]
+        names_only: bool,
+
         /// Search term.
         term: Vec<String>,
     },
 
     /// Show package information (xbps-query -R).
     Info {
+        /// Extra repository URL or path (can be repeated).
+        #[arg(long = "repo", value_name = "URL")]
+        repos: Vec<String>,
+
+        /// Print xbps-query's output as-is instead of the summary.
+        #[arg(long)]
+        raw: bool,
+
         /// Package name.
+        #[arg(add = ArgValueCandidates::new(completions::repo_pkgs))]
         pkg: String,
     },
 
     /// List installed files for a package (xbps-query -f).
     Files {
         /// Package name.
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
         pkg: String,
     },
 
     /// List installed packages (xbps-query -l).
     List {
+        /// Only manually installed packages.
+        #[arg(short = 'm', long, conflicts_with = "auto")]
+        manual: bool,
+
+        /// Only automatically installed packages (dependencies).
+        #[arg(short = 'a', long)]
+        auto: bool,
+
+        /// Only packages on hold.
+        #[arg(short = 'H', long)]
+        hold: bool,
+
+        /// Only packages installed from a repository whose URL contains NAME.
+        #[arg(short = 'r', long, value_name = "NAME")]
+        repo: Option<String>,
+
+        /// Only orphaned packages.
+        #[arg(short = 'O', long)]
+        orphans: bool,
+
         /// Filter by name substring.
         term: Option<String>,
     },
 
-    /// Find which package owns a path (xbps-query -o).
+    /// Find which package owns a path (xbps-query -o, or xlocate with --remote).
+    #[command(visible_alias = "provides")]
     Owns {
+        /// Search packages in the repositories instead (xlocate).
+        #[arg(short = 'R', long)]
+        remote: bool,
+
         /// Path to check.
         path: String,
     },
 
+    /// Show packages that depend on a package (xbps-query -X).
+    Rdeps {
+        /// Query repositories instead of installed packages (xbps-query -RX).
+        #[arg(short = 'R', long)]
+        remote: bool,
+
+        /// Package name.
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
+        pkg: String,
+    },
+
+    /// Explain why a package is installed (install mode, dependents, vx tracking).
+    Why {
+        /// Package name.
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
+        pkg: String,
+    },
+
+    /// Mark installed packages as automatically or manually installed (xbps-pkgdb -m).
+    Mark {
+        /// Mark as installed as a dependency (`vx orphans` removes it once unneeded).
+        #[arg(long, conflicts_with = "manual", required_unless_present = "manual")]
+        auto: bool,
+
+        /// Mark as explicitly installed.
+        #[arg(long)]
+        manual: bool,
+
+        /// Package names.
+        #[arg(required = true, add = ArgValueCandidates::new(completions::installed_pkgs))]
+        pkgs: Vec<String>,
+    },
+
     /// Install packages from repositories (xbps-install).
     Add {
         /// Assume yes.
@@ -119,7 +221,7 @@ pub enum Cmd {
         dry_run: bool,
 
         /// Additional repositories (can be repeated).
-        #[arg(short = 'R', long = "repository", value_name = "URL")]
+        #[arg(short = 'R', long = "repository", visible_alias = "repo", value_name = "URL")]
         repositories: Vec<String>,
 
         /// Full path to rootdir.
@@ -146,7 +248,8 @@ pub enum Cmd {
         #[arg(long = "xbps-verbose")]
         xbps_verbose: bool,
 
-        /// Packages to install.
+        /// Packages to install (names, `name>=ver` constraints or local `.xbps` files).
+        #[arg(add = ArgValueCandidates::new(completions::repo_pkgs))]
         pkgs: Vec<String>,
 
         /// Extra raw xbps-install args after `--`.
@@ -192,10 +295,18 @@ pub enum Cmd {
         #[arg(short = 'o', long)]
         orphans: bool,
 
+        /// Also remove dependencies nothing else needs (-R; this is the default).
+        #[arg(short = 'R', long, conflicts_with = "no_recursive")]
+        recursive: bool,
+
         /// Disable recursive dependency removal (default is recursive).
         #[arg(long = "no-recursive")]
         no_recursive: bool,
 
+        /// Also remove all orphaned packages in the same transaction.
+        #[arg(long, conflicts_with = "orphans")]
+        clean_deps: bool,
+
         /// Full path to rootdir.
         #[arg(short = 'r', long, value_name = "DIR")]
         rootdir: Option<PathBuf>,
@@ -204,7 +315,12 @@ pub enum Cmd {
         #[arg(long = "xbps-verbose")]
         xbps_verbose: bool,
 
+        /// Allow removing protected packages (base-system, xbps, the running kernel, ...).
+        #[arg(long)]
+        force_protected: bool,
+
         /// Packages to remove.
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
         pkgs: Vec<String>,
 
         /// Extra raw xbps-remove args after `--`.
@@ -212,6 +328,134 @@ pub enum Cmd {
         xbps_args: Vec<String>,
     },
 
+    /// Install an older cached version of a package (from /var/cache/xbps).
+    Downgrade {
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+
+        /// Package name.
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
+        pkg: String,
+
+        /// Version to install (e.g. 1.2.3 or 1.2.3_1); prompts if omitted.
+        version: Option<String>,
+    },
+
+    /// List installed kernel series and remove stale ones.
+    Kernels {
+        /// Remove kernel series that are not running, not newest and not required,
+        /// plus old versions reported by vkpurge.
+        #[arg(short = 'p', long)]
+        purge: bool,
+
+        /// Show the purge plan only; do not make changes.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+    },
+
+    /// Show installed size per package, largest first, with a total.
+    Size {
+        /// Show only the N largest packages (the total still covers all).
+        #[arg(short = 't', long)]
+        top: Option<usize>,
+
+        /// Packages to size (default: all installed).
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
+        pkgs: Vec<String>,
+    },
+
+    /// Review `.new-*` config files left by updates (keep, replace or merge).
+    Confdiff {
+        /// Only list pending files; do not prompt.
+        #[arg(short = 'l', long)]
+        list: bool,
+
+        /// Directory to scan (default: /etc).
+        dir: Option<PathBuf>,
+    },
+
+    /// Show the transaction journal (installs, removals, updates made through vx).
+    History {
+        /// Show the changes of one transaction.
+        id: Option<u64>,
+
+        /// Number of recent transactions to list.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// List running processes that still use replaced binaries or libraries.
+    Restartcheck,
+
+    /// Show the void-packages history of a package's template (git log --follow).
+    Changelog {
+        /// Number of commits to show.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Package name (subpackages resolve to their source package).
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
+        pkg: String,
+    },
+
+    /// Show the void-packages template an installed package is built from.
+    WhichTemplate {
+        /// Package name (subpackages resolve to their source package).
+        #[arg(add = ArgValueCandidates::new(completions::installed_pkgs))]
+        pkg: String,
+    },
+
+    /// Undo a journaled transaction using packages from the xbps cache.
+    ///
+    /// Without an id, rolls back the most recent transaction.
+    Rollback {
+        /// Show the rollback plan only; do not make changes.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+
+        /// Transaction id (see `vx history`).
+        id: Option<u64>,
+    },
+
+    /// Remove orphaned packages no longer required by anything (xbps-remove -o).
+    Orphans {
+        /// Show the orphan list only; do not remove anything.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+    },
+
+    /// Purge obsolete packages from the xbps cache (xbps-remove -O).
+    Clean {
+        /// Also remove orphaned packages (xbps-remove -o).
+        #[arg(short = 'a', long)]
+        all: bool,
+
+        /// Also remove obsolete distfiles of void-packages (vx src purge-distfiles).
+        #[arg(long)]
+        src: bool,
+
+        /// Show what would be cleaned without making changes.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+    },
+
     /// Update system packages and/or tracked source packages.
     ///
     /// Without --all: updates system only (xbps-install -Su).
@@ -236,10 +480,112 @@ pub enum Cmd {
         /// Build from local checkout instead of upstream (default is upstream).
         #[arg(long)]
         local: bool,
+
+        /// Build from upstream/master (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+
+        /// Remove stale kernels after a successful system update.
+        #[arg(long)]
+        purge_kernels: bool,
+
+        /// Before updating, check that no installed package loses a shared library it links.
+        #[arg(long, conflicts_with = "check")]
+        check_shlibs: bool,
+
+        /// Review the plan and leave out individual packages before applying.
+        #[arg(long, conflicts_with_all = ["check", "dry_run"])]
+        review: bool,
+
+        /// Show Void news posted since the last update before updating.
+        #[arg(long, conflicts_with = "check")]
+        news: bool,
+
+        /// Rebuild tracked source packages linked against libraries the update removed.
+        #[arg(long, conflicts_with_all = ["check", "download_only"])]
+        rebuild_broken: bool,
+
+        /// With --dry-run, list the void-packages commits behind each system update.
+        #[arg(long, requires = "dry_run")]
+        changelog: bool,
+
+        /// Only check for system updates: print the count, never prompt.
+        /// Exit code 0 = up to date, 100 = updates available.
+        #[arg(long, conflicts_with_all = ["all", "dry_run", "yes", "purge_kernels"])]
+        check: bool,
+
+        /// With --check, print `pkg old->new` per update instead of the count.
+        #[arg(long, requires = "check")]
+        list: bool,
+
+        /// Extra repository URL or path for the system update (can be repeated).
+        #[arg(long = "repo", value_name = "URL")]
+        repos: Vec<String>,
+
+        /// Skip updates for this package this time (can be repeated).
+        #[arg(long, value_name = "PKG")]
+        ignore: Vec<String>,
+
+        /// Only download the system updates into the cache (xbps-install -D).
+        #[arg(short = 'D', long = "download-only", conflicts_with_all = ["all", "purge_kernels"])]
+        download_only: bool,
+
+        /// Update only these system packages (default: everything).
+        #[arg(
+            conflicts_with_all = ["all", "check"],
+            add = ArgValueCandidates::new(completions::installed_pkgs)
+        )]
+        pkgs: Vec<String>,
+    },
+
+    /// Sync repodata (xbps-install -S) and fetch void-packages upstream, ignoring cache TTLs.
+    Sync {
+        /// Extra repository URL or path to sync (can be repeated).
+        #[arg(long = "repo", value_name = "URL")]
+        repos: Vec<String>,
+    },
+
+    /// Measure known Void mirrors and optionally switch the repositories to one.
+    Mirrors {
+        /// Only list the known mirrors (no measuring).
+        #[arg(long, conflicts_with_all = ["set", "best"])]
+        list: bool,
+
+        /// Switch to this mirror (its root URL, e.g. https://repo-fi.voidlinux.org).
+        #[arg(long, value_name = "URL", conflicts_with = "best")]
+        set: Option<String>,
+
+        /// Measure, then switch to the fastest mirror.
+        #[arg(long)]
+        best: bool,
+
+        /// Save the mirror as `xbps.mirror` in vx.rune instead of writing
+        /// /etc/xbps.d overrides (no root needed; only vx uses it).
+        #[arg(long, conflicts_with = "list")]
+        user: bool,
+
+        /// Seconds allowed per mirror.
+        #[arg(long, default_value_t = 5, value_name = "SECS")]
+        timeout: u64,
+
+        /// Skip confirmation prompt.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+    },
+
+    /// Manage repositories in /etc/xbps.d (list, add, rm, enable, disable).
+    Repo {
+        #[command(subcommand)]
+        cmd: RepoCmd,
     },
 
     /// void-packages / xbps-src source build operations.
     Src {
+        /// Source profile from source.profiles (its own void-packages checkout),
+        /// or a build profile: default, glibc or musl.
+        #[arg(long, global = true, value_name = "NAME")]
+        profile: Option<String>,
+
         #[command(subcommand)]
         cmd: SrcCmd,
     },
@@ -272,22 +618,137 @@ pub enum Cmd {
         #[command(subcommand)]
         cmd: Option<PkgCmd>,
     },
+
+    /// Read or change settings in vx.rune.
+    Config {
+        #[command(subcommand)]
+        cmd: ConfigCmd,
+    },
+
+    /// Show or clear what vx keeps under ~/.cache/vx (sync stamps, worktrees).
+    Cache {
+        #[command(subcommand)]
+        cmd: CacheCmd,
+    },
+
+    /// Clear the lock a crashed or killed vx left behind.
+    Unlock {
+        /// Remove it even though the process that took it still runs.
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Print a shell completion script (bash, zsh, fish, elvish, powershell).
+    Completions {
+        #[arg(value_parser = completions::SHELLS)]
+        shell: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCmd {
+    /// List sync stamps (age, TTL) and upstream worktrees (disk usage).
+    Status,
+
+    /// Print a cache entry and what was kept with it (`xbps.update-plan`:
+    /// the last update plan, without syncing).
+    Show { key: String },
+
+    /// Remove sync stamps and upstream worktrees (both unless one is picked).
+    Clear {
+        /// Only remove the upstream worktrees.
+        #[arg(long)]
+        worktrees: bool,
+
+        /// Only remove the sync stamps, so the next sync and fetch run.
+        #[arg(long)]
+        stamps: bool,
+
+        /// Show what would be removed without removing it.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCmd {
+    /// Print a setting (e.g. `source.default_remote`).
+    Get { key: String },
+
+    /// Change a setting, keeping the rest of the file (comments included) as is.
+    Set { key: String, value: String },
+
+    /// Open vx.rune in $VISUAL/$EDITOR, then check that it parses.
+    Edit,
+
+    /// Print where vx.rune lives.
+    Path,
+
+    /// Look for mistakes the config loads past: unknown keys, values of the
+    /// wrong type, paths that don't exist, settings that contradict each other.
+    Check,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RepoCmd {
+    /// List configured repositories with signature and last-sync info.
+    List,
+
+    /// Add a repository (writes /etc/xbps.d/<name>.conf).
+    Add {
+        /// Conf file name under /etc/xbps.d (default derived from the URL).
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Repository URL or local path.
+        url: String,
+    },
+
+    /// Remove a repository by URL or conf file name.
+    Rm {
+        /// Repository URL or conf file name.
+        repo: String,
+    },
+
+    /// Enable a commented-out repository.
+    Enable {
+        /// Repository URL or conf file name.
+        repo: String,
+    },
+
+    /// Disable a repository (comments it out; /usr/share entries get an /etc override).
+    Disable {
+        /// Repository URL or conf file name.
+        repo: String,
+    },
+
+    /// List trusted signing keys, the repositories using them, and unsigned repositories.
+    Keys,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum SrcCmd {
     /// Build + install a source package and start tracking it.
     ///
-    /// Builds from upstream by default. Use --local for your checkout.
+    /// Builds from upstream by default (`source.default_remote`); --local uses your
+    /// checkout, --remote forces upstream.
     Add {
         /// Assume yes.
         #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
         yes: bool,
 
+        /// Rebuild even if the template is unchanged since the last build.
+        #[arg(short = 'f', long)]
+        force: bool,
+
         /// Build from local checkout instead of upstream.
         #[arg(long)]
         local: bool,
 
+        /// Build from upstream/master (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+
         #[command(flatten)]
         build: SrcBuildFlags,
 
@@ -306,6 +767,7 @@ pub enum SrcCmd {
         yes: bool,
 
         /// Packages to remove and untrack.
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
         pkgs: Vec<String>,
     },
 
@@ -314,7 +776,8 @@ pub enum SrcCmd {
     /// With no arguments: rebuilds all tracked packages.
     /// With package names: rebuilds only those packages.
     ///
-    /// Builds from upstream by default. Use --local for your checkout.
+    /// Builds from upstream by default (`source.default_remote`); --local uses your
+    /// checkout, --remote forces upstream.
     Up {
         /// Show the plan only; do not make changes.
         #[arg(short = 'n', long)]
@@ -332,10 +795,34 @@ pub enum SrcCmd {
         #[arg(long)]
         local: bool,
 
+        /// Build from upstream/master (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+
+        /// Move templates that build a git commit (`_commit=`) to upstream's
+        /// latest commit first (default: all tracked ones), then rebuild them
+        /// from the local checkout.
+        #[arg(long, conflicts_with_all = ["remote", "stale"])]
+        head: bool,
+
+        /// Update the tracked packages `vx src checkvers` reports as stale.
+        #[arg(long, conflicts_with = "pkgs")]
+        stale: bool,
+
+        /// Update the tracked packages tagged with this group (`vx src tag`).
+        #[arg(long, value_name = "GROUP", conflicts_with_all = ["pkgs", "stale", "head"])]
+        group: Option<String>,
+
+        /// Also rebuild and reinstall installed packages whose templates
+        /// depend on the updated ones.
+        #[arg(long)]
+        revdeps: bool,
+
         #[command(flatten)]
         build: SrcBuildFlags,
 
         /// Packages to update (default: all tracked).
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
         pkgs: Vec<String>,
 
         /// Extra raw xbps-src args after `--`.
@@ -346,15 +833,191 @@ pub enum SrcCmd {
     /// List tracked source packages.
     List,
 
+    /// Tracked source packages with their installed and template versions.
+    Tracked {
+        /// Read templates from the local checkout.
+        #[arg(long)]
+        local: bool,
+
+        /// Read templates from upstream/master (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+
+        /// Only packages built from a void-packages commit older than upstream/master.
+        #[arg(long, conflicts_with_all = ["local", "remote"])]
+        outdated_tree: bool,
+    },
+
+    /// Track installed packages that came from the local repository.
+    Adopt {
+        /// Show them only; do not change the managed list.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+    },
+
+    /// Add a tracked package to groups, for `vx src up --group`.
+    Tag {
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkg: String,
+
+        #[arg(required = true)]
+        groups: Vec<String>,
+    },
+
+    /// Take a tracked package out of groups (all of them when none are given).
+    Untag {
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkg: String,
+
+        groups: Vec<String>,
+    },
+
+    /// Stop tracking source packages without removing them.
+    Untrack {
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkgs: Vec<String>,
+    },
+
+    /// Hold a tracked package back from `vx src up` and `vx up -a`.
+    Pin {
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkg: String,
+
+        /// Allow updates up to this version (e.g. 1.2 or 1.2_1) instead of none.
+        version: Option<String>,
+    },
+
+    /// Let a pinned package update again.
+    Unpin {
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkg: String,
+    },
+
+    /// Print the tracked package set (with pins) for `vx src import`.
+    Export,
+
+    /// Track the packages of a `vx src export` manifest.
+    Import {
+        /// Manifest written by `vx src export`.
+        file: PathBuf,
+
+        /// Also build and install the ones not installed here (plans and confirms first).
+        #[arg(long)]
+        apply: bool,
+
+        /// Show the plan only; do not make changes.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+
+        /// Build from local checkout instead of upstream (default is upstream).
+        #[arg(long)]
+        local: bool,
+
+        /// Build from upstream/master (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+    },
+
+    /// Show the latest build log of a package, or list logged builds.
+    Log {
+        /// Show an older log instead (1 = the one before the latest).
+        #[arg(long, default_value_t = 0, value_name = "N")]
+        previous: usize,
+
+        /// List failed builds (of all packages, or of <pkg>).
+        #[arg(long)]
+        failed: bool,
+
+        /// Package whose log to show (default: list recent builds).
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkg: Option<String>,
+    },
+
+    /// List the slowest packages by their recorded build times.
+    Stats {
+        /// Number of packages to show.
+        #[arg(long, default_value_t = 10, value_name = "N")]
+        top: usize,
+    },
+
+    /// Open a shell in the build directory a failed build left behind.
+    Debug {
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkg: String,
+    },
+
+    /// Open a shell in the masterdir chroot with the package's variables set
+    /// (./xbps-src chroot), in its builddir if a build left one.
+    Env {
+        /// Use the local checkout instead of the upstream worktree.
+        #[arg(long)]
+        local: bool,
+
+        /// Use the upstream worktree (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+
+        /// Absolute path to masterdir, or the name of a registered one.
+        #[arg(short = 'm', long = "masterdir", value_name = "DIR|NAME")]
+        masterdir: Option<PathBuf>,
+
+        pkg: String,
+    },
+
     /// Build a source package without installing (./xbps-src pkg).
     Build {
         /// Build from local checkout instead of upstream.
         #[arg(long)]
         local: bool,
 
+        /// Build from upstream/master (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+
+        /// Build inside a podman/docker void-buildroot container (for hosts
+        /// where xbps-src's chroot doesn't work).
+        #[arg(long)]
+        container: bool,
+
         #[command(flatten)]
         build: SrcBuildFlags,
 
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkgs: Vec<String>,
+
+        /// Extra raw xbps-src args after `--`.
+        #[arg(last = true, allow_hyphen_values = true)]
+        xbps_src_args: Vec<String>,
+    },
+
+    /// Download distfiles without building (./xbps-src fetch), e.g. to build
+    /// offline later.
+    Fetch {
+        /// Also extract them (./xbps-src extract).
+        #[arg(long)]
+        extract: bool,
+
+        /// Extract and apply patches (./xbps-src patch).
+        #[arg(long, conflicts_with = "extract")]
+        patch: bool,
+
+        /// Use the local checkout instead of upstream.
+        #[arg(long)]
+        local: bool,
+
+        /// Use upstream/master (overrides `source.default_remote false`).
+        #[arg(long, conflicts_with = "local")]
+        remote: bool,
+
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
         pkgs: Vec<String>,
 
         /// Extra raw xbps-src args after `--`.
@@ -363,11 +1026,209 @@ pub enum SrcCmd {
     },
 
     /// Clean build files (./xbps-src clean).
-    Clean { pkgs: Vec<String> },
+    Clean {
+        pkgs: Vec<String>,
+
+        /// Extra raw xbps-src args after `--`.
+        #[arg(last = true, allow_hyphen_values = true)]
+        xbps_src_args: Vec<String>,
+    },
+
+    /// Check, reindex or clean the local repository (hostdir/binpkgs and its subrepos).
+    Repo {
+        #[command(subcommand)]
+        cmd: SrcRepoCmd,
+    },
+
+    /// Copy the local repository to another host or directory (rsync), then
+    /// re-index (and sign) it there.
+    Push {
+        /// Show what would be copied.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Remove files at the destination that are gone locally.
+        #[arg(long)]
+        delete: bool,
+
+        /// Don't re-index at the destination.
+        #[arg(long)]
+        no_index: bool,
+
+        /// A `push.names` entry from the config, or host:/path / a directory.
+        dest: String,
+    },
+
+    /// Validate a template before a pull request: xlint, a clean build from
+    /// upstream/master with your template, and ./xbps-src check.
+    Check {
+        #[command(flatten)]
+        build: SrcBuildFlags,
+
+        pkg: String,
+
+        /// Extra raw xbps-src args after `--`.
+        #[arg(last = true, allow_hyphen_values = true)]
+        xbps_src_args: Vec<String>,
+    },
+
+    /// Create a branch off upstream/master carrying the package's template
+    /// from the working tree.
+    Branch {
+        /// Branch name (defaults to the template name).
+        #[arg(long)]
+        name: Option<String>,
+
+        pkg: String,
+    },
+
+    /// Commit the package's template with a Void-style message
+    /// ("<pkg>: update to X.Y.Z." / "New package: <pkg>-X.Y.Z").
+    Commit {
+        /// Use this message instead.
+        #[arg(short = 'm', long)]
+        message: Option<String>,
+
+        pkg: String,
+    },
+
+    /// Push the package branch to your fork and open a pull request against
+    /// void-linux/void-packages (with `gh`).
+    Pr {
+        /// Git remote of your fork.
+        #[arg(long, default_value = "origin")]
+        remote: String,
+
+        /// Open it as a draft.
+        #[arg(long)]
+        draft: bool,
+
+        /// Don't lint the template for the description.
+        #[arg(long)]
+        no_lint: bool,
+
+        /// Show the description and commands without pushing.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        pkg: String,
+    },
+
+    /// Remove distfiles no template references any more (like
+    /// ./xbps-src purge-distfiles).
+    PurgeDistfiles {
+        /// Only list them.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+    },
 
     /// Lint a template (./xbps-src lint).
     Lint { pkgs: Vec<String> },
 
+    /// Set a template's version, reset its revision and update its checksums
+    /// (like xbump; runs xgensum -i).
+    Bump {
+        /// Test-build the template afterwards.
+        #[arg(long = "build")]
+        then_build: bool,
+
+        #[command(flatten)]
+        build: SrcBuildFlags,
+
+        /// Package (or subpackage) whose template to bump.
+        pkg: String,
+
+        /// New upstream version.
+        version: String,
+    },
+
+    /// List packages in the local repository built from an older template
+    /// (like `xbps-checkvers -i`).
+    Checkvers,
+
+    /// Check templates for new upstream releases (./xbps-src update-check).
+    ///
+    /// Results are cached and shown in `vx status`.
+    UpdateCheck {
+        /// Check all tracked packages.
+        #[arg(short = 'a', long, conflicts_with = "pkgs")]
+        all: bool,
+
+        #[arg(add = ArgValueCandidates::new(completions::managed_pkgs))]
+        pkgs: Vec<String>,
+    },
+
+    /// Manage named masterdirs (per arch/libc) for `--masterdir <name>`.
+    Masterdir {
+        #[command(subcommand)]
+        cmd: MasterdirCmd,
+    },
+
+    /// List or prune the upstream worktrees vx builds in (~/.cache/vx/worktrees).
+    Worktree {
+        #[command(subcommand)]
+        cmd: SrcWorktreeCmd,
+    },
+
+    /// Remove the build chroot of your checkout (./xbps-src zap).
+    ///
+    /// Use after a glibc/musl switch or when the masterdir is broken.
+    Zap {
+        /// Bootstrap a fresh masterdir right after (./xbps-src binary-bootstrap).
+        #[arg(long)]
+        rebootstrap: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+    },
+
+    /// Open a template in $EDITOR, then optionally lint it and update its checksums.
+    Edit {
+        /// Create the package with xnew first if it has no template.
+        #[arg(long)]
+        new: bool,
+
+        /// Run ./xbps-src lint on it afterwards.
+        #[arg(long)]
+        lint: bool,
+
+        /// Run xgensum -i on it afterwards.
+        #[arg(long)]
+        gensum: bool,
+
+        /// Package (or subpackage) name.
+        pkg: String,
+    },
+
+    /// Show a template's metadata (description, license, dependencies, ...).
+    Show {
+        /// Read the template from upstream/master instead of your checkout.
+        #[arg(long)]
+        remote: bool,
+
+        /// Package (or subpackage) name.
+        pkg: String,
+    },
+
+    /// Show a template's build dependencies (hostmakedepends/makedepends).
+    Deps {
+        /// Install missing build dependencies on the host (marked automatic).
+        #[arg(long = "install-host")]
+        install_host: bool,
+
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+
+        /// Package name.
+        pkg: String,
+    },
+
     /// Search srcpkgs by name.
     Search {
         /// Only show packages that are installed.
@@ -381,9 +1242,98 @@ pub enum SrcCmd {
 
 #[derive(Subcommand, Debug)]
 pub enum PkgCmd {
-    /// Create a new template skeleton (xnew).
+    /// Create a new template: an xnew skeleton, or a filled-in template when
+    /// any of the fields below (or --interactive) is given.
     New {
-        /// Package name.
+        /// Ask for each field.
+        #[arg(short = 'i', long)]
+        interactive: bool,
+
+        /// Release tarball; name, version and homepage are inferred from it.
+        #[arg(long, value_name = "URL")]
+        from_url: Option<String>,
+
+        #[arg(long)]
+        version: Option<String>,
+
+        /// cargo, go, python3, gnu-configure, meson, cmake, ...
+        #[arg(long, value_name = "STYLE")]
+        build_style: Option<String>,
+
+        #[arg(long, value_name = "TEXT")]
+        short_desc: Option<String>,
+
+        /// SPDX license identifier.
+        #[arg(long)]
+        license: Option<String>,
+
+        #[arg(long, value_name = "URL")]
+        homepage: Option<String>,
+
+        /// Package name (default: inferred from --from-url).
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SrcRepoCmd {
+    /// Report index entries whose package file is gone and packages not indexed.
+    Check,
+
+    /// Drop stale index entries and index every package file (xbps-rindex -c, -a).
+    Index,
+
+    /// Remove obsolete package files, then stale entries (xbps-rindex -r, -c).
+    Clean,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SrcWorktreeCmd {
+    /// Show each worktree with its checkout, size and last use.
+    List,
+
+    /// Remove worktrees unused for a while or whose checkout is gone.
+    Prune {
+        /// Remove every worktree.
+        #[arg(long, conflicts_with = "older_than")]
+        all: bool,
+
+        /// Stale after this many days (default: source.worktree_max_age_days, or 30).
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+
+        /// Only list what would be removed.
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MasterdirCmd {
+    /// List registered masterdirs.
+    List,
+
+    /// Bootstrap a new masterdir and register it under a name.
+    Create {
+        /// Architecture to bootstrap (default: the host's, e.g. x86_64-musl).
+        #[arg(long, value_name = "ARCH")]
+        arch: Option<String>,
+
+        /// Where to put it (default: <void-packages>/masterdir-<name>).
+        #[arg(long, value_name = "DIR")]
+        path: Option<PathBuf>,
+
+        /// Name to refer to it by (a-z, 0-9 and _).
+        name: String,
+    },
+
+    /// Zap a registered masterdir and forget it.
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Assume yes.
+        #[arg(short = 'y', long, aliases = ["no-confirm", "noconfirm"])]
+        yes: bool,
+
         name: String,
     },
 }
@@ -426,8 +1376,8 @@ pub struct SrcBuildFlags {
     #[arg(short = 'H', long = "hostdir", value_name = "DIR")]
     pub hostdir: Option<PathBuf>,
 
-    /// Absolute path to masterdir.
-    #[arg(short = 'm', long = "masterdir", value_name = "DIR")]
+    /// Absolute path to masterdir, or the name of a registered one.
+    #[arg(short = 'm', long = "masterdir", value_name = "DIR|NAME")]
     pub masterdir: Option<PathBuf>,
 
     /// Use etc/conf.<name> as primary config.
@@ -477,4 +1427,9 @@ pub struct SrcBuildFlags {
     /// Treat some warnings as errors.
     #[arg(short = 's', long = "strict-warnings")]
     pub strict_warnings: bool,
+
+    /// Keep the builddir of a failed build: skip vx's `clean` before
+    /// building and leave the upstream worktree's masterdir in place.
+    #[arg(long)]
+    pub keep: bool,
 }
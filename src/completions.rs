@@ -0,0 +1,78 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{cache, cli::Cli, managed};
+use clap::CommandFactory;
+use clap_complete::{env::Shells, CompleteEnv, CompletionCandidate};
+use std::{env, fs, io, process::ExitCode};
+
+pub const SHELLS: [&str; 5] = ["bash", "zsh", "fish", "elvish", "powershell"];
+
+/// Env var the registration script sets when asking vx for candidates.
+const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Repository package names are refreshed at most once a day.
+const REPO_PKGS_TTL_SECS: u64 = 24 * 60 * 60;
+const REPO_PKGS_KEY: &str = "completion.repo-pkgs";
+const REPO_PKGS_FILE: &str = "repo-pkgs.txt";
+
+/// Answer a completion request from the shell, if this is one (exits when it is).
+pub fn handle_env() {
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
+}
+
+/// `vx completions <shell>` — print the registration script for `shell`.
+pub fn print(shell: &str) -> ExitCode {
+    let shells = Shells::builtins();
+    let Some(completer) = shells.completer(shell) else {
+        eprintln!("error: unknown shell {shell} (expected {})", SHELLS.join(", "));
+        return ExitCode::from(2);
+    };
+
+    // The script calls back into this binary for candidates.
+    let exe = env::current_exe()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "vx".into());
+    match completer.write_registration(COMPLETE_VAR, "vx", "vx", &exe, &mut io::stdout()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: failed to write completions: {e}");
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn candidates(names: impl IntoIterator<Item = String>) -> Vec<CompletionCandidate> {
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Package names from the repositories (cached `xbps-query -Rs`).
+pub fn repo_pkgs() -> Vec<CompletionCandidate> {
+    let path = cache::data_path(REPO_PKGS_FILE);
+    if cache::is_fresh(REPO_PKGS_KEY, REPO_PKGS_TTL_SECS)
+        && let Ok(text) = fs::read_to_string(&path)
+    {
+        return candidates(text.lines().map(str::to_string));
+    }
+
+    let names = crate::core::xbps::available_pkgnames().unwrap_or_default();
+    if !names.is_empty() && cache::write_data(REPO_PKGS_FILE, &names.join("\n")).is_ok() {
+        cache::mark(REPO_PKGS_KEY, REPO_PKGS_TTL_SECS);
+    }
+    candidates(names)
+}
+
+/// Installed package names (`xbps-query -l`).
+pub fn installed_pkgs() -> Vec<CompletionCandidate> {
+    let names = crate::core::xbps::installed_snapshot()
+        .map(|m| m.into_keys().collect::<Vec<_>>())
+        .unwrap_or_default();
+    candidates(names)
+}
+
+/// Source packages tracked by vx.
+pub fn managed_pkgs() -> Vec<CompletionCandidate> {
+    candidates(managed::load_managed().unwrap_or_default())
+}
@@ -1,18 +1,33 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use crate::paths::user_config_path;
+use crate::paths::{project_config_path, user_config_path};
 use rune_cfg::RuneConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use std::{
-    fs,
+    collections::BTreeMap,
+    env, fs,
     io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
 };
 
+/// Protected from `vx rm` unless `xbps.protected_packages` says otherwise.
+pub const DEFAULT_PROTECTED_PACKAGES: [&str; 2] = ["base-system", "xbps"];
+
+/// `source.worktree_max_age_days` when unset.
+pub const DEFAULT_WORKTREE_MAX_AGE_DAYS: u64 = 30;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub debug: bool,
 
+    /// Desktop notification when `vx up -a` or a source build finishes.
+    pub notify: bool,
+
+    /// Only notify for commands that ran at least this many minutes.
+    pub notify_after_min: u64,
+
     /// Optional: if empty/None, caller should fall back to:
     ///   1) --voidpkgs
     ///   2) VX_VOIDPKGS env var
@@ -24,6 +39,300 @@ pub struct Config {
 
     /// Use `.../nonfree` repo if present.
     pub use_nonfree: bool,
+
+    /// Run `vx kernels --purge` after a successful `vx up`.
+    pub purge_kernels: bool,
+
+    /// Run the shared-library check before every `vx up` (`--check-shlibs`).
+    pub check_shlibs: bool,
+
+    /// Show Void news since the last update before every `vx up` (`--news`).
+    pub check_news: bool,
+
+    /// Packages `vx up` never updates (like `--ignore`, but persistent).
+    pub ignore_packages: Vec<String>,
+
+    /// Packages `vx rm` refuses to remove without `--force-protected`.
+    /// The running kernel is always protected on top of these.
+    pub protected_packages: Vec<String>,
+
+    /// Alternate root directory for xbps (`--root` overrides). None means `/`.
+    pub root: Option<PathBuf>,
+
+    /// Mirror root vx points the xbps.d repositories at (`vx mirrors --user`);
+    /// None leaves them as configured.
+    pub mirror: Option<String>,
+
+    /// sudo | doas | run0 | none. None means auto-detect.
+    pub privilege_command: Option<String>,
+
+    /// Build source packages from upstream/master unless `--local` is given.
+    /// When false, the local checkout is used unless `--remote` is given.
+    pub default_remote: bool,
+
+    /// default | glibc | musl, or a `source.profiles` name; `--profile` overrides.
+    pub build_profile: String,
+
+    /// Named void-packages checkouts (`source.profiles.names` + a
+    /// `source.profiles.<name>` block each), picked with `--profile <name>`.
+    pub source_profiles: Vec<SourceProfile>,
+
+    /// Parallel jobs for xbps-src (`-j`); the command line wins.
+    pub build_jobs: Option<usize>,
+
+    /// Directories of personal templates (`<dir>/srcpkgs/<pkg>`) vx copies
+    /// into the build tree before building.
+    pub source_overlays: Vec<PathBuf>,
+
+    /// Days an upstream worktree may go unused before vx removes it (0: never).
+    pub worktree_max_age_days: u64,
+
+    /// XBPS_CHROOT_CMD for xbps-src (uunshare, uchroot, bwrap, ethereal, ...).
+    /// None leaves the choice to xbps-src.
+    pub chroot_cmd: Option<String>,
+
+    /// Image for `vx src build --container` (None: Void's void-buildroot image for the arch).
+    pub container_image: Option<String>,
+
+    /// `NAME=value` lines vx keeps in void-packages' etc/conf (and the upstream worktree's).
+    pub build_env: Vec<String>,
+
+    /// Named `vx src push` destinations (`push.names` + a `push.<name>` block each).
+    pub push_destinations: Vec<PushDest>,
+
+    /// Seconds a repodata sync counts as fresh.
+    pub repodata_ttl_secs: u64,
+
+    /// Seconds a void-packages upstream fetch counts as fresh.
+    pub git_ttl_secs: u64,
+
+    /// false: every TTL cache is bypassed, as with `--fresh`.
+    pub cache_enabled: bool,
+
+    /// Keys set by a `VX_*` variable or vx.rune; the rest are defaults.
+    pub origins: BTreeMap<String, Origin>,
+}
+
+/// Where the effective value of a setting came from, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    Cli,
+    Env,
+    Project,
+    Config,
+    Default,
+}
+
+impl Origin {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cli => "cli",
+            Self::Env => "env",
+            Self::Project => "project",
+            Self::Config => "config",
+            Self::Default => "default",
+        }
+    }
+}
+
+/// One line of `vx status --resolved`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolved {
+    pub key: &'static str,
+    pub env: &'static str,
+    pub value: String,
+    pub origin: Origin,
+}
+
+/// Config keys and the `VX_*` variable overriding each; a set variable wins
+/// over vx.rune. Profiles and push destinations are config-only.
+pub const ENV_KEYS: [(&str, &str); 25] = [
+    ("base.debug", "VX_DEBUG"),
+    ("base.notify", "VX_NOTIFY"),
+    ("base.notify_after", "VX_NOTIFY_AFTER"),
+    ("void_packages.path", "VX_VOIDPKGS"),
+    ("void_packages.local_repo", "VX_LOCAL_REPO"),
+    ("void_packages.use_nonfree", "VX_USE_NONFREE"),
+    ("xbps.purge_kernels", "VX_PURGE_KERNELS"),
+    ("xbps.check_shlibs", "VX_CHECK_SHLIBS"),
+    ("xbps.check_news", "VX_CHECK_NEWS"),
+    ("xbps.ignore_packages", "VX_IGNORE_PACKAGES"),
+    ("xbps.protected_packages", "VX_PROTECTED_PACKAGES"),
+    ("xbps.root", "VX_ROOT"),
+    ("xbps.mirror", "VX_MIRROR"),
+    ("privilege.command", "VX_SUDO_CMD"),
+    ("source.default_remote", "VX_DEFAULT_REMOTE"),
+    ("source.profile", "VX_PROFILE"),
+    ("source.overlays", "VX_OVERLAYS"),
+    ("source.worktree_max_age_days", "VX_WORKTREE_MAX_AGE_DAYS"),
+    ("build.jobs", "VX_JOBS"),
+    ("build.env", "VX_BUILD_ENV"),
+    ("build.chroot_cmd", "VX_CHROOT_CMD"),
+    ("build.container_image", "VX_CONTAINER_IMAGE"),
+    ("cache.repodata_ttl", "VX_REPODATA_TTL"),
+    ("cache.git_ttl", "VX_GIT_TTL"),
+    ("cache.enabled", "VX_CACHE"),
+];
+
+/// Older `VX_*` variables, still honoured below the `ENV_KEYS` one of their
+/// key: VX_SYNC_TTL_SECS sets both sync TTLs, a true VX_FRESH turns caching off.
+const ENV_ALIASES: [(&str, &str); 3] = [
+    ("cache.repodata_ttl", "VX_SYNC_TTL_SECS"),
+    ("cache.git_ttl", "VX_SYNC_TTL_SECS"),
+    ("cache.enabled", "VX_FRESH"),
+];
+
+fn env_override(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// The alias variable set for `key`, and its value as the key takes it.
+fn alias_override(key: &str) -> Option<(&'static str, String)> {
+    ENV_ALIASES.iter().filter(|(k, _)| *k == key).find_map(|&(_, var)| {
+        let v = env_override(var)?;
+        let v = match var {
+            // VX_FRESH=1 is `cache.enabled false`; VX_FRESH=0 leaves it be.
+            "VX_FRESH" => (env_value::<bool>(&v) == Some(true)).then(|| "false".to_string())?,
+            _ => v,
+        };
+        Some((var, v))
+    })
+}
+
+/// A `VX_*` value as `T`: JSON when it reads as such (`true`, `16`,
+/// `["a","b"]`), else the plain string, a yes/no word, or a comma-separated list.
+fn env_value<T: DeserializeOwned>(v: &str) -> Option<T> {
+    let v = v.trim();
+    let word = match v.to_ascii_lowercase().as_str() {
+        "1" | "yes" | "on" => Some(true),
+        "0" | "no" | "off" => Some(false),
+        _ => None,
+    };
+    let list = v
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| Value::String(s.to_string()))
+        .collect();
+    serde_json::from_str(v)
+        .ok()
+        .or_else(|| serde_json::from_value(Value::String(v.to_string())).ok())
+        .or_else(|| serde_json::from_value(Value::Bool(word?)).ok())
+        .or_else(|| serde_json::from_value(Value::Array(list)).ok())
+}
+
+fn parse(path: &Path) -> Result<RuneConfig, String> {
+    RuneConfig::from_file(path.to_str().ok_or("invalid config path")?)
+        .map_err(|e| format!("failed to parse config {}: {e}", path.display()))
+}
+
+/// What a project's `.vx.rune` may set: which checkout `vx src` works on,
+/// never how vx runs. Anything else in it is ignored.
+pub const PROJECT_KEYS: [&str; 3] =
+    ["void_packages.path", "void_packages.local_repo", "source.profile"];
+
+/// vx.rune (when there is one), then the nearest `.vx.rune` of the current
+/// directory, then the `VX_*` variables over both.
+struct Layers {
+    rune: Option<RuneConfig>,
+    /// The project config and the directory it is in.
+    project: Option<(RuneConfig, PathBuf)>,
+    env: bool,
+    origins: BTreeMap<String, Origin>,
+}
+
+impl Layers {
+    fn new(rune: Option<RuneConfig>) -> Result<Self, String> {
+        let project = match project_config_path() {
+            Some(p) => {
+                let dir = p.parent().map(Path::to_path_buf).unwrap_or_default();
+                Some((parse(&p)?, dir))
+            }
+            None => None,
+        };
+        Ok(Self {
+            rune,
+            project,
+            env: true,
+            origins: BTreeMap::new(),
+        })
+    }
+
+    /// The one place precedence is decided: `key`'s `VX_*` variable, then
+    /// the project's .vx.rune, then vx.rune; None leaves the default. A
+    /// variable that doesn't parse is an error rather than a silent default.
+    fn get<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>, String> {
+        if self.env
+            && let Some((_, var)) = ENV_KEYS.iter().find(|(k, _)| *k == key)
+            && let Some(v) = env_override(var)
+        {
+            let t = env_value(&v).ok_or_else(|| format!("{var}={v}: not a valid {key}"))?;
+            self.origins.insert(key.to_string(), Origin::Env);
+            return Ok(Some(t));
+        }
+        if self.env
+            && let Some((var, v)) = alias_override(key)
+        {
+            let t = env_value(&v).ok_or_else(|| format!("{var}={v}: not a valid {key}"))?;
+            self.origins.insert(key.to_string(), Origin::Env);
+            return Ok(Some(t));
+        }
+        if PROJECT_KEYS.contains(&key)
+            && let Some(t) = self.project.as_ref().and_then(|(p, _)| p.get(key).ok())
+        {
+            self.origins.insert(key.to_string(), Origin::Project);
+            return Ok(Some(t));
+        }
+        let t = self.rune.as_ref().and_then(|r| r.get(key).ok());
+        if t.is_some() {
+            self.origins.insert(key.to_string(), Origin::Config);
+        }
+        Ok(t)
+    }
+
+    /// `key` from vx.rune only (profile and push blocks aren't a project's).
+    fn config<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.rune.as_ref()?.get(key).ok()
+    }
+
+    /// A relative path the project config gave for `key` is taken from
+    /// where .vx.rune is, not from wherever vx runs.
+    fn path(&self, key: &str, p: &str) -> PathBuf {
+        match &self.project {
+            Some((_, dir)) if self.origins.get(key) == Some(&Origin::Project) => dir.join(p),
+            _ => PathBuf::from(p),
+        }
+    }
+}
+
+/// A `source.profiles.<name>` block; unset fields fall back to the
+/// top-level void_packages settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceProfile {
+    pub name: String,
+    /// void-packages checkout.
+    pub path: Option<PathBuf>,
+    /// Relative to that checkout.
+    pub local_repo: Option<PathBuf>,
+    /// Build for this architecture (own masterdir-<arch>, and hostdir-<arch>
+    /// when foreign).
+    pub arch: Option<String>,
+    pub use_nonfree: Option<bool>,
+}
+
+/// Where `vx src push <name>` copies the local repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PushDest {
+    pub name: String,
+    /// rsync target: `host:/path`, `user@host:/path` or a local directory.
+    pub dest: String,
+    /// Re-index the pushed repositories there (xbps-rindex -a).
+    pub reindex: bool,
+    /// Private key on the destination to sign the repositories with.
+    pub sign_key: Option<String>,
+    /// `--signedby` for signing; defaults to the destination name.
+    pub signed_by: Option<String>,
 }
 
 impl Config {
@@ -31,6 +340,7 @@ impl Config {
     /// - If config doesn't exist, ask ONCE (interactive) whether to create a default config at:
     ///     $HOME/.config/vx/vx.rune
     /// - If user says no, VX creates a sentinel so it won't ask again.
+    /// - Without a config, `VX_*` overrides still apply over the defaults.
     ///
     /// NOTE: This uses stdin/stdout; keep it early in program startup.
     pub fn load_or_bootstrap_interactive() -> Result<Option<Self>, String> {
@@ -41,13 +351,13 @@ impl Config {
 
         // Only prompt if stdin+stdout are terminals.
         if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
-            return Ok(None);
+            return Self::without_file();
         }
 
         // Ask-once sentinel (if user previously said "no", do not prompt again).
         let sentinel = bootstrap_sentinel_path(&path)?;
         if sentinel.exists() {
-            return Ok(None);
+            return Self::without_file();
         }
 
         println!(
@@ -65,14 +375,14 @@ impl Config {
 
         // If stdin read failed, do not create anything.
         if !ok {
-            return Ok(None);
+            return Self::without_file();
         }
 
         let yes = t.is_empty() || matches!(t.as_str(), "y" | "yes");
         if !yes {
             // Mark that we asked already so we don't nag on every run.
             write_bootstrap_sentinel(&sentinel)?;
-            return Ok(None);
+            return Self::without_file();
         }
 
         self::write_default_config(&path)?;
@@ -80,43 +390,291 @@ impl Config {
     }
 
     fn from_file(path: &Path) -> Result<Self, String> {
-        let cfg = RuneConfig::from_file(path.to_str().ok_or("invalid config path")?)
-            .map_err(|e| format!("failed to parse config {}: {e}", path.display()))?;
+        Self::from_layers(Layers::new(Some(parse(path)?))?)
+    }
+
+    /// Defaults with the project config and `VX_*` overrides, for when
+    /// there is no vx.rune. None when neither is there either.
+    fn without_file() -> Result<Option<Self>, String> {
+        let l = Layers::new(None)?;
+        let env_set = ENV_KEYS
+            .iter()
+            .any(|(key, var)| env_override(var).is_some() || alias_override(key).is_some());
+        if l.project.is_none() && !env_set {
+            return Ok(None);
+        }
+        Self::from_layers(l).map(Some)
+    }
+
+    /// Every setting at its default, whatever the environment says.
+    pub fn defaults() -> Self {
+        let l = Layers {
+            rune: None,
+            project: None,
+            env: false,
+            origins: BTreeMap::new(),
+        };
+        Self::from_layers(l).expect("defaults parse")
+    }
 
+    /// Each `ENV_KEYS` setting with its effective value and where it came from.
+    pub fn resolved(&self) -> Vec<Resolved> {
+        ENV_KEYS
+            .iter()
+            .map(|&(key, env)| {
+                let origin = self.origins.get(key).copied().unwrap_or(Origin::Default);
+                // Set through an older alias when the variable itself isn't.
+                let env = match alias_override(key) {
+                    Some((alias, _)) if origin == Origin::Env && env_override(env).is_none() => alias,
+                    _ => env,
+                };
+                Resolved {
+                    key,
+                    env,
+                    value: self.value(key),
+                    origin,
+                }
+            })
+            .collect()
+    }
+
+    /// The effective value of an `ENV_KEYS` setting, written as in vx.rune.
+    fn value(&self, key: &str) -> String {
+        let path = |p: &Option<PathBuf>| p.as_ref().map_or("unset".to_string(), |p| p.display().to_string());
+        let text = |s: &Option<String>| s.clone().unwrap_or_else(|| "unset".to_string());
+        match key {
+            "base.debug" => self.debug.to_string(),
+            "base.notify" => self.notify.to_string(),
+            "base.notify_after" => self.notify_after_min.to_string(),
+            "void_packages.path" => path(&self.void_packages_path),
+            "void_packages.local_repo" => self.local_repo_rel.display().to_string(),
+            "void_packages.use_nonfree" => self.use_nonfree.to_string(),
+            "xbps.purge_kernels" => self.purge_kernels.to_string(),
+            "xbps.check_shlibs" => self.check_shlibs.to_string(),
+            "xbps.check_news" => self.check_news.to_string(),
+            "xbps.ignore_packages" => format!("{:?}", self.ignore_packages),
+            "xbps.protected_packages" => format!("{:?}", self.protected_packages),
+            "xbps.root" => path(&self.root),
+            "xbps.mirror" => text(&self.mirror),
+            "privilege.command" => text(&self.privilege_command),
+            "source.default_remote" => self.default_remote.to_string(),
+            "source.profile" => self.build_profile.clone(),
+            "source.overlays" => format!("{:?}", self.source_overlays),
+            "source.worktree_max_age_days" => self.worktree_max_age_days.to_string(),
+            "build.jobs" => self.build_jobs.map_or("unset".to_string(), |n| n.to_string()),
+            "build.env" => format!("{:?}", self.build_env),
+            "build.chroot_cmd" => text(&self.chroot_cmd),
+            "build.container_image" => text(&self.container_image),
+            "cache.repodata_ttl" => self.repodata_ttl_secs.to_string(),
+            "cache.git_ttl" => self.git_ttl_secs.to_string(),
+            "cache.enabled" => self.cache_enabled.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn from_layers(mut l: Layers) -> Result<Self, String> {
         // base.debug (default false)
-        let debug: bool = cfg.get("base.debug").unwrap_or(false);
+        let debug: bool = l.get("base.debug")?.unwrap_or(false);
+
+        // base.notify (default false) + base.notify_after minutes (default 10)
+        let notify: bool = l.get("base.notify")?.unwrap_or(false);
+        let notify_after_min: u64 = l.get("base.notify_after")?.unwrap_or(10);
 
         // void_packages.path (optional; empty means None)
-        let void_packages_path_s: String = cfg
-            .get("void_packages.path")
-            .unwrap_or_else(|_| String::new());
+        let void_packages_path_s: String = l
+            .get("void_packages.path")?
+            .unwrap_or_default();
         let void_packages_path = {
             let p = void_packages_path_s.trim();
             if p.is_empty() {
                 None
             } else {
-                Some(PathBuf::from(p))
+                Some(l.path("void_packages.path", p))
             }
         };
 
         // void_packages.local_repo (default hostdir/binpkgs)
-        let local_repo_rel_s: String = cfg
-            .get("void_packages.local_repo")
-            .unwrap_or_else(|_| "hostdir/binpkgs".into());
+        let local_repo_rel_s: String = l
+            .get("void_packages.local_repo")?
+            .unwrap_or_else(|| "hostdir/binpkgs".into());
         let local_repo_rel = PathBuf::from(local_repo_rel_s);
 
         // void_packages.use_nonfree (default true)
-        let use_nonfree: bool = cfg.get("void_packages.use_nonfree").unwrap_or(true);
+        let use_nonfree: bool = l.get("void_packages.use_nonfree")?.unwrap_or(true);
+
+        // xbps.purge_kernels (default false)
+        let purge_kernels: bool = l.get("xbps.purge_kernels")?.unwrap_or(false);
+
+        // xbps.check_shlibs (default false)
+        let check_shlibs: bool = l.get("xbps.check_shlibs")?.unwrap_or(false);
+
+        // xbps.check_news (default false)
+        let check_news: bool = l.get("xbps.check_news")?.unwrap_or(false);
+
+        // xbps.ignore_packages (default empty)
+        let ignore_packages: Vec<String> = l.get("xbps.ignore_packages")?.unwrap_or_default();
+
+        // xbps.protected_packages (default base-system, xbps)
+        let protected_packages: Vec<String> = l
+            .get("xbps.protected_packages")?
+            .unwrap_or_else(|| DEFAULT_PROTECTED_PACKAGES.map(String::from).to_vec());
+
+        // xbps.root (optional; unset/empty means /)
+        let root: Option<PathBuf> = l
+            .get::<String>("xbps.root")?
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .map(|s| l.path("xbps.root", &s));
+
+        // xbps.mirror (optional; unset/empty keeps the xbps.d mirrors)
+        let mirror: Option<String> = l
+            .get::<String>("xbps.mirror")?
+            .map(|s| s.trim().trim_end_matches('/').to_string())
+            .filter(|s| !s.is_empty());
+
+        // privilege.command (optional; unset/empty means auto-detect)
+        let privilege_command: Option<String> = l
+            .get::<String>("privilege.command")?
+            .filter(|s| !s.trim().is_empty());
+
+        // source.default_remote (default true)
+        let default_remote: bool = l.get("source.default_remote")?.unwrap_or(true);
+
+        // source.profile (default "default")
+        let build_profile: String = l
+            .get("source.profile")?
+            .unwrap_or_else(|| "default".into());
+
+        // source.profiles.names + source.profiles.<name>.path/local_repo/arch/use_nonfree
+        let profile_names: Vec<String> = l.get("source.profiles.names")?.unwrap_or_default();
+        let source_profiles: Vec<SourceProfile> = profile_names
+            .into_iter()
+            .map(|name| {
+                let key = |k: &str| format!("source.profiles.{name}.{k}");
+                let opt = |k: &str| l.config::<String>(&key(k)).filter(|s| !s.trim().is_empty());
+                SourceProfile {
+                    path: opt("path").map(PathBuf::from),
+                    local_repo: opt("local_repo").map(PathBuf::from),
+                    arch: opt("arch"),
+                    use_nonfree: l.config(&key("use_nonfree")),
+                    name,
+                }
+            })
+            .collect();
+
+        // source.overlays (default empty; `~/` is the home directory)
+        let source_overlays: Vec<PathBuf> = l
+            .get::<Vec<String>>("source.overlays")?
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| match (s.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => l.path("source.overlays", s),
+            })
+            .collect();
+
+        // source.worktree_max_age_days (default 30; 0 keeps them)
+        let worktree_max_age_days: u64 = l
+            .get("source.worktree_max_age_days")?
+            .unwrap_or(DEFAULT_WORKTREE_MAX_AGE_DAYS);
+
+        // build.jobs (optional; unset/0 means xbps-src's default)
+        let build_jobs: Option<usize> = l
+            .get::<usize>("build.jobs")?
+            .filter(|n| *n > 0);
+
+        // build.env (default empty)
+        let build_env: Vec<String> = l.get("build.env")?.unwrap_or_default();
+
+        // build.chroot_cmd (optional; unset/empty means xbps-src's default)
+        let chroot_cmd: Option<String> = l
+            .get::<String>("build.chroot_cmd")?
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+
+        // build.container_image (optional; unset picks void-buildroot-glibc/musl)
+        let container_image: Option<String> = l
+            .get::<String>("build.container_image")?
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+
+        // cache.repodata_ttl / cache.git_ttl seconds (default 600) + cache.enabled (default true)
+        let repodata_ttl_secs: u64 = l
+            .get("cache.repodata_ttl")?
+            .unwrap_or(crate::cache::DEFAULT_SYNC_TTL_SECS);
+        let git_ttl_secs: u64 = l
+            .get("cache.git_ttl")?
+            .unwrap_or(crate::cache::DEFAULT_SYNC_TTL_SECS);
+        let cache_enabled: bool = l.get("cache.enabled")?.unwrap_or(true);
+
+        // push.names + push.<name>.dest/reindex/sign_key/signed_by (default none)
+        let push_names: Vec<String> = l.get("push.names")?.unwrap_or_default();
+        let push_destinations: Vec<PushDest> = push_names
+            .into_iter()
+            .filter_map(|name| {
+                let key = |k: &str| format!("push.{name}.{k}");
+                let opt = |k: &str| l.config::<String>(&key(k)).filter(|s| !s.trim().is_empty());
+                Some(PushDest {
+                    dest: opt("dest")?,
+                    reindex: l.config(&key("reindex")).unwrap_or(true),
+                    sign_key: opt("sign_key"),
+                    signed_by: opt("signed_by"),
+                    name,
+                })
+            })
+            .collect();
 
         Ok(Self {
             debug,
+            notify,
+            notify_after_min,
             void_packages_path,
             local_repo_rel,
             use_nonfree,
+            purge_kernels,
+            check_shlibs,
+            check_news,
+            ignore_packages,
+            protected_packages,
+            root,
+            mirror,
+            privilege_command,
+            default_remote,
+            build_profile,
+            source_profiles,
+            source_overlays,
+            worktree_max_age_days,
+            build_jobs,
+            build_env,
+            chroot_cmd,
+            container_image,
+            push_destinations,
+            repodata_ttl_secs,
+            git_ttl_secs,
+            cache_enabled,
+            origins: l.origins,
         })
     }
 }
 
+/// Parse the config at `path` the way vx loads it, without the project's
+/// .vx.rune: a broken one there mustn't block changes to vx.rune.
+pub fn check(path: &Path) -> Result<Config, String> {
+    Config::from_layers(Layers {
+        rune: Some(parse(path)?),
+        project: None,
+        env: true,
+        origins: BTreeMap::new(),
+    })
+}
+
+/// Parse a project's .vx.rune; `vx config check` reports it on its own.
+pub fn check_project(path: &Path) -> Result<(), String> {
+    parse(path).map(|_| ())
+}
+
 fn bootstrap_sentinel_path(config_path: &Path) -> Result<PathBuf, String> {
     let dir = config_path
         .parent()
@@ -151,7 +709,7 @@ fn write_default_config(path: &Path) -> Result<(), String> {
     Ok(())
 }
 
-fn default_config_text() -> String {
+pub fn default_config_text() -> String {
     // Keep this aligned with the shipped example config.
     // Intentionally does NOT hard-require void_packages.path because VX supports VX_VOIDPKGS / --voidpkgs.
     r#"@author "Dustin Pilgrim"
@@ -159,6 +717,11 @@ fn default_config_text() -> String {
 
 base:
   debug false
+
+  # desktop notification (notify-send) when `vx up -a` or a source build
+  # that ran for at least notify_after minutes finishes
+  #notify true
+  #notify_after 10
 end
 
 # Optional. Only needed if you want `vx src ...` without setting VX_VOIDPKGS or using --voidpkgs.
@@ -172,7 +735,167 @@ void_packages:
   # if true, and a `nonfree/` repo exists under local_repo, VX will add it as -R too
   use_nonfree true
 end
+
+xbps:
+  # remove stale kernel series after `vx up` (same as `vx up --purge-kernels`)
+  purge_kernels false
+
+  # before `vx up`, check no installed package loses a shared library (same as `vx up --check-shlibs`)
+  check_shlibs false
+
+  # before `vx up`, show Void news posted since the last update (same as `vx up --news`)
+  check_news false
+
+  # packages `vx up` should leave alone (one-off: `vx up --ignore <pkg>`)
+  #ignore_packages ["linux6.6", "firefox"]
+
+  # packages `vx rm` refuses to remove without --force-protected
+  # (the running kernel is always protected)
+  #protected_packages ["base-system", "xbps"]
+
+  # manage a chroot/container instead of / (one-off: `vx --root <dir> ...`)
+  #root "/mnt"
+
+  # fetch the xbps.d repositories from this mirror instead, without touching
+  # /etc/xbps.d (`vx mirrors --best --user` sets it)
+  #mirror "https://repo-fi.voidlinux.org"
+end
+
+privilege:
+  # sudo, doas, run0 or none; auto-detected when unset
+  #command "doas"
+end
+
+source:
+  # build `vx src add/up/build` from upstream/master; false uses your checkout
+  # (one-off: --remote / --local)
+  default_remote true
+
+  # build profile: default, glibc, musl or one of profiles.names below (one-off: --profile musl);
+  # glibc/musl use masterdir-<arch>, and hostdir-<arch> when not the host's libc
+  #profile "musl"
+
+  # more void-packages checkouts, picked with `vx src --profile <name> ...`
+  # (or made the default with `profile "<name>"` above)
+  profiles:
+    #names ["work", "rpi"]
+
+    #work:
+    #  path "$env.HOME/work/void-packages"
+    #  local_repo "hostdir/binpkgs"
+    #  use_nonfree false
+    #end
+
+    #rpi:
+    #  path "$env.HOME/rpi/void-packages"
+    #  # own masterdir-<arch>, and hostdir-<arch> for a foreign arch
+    #  arch "aarch64"
+    #end
+  end
+
+  # named masterdirs for `--masterdir <name>`; `vx src masterdir create/remove`
+  # keeps this list
+  masterdirs:
+    #names ["musl"]
+
+    #musl:
+    #  path "$env.HOME/void-packages/masterdir-musl"
+    #  arch "x86_64-musl"
+    #end
+  end
+
+  # directories of personal templates (<dir>/srcpkgs/<pkg>/template) copied into
+  # the upstream worktree before building; your checkout is never touched
+  #overlays ["$env.HOME/my-templates"]
+
+  # remove upstream worktrees (~/.cache/vx/worktrees) unused for this many days; 0 keeps them
+  worktree_max_age_days 30
+end
+
+build:
+  # parallel jobs for xbps-src (one-off: -j 16); unset uses etc/conf or 1
+  #jobs 16
+
+  # extra etc/conf settings vx keeps in void-packages and its upstream worktree
+  #env ["XBPS_CCACHE=yes", "XBPS_CHECK_PKGS=no"]
+
+  # how xbps-src enters the build chroot (XBPS_CHROOT_CMD): uunshare, uchroot,
+  # bwrap, or ethereal for throwaway containers; `vx doctor` checks it works
+  #chroot_cmd "bwrap"
+
+  # image for `vx src build --container` (default: void-buildroot for the arch)
+  #container_image "ghcr.io/void-linux/void-buildroot-glibc:latest"
+end
+
+cache:
+  # seconds a repodata sync (xbps-install -S) stays fresh (one-off: --fresh)
+  repodata_ttl 600
+
+  # seconds a void-packages upstream fetch stays fresh
+  git_ttl 600
+
+  # false ignores every cached sync, fetch and lookup (same as --fresh each run)
+  enabled true
+end
+
+push:
+  # destinations for `vx src push <name>` (rsync targets: host:/path or a directory)
+  #names ["mirror"]
+
+  #mirror:
+  #  dest "builder@mirror.lan:/srv/void/binpkgs"
+  #  # re-index the repositories there after copying
+  #  reindex true
+  #  # sign them with this key (a path on the destination)
+  #  #sign_key "/home/builder/.xbps/privkey.pem"
+  #  #signed_by "Builder <builder@mirror.lan>"
+  #end
+end
 "#
     .to_string()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{env_value, parse, Layers};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn project_config_only_picks_the_checkout() {
+        let dir = std::env::temp_dir().join(format!("vx-project-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".vx.rune");
+        std::fs::write(
+            &path,
+            "void_packages:\n  path \".\"\nend\nxbps:\n  root \"/mnt\"\nend\n",
+        )
+        .unwrap();
+
+        let mut l = Layers {
+            rune: None,
+            project: Some((parse(&path).unwrap(), dir.clone())),
+            env: false,
+            origins: BTreeMap::new(),
+        };
+        assert_eq!(l.get::<String>("void_packages.path").unwrap().as_deref(), Some("."));
+        assert_eq!(l.get::<String>("xbps.root").unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn env_values_take_the_type_of_the_setting() {
+        assert_eq!(env_value::<bool>("true"), Some(true));
+        assert_eq!(env_value::<bool>("yes"), Some(true));
+        assert_eq!(env_value::<bool>("0"), Some(false));
+        assert_eq!(env_value::<usize>("16"), Some(16));
+        assert_eq!(env_value::<usize>("many"), None);
+        assert_eq!(env_value::<String>("doas").as_deref(), Some("doas"));
+        assert_eq!(env_value::<String>("1").as_deref(), Some("1"));
+        assert_eq!(
+            env_value::<Vec<String>>("linux6.6, firefox"),
+            Some(vec!["linux6.6".to_string(), "firefox".to_string()])
+        );
+        assert_eq!(env_value::<Vec<String>>(r#"["a","b"]"#), Some(vec!["a".into(), "b".into()]));
+    }
+}
@@ -0,0 +1,277 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cache,
+    cli::CacheCmd,
+    core::{
+        source::{distfiles::size_of, git, worktree},
+        xbps::{format_age, format_size},
+    },
+    log::Log,
+};
+use serde::Serialize;
+use std::{
+    fs,
+    path::PathBuf,
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub fn dispatch(log: &Log, cmd: CacheCmd) -> ExitCode {
+    match cmd {
+        CacheCmd::Status => status(log),
+        CacheCmd::Show { key } => show(log, &key),
+        CacheCmd::Clear {
+            worktrees,
+            stamps,
+            dry_run,
+        } => {
+            // Neither flag clears both.
+            let both = !worktrees && !stamps;
+            clear(log, stamps || both, worktrees || both, dry_run)
+        }
+    }
+}
+
+/// `600` -> `10m`: the largest whole unit.
+fn format_ttl(secs: u64) -> String {
+    match secs {
+        s if s >= 86400 && s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s >= 3600 && s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s >= 60 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StampRow {
+    key: Option<String>,
+    marked: u64,
+    ttl_secs: Option<u64>,
+    fresh: Option<bool>,
+    command: Option<String>,
+    /// Whether something computed was kept with it (`vx cache show <key>`).
+    payload: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct WorktreeRow {
+    path: PathBuf,
+    source: Option<PathBuf>,
+    last_used: u64,
+    bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheStatus {
+    dir: PathBuf,
+    bytes: u64,
+    stamps: Vec<StampRow>,
+    worktrees: Vec<WorktreeRow>,
+}
+
+/// `vx cache status`: what is cached, how old it is and how much room it takes.
+fn status(log: &Log) -> ExitCode {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let dir = cache::vx_cache_dir();
+    let s = CacheStatus {
+        bytes: size_of(&dir),
+        stamps: cache::entries()
+            .into_iter()
+            .map(|e| StampRow {
+                fresh: e.ttl_secs.map(|t| !cache::force_fresh() && now.saturating_sub(e.created) <= t),
+                key: e.key,
+                marked: e.created,
+                ttl_secs: e.ttl_secs,
+                command: e.command,
+                payload: e.payload.is_some(),
+            })
+            .collect(),
+        worktrees: git::list_worktrees()
+            .into_iter()
+            .map(|w| WorktreeRow {
+                bytes: size_of(&w.bucket),
+                path: w.worktree(),
+                source: w.source,
+                last_used: w.last_used,
+            })
+            .collect(),
+        dir,
+    };
+
+    if log.json {
+        log.emit_json(&s);
+        return ExitCode::SUCCESS;
+    }
+
+    println!("cache: {} ({})", s.dir.display(), format_size(s.bytes));
+    if s.stamps.is_empty() {
+        println!("stamps: none");
+    } else {
+        println!("stamps:");
+        let w = s.stamps.iter().filter_map(|r| r.key.as_ref()).map(|k| k.len()).max().unwrap_or(0);
+        for r in &s.stamps {
+            let ttl = match (r.ttl_secs, r.fresh) {
+                (Some(t), Some(true)) => format!("ttl {}, fresh", format_ttl(t)),
+                (Some(t), _) => format!("ttl {}, stale", format_ttl(t)),
+                (None, _) => "ttl unknown".to_string(),
+            };
+            let key = r.key.as_deref().unwrap_or("(older stamp)");
+            let mut extra = String::new();
+            if let Some(c) = &r.command {
+                extra.push_str(&format!("  by `{c}`"));
+            }
+            if r.payload {
+                extra.push_str("  (+data)");
+            }
+            println!("  {key:<w$}  {:<10}  {ttl}{extra}", format_age(r.marked));
+        }
+    }
+
+    if s.worktrees.is_empty() {
+        println!("worktrees: none");
+    } else {
+        let total: u64 = s.worktrees.iter().map(|w| w.bytes).sum();
+        println!("worktrees: {} ({})", s.worktrees.len(), format_size(total));
+        for w in &s.worktrees {
+            let from = w
+                .source
+                .as_ref()
+                .map_or("(unknown)".to_string(), |p| p.display().to_string());
+            println!(
+                "  {:>9}  {:<10}  {}\n    from {from}",
+                format_size(w.bytes),
+                format_age(w.last_used),
+                w.path.display()
+            );
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// `vx cache show <key>`: one entry, with what was kept with it (for
+/// `xbps.update-plan`, the last update plan, without syncing again).
+fn show(log: &Log, key: &str) -> ExitCode {
+    let Some(e) = cache::entry(key) else {
+        log.error(format!("nothing cached under {key} (see `vx cache status`)"));
+        return ExitCode::from(1);
+    };
+    if log.json {
+        log.emit_json(&e);
+        return ExitCode::SUCCESS;
+    }
+
+    println!("key: {key}");
+    println!("marked: {}", format_age(e.created));
+    if let Some(t) = e.ttl_secs {
+        println!("ttl: {}", format_ttl(t));
+    }
+    if let Some(c) = &e.command {
+        println!("by: {c}");
+    }
+    if let Some(p) = &e.payload {
+        match serde_json::to_string_pretty(p) {
+            Ok(text) => println!("{text}"),
+            Err(e) => log.warn(format!("failed to encode the cached data: {e}")),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// What `vx cache clear --json` removed, or would remove with `--dry-run`.
+#[derive(Debug, Serialize)]
+struct ClearReport {
+    dry_run: bool,
+    /// Keys of the stamps (None for ones an older vx wrote).
+    stamps: Vec<Option<String>>,
+    worktrees: Vec<WorktreeRow>,
+}
+
+/// `vx cache clear [--stamps] [--worktrees] [-n]`.
+fn clear(log: &Log, stamps: bool, worktrees: bool, dry_run: bool) -> ExitCode {
+    let mut failed = false;
+    let mut report = ClearReport {
+        dry_run,
+        stamps: Vec::new(),
+        worktrees: Vec::new(),
+    };
+
+    if stamps {
+        // Not a sync: without it the next vx src would prune worktrees again.
+        let all: Vec<cache::Entry> = cache::entries()
+            .into_iter()
+            .filter(|e| e.key.as_deref() != Some(git::AUTO_PRUNE_KEY))
+            .collect();
+        let mut removed = 0;
+        for st in &all {
+            let key = st.key.as_deref().unwrap_or("(older stamp)");
+            if dry_run {
+                if !log.json {
+                    println!("would remove stamp {key}");
+                }
+                report.stamps.push(st.key.clone());
+                continue;
+            }
+            if log.verbose && !log.quiet {
+                log.exec(format!("rm {}", st.path.display()));
+            }
+            match fs::remove_file(&st.path) {
+                Ok(()) => {
+                    removed += 1;
+                    report.stamps.push(st.key.clone());
+                }
+                Err(e) => {
+                    log.warn(format!("failed to remove {}: {e}", st.path.display()));
+                    failed = true;
+                }
+            }
+        }
+        if !dry_run {
+            log.info(format!("vx: removed {removed} stamp(s); the next sync and fetch run again."));
+        }
+    }
+
+    if worktrees {
+        if log.json {
+            for w in git::list_worktrees() {
+                let row = WorktreeRow {
+                    bytes: size_of(&w.bucket),
+                    path: w.worktree(),
+                    source: w.source.clone(),
+                    last_used: w.last_used,
+                };
+                if !dry_run && let Err(e) = git::remove_worktree(log, &w) {
+                    log.warn(e);
+                    failed = true;
+                    continue;
+                }
+                report.worktrees.push(row);
+            }
+        } else if worktree::prune(log, true, 0, dry_run) != ExitCode::SUCCESS {
+            failed = true;
+        }
+    }
+
+    if log.json {
+        log.emit_json(&report);
+    }
+    if failed { ExitCode::from(1) } else { ExitCode::SUCCESS }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_ttl;
+
+    #[test]
+    fn ttl_in_the_largest_whole_unit() {
+        assert_eq!(format_ttl(600), "10m");
+        assert_eq!(format_ttl(6 * 3600), "6h");
+        assert_eq!(format_ttl(86400), "1d");
+        assert_eq!(format_ttl(90), "90s");
+        assert_eq!(format_ttl(0), "0s");
+    }
+}
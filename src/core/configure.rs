@@ -0,0 +1,856 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cli::ConfigCmd,
+    config::{self, Config},
+    core::{
+        repo::xbps_arch,
+        source::{container, profile::PROFILES},
+    },
+    log::Log,
+    paths::{project_config_path, user_config_path},
+    privilege::{in_path, Escalation},
+};
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::Path,
+    process::{Command, ExitCode},
+};
+
+/// A `key value` line (or an array spanning lines) of a rune file.
+#[derive(Debug)]
+struct Entry {
+    /// Dotted path: `source.default_remote`.
+    key: String,
+    first: usize,
+    last: usize,
+    /// A `#key value` example left commented out.
+    commented: bool,
+}
+
+/// A `name:` ... `end` block.
+#[derive(Debug)]
+struct Section {
+    path: String,
+    start: usize,
+    end: usize,
+}
+
+fn is_key(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether a commented-out line's rest reads as a value rather than prose.
+fn looks_like_value(v: &str) -> bool {
+    matches!(v, "true" | "false")
+        || v.parse::<f64>().is_ok()
+        || (v.len() > 1 && v.starts_with('"') && v.ends_with('"'))
+        || (v.starts_with('[') && v.ends_with(']'))
+}
+
+fn scan(lines: &[&str]) -> (Vec<Entry>, Vec<Section>) {
+    let mut entries = Vec::new();
+    let mut sections = Vec::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut opened: Vec<usize> = Vec::new();
+    // Commented-out blocks (`#work:` ... `#end`) are examples of dynamic
+    // sections; nothing inside them is a key of the enclosing one.
+    let mut commented_blocks = 0usize;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let t = lines[i].trim();
+        let (body, commented) = match t.strip_prefix('#') {
+            Some(b) => (b.trim(), true),
+            None => (t, false),
+        };
+        let at = i;
+        i += 1;
+
+        if body.is_empty() || body.starts_with('@') {
+            continue;
+        }
+        if body == "end" {
+            if commented {
+                commented_blocks = commented_blocks.saturating_sub(1);
+            } else if !stack.is_empty() {
+                sections.push(Section {
+                    path: stack.join("."),
+                    start: opened.pop().unwrap_or(at),
+                    end: at,
+                });
+                stack.pop();
+            }
+            continue;
+        }
+        if let Some(name) = body.strip_suffix(':').filter(|n| is_key(n)) {
+            if commented {
+                commented_blocks += 1;
+            } else {
+                stack.push(name);
+                opened.push(at);
+            }
+            continue;
+        }
+        if commented && commented_blocks > 0 {
+            continue;
+        }
+
+        let split = body.find(|c: char| c.is_whitespace() || c == '[').unwrap_or(body.len());
+        let (key, value) = (&body[..split], body[split..].trim());
+        if !is_key(key) || value.is_empty() || (commented && !looks_like_value(value)) {
+            continue;
+        }
+        let mut last = at;
+        if !commented && value.starts_with('[') && !value.contains(']') {
+            while i < lines.len() && !lines[i].contains(']') {
+                i += 1;
+            }
+            last = i.min(lines.len() - 1);
+            i += 1;
+        }
+        let mut path = stack.join(".");
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(key);
+        entries.push(Entry {
+            key: path,
+            first: at,
+            last,
+            commented,
+        });
+    }
+    (entries, sections)
+}
+
+/// The value of `e` exactly as written, arrays joined onto one line.
+fn raw_value(lines: &[&str], e: &Entry) -> String {
+    let name = e.key.rsplit('.').next().unwrap_or(&e.key);
+    let raw: Vec<&str> = lines[e.first..=e.last].iter().map(|l| l.trim()).collect();
+    let raw = raw.join(" ");
+    raw[raw.find(name).map_or(0, |i| i + name.len())..].trim().to_string()
+}
+
+fn unquote(v: &str) -> String {
+    match v.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(s) if !s.contains('"') => s.to_string(),
+        _ => v.to_string(),
+    }
+}
+
+/// Value of `key` as written (quotes of a plain string removed); None when unset.
+fn get_value(text: &str, key: &str) -> Option<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let (entries, _) = scan(&lines);
+    let e = entries.iter().find(|e| e.key == key && !e.commented)?;
+    Some(unquote(&raw_value(&lines, e)))
+}
+
+/// `value` as rune: booleans, numbers, arrays and quoted strings as given,
+/// anything else quoted.
+fn format_value(value: &str) -> String {
+    let v = value.trim();
+    if matches!(v, "true" | "false")
+        || v.parse::<f64>().is_ok()
+        || v.starts_with('[')
+        || (v.len() > 1 && v.starts_with('"') && v.ends_with('"'))
+    {
+        v.to_string()
+    } else {
+        format!("\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// `text` with `key` set to `value`: the existing line is replaced, else a
+/// commented-out example of it is uncommented, else it is added to its
+/// section (created if missing). Everything else is kept as written.
+fn set_value(text: &str, key: &str, value: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let (entries, sections) = scan(&lines);
+    let (section, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let depth = if section.is_empty() { 0 } else { section.split('.').count() };
+    let line = format!("{}{name} {}", "  ".repeat(depth), format_value(value));
+
+    let mut out: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    if let Some(e) = entries.iter().find(|e| e.key == key && !e.commented) {
+        out.splice(e.first..=e.last, [line]);
+    } else if let Some(e) = entries.iter().find(|e| e.key == key) {
+        out[e.first] = line;
+    } else if let Some(s) = sections.iter().find(|s| s.path == section) {
+        out.insert(s.end, line);
+    } else if section.is_empty() {
+        out.push(line);
+    } else {
+        // Open the missing sections inside the deepest one that exists.
+        let parts: Vec<&str> = section.split('.').collect();
+        let (have, at) = (1..parts.len())
+            .rev()
+            .find_map(|n| {
+                let p = parts[..n].join(".");
+                sections.iter().find(|s| s.path == p).map(|s| (n, s.end))
+            })
+            .unwrap_or((0, out.len()));
+        let mut block = Vec::new();
+        if have == 0 && out.last().is_some_and(|l| !l.trim().is_empty()) {
+            block.push(String::new());
+        }
+        for (d, p) in parts.iter().enumerate().skip(have) {
+            block.push(format!("{}{p}:", "  ".repeat(d)));
+        }
+        block.push(line);
+        for d in (have..parts.len()).rev() {
+            block.push(format!("{}end", "  ".repeat(d)));
+        }
+        out.splice(at..at, block);
+    }
+
+    let mut text = out.join("\n");
+    text.push('\n');
+    text
+}
+
+/// `text` without the `section` block (`source.masterdirs.musl`); unchanged
+/// when there is no such block.
+fn remove_section(text: &str, section: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let (_, sections) = scan(&lines);
+    let mut out: Vec<&str> = lines.clone();
+    if let Some(s) = sections.iter().find(|s| s.path == section) {
+        out.drain(s.start..=s.end);
+    }
+    let mut text = out.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Keys the default config sets or shows commented out.
+fn known_keys() -> BTreeSet<String> {
+    let text = config::default_config_text();
+    let lines: Vec<&str> = text.lines().collect();
+    scan(&lines).0.into_iter().map(|e| e.key).collect()
+}
+
+/// What a value written as `v` parses as.
+fn kind(v: &str) -> &'static str {
+    if v.starts_with('[') {
+        "a list"
+    } else if matches!(v, "true" | "false") {
+        "true/false"
+    } else if v.parse::<f64>().is_ok() {
+        "a number"
+    } else {
+        "a string"
+    }
+}
+
+/// Edit distance between `a` and `b`, for spotting misspelled keys.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = (prev + usize::from(ca != *cb)).min(row[j] + 1).min(cur + 1);
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+/// `["a", "b"]` -> a, b.
+fn list_items(v: &str) -> Vec<String> {
+    let inner = v.trim().trim_start_matches('[').trim_end_matches(']');
+    inner
+        .split(',')
+        .map(|s| unquote(s.trim()))
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Something `vx config check` found, and how to fix it.
+#[derive(Debug)]
+struct Finding {
+    /// Only a warning: vx works, maybe not as meant.
+    warn: bool,
+    key: String,
+    problem: String,
+    hint: String,
+}
+
+fn finding(warn: bool, key: &str, problem: String, hint: String) -> Finding {
+    Finding {
+        warn,
+        key: key.to_string(),
+        problem,
+        hint,
+    }
+}
+
+/// Mistakes visible in the text itself, which the loader turns into
+/// defaults without a word: unknown keys, wrongly typed values, profile and
+/// push blocks nothing refers to, and settings that contradict each other.
+fn check_text(text: &str) -> Vec<Finding> {
+    let defaults = config::default_config_text();
+    let default_lines: Vec<&str> = defaults.lines().collect();
+    let known: Vec<(String, &'static str)> = scan(&default_lines)
+        .0
+        .iter()
+        .map(|e| (e.key.clone(), kind(&raw_value(&default_lines, e))))
+        .collect();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let (entries, _) = scan(&lines);
+    let set: Vec<(&str, String)> = entries
+        .iter()
+        .filter(|e| !e.commented)
+        .map(|e| (e.key.as_str(), raw_value(&lines, e)))
+        .collect();
+    let value = |key: &str| set.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_str());
+    let profiles = value("source.profiles.names").map(list_items).unwrap_or_default();
+    let pushes = value("push.names").map(list_items).unwrap_or_default();
+    let masterdirs = value("source.masterdirs.names")
+        .map(list_items)
+        .unwrap_or_default();
+
+    let mut out = Vec::new();
+    for (key, v) in &set {
+        // source.profiles.<name>.<key>, source.masterdirs.<name>.<key> and
+        // push.<name>.<key> are per name.
+        let dynamic = match key.split('.').collect::<Vec<_>>()[..] {
+            ["source", "profiles", name, k] if name != "names" => Some((
+                name,
+                &profiles,
+                "source.profiles.names",
+                match k {
+                    "path" | "local_repo" | "arch" => Some("a string"),
+                    "use_nonfree" => Some("true/false"),
+                    _ => None,
+                },
+            )),
+            ["source", "masterdirs", name, k] if name != "names" => Some((
+                name,
+                &masterdirs,
+                "source.masterdirs.names",
+                match k {
+                    "path" | "arch" => Some("a string"),
+                    _ => None,
+                },
+            )),
+            ["push", name, k] => Some((
+                name,
+                &pushes,
+                "push.names",
+                match k {
+                    "dest" | "sign_key" | "signed_by" => Some("a string"),
+                    "reindex" => Some("true/false"),
+                    _ => None,
+                },
+            )),
+            _ => None,
+        };
+        let want = match dynamic {
+            Some((name, names, names_key, want)) => {
+                if want.is_some() && !names.iter().any(|n| n == name) {
+                    out.push(finding(
+                        false,
+                        key,
+                        format!("{name} is not in {names_key}, so this is ignored"),
+                        format!("add \"{name}\" to {names_key}"),
+                    ));
+                }
+                want
+            }
+            None => known.iter().find(|(k, _)| k == key).map(|(_, want)| *want),
+        };
+        let Some(want) = want else {
+            let close = known
+                .iter()
+                .map(|(k, _)| (distance(k, key), k))
+                .filter(|(d, _)| *d <= 2)
+                .min();
+            let hint = match close {
+                Some((_, k)) => format!("did you mean {k}?"),
+                None => "remove it; vx has no such setting".to_string(),
+            };
+            out.push(finding(false, key, "unknown setting, ignored".to_string(), hint));
+            continue;
+        };
+        let got = kind(v);
+        if got != want {
+            out.push(finding(
+                false,
+                key,
+                format!("{v} is {got}, not {want}; the default is used instead"),
+                format!("vx config set {key} <{}>", want.trim_start_matches("a ")),
+            ));
+        }
+    }
+
+    for name in &profiles {
+        if value(&format!("source.profiles.{name}.path")).is_none() {
+            out.push(finding(
+                true,
+                &format!("source.profiles.{name}"),
+                "no path; the profile builds in the default checkout".to_string(),
+                format!("vx config set source.profiles.{name}.path <void-packages dir>"),
+            ));
+        }
+    }
+    for name in &pushes {
+        if value(&format!("push.{name}.dest")).is_none() {
+            out.push(finding(
+                false,
+                &format!("push.{name}"),
+                "no dest; the destination is dropped".to_string(),
+                format!("vx config set push.{name}.dest <host:/path>"),
+            ));
+        }
+    }
+
+    if let Some(p) = value("source.profile").map(unquote)
+        && !PROFILES.contains(&p.as_str())
+        && !profiles.contains(&p)
+    {
+        out.push(finding(
+            false,
+            "source.profile",
+            format!("no build profile \"{p}\""),
+            format!("use {} or add it to source.profiles.names", PROFILES.join(", ")),
+        ));
+    }
+    if let Some(c) = value("privilege.command").map(unquote)
+        && Escalation::parse(&c).is_none()
+    {
+        out.push(finding(
+            false,
+            "privilege.command",
+            format!("\"{c}\" is not sudo, doas, run0 or none"),
+            "vx config set privilege.command doas (or remove it to auto-detect)".to_string(),
+        ));
+    }
+    if let Some(c) = value("build.chroot_cmd").map(unquote)
+        && !CHROOT_CMDS.contains(&c.as_str())
+    {
+        out.push(finding(
+            true,
+            "build.chroot_cmd",
+            format!("xbps-src has no chroot method \"{c}\""),
+            format!("use one of {}", CHROOT_CMDS.join(", ")),
+        ));
+    }
+    if value("base.notify_after").is_some() && value("base.notify") != Some("true") {
+        out.push(finding(
+            true,
+            "base.notify_after",
+            "has no effect while base.notify is off".to_string(),
+            "vx config set base.notify true".to_string(),
+        ));
+    }
+    out
+}
+
+/// Settings of a project's .vx.rune that vx ignores there.
+fn project_only(text: &str) -> Vec<Finding> {
+    let lines: Vec<&str> = text.lines().collect();
+    scan(&lines)
+        .0
+        .iter()
+        .filter(|e| !e.commented && !config::PROJECT_KEYS.contains(&e.key.as_str()))
+        .map(|e| {
+            finding(
+                true,
+                &e.key,
+                "is ignored in a project's .vx.rune".to_string(),
+                format!("set it in vx.rune; .vx.rune takes only {}", config::PROJECT_KEYS.join(", ")),
+            )
+        })
+        .collect()
+}
+
+/// XBPS_CHROOT_CMD methods xbps-src knows.
+const CHROOT_CMDS: [&str; 5] = ["uunshare", "uchroot", "bwrap", "ethereal", "proot"];
+
+/// A void-packages checkout at `dir` as `key` names it.
+fn check_checkout(out: &mut Vec<Finding>, key: &str, dir: &Path, local_repo: &Path) {
+    if !dir.is_dir() {
+        out.push(finding(
+            false,
+            key,
+            format!("{} does not exist", dir.display()),
+            format!("git clone https://github.com/void-linux/void-packages {}", dir.display()),
+        ));
+        return;
+    }
+    if !dir.join("xbps-src").is_file() {
+        out.push(finding(
+            false,
+            key,
+            format!("{} has no xbps-src; not a void-packages checkout", dir.display()),
+            format!("point {key} at the directory holding xbps-src"),
+        ));
+        return;
+    }
+    let repo = dir.join(local_repo);
+    let arch = xbps_arch();
+    if !repo.join(format!("{arch}-repodata")).is_file() {
+        out.push(finding(
+            true,
+            key,
+            format!("no {arch}-repodata in {}; nothing built there yet", repo.display()),
+            "build something (vx src build <pkg>), or fix void_packages.local_repo".to_string(),
+        ));
+    }
+}
+
+/// Mistakes only the filesystem shows: checkouts, overlays and the root
+/// that aren't there, and tools the config relies on that aren't installed.
+fn check_paths(cfg: &Config) -> Vec<Finding> {
+    let mut out = Vec::new();
+    if let Some(vp) = &cfg.void_packages_path {
+        check_checkout(&mut out, "void_packages.path", vp, &cfg.local_repo_rel);
+    }
+    for p in &cfg.source_profiles {
+        if let Some(dir) = &p.path {
+            let repo = p.local_repo.as_deref().unwrap_or(&cfg.local_repo_rel);
+            check_checkout(&mut out, &format!("source.profiles.{}.path", p.name), dir, repo);
+        }
+    }
+    for dir in &cfg.source_overlays {
+        if !dir.join("srcpkgs").is_dir() {
+            out.push(finding(
+                true,
+                "source.overlays",
+                format!("{} has no srcpkgs/", dir.display()),
+                format!("templates go in {}/srcpkgs/<pkg>/template", dir.display()),
+            ));
+        }
+    }
+    if let Some(root) = &cfg.root
+        && !root.is_dir()
+    {
+        out.push(finding(
+            false,
+            "xbps.root",
+            format!("{} does not exist", root.display()),
+            "create it, or remove xbps.root to manage /".to_string(),
+        ));
+    }
+    if let Some(prog) = cfg
+        .privilege_command
+        .as_deref()
+        .and_then(Escalation::parse)
+        .and_then(Escalation::program)
+        && !in_path(prog)
+    {
+        out.push(finding(
+            false,
+            "privilege.command",
+            format!("{prog} is not installed"),
+            format!("xbps-install {prog}, or pick another command"),
+        ));
+    }
+    if cfg.container_image.is_some() && container::runtime().is_none() {
+        out.push(finding(
+            true,
+            "build.container_image",
+            "neither podman nor docker is installed".to_string(),
+            "xbps-install podman".to_string(),
+        ));
+    }
+    out
+}
+
+/// `vx config check`: what the config says that vx would quietly ignore.
+fn check(log: &Log, path: &Path) -> ExitCode {
+    let text = match fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(_) => {
+            log.info(format!("vx: no config at {}; the defaults are used.", path.display()));
+            return ExitCode::SUCCESS;
+        }
+    };
+
+    let mut found = check_text(&text);
+    // A .vx.rune here overrides vx.rune, so its mistakes count too.
+    if let Some(project) = project_config_path()
+        && let Ok(text) = fs::read_to_string(&project)
+    {
+        let mut own = check_text(&text);
+        own.extend(project_only(&text));
+        if let Err(e) = config::check_project(&project) {
+            own.insert(0, finding(false, ".vx.rune", e, "fix or remove it".to_string()));
+        }
+        found.extend(own.into_iter().map(|mut f| {
+            f.key = format!("{}: {}", project.display(), f.key);
+            f
+        }));
+    }
+    match config::check(path) {
+        Ok(cfg) => found.extend(check_paths(&cfg)),
+        Err(e) => found.insert(
+            0,
+            finding(false, "vx.rune", e, "fix it with `vx config edit`".to_string()),
+        ),
+    }
+
+    if found.is_empty() {
+        log.info(format!("vx: {} is fine.", path.display()));
+        return ExitCode::SUCCESS;
+    }
+    let errors = found.iter().filter(|f| !f.warn).count();
+    for f in &found {
+        if log.quiet && f.warn {
+            continue;
+        }
+        let tag = if f.warn { "warn" } else { "FAIL" };
+        println!("  {tag:<4}  {}: {}", f.key, f.problem);
+        println!("        -> {}", f.hint);
+    }
+    if errors > 0 { ExitCode::from(1) } else { ExitCode::SUCCESS }
+}
+
+fn edit(log: &Log, path: &Path) -> ExitCode {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    if log.verbose && !log.quiet {
+        log.exec(format!("{editor} {}", path.display()));
+    }
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(path)
+        .status();
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => return ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(e) => {
+            log.error(format!("failed to run {editor}: {e}"));
+            return ExitCode::from(1);
+        }
+    }
+    if let Err(e) = config::check(path) {
+        log.warn(e);
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Set `key` in the config at `path` (the default config when there is
+/// none yet). The result has to parse before it replaces the file.
+pub fn write_setting(path: &Path, key: &str, value: &str) -> Result<(), String> {
+    rewrite(path, |text| set_value(text, key, value))
+}
+
+/// Like `write_setting` for several keys at once.
+pub fn write_settings(path: &Path, settings: &[(String, String)]) -> Result<(), String> {
+    rewrite(path, |text| {
+        settings
+            .iter()
+            .fold(text.to_string(), |t, (k, v)| set_value(&t, k, v))
+    })
+}
+
+/// Drop the `section` block from the config at `path`.
+pub fn remove_setting_section(path: &Path, section: &str) -> Result<(), String> {
+    rewrite(path, |text| remove_section(text, section))
+}
+
+fn rewrite(path: &Path, edit: impl FnOnce(&str) -> String) -> Result<(), String> {
+    let text = fs::read_to_string(path).unwrap_or_else(|_| config::default_config_text());
+    let new = edit(&text);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let tmp = path.with_extension("rune.new");
+    fs::write(&tmp, &new).map_err(|e| format!("failed to write {}: {e}", tmp.display()))?;
+    if let Err(e) = config::check(&tmp) {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("not changed: {e}"));
+    }
+    fs::rename(&tmp, path).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+/// `vx config get|set|edit|path|check`. Runs before the config is loaded, so a
+/// broken config can still be fixed with it.
+pub fn dispatch(log: &Log, cmd: &ConfigCmd) -> ExitCode {
+    let path = match user_config_path() {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    let text = || fs::read_to_string(&path).unwrap_or_else(|_| config::default_config_text());
+
+    match cmd {
+        ConfigCmd::Path => {
+            println!("{}", path.display());
+            ExitCode::SUCCESS
+        }
+
+        ConfigCmd::Get { key } => match get_value(&text(), key) {
+            Some(v) => {
+                println!("{v}");
+                ExitCode::SUCCESS
+            }
+            None => {
+                log.warn(format!("{key} is not set."));
+                ExitCode::from(1)
+            }
+        },
+
+        ConfigCmd::Set { key, value } => {
+            let dynamic = key.starts_with("source.profiles.") || key.starts_with("push.");
+            if !key.split('.').all(is_key) {
+                log.error(format!("invalid key: {key}"));
+                return ExitCode::from(2);
+            }
+            if !dynamic && !known_keys().contains(key) {
+                log.warn(format!("{key} is not a setting vx knows; setting it anyway."));
+            }
+
+            if let Err(e) = write_setting(&path, key, value) {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+            log.info(format!("vx: {key} = {}", format_value(value)));
+            ExitCode::SUCCESS
+        }
+
+        ConfigCmd::Edit => {
+            if !path.exists() {
+                if let Some(dir) = path.parent() {
+                    let _ = fs::create_dir_all(dir);
+                }
+                if let Err(e) = fs::write(&path, config::default_config_text()) {
+                    log.error(format!("failed to write {}: {e}", path.display()));
+                    return ExitCode::from(1);
+                }
+            }
+            edit(log, &path)
+        }
+
+        ConfigCmd::Check => check(log, &path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_text, format_value, get_value, remove_section, set_value};
+
+    const TEXT: &str = r#"@author "me"
+base:
+  # notify when done
+  #notify true
+  debug false
+end
+
+source:
+  default_remote true
+  profiles:
+    #names ["work"]
+    #work:
+    #  path "/w"
+    #end
+  end
+  overlays [
+    "/a"
+  ]
+end
+"#;
+
+    #[test]
+    fn get_reads_values_as_written() {
+        assert_eq!(get_value(TEXT, "base.debug").as_deref(), Some("false"));
+        assert_eq!(get_value(TEXT, "source.overlays").as_deref(), Some("[ \"/a\" ]"));
+        assert_eq!(get_value(TEXT, "base.notify"), None);
+        assert_eq!(get_value(TEXT, "source.profiles.path"), None);
+    }
+
+    #[test]
+    fn set_keeps_comments_and_layout() {
+        let t = set_value(TEXT, "base.debug", "true");
+        assert!(t.contains("  debug true\n") && t.contains("  # notify when done\n"));
+
+        // A commented-out example is uncommented in place.
+        let t = set_value(TEXT, "base.notify", "false");
+        assert!(t.contains("  # notify when done\n  notify false\n  debug false\n"));
+
+        let t = set_value(TEXT, "source.overlays", "[\"/b\"]");
+        assert!(t.contains("  overlays [\"/b\"]\nend\n") && !t.contains("\"/a\""));
+
+        let t = set_value(TEXT, "source.worktree_max_age_days", "7");
+        assert!(t.contains("  ]\n  worktree_max_age_days 7\nend\n"));
+
+        let t = set_value(TEXT, "build.jobs", "8");
+        assert!(t.ends_with("end\n\nbuild:\n  jobs 8\nend\n"));
+
+        let t = set_value(TEXT, "source.profiles.work.path", "/w");
+        assert!(t.contains("    #end\n    work:\n      path \"/w\"\n    end\n  end\n"));
+    }
+
+    #[test]
+    fn remove_drops_only_the_block() {
+        let t = set_value(TEXT, "source.profiles.work.path", "/w");
+        let t = remove_section(&t, "source.profiles.work");
+        assert!(t.contains("    #end\n  end\n  overlays [\n"));
+        assert!(t.contains("    #work:\n"));
+        assert_eq!(remove_section(TEXT, "source.profiles.rpi"), TEXT);
+    }
+
+    #[test]
+    fn values_are_quoted_unless_literal() {
+        assert_eq!(format_value("true"), "true");
+        assert_eq!(format_value("16"), "16");
+        assert_eq!(format_value("[\"a\", \"b\"]"), "[\"a\", \"b\"]");
+        assert_eq!(format_value("doas"), "\"doas\"");
+        assert_eq!(format_value("$env.HOME/vp"), "\"$env.HOME/vp\"");
+    }
+
+    #[test]
+    fn check_flags_what_the_loader_ignores() {
+        let text = r#"base:
+  debug "yes"
+  notfy true
+  notify_after 5
+end
+source:
+  profile "work"
+  profiles:
+    names ["rpi"]
+    rpi:
+      path "/r"
+    end
+    old:
+      path "/o"
+    end
+  end
+end
+push:
+  names ["mirror"]
+end
+"#;
+        let found = check_text(text);
+        let keys: Vec<&str> = found.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "base.debug",
+                "base.notfy",
+                "source.profiles.old.path",
+                "push.mirror",
+                "source.profile",
+                "base.notify_after",
+            ]
+        );
+        assert_eq!(found[1].hint, "did you mean base.notify?");
+        assert!(check_text(&crate::config::default_config_text()).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    config::Config,
+    core::source::resolve::{resolve_voidpkgs, SrcResolved},
+    log::Log,
+    privilege::{in_path, is_root},
+};
+use std::{
+    path::PathBuf,
+    process::{Command, ExitCode, Stdio},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    level: Level,
+    msg: String,
+}
+
+/// A harmless command that only succeeds when `method` can set up a chroot
+/// as this user (None: nothing to try unprivileged).
+fn probe_command(method: &str) -> Option<Vec<&'static str>> {
+    match method {
+        "bwrap" => Some(vec!["bwrap", "--unshare-user", "--ro-bind", "/", "/", "true"]),
+        "uunshare" => Some(vec!["unshare", "--user", "--map-root-user", "--mount", "true"]),
+        "proot" => Some(vec!["proot", "-r", "/", "true"]),
+        _ => None,
+    }
+}
+
+fn runs(argv: &[&str]) -> Result<(), String> {
+    let out = Command::new(argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("{}: {e}", argv[0]))?;
+    if out.status.success() {
+        return Ok(());
+    }
+    let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+    Err(if err.is_empty() { format!("`{}` failed", argv.join(" ")) } else { err })
+}
+
+fn check_chroot(res: Option<&SrcResolved>, cfg: Option<&Config>) -> Check {
+    let name = "chroot";
+    let Some(method) = cfg.and_then(|c| c.chroot_cmd.as_deref()) else {
+        return Check {
+            name,
+            level: Level::Ok,
+            msg: "build.chroot_cmd unset; xbps-src picks the method".to_string(),
+        };
+    };
+
+    // xbps-src only knows the styles it ships a script for.
+    if let Some(r) = res {
+        let style = r.voidpkgs.join("common/chroot-style").join(format!("{method}.sh"));
+        if r.voidpkgs.join("common/chroot-style").is_dir() && !style.is_file() {
+            return Check {
+                name,
+                level: Level::Fail,
+                msg: format!("{method}: this void-packages has no {}", style.display()),
+            };
+        }
+    }
+
+    let (level, msg) = match method {
+        "ethereal" if is_root() => (
+            Level::Warn,
+            "ethereal builds straight on this system; only use it in a throwaway container".to_string(),
+        ),
+        "ethereal" => (
+            Level::Fail,
+            "ethereal needs root (and a throwaway container)".to_string(),
+        ),
+        "uchroot" if in_path("xbps-uchroot") => (
+            Level::Ok,
+            "uchroot via xbps-uchroot (setuid, group xbuilder)".to_string(),
+        ),
+        "uchroot" => (Level::Fail, "uchroot: xbps-uchroot not found".to_string()),
+        m => match probe_command(m) {
+            Some(argv) => match runs(&argv) {
+                Ok(()) => (Level::Ok, format!("{m} works unprivileged")),
+                Err(e) => (Level::Fail, format!("{m} does not work here: {e}")),
+            },
+            None => (Level::Warn, format!("{m}: don't know how to test this method")),
+        },
+    };
+    Check { name, level, msg }
+}
+
+fn check_voidpkgs(res: Result<&SrcResolved, &String>) -> Check {
+    let name = "void-packages";
+    match res {
+        Ok(r) if r.voidpkgs.join("xbps-src").is_file() => Check {
+            name,
+            level: Level::Ok,
+            msg: r.voidpkgs.display().to_string(),
+        },
+        Ok(r) => Check {
+            name,
+            level: Level::Fail,
+            msg: format!("no ./xbps-src in {}", r.voidpkgs.display()),
+        },
+        Err(_) => Check {
+            name,
+            level: Level::Warn,
+            msg: "not configured (vx src commands need it)".to_string(),
+        },
+    }
+}
+
+/// `vx doctor`: check the setup source builds depend on.
+pub fn run_doctor(log: &Log, voidpkgs_override: Option<PathBuf>, cfg: Option<&Config>) -> ExitCode {
+    let res = resolve_voidpkgs(voidpkgs_override, cfg);
+    let checks = [
+        check_voidpkgs(res.as_ref()),
+        check_chroot(res.as_ref().ok(), cfg),
+    ];
+
+    let w = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for c in &checks {
+        let tag = match c.level {
+            Level::Ok => "ok",
+            Level::Warn => "warn",
+            Level::Fail => "FAIL",
+        };
+        if !log.quiet || c.level == Level::Fail {
+            println!("  {tag:<4}  {:<w$}  {}", c.name, c.msg);
+        }
+    }
+
+    if checks.iter().any(|c| c.level == Level::Fail) {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::probe_command;
+
+    #[test]
+    fn chroot_methods_have_unprivileged_probes() {
+        assert_eq!(probe_command("bwrap").unwrap()[0], "bwrap");
+        assert_eq!(probe_command("uunshare").unwrap()[0], "unshare");
+        assert_eq!(probe_command("ethereal"), None);
+    }
+}
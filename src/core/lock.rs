@@ -0,0 +1,287 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cli::{CacheCmd, Cmd, MasterdirCmd, RepoCmd, SrcCmd, SrcRepoCmd},
+    log::Log,
+};
+use std::{
+    env,
+    fs::{self, File, OpenOptions, Permissions, TryLockError},
+    io::{self, Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// `<pid>\t<command>` of the vx holding it; the lock itself is a flock, so a
+/// vx that dies releases it.
+const LOCK_FILE: &str = "vx.lock";
+
+/// Shared by every user: two users updating the system at once collide just
+/// like two runs of one user.
+const LOCK_DIR: &str = "/run/lock";
+
+/// Held for the whole run of a mutating command; released on drop.
+pub struct GlobalLock {
+    _file: File,
+}
+
+/// Commands that change the system, its configuration, the managed list or
+/// templates: installs, removals, updates, source installs, ... Plans and dry
+/// runs don't lock.
+fn mutating(cmd: &Cmd) -> Option<&'static str> {
+    match cmd {
+        Cmd::Add { .. } => Some("vx add"),
+        Cmd::Rm { .. } => Some("vx rm"),
+        Cmd::Mark { .. } => Some("vx mark"),
+        Cmd::Kernels {
+            purge: true,
+            dry_run: false,
+            ..
+        } => Some("vx kernels --purge"),
+        Cmd::Clean { dry_run: false, .. } => Some("vx clean"),
+        Cmd::Sync { .. } => Some("vx sync"),
+        Cmd::Mirrors { set: Some(_), .. }
+        | Cmd::Mirrors { best: true, .. }
+        | Cmd::Mirrors { user: true, .. } => Some("vx mirrors"),
+        Cmd::Pkg { .. } => Some("vx pkg"),
+        Cmd::Repo {
+            cmd:
+                RepoCmd::Add { .. }
+                | RepoCmd::Rm { .. }
+                | RepoCmd::Enable { .. }
+                | RepoCmd::Disable { .. },
+        } => Some("vx repo"),
+        Cmd::Confdiff { list: false, .. } => Some("vx confdiff"),
+        Cmd::Up {
+            dry_run: false,
+            check: false,
+            ..
+        } => Some("vx up"),
+        Cmd::Downgrade { .. } => Some("vx downgrade"),
+        Cmd::Rollback { dry_run: false, .. } => Some("vx rollback"),
+        Cmd::Orphans { dry_run: false, .. } => Some("vx orphans"),
+        Cmd::Cache {
+            cmd: CacheCmd::Clear { dry_run: false, .. },
+        } => Some("vx cache clear"),
+        Cmd::Src { cmd, .. } => match cmd {
+            SrcCmd::Add { .. } => Some("vx src add"),
+            SrcCmd::Rm { .. } => Some("vx src rm"),
+            SrcCmd::Up { dry_run: false, .. } => Some("vx src up"),
+            SrcCmd::Import { apply: true, dry_run: false, .. } => Some("vx src import"),
+            SrcCmd::Deps { install_host: true, .. } => Some("vx src deps"),
+            SrcCmd::Build { .. } => Some("vx src build"),
+            SrcCmd::Clean { .. } => Some("vx src clean"),
+            SrcCmd::Zap { .. } => Some("vx src zap"),
+            SrcCmd::Masterdir {
+                cmd: MasterdirCmd::Create { .. } | MasterdirCmd::Remove { .. },
+            } => Some("vx src masterdir"),
+            SrcCmd::PurgeDistfiles { dry_run: false, .. } => Some("vx src purge-distfiles"),
+            SrcCmd::Repo {
+                cmd: SrcRepoCmd::Index | SrcRepoCmd::Clean,
+            } => Some("vx src repo"),
+            SrcCmd::Bump { .. } => Some("vx src bump"),
+            SrcCmd::Edit { .. } => Some("vx src edit"),
+            SrcCmd::Tag { .. } => Some("vx src tag"),
+            SrcCmd::Untag { .. } => Some("vx src untag"),
+            SrcCmd::Pin { .. } => Some("vx src pin"),
+            SrcCmd::Unpin { .. } => Some("vx src unpin"),
+            SrcCmd::Untrack { .. } => Some("vx src untrack"),
+            SrcCmd::Adopt { dry_run: false, .. } => Some("vx src adopt"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `123\tvx src up` -> (123, "vx src up").
+fn parse_holder(text: &str) -> Option<(u32, String)> {
+    let (pid, what) = text.trim().split_once('\t')?;
+    Some((pid.parse().ok()?, what.to_string()))
+}
+
+/// `/run/lock/vx.lock`, or in $TMPDIR where there is no /run/lock.
+fn lock_path() -> PathBuf {
+    let dir = Path::new(LOCK_DIR);
+    if dir.is_dir() {
+        dir.join(LOCK_FILE)
+    } else {
+        env::temp_dir().join(LOCK_FILE)
+    }
+}
+
+/// The lock file, writable by everyone so any user can record holding it.
+/// When another user's umask kept it to themselves it is only read; the
+/// flock works the same. A symlink put in its place (the directory is
+/// world-writable) is refused by the open itself.
+fn open(path: &Path) -> io::Result<File> {
+    // Not truncated: a waiting vx reads who holds it.
+    match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o666)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(path)
+    {
+        Ok(f) => {
+            let _ = f.set_permissions(Permissions::from_mode(0o666));
+            Ok(f)
+        }
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(path),
+        Err(e) => Err(e),
+    }
+}
+
+fn holder(file: &mut File) -> Option<(u32, String)> {
+    let mut text = String::new();
+    file.read_to_string(&mut text).ok()?;
+    parse_holder(&text)
+}
+
+/// Take the global lock for `cmd` when it mutates anything; another vx
+/// holding it is an error right away instead of a second sudo prompt.
+pub fn acquire(cmd: &Cmd) -> Result<Option<GlobalLock>, String> {
+    let Some(what) = mutating(cmd) else {
+        return Ok(None);
+    };
+    let path = lock_path();
+    let mut file = open(&path).map_err(|e| format!("failed to open {}: {e}", path.display()))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let by = match holder(&mut file) {
+                Some((pid, other)) => format!(" (pid {pid}: {other})"),
+                None => String::new(),
+            };
+            return Err(format!(
+                "another vx is running{by}; wait for it to finish, or run `vx unlock` if it is stuck"
+            ));
+        }
+        Err(TryLockError::Error(e)) => return Err(format!("failed to lock {}: {e}", path.display())),
+    }
+
+    let _ = file.set_len(0);
+    let _ = writeln!(file, "{}\t{what}", std::process::id());
+    Ok(Some(GlobalLock { _file: file }))
+}
+
+/// `vx unlock [--force]`: clear the global lock left by a vx that is gone.
+pub fn cmd_unlock(log: &Log, force: bool) -> ExitCode {
+    let path = lock_path();
+    if !path.exists() {
+        log.info("vx: not locked.");
+        return ExitCode::SUCCESS;
+    }
+    let mut file = match open(&path) {
+        Ok(f) => f,
+        Err(e) => {
+            log.error(format!("failed to open {}: {e}", path.display()));
+            return ExitCode::from(1);
+        }
+    };
+
+    if file.try_lock().is_ok() {
+        log.info("vx: not locked.");
+        return ExitCode::SUCCESS;
+    }
+
+    let held = holder(&mut file);
+    let alive = held
+        .as_ref()
+        .is_some_and(|(pid, _)| Path::new("/proc").join(pid.to_string()).exists());
+    if alive && !force {
+        let (pid, what) = held.unwrap_or_default();
+        log.error(format!(
+            "pid {pid} ({what}) still holds the lock; stop it first or use `vx unlock --force`"
+        ));
+        return ExitCode::from(1);
+    }
+
+    // A new lock file is a new lock; whatever held the old one no longer blocks.
+    if let Err(e) = fs::remove_file(&path) {
+        log.error(format!("failed to remove {}: {e}", path.display()));
+        return ExitCode::from(1);
+    }
+    match held {
+        Some((pid, what)) => log.info(format!("vx: removed the lock of pid {pid} ({what}).")),
+        None => log.info("vx: removed the lock."),
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mutating, parse_holder};
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn holder_is_pid_and_command() {
+        assert_eq!(parse_holder("123\tvx src up\n"), Some((123, "vx src up".to_string())));
+        assert_eq!(parse_holder(""), None);
+        assert_eq!(parse_holder("x\tvx up"), None);
+    }
+
+    fn locks(argv: &str) -> bool {
+        let cli = Cli::try_parse_from(format!("vx {argv}").split_whitespace()).unwrap();
+        mutating(&cli.cmd).is_some()
+    }
+
+    #[test]
+    fn mutating_subcommands_lock() {
+        for argv in [
+            "add foo",
+            "rm foo",
+            "up",
+            "sync",
+            "repo add https://example.org/repo",
+            "repo rm example",
+            "repo enable example",
+            "repo disable example",
+            "confdiff",
+            "src deps --install-host foo",
+            "src build foo",
+            "src clean foo",
+            "src zap -y",
+            "src masterdir create x86_64",
+            "src masterdir remove x86_64 -y",
+            "src purge-distfiles -y",
+            "src repo index",
+            "src repo clean",
+            "src bump foo 1.0",
+            "src edit foo",
+            "src tag foo desktop",
+            "src untag foo desktop",
+            "src pin foo",
+            "src unpin foo",
+            "src untrack foo",
+            "src adopt",
+        ] {
+            assert!(locks(argv), "{argv} should lock");
+        }
+    }
+
+    #[test]
+    fn reads_and_dry_runs_do_not_lock() {
+        for argv in [
+            "status",
+            "up -n",
+            "repo list",
+            "confdiff -l",
+            "src deps foo",
+            "src masterdir list",
+            "src purge-distfiles -n",
+            "src repo check",
+            "src adopt -n",
+        ] {
+            assert!(!locks(argv), "{argv} should not lock");
+        }
+    }
+}
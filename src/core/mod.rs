@@ -2,32 +2,79 @@
 // License: MIT
 
 use crate::{
-    cli::{Cli, Cmd, PkgCmd, SrcBuildFlags, SrcCmd},
+    cli::{Cli, Cmd, PkgCmd},
     config::Config,
     log::Log,
 };
 use std::process::ExitCode;
 
+pub mod caches;
+pub mod configure;
+pub mod doctor;
+pub mod lock;
+pub mod news;
+pub mod notify;
 pub mod pkg;
+pub mod repo;
+pub mod review;
 pub mod source;
 pub mod status;
+pub mod sync;
+pub mod version;
 pub mod xbps;
 
 pub fn dispatch(log: &Log, cli: Cli, cfg: Option<Config>) -> ExitCode {
     let voidpkgs_override = cli.voidpkgs.clone();
 
     match cli.cmd {
-        Cmd::Status => status::run_status(log, &cli, cfg.as_ref()),
+        Cmd::Status { resolved } => status::run_status(log, &cli, cfg.as_ref(), resolved),
 
-        Cmd::Search { term } => xbps::search(log, cfg.as_ref(), false, &term),
+        Cmd::Doctor => doctor::run_doctor(log, voidpkgs_override, cfg.as_ref()),
 
-        Cmd::Info { pkg } => xbps::info(log, cfg.as_ref(), &pkg),
+        Cmd::Search {
+            repos,
+            pick,
+            names_only,
+            term,
+        } => {
+            if pick {
+                xbps::search_pick(log, cfg.as_ref(), &term, &repos)
+            } else {
+                xbps::search(log, cfg.as_ref(), false, &term, &repos, names_only)
+            }
+        }
+
+        Cmd::Info { repos, raw, pkg } => xbps::info(log, cfg.as_ref(), &pkg, &repos, raw),
 
         Cmd::Files { pkg } => xbps::files(log, cfg.as_ref(), &pkg),
 
-        Cmd::List { term } => xbps::list(log, cfg.as_ref(), term.as_deref()),
+        Cmd::List {
+            manual,
+            auto,
+            hold,
+            repo,
+            orphans,
+            term,
+        } => xbps::list(
+            log,
+            cfg.as_ref(),
+            term.as_deref(),
+            &xbps::ListOptions {
+                manual,
+                auto,
+                hold,
+                repo,
+                orphans,
+            },
+        ),
+
+        Cmd::Owns { remote, path } => xbps::owns(log, cfg.as_ref(), &path, remote),
 
-        Cmd::Owns { path } => xbps::owns(log, cfg.as_ref(), &path),
+        Cmd::Rdeps { remote, pkg } => xbps::rdeps(log, cfg.as_ref(), &pkg, remote),
+
+        Cmd::Why { pkg } => xbps::why(log, cfg.as_ref(), &pkg),
+
+        Cmd::Mark { auto, manual: _, pkgs } => xbps::mark(log, auto, &pkgs),
 
         Cmd::Add {
             yes,
@@ -89,9 +136,12 @@ pub fn dispatch(log: &Log, cli: Cli, cfg: Option<Config>) -> ExitCode {
             dry_run,
             clean_cache,
             orphans,
+            recursive,
             no_recursive,
+            clean_deps,
             rootdir,
             xbps_verbose,
+            force_protected,
             xbps_args,
             pkgs,
         } => xbps::rm(
@@ -107,27 +157,113 @@ pub fn dispatch(log: &Log, cli: Cli, cfg: Option<Config>) -> ExitCode {
                 dry_run,
                 clean_cache,
                 orphans,
-                recursive: !no_recursive,
+                recursive: recursive || !no_recursive,
                 rootdir,
                 xbps_verbose,
                 xbps_args,
+                force_protected,
+                clean_deps,
             },
             &pkgs,
         ),
 
+        Cmd::Downgrade { yes, pkg, version } => {
+            xbps::downgrade(log, cfg.as_ref(), &pkg, version.as_deref(), yes)
+        }
+
+        Cmd::Kernels {
+            purge,
+            dry_run,
+            yes,
+        } => xbps::kernels(log, cfg.as_ref(), purge, dry_run, yes),
+
+        Cmd::Size { top, pkgs } => xbps::size(log, cfg.as_ref(), &pkgs, top),
+
+        Cmd::Confdiff { list, dir } => xbps::confdiff(log, cfg.as_ref(), dir.as_deref(), list),
+
+        Cmd::History { id, limit } => xbps::history(log, id, limit),
+
+        Cmd::Restartcheck => xbps::restartcheck(log, cfg.as_ref()),
+
+        Cmd::Changelog { limit, pkg } => {
+            source::changelog::run(log, voidpkgs_override, cfg.as_ref(), &pkg, limit)
+        }
+
+        Cmd::WhichTemplate { pkg } => {
+            source::srcpkgs::cmd_which_template(log, voidpkgs_override, cfg.as_ref(), &pkg)
+        }
+
+        Cmd::Rollback { dry_run, yes, id } => {
+            xbps::rollback(log, cfg.as_ref(), id, dry_run, yes)
+        }
+
+        Cmd::Orphans { dry_run, yes } => xbps::orphans(log, cfg.as_ref(), dry_run, yes),
+
+        Cmd::Clean {
+            all,
+            src,
+            dry_run,
+            yes,
+        } => {
+            let c = xbps::clean(log, cfg.as_ref(), all, dry_run, yes);
+            if c != ExitCode::SUCCESS || !src {
+                return c;
+            }
+            source::clean_src(log, voidpkgs_override, cfg.as_ref(), dry_run, yes)
+        }
+
         Cmd::Up {
             all,
             dry_run,
             force,
             yes,
             local,
+            remote,
+            purge_kernels,
+            check_shlibs,
+            news,
+            review,
+            rebuild_broken,
+            changelog,
+            check,
+            list,
+            repos,
+            download_only,
+            ignore,
+            pkgs,
         } => {
-            // remote = true unless --local was passed
-            let remote = !local;
+            let mut up_opts = xbps::UpOptions {
+                yes,
+                dry_run,
+                synced: false,
+                download_only,
+                repositories: repos,
+                ignore: cfg
+                    .as_ref()
+                    .map(|c| c.ignore_packages.clone())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .chain(ignore)
+                    .collect(),
+                pkgs,
+            };
+
+            if check {
+                return xbps::check_updates(log, cfg.as_ref(), list, &up_opts);
+            }
+
+            let remote = source::use_remote(cfg.as_ref(), local, remote);
+            let purge_kernels = purge_kernels || cfg.as_ref().is_some_and(|c| c.purge_kernels);
+            let check_shlibs = check_shlibs || cfg.as_ref().is_some_and(|c| c.check_shlibs);
+            let news = news || cfg.as_ref().is_some_and(|c| c.check_news);
+
+            if news && !log.json && !news::check_before_update(log, yes || dry_run) {
+                return ExitCode::SUCCESS;
+            }
 
             // vx up — system only
             if !all {
-                let sys_plan = match xbps::plan_system_updates_fresh(log, cfg.as_ref()) {
+                let mut sys_plan = match xbps::plan_up(log, cfg.as_ref(), &mut up_opts) {
                     Ok(v) => v,
                     Err(e) => {
                         log.error(e);
@@ -135,24 +271,131 @@ pub fn dispatch(log: &Log, cli: Cli, cfg: Option<Config>) -> ExitCode {
                     }
                 };
 
+                if dry_run && log.json {
+                    log.emit_json(&serde_json::json!({ "system": sys_plan }));
+                    return ExitCode::SUCCESS;
+                }
+
                 if sys_plan.is_empty() {
                     log.info("vx: system already up to date.");
                     return ExitCode::SUCCESS;
                 }
 
-                if dry_run {
-                    println!("system update plan:");
-                    for u in sys_plan {
-                        println!("  {}  {} → {}", u.name, u.from, u.to);
+                if review {
+                    let total = sys_plan.len();
+                    match review::review_up(&mut sys_plan, &mut Vec::new()) {
+                        Ok(true) => {
+                            if sys_plan.len() != total {
+                                up_opts.pkgs = xbps::update_targets(&sys_plan);
+                            }
+                        }
+                        Ok(false) => {
+                            log.info("vx: nothing left to update.");
+                            return ExitCode::SUCCESS;
+                        }
+                        Err(e) => {
+                            log.error(e);
+                            return ExitCode::from(2);
+                        }
                     }
-                    return ExitCode::SUCCESS;
                 }
 
-                return xbps::up_with_yes(log, cfg.as_ref(), yes);
+                if dry_run || download_only {
+                    if download_only {
+                        println!("download plan:");
+                    } else {
+                        println!("system update plan:");
+                    }
+                    for u in &sys_plan {
+                        println!("  {}  {} → {}{}", u.name, u.from, u.to, source::action_note(&u.action));
+                    }
+                    if download_only {
+                        println!("{}", xbps::download_summary(&sys_plan));
+                    } else {
+                        println!("{}", xbps::plan_totals(&sys_plan));
+                    }
+                    if dry_run {
+                        if check_shlibs {
+                            let _ = xbps::shlib_guard(log, &sys_plan, &up_opts, true);
+                        }
+                        if changelog {
+                            source::changelog::annotate_plan(
+                                log,
+                                voidpkgs_override,
+                                cfg.as_ref(),
+                                &sys_plan,
+                            );
+                        }
+                        return ExitCode::SUCCESS;
+                    }
+                }
+
+                if check_shlibs
+                    && !download_only
+                    && let Err(c) = xbps::shlib_guard(log, &sys_plan, &up_opts, false)
+                {
+                    return c;
+                }
+
+                let removed = if download_only {
+                    Default::default()
+                } else {
+                    source::removed_shlibs_for_managed(log, &sys_plan, &up_opts.repositories)
+                };
+
+                let c = xbps::up(log, cfg.as_ref(), &up_opts);
+                if c != ExitCode::SUCCESS || download_only {
+                    return c;
+                }
+                if purge_kernels {
+                    let c = xbps::kernels(log, cfg.as_ref(), true, false, yes);
+                    if c != ExitCode::SUCCESS {
+                        return c;
+                    }
+                }
+                xbps::restart_hint(log);
+                return source::rebuild_after_update(
+                    log,
+                    voidpkgs_override,
+                    cfg.as_ref(),
+                    &removed,
+                    rebuild_broken,
+                    remote,
+                );
+            }
+
+            // vx up -a — system + source, planned side by side (the dry-run
+            // plan and the void-packages fetch are both mostly waiting on the
+            // network). The repodata sync may ask for a password, so it runs
+            // first rather than under the git fetch output.
+            if !dry_run {
+                match xbps::sync_repodata(log, &up_opts.repositories) {
+                    Ok(()) => up_opts.synced = true,
+                    Err(e) => {
+                        log.error(e);
+                        return ExitCode::from(1);
+                    }
+                }
             }
+            let (sys_plan, src_plan) = std::thread::scope(|s| {
+                let src = s.spawn(|| {
+                    source::plan_src_updates(
+                        log,
+                        voidpkgs_override.clone(),
+                        cfg.as_ref(),
+                        None,
+                        force,
+                        remote,
+                    )
+                });
+                let sys = xbps::plan_up(log, cfg.as_ref(), &mut up_opts);
+                let src = src
+                    .join()
+                    .unwrap_or_else(|_| Err("source update planning panicked".to_string()));
+                (sys, src)
+            });
 
-            // vx up -a — system + source
-            let sys_plan = match xbps::plan_system_updates_fresh(log, cfg.as_ref()) {
+            let mut sys_plan = match sys_plan {
                 Ok(v) => v,
                 Err(e) => {
                     log.error(e);
@@ -160,20 +403,23 @@ pub fn dispatch(log: &Log, cli: Cli, cfg: Option<Config>) -> ExitCode {
                 }
             };
 
-            let src_plan = match source::plan_src_updates(
-                log,
-                voidpkgs_override.clone(),
-                cfg.as_ref(),
-                None,
-                force,
-                remote,
-            ) {
+            let mut src_plan = match src_plan {
                 Ok(v) => v,
                 Err(e) => {
                     log.error(e);
                     return ExitCode::from(1);
                 }
             };
+            let planned_head = if remote {
+                source::planned_upstream_head(voidpkgs_override.clone(), cfg.as_ref())
+            } else {
+                None
+            };
+
+            if dry_run && log.json {
+                log.emit_json(&serde_json::json!({ "system": sys_plan, "source": src_plan }));
+                return ExitCode::SUCCESS;
+            }
 
             source::print_up_all_summary(log, &sys_plan, &src_plan);
 
@@ -185,44 +431,154 @@ pub fn dispatch(log: &Log, cli: Cli, cfg: Option<Config>) -> ExitCode {
             }
 
             if dry_run {
+                if check_shlibs && !sys_plan.is_empty() {
+                    let _ = xbps::shlib_guard(log, &sys_plan, &up_opts, true);
+                }
+                if changelog {
+                    source::changelog::annotate_plan(
+                        log,
+                        voidpkgs_override,
+                        cfg.as_ref(),
+                        &sys_plan,
+                    );
+                }
                 return ExitCode::SUCCESS;
             }
 
-            if !yes && !source::confirm_once("Proceed?") {
+            if review {
+                let total = sys_plan.len();
+                match review::review_up(&mut sys_plan, &mut src_plan) {
+                    Ok(true) => {
+                        if sys_plan.len() != total {
+                            up_opts.pkgs = xbps::update_targets(&sys_plan);
+                        }
+                    }
+                    Ok(false) => {
+                        log.info("vx: nothing left to update.");
+                        return ExitCode::SUCCESS;
+                    }
+                    Err(e) => {
+                        log.error(e);
+                        return ExitCode::from(2);
+                    }
+                }
+            }
+
+            if check_shlibs
+                && !sys_plan.is_empty()
+                && let Err(c) = xbps::shlib_guard(log, &sys_plan, &up_opts, false)
+            {
+                return c;
+            }
+
+            // With work on both sides, let the user apply just one of them.
+            let scope = if yes {
+                Some(review::UpScope::Both)
+            } else if !sys_plan.is_empty() && !src_plan.is_empty() {
+                review::confirm_scope()
+            } else {
+                source::confirm_once("Proceed?").then_some(review::UpScope::Both)
+            };
+            let Some(scope) = scope else {
                 log.info("aborted.");
                 return ExitCode::SUCCESS;
+            };
+            match scope {
+                review::UpScope::System => src_plan.clear(),
+                review::UpScope::Source => sys_plan.clear(),
+                review::UpScope::Both => {}
             }
 
+            let removed =
+                source::removed_shlibs_for_managed(log, &sys_plan, &up_opts.repositories);
+
             // System first, then source.
             if !sys_plan.is_empty() {
-                let c = xbps::up_with_yes(log, cfg.as_ref(), true);
+                let c = xbps::up(
+                    log,
+                    cfg.as_ref(),
+                    &xbps::UpOptions {
+                        yes: true,
+                        ..up_opts
+                    },
+                );
                 if c != ExitCode::SUCCESS {
                     return c;
                 }
+                if purge_kernels {
+                    let c = xbps::kernels(log, cfg.as_ref(), true, false, yes);
+                    if c != ExitCode::SUCCESS {
+                        return c;
+                    }
+                }
+                xbps::restart_hint(log);
+            }
+
+            let mut pkgs_to_update: Vec<String> = src_plan.iter().map(|p| p.name.clone()).collect();
+
+            // Managed packages linked against sonames the system update removed.
+            let broken: Vec<_> = source::rebuild_suggestions(&removed)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|r| !pkgs_to_update.contains(&r.name))
+                .collect();
+            if rebuild_broken && scope != review::UpScope::System {
+                pkgs_to_update.extend(broken.into_iter().map(|r| r.name));
+            } else {
+                source::print_rebuild_suggestions(log, &broken);
             }
 
-            let pkgs_to_update: Vec<String> = src_plan.iter().map(|p| p.name.clone()).collect();
             if pkgs_to_update.is_empty() {
                 return ExitCode::SUCCESS;
             }
 
-            source::dispatch_src(
+            source::apply_up_all(
                 log,
                 voidpkgs_override,
                 cfg.as_ref(),
-                SrcCmd::Up {
-                    dry_run: false,
-                    force: true,
-                    yes: true,
-                    local: !remote,
-                    build: SrcBuildFlags::default(),
-                    pkgs: pkgs_to_update,
-                    xbps_src_args: Vec::new(),
-                },
+                &pkgs_to_update,
+                remote,
+                planned_head.as_deref(),
+                yes,
             )
         }
 
-        Cmd::Src { cmd } => source::dispatch_src(log, voidpkgs_override, cfg.as_ref(), cmd),
+        Cmd::Sync { repos } => sync::run_sync(log, voidpkgs_override, cfg.as_ref(), &repos),
+
+        Cmd::Mirrors {
+            list,
+            set,
+            best,
+            user,
+            timeout,
+            yes,
+        } => repo::mirrors::cmd_mirrors(
+            log,
+            cfg.as_ref(),
+            &repo::mirrors::MirrorsOptions {
+                list,
+                set,
+                best,
+                user,
+                timeout,
+                yes,
+            },
+        ),
+
+        Cmd::Repo { cmd } => repo::dispatch_repo(log, cfg.as_ref(), cmd),
+
+        Cmd::Completions { shell } => crate::completions::print(&shell),
+
+        Cmd::Unlock { force } => lock::cmd_unlock(log, force),
+
+        Cmd::Cache { cmd } => caches::dispatch(log, cmd),
+
+        // Handled in app::run before the config is loaded.
+        Cmd::Config { .. } => unreachable!(),
+
+        Cmd::Src { profile, cmd } => {
+            source::dispatch_src(log, voidpkgs_override, cfg.as_ref(), profile.as_deref(), cmd)
+        }
 
         Cmd::Pkg {
             name,
@@ -235,8 +591,37 @@ pub fn dispatch(log: &Log, cli: Cli, cfg: Option<Config>) -> ExitCode {
         } => {
             if let Some(sub) = cmd {
                 match sub {
-                    PkgCmd::New { name } => {
-                        pkg::pkg_new(log, voidpkgs_override, cfg.as_ref(), &name)
+                    PkgCmd::New {
+                        interactive,
+                        from_url,
+                        version,
+                        build_style,
+                        short_desc,
+                        license,
+                        homepage,
+                        name,
+                    } => {
+                        let spec = pkg::NewTemplate {
+                            version,
+                            build_style,
+                            short_desc,
+                            license,
+                            homepage,
+                            distfile: from_url,
+                        };
+                        match name {
+                            Some(name) if spec.is_empty() && !interactive => {
+                                pkg::pkg_new(log, voidpkgs_override, cfg.as_ref(), &name)
+                            }
+                            name => pkg::pkg_scaffold(
+                                log,
+                                voidpkgs_override,
+                                cfg.as_ref(),
+                                name.as_deref(),
+                                spec,
+                                interactive,
+                            ),
+                        }
                     }
                 }
             } else if gensum {
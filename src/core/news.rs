@@ -0,0 +1,196 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{cache, core::source::confirm_once, history, log::Log};
+use std::{
+    fs,
+    process::{Command, Stdio},
+};
+
+/// Void Linux news/announcements (Atom).
+pub const NEWS_FEED_URL: &str = "https://voidlinux.org/atom.xml";
+
+/// How long a fetched feed is reused (6h). --fresh refetches.
+const NEWS_TTL_SECS: u64 = 6 * 60 * 60;
+const NEWS_KEY: &str = "void.news";
+const NEWS_FILE: &str = "news.xml";
+
+/// Entries shown when there is no journaled `vx up` to compare against.
+const NEWS_FIRST_RUN: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewsEntry {
+    pub title: String,
+    pub link: Option<String>,
+    /// Unix time of <published> (or <updated>).
+    pub time: u64,
+}
+
+/// Text inside `<tag ...>...</tag>`, with CDATA and the common entities undone.
+fn element(xml: &str, tag: &str) -> Option<String> {
+    let open = xml.find(&format!("<{tag}"))?;
+    let start = open + xml[open..].find('>')? + 1;
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    let v = xml[start..end].trim();
+    let v = v
+        .strip_prefix("<![CDATA[")
+        .and_then(|v| v.strip_suffix("]]>"))
+        .unwrap_or(v);
+    Some(
+        v.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// `2024-03-01T12:00:00+01:00` / `...Z` -> unix time.
+pub fn parse_rfc3339(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let num = |a: usize, b: usize| s.get(a..b)?.parse::<i64>().ok();
+    let days = days_from_civil(num(0, 4)?, num(5, 7)?, num(8, 10)?);
+    let secs = num(11, 13)? * 3600 + num(14, 16)? * 60 + num(17, 19)?;
+
+    // Skip fractional seconds, then read the zone.
+    let zone = s.get(19..)?.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match zone {
+        "" | "Z" | "z" => 0,
+        z => {
+            let sign = if z.starts_with('-') { -1 } else { 1 };
+            let h = z.get(1..3)?.parse::<i64>().ok()?;
+            let m = z.get(4..6)?.parse::<i64>().ok()?;
+            sign * (h * 3600 + m * 60)
+        }
+    };
+    u64::try_from(days * 86400 + secs - offset).ok()
+}
+
+/// Entries of an Atom feed, newest first.
+pub fn parse_feed(xml: &str) -> Vec<NewsEntry> {
+    let mut out: Vec<NewsEntry> = xml
+        .split("<entry")
+        .skip(1)
+        .filter_map(|chunk| {
+            let chunk = &chunk[..chunk.find("</entry>")?];
+            let time = element(chunk, "published")
+                .or_else(|| element(chunk, "updated"))
+                .and_then(|t| parse_rfc3339(&t))?;
+            let link = chunk.find("<link").and_then(|i| {
+                let rest = &chunk[i..];
+                let start = rest.find("href=\"")? + "href=\"".len();
+                let end = start + rest[start..].find('"')?;
+                Some(rest[start..end].to_string())
+            });
+            Some(NewsEntry {
+                title: element(chunk, "title")?,
+                link,
+                time,
+            })
+        })
+        .collect();
+    out.sort_by_key(|e| std::cmp::Reverse(e.time));
+    out
+}
+
+/// The feed, from cache when fresh; falls back to a stale copy when offline.
+fn load_feed(log: &Log) -> Option<String> {
+    let path = cache::data_path(NEWS_FILE);
+    if cache::is_fresh(NEWS_KEY, NEWS_TTL_SECS)
+        && let Ok(text) = fs::read_to_string(&path)
+    {
+        if log.verbose && !log.quiet {
+            log.exec(format!("cache hit: news feed (ttl={NEWS_TTL_SECS}s)"));
+        }
+        return Some(text);
+    }
+
+    if log.verbose && !log.quiet {
+        log.exec(format!("curl {NEWS_FEED_URL}"));
+    }
+    let out = Command::new("curl")
+        .args(["-fsSL", "--max-time", "10", NEWS_FEED_URL])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    match out {
+        Ok(o) if o.status.success() && !o.stdout.is_empty() => {
+            let text = String::from_utf8_lossy(&o.stdout).into_owned();
+            if cache::write_data(NEWS_FILE, &text).is_ok() {
+                cache::mark(NEWS_KEY, NEWS_TTL_SECS);
+            }
+            Some(text)
+        }
+        _ => {
+            log.warn("could not fetch Void news; showing cached entries if any");
+            fs::read_to_string(&path).ok()
+        }
+    }
+}
+
+/// Before `vx up`: print news posted since the last journaled update.
+/// Returns false when the user chose not to continue.
+pub fn check_before_update(log: &Log, yes: bool) -> bool {
+    let Some(xml) = load_feed(log) else {
+        return true;
+    };
+    let entries = parse_feed(&xml);
+    let unread: Vec<&NewsEntry> = match history::last_update() {
+        Some(since) => entries.iter().filter(|e| e.time > since).collect(),
+        None => entries.iter().take(NEWS_FIRST_RUN).collect(),
+    };
+    if unread.is_empty() {
+        return true;
+    }
+
+    println!("Void news since your last update:");
+    for e in &unread {
+        println!("  {}  {}", crate::core::xbps::format_age(e.time), e.title);
+        if let Some(link) = &e.link {
+            println!("      {link}");
+        }
+    }
+
+    if yes {
+        return true;
+    }
+    if !confirm_once("Continue with the update?") {
+        log.info("aborted.");
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_feed, parse_rfc3339};
+
+    #[test]
+    fn feed_entries_are_parsed_newest_first() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+<title>Void Linux</title>
+<entry><title type="html">Changes to Python 3 &amp; pip</title>
+<link href="https://voidlinux.org/news/2024/05/python.html" rel="alternate"/>
+<published>2024-05-08T00:00:00+00:00</published></entry>
+<entry><title>New Images!</title>
+<link href="https://voidlinux.org/news/2025/02/new-images.html"/>
+<published>2025-02-02T12:30:00+02:00</published></entry>
+</feed>"#;
+        let e = parse_feed(xml);
+        assert_eq!(e.len(), 2);
+        assert_eq!(e[0].title, "New Images!");
+        assert_eq!(e[1].title, "Changes to Python 3 & pip");
+        assert_eq!(e[1].time, 1715126400);
+        assert_eq!(e[0].time, parse_rfc3339("2025-02-02T10:30:00Z").unwrap());
+    }
+}
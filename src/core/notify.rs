@@ -0,0 +1,130 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cli::{Cmd, SrcCmd},
+    config::Config,
+    core::source::{buildlog, stats::format_duration},
+    privilege::in_path,
+};
+use std::{
+    process::{Command, ExitCode, Stdio},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// A long-running command to report on once it returns (`base.notify`).
+pub struct Watch {
+    label: String,
+    started: Instant,
+    started_secs: u64,
+    after: Duration,
+}
+
+/// Watch `vx up -a` and source builds when notifications are on.
+pub fn watch(cmd: &Cmd, cfg: Option<&Config>) -> Option<Watch> {
+    let cfg = cfg.filter(|c| c.notify)?;
+    let label = match cmd {
+        Cmd::Up { all: true, dry_run: false, .. } => "vx up -a".to_string(),
+        Cmd::Src { cmd, .. } => match cmd {
+            SrcCmd::Build { pkgs, .. } => format!("vx src build {}", pkgs.join(" ")),
+            SrcCmd::Add { pkgs, .. } => format!("vx src add {}", pkgs.join(" ")),
+            SrcCmd::Up { dry_run: false, pkgs, .. } => format!("vx src up {}", pkgs.join(" ")),
+            SrcCmd::Check { pkg, .. } => format!("vx src check {pkg}"),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(Watch {
+        label: label.trim_end().to_string(),
+        started: Instant::now(),
+        started_secs: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        after: Duration::from_secs(cfg.notify_after_min * 60),
+    })
+}
+
+/// Notification title and body.
+fn message(label: &str, ok: bool, elapsed: u64, built: usize, failed: usize) -> (String, String) {
+    let title = format!("{label} {}", if ok { "finished" } else { "failed" });
+    let mut body = format!("after {}", format_duration(elapsed));
+    if built + failed > 0 {
+        body.push_str(&format!("; {built} package(s) built"));
+        if failed > 0 {
+            body.push_str(&format!(", {failed} failed"));
+        }
+    }
+    (title, body)
+}
+
+fn send(title: &str, body: &str, ok: bool) {
+    let quiet = |c: &mut Command| {
+        c.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    };
+    if in_path("notify-send") {
+        let mut c = Command::new("notify-send");
+        c.args(["-a", "vx", "-u", if ok { "normal" } else { "critical" }, title, body]);
+        quiet(&mut c);
+        let _ = c.status();
+        return;
+    }
+    // Straight to the notification daemon over D-Bus.
+    let mut c = Command::new("gdbus");
+    c.args([
+        "call",
+        "--session",
+        "--dest",
+        "org.freedesktop.Notifications",
+        "--object-path",
+        "/org/freedesktop/Notifications",
+        "--method",
+        "org.freedesktop.Notifications.Notify",
+        "vx",
+        "0",
+        "",
+        title,
+        body,
+        "[]",
+        "{}",
+        "-1",
+    ]);
+    quiet(&mut c);
+    let _ = c.status();
+}
+
+impl Watch {
+    /// Notify if the command ran for at least `base.notify_after` minutes.
+    pub fn finish(self, code: ExitCode) {
+        let elapsed = self.started.elapsed();
+        if elapsed < self.after {
+            return;
+        }
+        let logs: Vec<_> = buildlog::list(None)
+            .into_iter()
+            .filter(|l| l.time >= self.started_secs)
+            .collect();
+        let built = logs.iter().filter(|l| l.ok == Some(true)).count();
+        let failed = logs.iter().filter(|l| l.ok == Some(false)).count();
+        let ok = code == ExitCode::SUCCESS;
+        let (title, body) = message(&self.label, ok, elapsed.as_secs(), built, failed);
+        send(&title, &body, ok);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::message;
+
+    #[test]
+    fn message_counts_builds() {
+        assert_eq!(
+            message("vx up -a", true, 600, 0, 0),
+            ("vx up -a finished".to_string(), "after ~10 min".to_string())
+        );
+        assert_eq!(
+            message("vx src up", false, 3600, 3, 1).1,
+            "after ~1 h; 3 package(s) built, 1 failed"
+        );
+    }
+}
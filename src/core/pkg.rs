@@ -3,12 +3,43 @@
 
 use crate::{config::Config, log::Log};
 use std::{
-    env,
     fs,
-    path::PathBuf,
+    io::{self, Write},
+    path::{Path, PathBuf},
     process::{Command, ExitCode, Stdio},
 };
 
+/// build_style values `vx pkg new --build-style` knows; `python3` means python3-pep517.
+pub const BUILD_STYLES: [&str; 9] = [
+    "cargo",
+    "go",
+    "python3",
+    "python3-pep517",
+    "python3-module",
+    "gnu-configure",
+    "gnu-makefile",
+    "meson",
+    "cmake",
+];
+
+/// Fields for a template generated by `vx pkg new` instead of xnew.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewTemplate {
+    pub version: Option<String>,
+    pub build_style: Option<String>,
+    pub short_desc: Option<String>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+    /// Distfile URL; the version in it becomes `${version}`.
+    pub distfile: Option<String>,
+}
+
+impl NewTemplate {
+    pub fn is_empty(&self) -> bool {
+        *self == NewTemplate::default()
+    }
+}
+
 pub fn pkg_new(
     log: &Log,
     voidpkgs_override: Option<PathBuf>,
@@ -179,18 +210,200 @@ pub fn pkg_gensum(
     ExitCode::SUCCESS
 }
 
-fn resolve_voidpkgs_path(
+/// Name and version from a release tarball URL:
+/// `.../foo-1.2.tar.gz` -> (foo, 1.2), `github.com/o/foo/archive/refs/tags/v1.2.tar.gz` -> (foo, 1.2).
+pub fn infer_from_url(url: &str) -> Option<(Option<String>, String)> {
+    let path = url.split(['?', '#']).next()?.trim_end_matches('/');
+    let file = path.rsplit('/').next()?;
+    let stem = [".tar.gz", ".tar.xz", ".tar.bz2", ".tar.zst", ".tar.lz", ".tgz", ".txz", ".zip"]
+        .iter()
+        .find_map(|ext| file.strip_suffix(ext))?;
+
+    let digit_at = |i: usize| stem[i..].starts_with(|c: char| c.is_ascii_digit());
+    if let Some(i) = stem.match_indices('-').map(|(i, _)| i).find(|i| digit_at(i + 1)) {
+        let version = stem[i + 1..].to_string();
+        return Some((Some(stem[..i].to_string()), version));
+    }
+
+    // Bare tag (v1.2 / 1.2): the name is the GitHub/GitLab/Codeberg repository.
+    let version = stem.strip_prefix('v').unwrap_or(stem);
+    if !version.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let repo = forge_repo(url).map(|(_, repo)| repo);
+    Some((repo, version.to_string()))
+}
+
+/// (`https://host/owner/repo`, repo) for a forge URL.
+fn forge_repo(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("https://")?;
+    let mut parts = rest.split('/');
+    let host = parts.next()?;
+    if !["github.com", "gitlab.com", "codeberg.org"].contains(&host) {
+        return None;
+    }
+    let (owner, repo) = (parts.next()?, parts.next()?);
+    Some((format!("https://{host}/{owner}/{repo}"), repo.to_string()))
+}
+
+/// Template text for `name`; unset fields are left for the packager to fill in.
+fn render_template(name: &str, t: &NewTemplate, maintainer: &str) -> String {
+    let version = t.version.as_deref().unwrap_or("");
+    let style = match t.build_style.as_deref() {
+        Some("python3") => Some("python3-pep517"),
+        other => other,
+    };
+
+    let mut out = format!("# Template file for '{name}'\npkgname={name}\nversion={version}\nrevision=1\n");
+    if let Some(s) = style {
+        out.push_str(&format!("build_style={s}\n"));
+    }
+    if style == Some("go")
+        && let Some((home, _)) = t.distfile.as_deref().and_then(forge_repo)
+    {
+        out.push_str(&format!("go_import_path=\"{}\"\n", home.trim_start_matches("https://")));
+    }
+    let field = |v: &Option<String>| v.clone().unwrap_or_default();
+    out.push_str(&format!("short_desc=\"{}\"\n", field(&t.short_desc)));
+    out.push_str(&format!("maintainer=\"{maintainer}\"\n"));
+    out.push_str(&format!("license=\"{}\"\n", field(&t.license)));
+    out.push_str(&format!("homepage=\"{}\"\n", field(&t.homepage)));
+    if let Some(url) = &t.distfile {
+        // Only the path: a version like `1` may well appear in the host name.
+        let url = match url.find("://").and_then(|i| url[i + 3..].find('/').map(|j| i + 3 + j)) {
+            Some(i) if !version.is_empty() => {
+                format!("{}{}", &url[..i], url[i..].replace(version, "${version}"))
+            }
+            _ => url.clone(),
+        };
+        out.push_str(&format!("distfiles=\"{url}\"\n"));
+        out.push_str("checksum=badbadbadbadbadbadbadbadbadbadbadbadbadbadbadbadbadbadbadbadbadb\n");
+    }
+    out
+}
+
+fn prompt(label: &str, default: Option<&str>) -> Option<String> {
+    match default {
+        Some(d) if !d.is_empty() => print!("{label} [{d}]: "),
+        _ => print!("{label}: "),
+    }
+    io::stdout().flush().ok();
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).ok()?;
+    let line = line.trim();
+    if line.is_empty() {
+        default.map(String::from).filter(|d| !d.is_empty())
+    } else {
+        Some(line.to_string())
+    }
+}
+
+/// `Name <email>` from git config in void-packages, as xnew does.
+fn git_maintainer(voidpkgs: &Path) -> String {
+    let get = |key: &str| {
+        Command::new("git")
+            .args(["config", key])
+            .current_dir(voidpkgs)
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .unwrap_or_default()
+    };
+    let (name, email) = (get("user.name"), get("user.email"));
+    if name.is_empty() || email.is_empty() {
+        return "Orphaned <orphan@voidlinux.org>".to_string();
+    }
+    format!("{name} <{email}>")
+}
+
+/// `vx pkg new [name] [--from-url URL] [--build-style ...] [-i]`: write a
+/// filled-in template (instead of xnew's skeleton) and run xgensum on it.
+pub fn pkg_scaffold(
+    log: &Log,
     voidpkgs_override: Option<PathBuf>,
     cfg: Option<&Config>,
-) -> Result<PathBuf, String> {
-    if let Some(p) = voidpkgs_override {
-        if !p.as_os_str().is_empty() {
-            return Ok(p);
+    name: Option<&str>,
+    mut t: NewTemplate,
+    interactive: bool,
+) -> ExitCode {
+    let voidpkgs = match resolve_voidpkgs_path(voidpkgs_override, cfg) {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(2);
         }
+    };
+
+    if !voidpkgs.join("xbps-src").is_file() {
+        log.error(format!(
+            "not a void-packages directory (missing ./xbps-src): {}",
+            voidpkgs.display()
+        ));
+        return ExitCode::from(2);
+    }
+
+    let inferred = t.distfile.as_deref().and_then(infer_from_url);
+    if t.distfile.is_some() && inferred.is_none() {
+        log.warn("could not infer name/version from the URL");
+    }
+    let (url_name, url_version) = inferred.unwrap_or_default();
+    if t.version.is_none() && !url_version.is_empty() {
+        t.version = Some(url_version);
+    }
+    if t.homepage.is_none() {
+        t.homepage = t.distfile.as_deref().and_then(forge_repo).map(|(home, _)| home);
+    }
+
+    let mut name = name.map(str::trim).map(String::from).or(url_name);
+    if interactive {
+        name = prompt("name", name.as_deref());
+        t.version = prompt("version", t.version.as_deref());
+        t.build_style = prompt(
+            &format!("build_style ({})", BUILD_STYLES.join(", ")),
+            t.build_style.as_deref(),
+        );
+        t.short_desc = prompt("short_desc", t.short_desc.as_deref());
+        t.license = prompt("license (SPDX)", t.license.as_deref());
+        t.homepage = prompt("homepage", t.homepage.as_deref());
+        t.distfile = prompt("distfile URL", t.distfile.as_deref());
+    }
+
+    let Some(name) = name.filter(|n| !n.is_empty()) else {
+        log.error("usage: vx pkg new <name> (or --from-url with a name-version tarball)");
+        return ExitCode::from(2);
+    };
+    if let Some(s) = t.build_style.as_deref()
+        && !BUILD_STYLES.contains(&s)
+    {
+        log.error(format!("unknown build style '{s}' (expected one of: {})", BUILD_STYLES.join(", ")));
+        return ExitCode::from(2);
+    }
+
+    let dir = voidpkgs.join("srcpkgs").join(&name);
+    if dir.exists() {
+        log.error(format!("{} already exists", dir.display()));
+        return ExitCode::from(2);
     }
+    let tpl = dir.join("template");
+    let text = render_template(&name, &t, &git_maintainer(&voidpkgs));
+    if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(&tpl, text)) {
+        log.error(format!("failed to write {}: {e}", tpl.display()));
+        return ExitCode::from(1);
+    }
+    log.info(format!("vx: created {}", tpl.display()));
+
+    if t.distfile.is_none() {
+        return ExitCode::SUCCESS;
+    }
+    pkg_gensum(log, Some(voidpkgs), None, &name, false, false, None, None)
+}
 
-    if let Ok(v) = env::var("VX_VOIDPKGS") {
-        let p = PathBuf::from(v);
+fn resolve_voidpkgs_path(
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+) -> Result<PathBuf, String> {
+    if let Some(p) = voidpkgs_override {
         if !p.as_os_str().is_empty() {
             return Ok(p);
         }
@@ -213,3 +426,31 @@ fn resolve_voidpkgs_path(
             .to_string(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{infer_from_url, render_template, NewTemplate};
+
+    #[test]
+    fn tarball_urls_give_name_and_version() {
+        assert_eq!(
+            infer_from_url("https://example.org/dl/foo-bar-1.2.3.tar.gz"),
+            Some((Some("foo-bar".to_string()), "1.2.3".to_string()))
+        );
+        assert_eq!(
+            infer_from_url("https://github.com/o/tool/archive/refs/tags/v0.4.tar.gz"),
+            Some((Some("tool".to_string()), "0.4".to_string()))
+        );
+        assert_eq!(infer_from_url("https://example.org/README"), None);
+
+        let t = NewTemplate {
+            version: Some("0.4".to_string()),
+            build_style: Some("go".to_string()),
+            distfile: Some("https://github.com/o/tool/archive/refs/tags/v0.4.tar.gz".to_string()),
+            ..NewTemplate::default()
+        };
+        let text = render_template("tool", &t, "Jane <jane@example.org>");
+        assert!(text.contains("version=0.4\nrevision=1\nbuild_style=go\ngo_import_path=\"github.com/o/tool\"\n"));
+        assert!(text.contains("distfiles=\"https://github.com/o/tool/archive/refs/tags/v${version}.tar.gz\"\n"));
+    }
+}
@@ -0,0 +1,171 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const ETC_XBPS_D: &str = "/etc/xbps.d";
+pub const SHARE_XBPS_D: &str = "/usr/share/xbps.d";
+
+/// One `repository=` line from an xbps.d conf file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoEntry {
+    pub url: String,
+    pub enabled: bool,
+    pub file: PathBuf,
+}
+
+/// `repository=<url>` -> (url, true); `#repository=<url>` -> (url, false).
+fn parse_line(line: &str) -> Option<(String, bool)> {
+    let line = line.trim();
+    let (body, enabled) = match line.strip_prefix('#') {
+        Some(rest) => (rest.trim_start(), false),
+        None => (line, true),
+    };
+    let url = body.strip_prefix("repository")?.trim_start();
+    let url = url.strip_prefix('=')?.trim();
+    if url.is_empty() {
+        return None;
+    }
+    Some((url.to_string(), enabled))
+}
+
+/// Repositories in a conf file, in order, with enabled state.
+pub fn parse_conf(text: &str) -> Vec<(String, bool)> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+/// Comment out / uncomment every line for `url`. None if `url` is not in `text`.
+pub fn set_enabled(text: &str, url: &str, enabled: bool) -> Option<String> {
+    let mut found = false;
+    let mut out = String::with_capacity(text.len() + 1);
+    for line in text.lines() {
+        match parse_line(line) {
+            Some((u, _)) if u == url => {
+                found = true;
+                if enabled {
+                    out.push_str(&format!("repository={url}"));
+                } else {
+                    out.push_str(&format!("#repository={url}"));
+                }
+            }
+            _ => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    found.then_some(out)
+}
+
+/// Drop every line for `url`. None if `url` is not in `text`.
+pub fn remove_repo(text: &str, url: &str) -> Option<String> {
+    let mut found = false;
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        if matches!(parse_line(line), Some((u, _)) if u == url) {
+            found = true;
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    found.then_some(out)
+}
+
+/// Conf file name for a repo added by vx: `/srv/repo` -> `50-vx-srv-repo.conf`.
+pub fn conf_name_for(url: &str) -> String {
+    let stripped = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    let mut slug = String::new();
+    for c in stripped.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    format!("50-vx-{}.conf", if slug.is_empty() { "repo" } else { slug })
+}
+
+/// All repositories from `share` and `etc`, where a file in `etc` overrides the
+/// file with the same name in `share` (xbps.d semantics). Ordered by file name.
+pub fn load_all(etc: &Path, share: &Path) -> Vec<RepoEntry> {
+    let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in [share, etc] {
+        let Ok(rd) = fs::read_dir(dir) else {
+            continue;
+        };
+        for ent in rd.flatten() {
+            let name = ent.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".conf") {
+                files.insert(name, ent.path());
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    for file in files.into_values() {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for (url, enabled) in parse_conf(&text) {
+            out.push(RepoEntry {
+                url,
+                enabled,
+                file: file.clone(),
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{conf_name_for, parse_conf, remove_repo, set_enabled};
+
+    const CONF: &str = "# main mirror\n\
+                        repository=https://repo-default.voidlinux.org/current\n\
+                        #repository=https://repo-default.voidlinux.org/current/nonfree\n\
+                        ignorepkg=sudo\n";
+
+    #[test]
+    fn parses_enabled_and_commented_repositories() {
+        assert_eq!(
+            parse_conf(CONF),
+            vec![
+                ("https://repo-default.voidlinux.org/current".to_string(), true),
+                (
+                    "https://repo-default.voidlinux.org/current/nonfree".to_string(),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn toggles_and_removes_without_touching_other_lines() {
+        let url = "https://repo-default.voidlinux.org/current/nonfree";
+        let on = set_enabled(CONF, url, true).unwrap();
+        assert!(on.contains(&format!("\nrepository={url}\n")));
+        assert!(on.starts_with("# main mirror\n"));
+        assert!(on.ends_with("ignorepkg=sudo\n"));
+
+        let off = set_enabled(&on, url, false).unwrap();
+        assert_eq!(off, CONF);
+
+        let gone = remove_repo(CONF, url).unwrap();
+        assert_eq!(parse_conf(&gone).len(), 1);
+        assert!(set_enabled(CONF, "https://example.org", true).is_none());
+    }
+
+    #[test]
+    fn conf_name_is_a_slug_of_the_url() {
+        assert_eq!(
+            conf_name_for("https://repo.example.org/current/"),
+            "50-vx-repo.example.org-current.conf"
+        );
+        assert_eq!(conf_name_for("/srv/xbps repo"), "50-vx-srv-xbps-repo.conf");
+    }
+}
@@ -0,0 +1,226 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{core::xbps, log::Log, rootdir};
+use std::{collections::BTreeMap, fs, path::PathBuf, process::ExitCode, process::Stdio};
+
+/// Where xbps stores imported repository keys (`<fingerprint>.plist`).
+pub const KEYS_DIR: &str = "/var/db/xbps/keys";
+
+/// One repository row of `xbps-query -vL`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RepoSig {
+    pub url: String,
+    pub signed: bool,
+    pub signed_by: Option<String>,
+    pub fingerprint: Option<String>,
+}
+
+/// A trusted key from KEYS_DIR.
+#[derive(Debug, Clone)]
+pub struct TrustedKey {
+    pub fingerprint: String,
+    pub signed_by: Option<String>,
+    pub file: PathBuf,
+}
+
+/// Parse `xbps-query -vL`:
+///
+/// ```text
+///  15563 https://repo-default.voidlinux.org/current (RSA signed)
+///       Signed-by: Void Linux
+///       4096 60:ae:0c:d6:f0:95:17:80:bc:93:46:7a:89:af:a3:2d
+///     12 /home/me/void-packages/hostdir/binpkgs (RSA unsigned)
+/// ```
+pub fn parse_repo_list(text: &str) -> Vec<RepoSig> {
+    let mut out: Vec<RepoSig> = Vec::new();
+    for line in text.lines() {
+        let t = line.trim();
+        if let Some(who) = t.strip_prefix("Signed-by:") {
+            if let Some(r) = out.last_mut() {
+                r.signed_by = Some(who.trim().to_string());
+            }
+            continue;
+        }
+
+        let mut it = t.split_whitespace();
+        let (Some(first), Some(second)) = (it.next(), it.next()) else {
+            continue;
+        };
+        if first.parse::<u64>().is_err() {
+            continue;
+        }
+
+        // `<bits> <fingerprint>` follows Signed-by; a repo row has a URL or path.
+        if second.contains(':') && !second.contains('/') {
+            if let Some(r) = out.last_mut() {
+                r.fingerprint = Some(second.to_string());
+            }
+            continue;
+        }
+
+        let rest = it.collect::<Vec<_>>().join(" ");
+        out.push(RepoSig {
+            url: second.to_string(),
+            signed: rest.contains("signed") && !rest.contains("unsigned"),
+            ..Default::default()
+        });
+    }
+    out
+}
+
+/// `xbps-query -vL` with extra repositories; empty when it can't run.
+pub fn repo_sigs(repos: &[String]) -> Vec<RepoSig> {
+    let out = rootdir::command("xbps-query")
+        .args(xbps::repository_args(repos))
+        .arg("-vL")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    match out {
+        Ok(o) => parse_repo_list(&String::from_utf8_lossy(&o.stdout)),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// `signature-by` value from a key plist.
+pub fn plist_signed_by(text: &str) -> Option<String> {
+    let after = &text[text.find("<key>signature-by</key>")? + "<key>signature-by</key>".len()..];
+    let start = after.find("<string>")? + "<string>".len();
+    let end = after[start..].find("</string>")? + start;
+    let v = after[start..end].trim();
+    (!v.is_empty()).then(|| v.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&"))
+}
+
+pub fn trusted_keys() -> Vec<TrustedKey> {
+    let Ok(rd) = fs::read_dir(rootdir::path(KEYS_DIR)) else {
+        return Vec::new();
+    };
+    let mut out: Vec<TrustedKey> = rd
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "plist"))
+        .filter_map(|file| {
+            let fingerprint = file.file_stem()?.to_string_lossy().into_owned();
+            let signed_by = fs::read_to_string(&file).ok().and_then(|t| plist_signed_by(&t));
+            Some(TrustedKey {
+                fingerprint,
+                signed_by,
+                file,
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+    out
+}
+
+/// `vx repo keys`
+pub fn cmd_keys(log: &Log) -> ExitCode {
+    let keys = trusted_keys();
+    let sigs = repo_sigs(&[]);
+
+    let mut users: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for s in &sigs {
+        if let Some(fp) = &s.fingerprint {
+            users.entry(fp.as_str()).or_default().push(s.url.as_str());
+        }
+    }
+
+    if keys.is_empty() {
+        log.info(format!("vx: no trusted keys in {}.", rootdir::path(KEYS_DIR).display()));
+    } else {
+        println!("trusted keys ({}):", keys.len());
+        for k in &keys {
+            println!("  {}  {}", k.fingerprint, k.signed_by.as_deref().unwrap_or("(unknown signer)"));
+            match users.get(k.fingerprint.as_str()) {
+                Some(urls) => println!("      used by: {}", urls.join(" ")),
+                None => println!("      used by: no enabled repository"),
+            }
+            if log.verbose {
+                println!("      file: {}", k.file.display());
+            }
+        }
+    }
+
+    if sigs.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    println!("repositories ({}):", sigs.len());
+    for s in &sigs {
+        let note = if !s.signed {
+            "UNSIGNED".to_string()
+        } else {
+            let who = s.signed_by.as_deref().unwrap_or("unknown signer");
+            match &s.fingerprint {
+                Some(fp) if !keys.iter().any(|k| &k.fingerprint == fp) => {
+                    format!("signed by {who}, key not imported yet ({fp})")
+                }
+                _ => format!("signed by {who}"),
+            }
+        };
+        println!("  {}  ({note})", s.url);
+    }
+
+    let unsigned = sigs.iter().filter(|s| !s.signed).count();
+    if unsigned > 0 {
+        println!("hint: packages from unsigned repositories are installed without verification; sign local repos with xbps-rindex --sign.");
+    }
+    ExitCode::SUCCESS
+}
+
+/// Warn when any of `pkgs` would be installed from an unsigned repository.
+pub fn warn_unsigned_sources(log: &Log, pkgs: &[String], repos: &[String]) {
+    let unsigned: Vec<String> = repo_sigs(repos)
+        .into_iter()
+        .filter(|s| !s.signed)
+        .map(|s| s.url)
+        .collect();
+    if unsigned.is_empty() {
+        return;
+    }
+
+    for p in pkgs {
+        let out = rootdir::command("xbps-query")
+            .args(repos.iter().map(|r| format!("--repository={r}")))
+            .args(["-R", "-p", "repository", p])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output();
+        let Ok(out) = out else { continue };
+        let repo = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        if unsigned.contains(&repo) {
+            log.warn(format!("{p} comes from unsigned repository {repo} (not verified)"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_repo_list, plist_signed_by};
+
+    #[test]
+    fn repo_list_maps_signers_and_fingerprints() {
+        let text = "\
+ 15563 https://repo-default.voidlinux.org/current (RSA signed)
+      Signed-by: Void Linux
+      4096 60:ae:0c:d6:f0:95:17:80:bc:93:46:7a:89:af:a3:2d
+    12 /home/me/void-packages/hostdir/binpkgs (RSA unsigned)
+";
+        let sigs = parse_repo_list(text);
+        assert_eq!(sigs.len(), 2);
+        assert!(sigs[0].signed);
+        assert_eq!(sigs[0].signed_by.as_deref(), Some("Void Linux"));
+        assert_eq!(
+            sigs[0].fingerprint.as_deref(),
+            Some("60:ae:0c:d6:f0:95:17:80:bc:93:46:7a:89:af:a3:2d")
+        );
+        assert!(!sigs[1].signed);
+        assert_eq!(sigs[1].url, "/home/me/void-packages/hostdir/binpkgs");
+
+        let plist = "<dict>\n\t<key>public-key</key>\n\t<data>AAAA</data>\n\t<key>signature-by</key>\n\t<string>Me &lt;me@example.org&gt;</string>\n</dict>";
+        assert_eq!(plist_signed_by(plist).as_deref(), Some("Me <me@example.org>"));
+    }
+}
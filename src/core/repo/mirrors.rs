@@ -0,0 +1,350 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    config::Config,
+    core::{configure, source::confirm_once, xbps::format_size},
+    log::Log,
+    paths::user_config_path,
+    rootdir,
+};
+use std::{
+    path::Path,
+    process::{Command, ExitCode, Stdio},
+};
+
+use super::{conf, override_path, read_current, write_root, xbps_arch};
+
+/// Known Void mirrors: (base URL, location).
+pub const MIRRORS: [(&str, &str); 12] = [
+    ("https://repo-default.voidlinux.org", "global (Fastly CDN)"),
+    ("https://repo-fastly.voidlinux.org", "global (Fastly CDN)"),
+    ("https://repo-fi.voidlinux.org", "Helsinki, Finland"),
+    ("https://repo-de.voidlinux.org", "Frankfurt, Germany"),
+    ("https://mirrors.servercentral.com/voidlinux", "Chicago, USA"),
+    ("https://repo-us.voidlinux.org", "Kansas City, USA"),
+    ("https://mirror.aarnet.edu.au/pub/voidlinux", "Australia"),
+    ("https://mirrors.dotsrc.org/voidlinux", "Denmark"),
+    ("https://ftp.lysator.liu.se/pub/voidlinux", "Sweden"),
+    ("https://mirror.clarkson.edu/voidlinux", "New York, USA"),
+    ("https://mirror.yandex.ru/mirrors/voidlinux", "Russia"),
+    ("https://mirrors.tuna.tsinghua.edu.cn/voidlinux", "China"),
+];
+
+/// Bytes fetched per mirror to estimate throughput.
+const SAMPLE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct MirrorResult {
+    pub url: String,
+    pub location: String,
+    /// Seconds to the first byte.
+    pub latency: Option<f64>,
+    /// Bytes per second over the sample.
+    pub speed: Option<f64>,
+}
+
+/// `<base>/current/<arch>-repodata` (musl and aarch64 live in subdirectories).
+pub fn repodata_url(base: &str, arch: &str) -> String {
+    let base = base.trim_end_matches('/');
+    if arch.ends_with("-musl") {
+        format!("{base}/current/musl/{arch}-repodata")
+    } else if arch.starts_with("aarch64") {
+        format!("{base}/current/aarch64/{arch}-repodata")
+    } else {
+        format!("{base}/current/{arch}-repodata")
+    }
+}
+
+/// Mirror root of a remote repository URL: everything before its `/current`
+/// (`https://repo-fi.voidlinux.org/current/musl` -> `https://repo-fi.voidlinux.org`).
+/// None for local repositories and URLs without a `/current` path.
+pub fn mirror_root(url: &str) -> Option<&str> {
+    if !url.contains("://") {
+        return None;
+    }
+    url.match_indices("/current")
+        .map(|(i, m)| (i, &url[i + m.len()..]))
+        .find(|(_, rest)| rest.is_empty() || rest.starts_with('/'))
+        .map(|(i, _)| &url[..i])
+}
+
+/// Rewrite `repository=` lines of a mirror (any `.../current` URL) to `to`,
+/// keeping the path below the mirror root. None when nothing changes.
+pub fn rewrite_mirror(text: &str, to: &str) -> Option<String> {
+    let to = to.trim_end_matches('/');
+    let mut changed = false;
+    let mut out = String::with_capacity(text.len() + 32);
+    for line in text.lines() {
+        let (prefix, body) = match line.trim_start().strip_prefix('#') {
+            Some(rest) => ("#", rest.trim_start()),
+            None => ("", line.trim_start()),
+        };
+        let url = body
+            .strip_prefix("repository")
+            .map(str::trim_start)
+            .and_then(|r| r.strip_prefix('='))
+            .map(str::trim);
+        let base = url.and_then(mirror_root);
+        match (url, base) {
+            (Some(u), Some(b)) if b != to => {
+                changed = true;
+                out.push_str(&format!("{prefix}repository={to}{}", &u[b.len()..]));
+            }
+            _ => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    changed.then_some(out)
+}
+
+fn measure(base: &str, location: &str, arch: &str, timeout: u64) -> MirrorResult {
+    let out = Command::new("curl")
+        .args(["-s", "-o", "/dev/null", "-w", "%{time_starttransfer} %{speed_download} %{http_code}"])
+        .args(["--max-time", &timeout.to_string()])
+        .args(["--range", &format!("0-{}", SAMPLE_BYTES - 1)])
+        .arg(repodata_url(base, arch))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+
+    let mut r = MirrorResult {
+        url: base.to_string(),
+        location: location.to_string(),
+        latency: None,
+        speed: None,
+    };
+    let Ok(out) = out else {
+        return r;
+    };
+    let text = String::from_utf8_lossy(&out.stdout).to_string();
+    let mut it = text.split_whitespace();
+    let (ttfb, speed, code) = (it.next(), it.next(), it.next().unwrap_or("000"));
+    if !code.starts_with('2') {
+        return r;
+    }
+    r.latency = ttfb.and_then(|s| s.parse().ok());
+    r.speed = speed.and_then(|s| s.parse().ok()).filter(|s: &f64| *s > 0.0);
+    r
+}
+
+fn print_results(results: &[MirrorResult], current: Option<&str>) {
+    let w = results.iter().map(|r| r.url.len()).max().unwrap_or(0);
+    for r in results {
+        let lat = r.latency.map(|l| format!("{:>6.0}ms", l * 1000.0)).unwrap_or_else(|| "      -".into());
+        let spd = r
+            .speed
+            .map(|s| format!("{:>9}/s", format_size(s as u64)))
+            .unwrap_or_else(|| "  unreachable".into());
+        let mark = if current == Some(r.url.as_str()) { "  (current)" } else { "" };
+        println!("  {:w$}  {lat}  {spd}  {}{mark}", r.url, r.location);
+    }
+}
+
+/// Mirror root the configured repositories currently use.
+fn current_mirror(etc: &Path, share: &Path) -> Option<String> {
+    conf::load_all(etc, share)
+        .iter()
+        .filter(|e| e.enabled)
+        .find_map(|e| mirror_root(&e.url).map(String::from))
+}
+
+/// The enabled xbps.d repositories moved to `mirror` (`xbps.mirror`), for
+/// vx to pass to xbps as `--repository`, which wins over xbps.d.
+pub fn user_repositories(mirror: &str) -> Vec<String> {
+    let etc = rootdir::path(conf::ETC_XBPS_D);
+    let share = rootdir::path(conf::SHARE_XBPS_D);
+    let mirror = mirror.trim_end_matches('/');
+    let mut out: Vec<String> = Vec::new();
+    for e in conf::load_all(&etc, &share) {
+        let Some(root) = mirror_root(&e.url).filter(|_| e.enabled) else {
+            continue;
+        };
+        let url = format!("{mirror}{}", &e.url[root.len()..]);
+        if !out.contains(&url) {
+            out.push(url);
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MirrorsOptions {
+    pub list: bool,
+    pub set: Option<String>,
+    pub best: bool,
+    /// Save as `xbps.mirror` in vx.rune rather than in /etc/xbps.d.
+    pub user: bool,
+    pub timeout: u64,
+    pub yes: bool,
+}
+
+/// `vx mirrors [--list] [--set URL | --best] [--user]`
+pub fn cmd_mirrors(log: &Log, cfg: Option<&Config>, opts: &MirrorsOptions) -> ExitCode {
+    let etc = rootdir::path(conf::ETC_XBPS_D);
+    let share = rootdir::path(conf::SHARE_XBPS_D);
+    let user = cfg.and_then(|c| c.mirror.as_deref()).map(|m| m.trim_end_matches('/').to_string());
+    let current = user.clone().or_else(|| current_mirror(&etc, &share));
+    let current = current.as_deref();
+    let timeout = opts.timeout;
+
+    if opts.list {
+        for (url, location) in MIRRORS {
+            let mark = if current == Some(url) { "  (current)" } else { "" };
+            println!("  {url}  {location}{mark}");
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let target = match &opts.set {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => {
+            let arch = xbps_arch();
+            log.info(format!("measuring {} mirrors ({arch}, up to {timeout}s each)...", MIRRORS.len()));
+            let mut results: Vec<MirrorResult> = MIRRORS
+                .iter()
+                .map(|(url, loc)| measure(url, loc, &arch, timeout))
+                .collect();
+            // Fastest first; unreachable last.
+            results.sort_by(|a, b| {
+                b.speed
+                    .unwrap_or(0.0)
+                    .partial_cmp(&a.speed.unwrap_or(0.0))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            print_results(&results, current);
+
+            if !opts.best {
+                log.info("hint: switch with `vx mirrors --best` or `vx mirrors --set <url>`.");
+                return ExitCode::SUCCESS;
+            }
+            match results.first().filter(|r| r.speed.is_some()) {
+                Some(r) => r.url.clone(),
+                None => {
+                    log.error("no mirror could be reached");
+                    return ExitCode::from(1);
+                }
+            }
+        }
+    };
+
+    if opts.user {
+        return save_user_mirror(log, user.as_deref(), &target);
+    }
+    switch_to(log, &etc, &share, &target, opts.yes)
+}
+
+/// `--user`: record the mirror in vx.rune; vx passes it to xbps itself.
+fn save_user_mirror(log: &Log, current: Option<&str>, target: &str) -> ExitCode {
+    if current == Some(target) {
+        log.info(format!("vx: xbps.mirror is already {target}."));
+        return ExitCode::SUCCESS;
+    }
+    let path = match user_config_path() {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if let Err(e) = configure::write_setting(&path, "xbps.mirror", target) {
+        log.error(e);
+        return ExitCode::from(1);
+    }
+    log.info(format!(
+        "vx: xbps.mirror = {target} in {}; run `vx sync` to fetch repodata from it.",
+        path.display()
+    ));
+    ExitCode::SUCCESS
+}
+
+/// Point every conf file that uses a known mirror at `target` via /etc overrides.
+fn switch_to(log: &Log, etc: &Path, share: &Path, target: &str, yes: bool) -> ExitCode {
+    let mut files: Vec<_> = conf::load_all(etc, share).into_iter().map(|e| e.file).collect();
+    files.dedup();
+
+    let mut edits = Vec::new();
+    for file in files {
+        let text = match read_current(etc, &file) {
+            Ok(t) => t,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        };
+        if let Some(new_text) = rewrite_mirror(&text, target) {
+            edits.push((override_path(etc, &file), new_text));
+        }
+    }
+
+    if edits.is_empty() {
+        log.info(format!("vx: repositories already use {target}."));
+        return ExitCode::SUCCESS;
+    }
+
+    println!("switch to {target}:");
+    for (path, _) in &edits {
+        println!("  {}", path.display());
+    }
+    if !yes && !confirm_once("Proceed?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    for (path, text) in &edits {
+        let c = write_root(log, path, text);
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+    }
+    log.info("vx: mirror switched; run `vx sync` to fetch repodata from it.");
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mirror_root, repodata_url, rewrite_mirror};
+
+    #[test]
+    fn mirror_root_is_whatever_precedes_current() {
+        assert_eq!(
+            mirror_root("https://repo-fi.voidlinux.org/current/musl"),
+            Some("https://repo-fi.voidlinux.org")
+        );
+        assert_eq!(
+            mirror_root("https://current.example.org/void/current"),
+            Some("https://current.example.org/void")
+        );
+        assert_eq!(mirror_root("https://example.org/void/currently"), None);
+        assert_eq!(mirror_root("/srv/void/current"), None);
+    }
+
+    #[test]
+    fn rewrite_recognizes_custom_mirrors() {
+        let text = "repository=https://mirror.example.org/void/current\n";
+        let out = rewrite_mirror(text, "https://repo-de.voidlinux.org").unwrap();
+        assert_eq!(out, "repository=https://repo-de.voidlinux.org/current\n");
+        let back = rewrite_mirror(&out, "https://mirror.example.org/void").unwrap();
+        assert_eq!(back, text);
+        assert_eq!(rewrite_mirror(&back, "https://mirror.example.org/void/"), None);
+    }
+
+    #[test]
+    fn rewrite_keeps_repo_paths_and_comments() {
+        let text = "repository=https://repo-default.voidlinux.org/current/nonfree\n\
+                    #repository=https://repo-default.voidlinux.org/current/debug\n\
+                    repository=/srv/local\n";
+        let out = rewrite_mirror(text, "https://repo-fi.voidlinux.org/").unwrap();
+        assert_eq!(
+            out,
+            "repository=https://repo-fi.voidlinux.org/current/nonfree\n\
+             #repository=https://repo-fi.voidlinux.org/current/debug\n\
+             repository=/srv/local\n"
+        );
+        assert_eq!(rewrite_mirror(&out, "https://repo-fi.voidlinux.org"), None);
+        assert_eq!(
+            repodata_url("https://repo-fi.voidlinux.org", "x86_64-musl"),
+            "https://repo-fi.voidlinux.org/current/musl/x86_64-musl-repodata"
+        );
+    }
+}
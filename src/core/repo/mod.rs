@@ -0,0 +1,315 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{cli::RepoCmd, config::Config, core::xbps, log::Log, privilege, rootdir, tempdir::TempDir};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    time::UNIX_EPOCH,
+};
+
+pub mod conf;
+pub mod keys;
+pub mod mirrors;
+
+use conf::{RepoEntry, ETC_XBPS_D, SHARE_XBPS_D};
+
+pub fn dispatch_repo(log: &Log, _cfg: Option<&Config>, cmd: RepoCmd) -> ExitCode {
+    let etc = rootdir::path(ETC_XBPS_D);
+    let share = rootdir::path(SHARE_XBPS_D);
+    let (etc, share) = (etc.as_path(), share.as_path());
+
+    match cmd {
+        RepoCmd::List => cmd_list(log, etc, share),
+        RepoCmd::Add { name, url } => cmd_add(log, etc, share, &url, name.as_deref()),
+        RepoCmd::Rm { repo } => cmd_rm(log, etc, share, &repo),
+        RepoCmd::Enable { repo } => cmd_toggle(log, etc, share, &repo, true),
+        RepoCmd::Disable { repo } => cmd_toggle(log, etc, share, &repo, false),
+        RepoCmd::Keys => keys::cmd_keys(log),
+    }
+}
+
+/// url -> signed, from `xbps-query -vL` (only lists enabled, reachable repos).
+fn signed_status() -> BTreeMap<String, bool> {
+    keys::repo_sigs(&[]).into_iter().map(|s| (s.url, s.signed)).collect()
+}
+
+pub(crate) fn xbps_arch() -> String {
+    if let Ok(a) = env::var("XBPS_ARCH")
+        && !a.trim().is_empty()
+    {
+        return a.trim().to_string();
+    }
+    Command::new("xbps-uhelper")
+        .arg("arch")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| env::consts::ARCH.to_string())
+}
+
+/// Where xbps keeps the synced index for `url` (`.`, `:` and `/` become `_`).
+pub(crate) fn repodata_path(url: &str, arch: &str) -> PathBuf {
+    let file = format!("{arch}-repodata");
+    if url.starts_with('/') {
+        return Path::new(url).join(file);
+    }
+    let escaped: String = url
+        .chars()
+        .map(|c| if matches!(c, ':' | '/' | '.') { '_' } else { c })
+        .collect();
+    rootdir::path("/var/db/xbps").join(escaped).join(file)
+}
+
+fn last_synced(url: &str, arch: &str) -> Option<u64> {
+    let mtime = fs::metadata(repodata_path(url, arch)).ok()?.modified().ok()?;
+    Some(mtime.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// `vx repo list`
+fn cmd_list(log: &Log, etc: &Path, share: &Path) -> ExitCode {
+    let entries = conf::load_all(etc, share);
+    if entries.is_empty() {
+        log.info(format!(
+            "vx: no repositories configured in {} or {}.",
+            etc.display(),
+            share.display()
+        ));
+        return ExitCode::SUCCESS;
+    }
+
+    let signed = signed_status();
+    let arch = xbps_arch();
+
+    println!("repositories ({}):", entries.len());
+    for e in &entries {
+        let mut notes = Vec::new();
+        if !e.enabled {
+            notes.push("disabled".to_string());
+        } else {
+            match signed.get(&e.url) {
+                Some(true) => notes.push("signed".to_string()),
+                Some(false) => notes.push("unsigned".to_string()),
+                None => {}
+            }
+            match last_synced(&e.url, &arch) {
+                Some(t) => notes.push(format!("synced {}", xbps::format_age(t))),
+                None => notes.push("never synced".to_string()),
+            }
+        }
+        println!("  {}  ({})", e.url, notes.join(", "));
+        println!("      {}", e.file.display());
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Entries matching a url or a conf file name (`00-repository-main` or `.conf`).
+fn find<'a>(entries: &'a [RepoEntry], repo: &str) -> Vec<&'a RepoEntry> {
+    let by_url: Vec<&RepoEntry> = entries.iter().filter(|e| e.url == repo).collect();
+    if !by_url.is_empty() {
+        return by_url;
+    }
+    entries
+        .iter()
+        .filter(|e| {
+            let name = e.file.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            name == repo || name.strip_suffix(".conf") == Some(repo)
+        })
+        .collect()
+}
+
+/// `vx repo add [--name NAME] <url>`
+fn cmd_add(log: &Log, etc: &Path, share: &Path, url: &str, name: Option<&str>) -> ExitCode {
+    let url = url.trim().trim_end_matches('/');
+    if url.is_empty() {
+        log.error("usage: vx repo add [--name NAME] <url-or-path>");
+        return ExitCode::from(2);
+    }
+
+    let entries = conf::load_all(etc, share);
+    if let Some(e) = entries.iter().find(|e| e.url == url) {
+        if e.enabled {
+            log.info(format!("vx: {url} already configured in {}.", e.file.display()));
+            return ExitCode::SUCCESS;
+        }
+        return cmd_toggle(log, etc, share, url, true);
+    }
+
+    let file = match name {
+        Some(n) => {
+            let n = n.trim();
+            if n.is_empty() || n.contains('/') {
+                log.error(format!("invalid repository name: {n:?}"));
+                return ExitCode::from(2);
+            }
+            if n.ends_with(".conf") {
+                n.to_string()
+            } else {
+                format!("{n}.conf")
+            }
+        }
+        None => conf::conf_name_for(url),
+    };
+    let path = etc.join(file);
+
+    let mut text = fs::read_to_string(&path).unwrap_or_default();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(&format!("repository={url}\n"));
+
+    let c = write_root(log, &path, &text);
+    if c == ExitCode::SUCCESS {
+        log.info(format!("vx: added {url} ({}); it syncs on the next `vx up`.", path.display()));
+    }
+    c
+}
+
+/// `vx repo rm <url|name>`
+fn cmd_rm(log: &Log, etc: &Path, share: &Path, repo: &str) -> ExitCode {
+    let entries = conf::load_all(etc, share);
+    let hits = find(&entries, repo);
+    if hits.is_empty() {
+        log.error(format!("no repository {repo} in {} or {}", etc.display(), share.display()));
+        return ExitCode::from(1);
+    }
+
+    for e in hits {
+        let text = match read_current(etc, &e.file) {
+            Ok(t) => t,
+            Err(err) => {
+                log.error(err);
+                return ExitCode::from(1);
+            }
+        };
+        let Some(new_text) = conf::remove_repo(&text, &e.url) else {
+            continue;
+        };
+
+        let target = override_path(etc, &e.file);
+        let shadows_share = target != e.file || share.join(file_name(&e.file)).exists();
+        let empty = new_text.lines().all(|l| {
+            let l = l.trim();
+            l.is_empty() || l.starts_with('#')
+        });
+
+        // An /etc file that only existed for this repo can go; anything that
+        // overrides /usr/share must stay so the system entry remains hidden.
+        let c = if empty && !shadows_share {
+            remove_root(log, &target)
+        } else {
+            write_root(log, &target, &new_text)
+        };
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+        log.info(format!("vx: removed {} ({})", e.url, target.display()));
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// `vx repo enable|disable <url|name>`
+fn cmd_toggle(log: &Log, etc: &Path, share: &Path, repo: &str, enabled: bool) -> ExitCode {
+    let entries = conf::load_all(etc, share);
+    let hits = find(&entries, repo);
+    if hits.is_empty() {
+        log.error(format!("no repository {repo} in {} or {}", etc.display(), share.display()));
+        return ExitCode::from(1);
+    }
+
+    for e in hits {
+        if e.enabled == enabled {
+            continue;
+        }
+        let text = match read_current(etc, &e.file) {
+            Ok(t) => t,
+            Err(err) => {
+                log.error(err);
+                return ExitCode::from(1);
+            }
+        };
+        let Some(new_text) = conf::set_enabled(&text, &e.url, enabled) else {
+            continue;
+        };
+
+        // Files under /usr/share are owned by packages; write an /etc override instead.
+        let target = override_path(etc, &e.file);
+        let c = write_root(log, &target, &new_text);
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+        let verb = if enabled { "enabled" } else { "disabled" };
+        log.info(format!("vx: {verb} {} ({})", e.url, target.display()));
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Text of `file`, or of its /etc override if an earlier edit already wrote one.
+fn read_current(etc: &Path, file: &Path) -> Result<String, String> {
+    let target = override_path(etc, file);
+    let src = if target.exists() { &target } else { file };
+    fs::read_to_string(src).map_err(|e| format!("failed to read {}: {e}", src.display()))
+}
+
+fn file_name(path: &Path) -> PathBuf {
+    PathBuf::from(path.file_name().unwrap_or_default())
+}
+
+fn override_path(etc: &Path, file: &Path) -> PathBuf {
+    etc.join(file_name(file))
+}
+
+/// Write a root-owned file via a temp file and a privileged `install -m 644`.
+fn write_root(log: &Log, path: &Path, text: &str) -> ExitCode {
+    let dir = match TempDir::new("vx-repo") {
+        Ok(d) => d,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    let tmp = dir.join(file_name(path));
+    if let Err(e) = fs::write(&tmp, text) {
+        log.error(format!("failed to write {}: {e}", tmp.display()));
+        return ExitCode::from(1);
+    }
+
+    let mut cmd = privilege::command("install");
+    cmd.args(["-D", "-m", "644"]).arg(&tmp).arg(path);
+    xbps::run(log, cmd, &privilege::label(&format!("install -D -m 644 {} {}", tmp.display(), path.display())))
+}
+
+fn remove_root(log: &Log, path: &Path) -> ExitCode {
+    let mut cmd = privilege::command("rm");
+    cmd.arg("-f").arg(path);
+    xbps::run(log, cmd, &privilege::label(&format!("rm -f {}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::repodata_path;
+    use std::path::Path;
+
+    #[test]
+    fn repodata_path_escapes_like_xbps() {
+        let p = repodata_path("https://repo-default.voidlinux.org/current", "x86_64");
+        assert_eq!(p.file_name().unwrap(), "x86_64-repodata");
+        assert_eq!(
+            p.parent().unwrap().file_name().unwrap(),
+            "https___repo-default_voidlinux_org_current"
+        );
+
+        assert_eq!(
+            repodata_path("/home/me/void-packages/hostdir/binpkgs", "x86_64-musl"),
+            Path::new("/home/me/void-packages/hostdir/binpkgs/x86_64-musl-repodata")
+        );
+    }
+}
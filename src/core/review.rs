@@ -0,0 +1,142 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::core::{
+    source::{action_note, plan::SrcUpdate},
+    xbps::SysUpdate,
+};
+use std::{
+    collections::BTreeSet,
+    io::{self, IsTerminal, Write},
+};
+
+/// 1-based numbers and ranges (`2 5-7`, `1,3`) -> 0-based indices below `max`.
+pub fn parse_selection(input: &str, max: usize) -> Result<BTreeSet<usize>, String> {
+    let mut out = BTreeSet::new();
+    for tok in input.split([' ', ',']).map(str::trim).filter(|t| !t.is_empty()) {
+        let num = |s: &str| -> Result<usize, String> {
+            match s.trim().parse::<usize>() {
+                Ok(n) if (1..=max).contains(&n) => Ok(n - 1),
+                _ => Err(format!("'{s}' is not a number between 1 and {max}")),
+            }
+        };
+        match tok.split_once('-') {
+            Some((a, b)) => {
+                let (a, b) = (num(a)?, num(b)?);
+                if a > b {
+                    return Err(format!("'{tok}' is not an ascending range"));
+                }
+                out.extend(a..=b);
+            }
+            None => {
+                out.insert(num(tok)?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Which half of `vx up -a` to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpScope {
+    Both,
+    System,
+    Source,
+}
+
+/// `b`oth (or `y`es), `s`ystem, s`o`urce; anything else is None (abort).
+pub fn parse_scope(answer: &str) -> Option<UpScope> {
+    match answer.trim().to_lowercase().as_str() {
+        "b" | "both" | "y" | "yes" => Some(UpScope::Both),
+        "s" | "system" => Some(UpScope::System),
+        "o" | "source" => Some(UpScope::Source),
+        _ => None,
+    }
+}
+
+/// Confirmation for `vx up -a` when both plans have work. None means abort.
+pub fn confirm_scope() -> Option<UpScope> {
+    print!("Proceed? [b]oth, [s]ystem only, s[o]urce only, [N]o: ");
+    io::stdout().flush().ok();
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).ok()?;
+    parse_scope(&line)
+}
+
+/// Show the `vx up` plan numbered and drop the entries the user leaves out.
+/// Returns false when nothing is left to do.
+pub fn review_up(sys: &mut Vec<SysUpdate>, src: &mut Vec<SrcUpdate>) -> Result<bool, String> {
+    if !io::stdin().is_terminal() {
+        return Err("--review needs an interactive terminal".into());
+    }
+
+    let mut rows: Vec<String> = sys
+        .iter()
+        .map(|u| format!("{}  {} → {}{}", u.name, u.from, u.to, action_note(&u.action)))
+        .collect();
+    rows.extend(src.iter().map(|u| {
+        let inst = u.installed.as_deref().unwrap_or("(not installed)");
+        format!("{}  {inst} → {}{}  (source)", u.name, u.candidate, action_note(&u.action))
+    }));
+
+    println!("review updates:");
+    let w = rows.len().to_string().len();
+    for (i, r) in rows.iter().enumerate() {
+        println!("  {:>w$}) {r}", i + 1);
+    }
+
+    let skip = loop {
+        print!("leave out [e.g. 2 5-7, empty keeps all]: ");
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| format!("failed to read answer: {e}"))?;
+        match parse_selection(&line, rows.len()) {
+            Ok(s) => break s,
+            Err(e) => println!("{e}"),
+        }
+    };
+
+    let n_sys = sys.len();
+    let mut i = 0;
+    sys.retain(|_| {
+        i += 1;
+        !skip.contains(&(i - 1))
+    });
+    let mut i = n_sys;
+    src.retain(|_| {
+        i += 1;
+        !skip.contains(&(i - 1))
+    });
+
+    if !skip.is_empty() {
+        println!("leaving out {} of {} update(s).", skip.len(), rows.len());
+    }
+    Ok(!sys.is_empty() || !src.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_scope, parse_selection, UpScope};
+
+    #[test]
+    fn selection_accepts_numbers_and_ranges() {
+        let s = parse_selection(" 2, 5-7 1 ", 8).unwrap();
+        assert_eq!(s.into_iter().collect::<Vec<_>>(), [0, 1, 4, 5, 6]);
+        assert!(parse_selection("", 3).unwrap().is_empty());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("3-1", 3).is_err());
+        assert!(parse_selection("x", 3).is_err());
+    }
+
+    #[test]
+    fn scope_answers() {
+        assert_eq!(parse_scope("B\n"), Some(UpScope::Both));
+        assert_eq!(parse_scope("yes"), Some(UpScope::Both));
+        assert_eq!(parse_scope("s"), Some(UpScope::System));
+        assert_eq!(parse_scope("source"), Some(UpScope::Source));
+        assert_eq!(parse_scope(""), None);
+        assert_eq!(parse_scope("n"), None);
+    }
+}
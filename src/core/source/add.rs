@@ -2,10 +2,11 @@
 // License: MIT
 
 use crate::log::Log;
+use crate::privilege;
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitCode, Stdio};
+use std::process::{ExitCode, Stdio};
 
 use super::resolve::SrcResolved;
 
@@ -58,7 +59,10 @@ pub fn add_from_local_repo(
     // - hostdir/binpkgs/nonfree
     // - hostdir/binpkgs/<subrepo> (e.g. hostdir/binpkgs/stasis)
     // - hostdir/binpkgs/<subrepo>/nonfree
-    let repo_pool = match discover_local_repo_dirs(&base, res.use_nonfree) {
+    // A build profile for another libc/arch indexes its packages under that
+    // arch; only look at those, and tell xbps-install to accept them.
+    let arch = res.profile.arch.as_deref();
+    let repo_pool = match discover_local_repo_dirs(&base, res.use_nonfree, arch) {
         Ok(v) => v,
         Err(e) => {
             log.error(e);
@@ -80,7 +84,7 @@ pub fn add_from_local_repo(
     let mut missing: Vec<String> = Vec::new();
 
     for pkg in &to_install {
-        match choose_repo_for_pkg(&repo_pool, pkg) {
+        match choose_repo_for_pkg(&repo_pool, pkg, arch) {
             Some(repo) => {
                 plan.entry(repo).or_default().push(pkg.clone());
             }
@@ -96,14 +100,29 @@ pub fn add_from_local_repo(
         if log.verbose && !log.quiet {
             log.exec("hint: ensure you built them and that their .xbps exists in hostdir/binpkgs/<repo>/".to_string());
         }
+        log.info("hint: `vx src repo check` lists index entries without package files; `vx src repo index` fixes them.");
         return ExitCode::from(2);
     }
 
+    let what = format!("vx src add {}", to_install.join(" "));
+    let foreign = arch.filter(|_| res.profile.is_foreign(&crate::core::repo::xbps_arch()));
+    crate::core::xbps::with_journal(log, &what, || install_plan(log, plan, force, yes, foreign))
+}
+
+fn install_plan(
+    log: &Log,
+    plan: BTreeMap<PathBuf, Vec<String>>,
+    force: bool,
+    yes: bool,
+    arch: Option<&str>,
+) -> ExitCode {
     // Install per-repo so we never accidentally resolve a pkg from the wrong local repo.
     for (repo_dir, pkgs_for_repo) in plan {
-        let mut cmd = Command::new("sudo");
-        cmd.arg("xbps-install");
+        let mut cmd = privilege::command("xbps-install");
         cmd.arg("-R").arg(&repo_dir);
+        if let Some(a) = arch {
+            cmd.env("XBPS_ARCH", a);
+        }
 
         if force {
             cmd.arg("-f");
@@ -114,7 +133,8 @@ pub fn add_from_local_repo(
         cmd.args(&pkgs_for_repo);
 
         if log.verbose && !log.quiet {
-            let mut s = format!("sudo xbps-install -R {}", repo_dir.display());
+            let env = arch.map(|a| format!("XBPS_ARCH={a} ")).unwrap_or_default();
+            let mut s = privilege::label(&format!("{env}xbps-install -R {}", repo_dir.display()));
             if force {
                 s.push_str(" -f");
             }
@@ -140,7 +160,7 @@ pub fn add_from_local_repo(
                 }
             }
             Err(e) => {
-                log.error(format!("failed to run sudo xbps-install: {e}"));
+                log.error(format!("failed to run xbps-install: {e}"));
                 return ExitCode::from(1);
             }
         }
@@ -150,7 +170,7 @@ pub fn add_from_local_repo(
 }
 
 fn is_installed_system(pkg: &str) -> Result<bool, String> {
-    let status = Command::new("xbps-query")
+    let status = crate::rootdir::command("xbps-query")
         .arg("-p")
         .arg("pkgver")
         .arg(pkg)
@@ -165,18 +185,23 @@ fn is_installed_system(pkg: &str) -> Result<bool, String> {
 
 /// Discover local xbps repository directories under `base` (hostdir/binpkgs).
 ///
-/// We consider a directory a repo if it contains an `*-repodata` file (e.g. x86_64-repodata).
-fn discover_local_repo_dirs(base: &Path, use_nonfree: bool) -> Result<Vec<PathBuf>, String> {
+/// We consider a directory a repo if it contains an `*-repodata` file (e.g. x86_64-repodata),
+/// or `<arch>-repodata` when an arch is given.
+pub(super) fn discover_local_repo_dirs(
+    base: &Path,
+    use_nonfree: bool,
+    arch: Option<&str>,
+) -> Result<Vec<PathBuf>, String> {
     let mut out: Vec<PathBuf> = Vec::new();
 
     // base itself
-    if is_repo_dir(base) {
+    if is_repo_dir(base, arch) {
         out.push(base.to_path_buf());
     }
 
     // base/nonfree
     let nonfree = base.join("nonfree");
-    if use_nonfree && nonfree.is_dir() && is_repo_dir(&nonfree) {
+    if use_nonfree && nonfree.is_dir() && is_repo_dir(&nonfree, arch) {
         out.push(nonfree);
     }
 
@@ -189,13 +214,13 @@ fn discover_local_repo_dirs(base: &Path, use_nonfree: bool) -> Result<Vec<PathBu
             continue;
         }
 
-        if is_repo_dir(&p) {
+        if is_repo_dir(&p, arch) {
             out.push(p.clone());
         }
 
         if use_nonfree {
             let nf = p.join("nonfree");
-            if nf.is_dir() && is_repo_dir(&nf) {
+            if nf.is_dir() && is_repo_dir(&nf, arch) {
                 out.push(nf);
             }
         }
@@ -212,10 +237,13 @@ fn discover_local_repo_dirs(base: &Path, use_nonfree: bool) -> Result<Vec<PathBu
     Ok(dedup)
 }
 
-fn is_repo_dir(dir: &Path) -> bool {
+fn is_repo_dir(dir: &Path, arch: Option<&str>) -> bool {
     if !dir.is_dir() {
         return false;
     }
+    if let Some(a) = arch {
+        return dir.join(format!("{a}-repodata")).is_file();
+    }
     // Void repo metadata: e.g. x86_64-repodata
     match fs::read_dir(dir) {
         Ok(rd) => rd
@@ -229,18 +257,22 @@ fn is_repo_dir(dir: &Path) -> bool {
 ///
 /// This is stricter than "repodata claims it exists", and avoids:
 ///   ERROR: <pkg>: failed to checksum: No such file or directory
-fn choose_repo_for_pkg(repos: &[PathBuf], pkg: &str) -> Option<PathBuf> {
+fn choose_repo_for_pkg(repos: &[PathBuf], pkg: &str, arch: Option<&str>) -> Option<PathBuf> {
     // Prefer repos where the actual .xbps file exists.
     for r in repos {
-        if repo_has_pkg_file(r, pkg) {
+        if repo_has_pkg_file(r, pkg, arch) {
             return Some(r.clone());
         }
     }
     None
 }
 
-/// True if repo dir contains a file that looks like: <pkg>-*.xbps
-fn repo_has_pkg_file(repo: &Path, pkg: &str) -> bool {
+/// True if repo dir contains a file that looks like: <pkg>-<version>*.xbps
+///
+/// The version must follow directly, so `foo` doesn't match a `foo-devel`
+/// subpackage built next to it. With an arch, only `.<arch>.xbps` and
+/// `.noarch.xbps` files count.
+fn repo_has_pkg_file(repo: &Path, pkg: &str, arch: Option<&str>) -> bool {
     let Ok(rd) = fs::read_dir(repo) else {
         return false;
     };
@@ -249,7 +281,13 @@ fn repo_has_pkg_file(repo: &Path, pkg: &str) -> bool {
     for entry in rd.flatten() {
         let name = entry.file_name();
         let name = name.to_string_lossy();
-        if name.starts_with(&prefix) && name.ends_with(".xbps") {
+        if let Some(rest) = name.strip_prefix(&prefix)
+            && rest.starts_with(|c: char| c.is_ascii_digit())
+            && name.ends_with(".xbps")
+            && arch.is_none_or(|a| {
+                name.ends_with(&format!(".{a}.xbps")) || name.ends_with(".noarch.xbps")
+            })
+        {
             return true;
         }
     }
@@ -0,0 +1,103 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{core::version, core::xbps, log::Log, managed};
+use std::{path::Path, process::ExitCode};
+
+use super::{confirm_once, resolve::SrcResolved};
+
+/// Whether an installed package's `repository` is a local repository of the
+/// void-packages checkout at `voidpkgs` (hostdir/binpkgs, its nonfree/ and
+/// the per-arch hostdirs of build profiles all live under it).
+fn built_here(repository: &str, voidpkgs: &Path) -> bool {
+    let repo = Path::new(repository.strip_prefix("file://").unwrap_or(repository));
+    repo.is_absolute()
+        && (repo.starts_with(voidpkgs)
+            || voidpkgs.canonicalize().is_ok_and(|v| repo.starts_with(v)))
+}
+
+/// `vx src adopt [-n] [-y]`: track installed packages that came from the
+/// local repository but aren't in the managed list yet.
+pub fn cmd_adopt(log: &Log, res: &SrcResolved, yes: bool, dry_run: bool) -> ExitCode {
+    let managed = match managed::load_managed() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(format!("failed to load managed list: {e}"));
+            return ExitCode::from(1);
+        }
+    };
+    let installed = match xbps::installed_snapshot() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    let repos = match xbps::installed_properties("repository") {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let found: Vec<(String, String)> = installed
+        .into_iter()
+        .filter(|(name, _)| !managed.contains(name))
+        .filter(|(name, _)| repos.get(name).is_some_and(|r| built_here(r, &res.voidpkgs)))
+        .collect();
+
+    if found.is_empty() {
+        log.info(format!(
+            "vx: no untracked packages installed from {}.",
+            res.voidpkgs.display()
+        ));
+        return ExitCode::SUCCESS;
+    }
+
+    if !log.quiet {
+        println!("installed from {} but not tracked ({}):", res.voidpkgs.display(), found.len());
+        for (_, pkgver) in &found {
+            println!("  {pkgver}");
+        }
+    }
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+    if !yes && !confirm_once("Track them?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    // Nothing is rebuilt, so only the installed version is known.
+    let records: Vec<managed::ManagedPkg> = found
+        .iter()
+        .map(|(name, pkgver)| managed::ManagedPkg {
+            name: name.clone(),
+            version: Some(version::pkgver_version(pkgver).to_string()),
+            ..managed::ManagedPkg::default()
+        })
+        .collect();
+    if let Err(e) = managed::add_managed(log, &records) {
+        log.error(e);
+        return ExitCode::from(1);
+    }
+    log.info(format!("vx: now tracking {} package(s).", records.len()));
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::built_here;
+    use std::path::Path;
+
+    #[test]
+    fn local_repositories_are_under_the_checkout() {
+        let vp = Path::new("/nonexistent/void-packages");
+        assert!(built_here("/nonexistent/void-packages/hostdir/binpkgs", vp));
+        assert!(built_here("/nonexistent/void-packages/hostdir/binpkgs/nonfree", vp));
+        assert!(built_here("file:///nonexistent/void-packages/hostdir-aarch64/binpkgs", vp));
+        assert!(!built_here("https://repo-default.voidlinux.org/current", vp));
+        assert!(!built_here("/nonexistent/void-packages-old/hostdir/binpkgs", vp));
+    }
+}
@@ -0,0 +1,440 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{core::xbps::format_age, log::Log};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    sync::Mutex,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{stats, xbps_src::xbps_src_command};
+
+/// Logs kept per package; older ones are removed after each build.
+const KEEP_LOGS: usize = 10;
+
+/// Next to a package's logs: the builddir its last failed build left behind,
+/// spared by vx's cleanups until the package builds again.
+const BUILDDIR_FILE: &str = "builddir";
+
+/// Log lines shown after a failed build.
+const TAIL_LINES: usize = 15;
+
+/// `$XDG_STATE_HOME/vx/build-logs` (default ~/.local/state/vx/build-logs).
+pub fn log_root() -> PathBuf {
+    crate::paths::state_dir()
+        .unwrap_or_else(|_| PathBuf::from("/tmp/vx"))
+        .join("build-logs")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildLog {
+    pub pkg: String,
+    pub time: u64,
+    /// None while running (or when vx was interrupted).
+    pub ok: Option<bool>,
+    pub path: PathBuf,
+}
+
+/// `<unix time>[-ok|-failed].log` -> (time, ok).
+fn parse_log_name(name: &str) -> Option<(u64, Option<bool>)> {
+    let stem = name.strip_suffix(".log")?;
+    let (time, ok) = match stem.split_once('-') {
+        Some((t, "ok")) => (t, Some(true)),
+        Some((t, "failed")) => (t, Some(false)),
+        Some(_) => return None,
+        None => (stem, None),
+    };
+    Some((time.parse().ok()?, ok))
+}
+
+fn logs_in(dir: &Path, pkg: &str) -> Vec<BuildLog> {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut out: Vec<BuildLog> = rd
+        .flatten()
+        .filter_map(|e| {
+            let (time, ok) = parse_log_name(&e.file_name().to_string_lossy())?;
+            Some(BuildLog {
+                pkg: pkg.to_string(),
+                time,
+                ok,
+                path: e.path(),
+            })
+        })
+        .collect();
+    out.sort_by_key(|l| std::cmp::Reverse(l.time));
+    out
+}
+
+/// Build logs, newest first; all packages when `pkg` is None.
+pub fn list(pkg: Option<&str>) -> Vec<BuildLog> {
+    let root = log_root();
+    if let Some(p) = pkg {
+        return logs_in(&root.join(p), p);
+    }
+
+    let mut out = Vec::new();
+    for e in fs::read_dir(&root).into_iter().flatten().flatten() {
+        let name = e.file_name().to_string_lossy().into_owned();
+        out.extend(logs_in(&e.path(), &name));
+    }
+    out.sort_by_key(|l| std::cmp::Reverse(l.time));
+    out
+}
+
+fn tee(mut from: impl Read, mut to: impl Write, file: &Mutex<File>) {
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match from.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let _ = to.write_all(&buf[..n]);
+        let _ = to.flush();
+        if let Ok(mut f) = file.lock() {
+            let _ = f.write_all(&buf[..n]);
+        }
+    }
+}
+
+/// Run `./xbps-src <args>` for `pkg`, streaming to the terminal and into a
+/// new log under `log_root()/<pkg>/`.
+pub fn run_logged(
+    log: &Log,
+    voidpkgs: &Path,
+    args: Vec<OsString>,
+    env: &[(String, String)],
+    pkg: &str,
+) -> ExitCode {
+    match xbps_src_command(log, voidpkgs, args, env) {
+        Ok(cmd) => run_command_logged(log, cmd, pkg),
+        Err(c) => c,
+    }
+}
+
+/// Like [`run_logged`] for an already prepared command (a container run).
+pub fn run_command_logged(log: &Log, mut cmd: Command, pkg: &str) -> ExitCode {
+    let dir = log_root().join(pkg);
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = dir.join(format!("{time}.log"));
+    let file = match fs::create_dir_all(&dir).and_then(|_| File::create(&path)) {
+        Ok(f) => Mutex::new(f),
+        Err(e) => {
+            log.warn(format!("failed to create build log {}: {e}", path.display()));
+            cmd.stdin(Stdio::inherit())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit());
+            return match cmd.status() {
+                Ok(s) => ExitCode::from(s.code().unwrap_or(1) as u8),
+                Err(e) => {
+                    log.error(format!("failed to run ./xbps-src: {e}"));
+                    ExitCode::from(1)
+                }
+            };
+        }
+    };
+
+    let mut child = match cmd
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            log.error(format!("failed to run ./xbps-src: {e}"));
+            let _ = fs::remove_file(&path);
+            return ExitCode::from(1);
+        }
+    };
+
+    let (out, err) = (child.stdout.take(), child.stderr.take());
+    let status = thread::scope(|s| {
+        if let Some(out) = out {
+            s.spawn(|| tee(out, io::stdout(), &file));
+        }
+        if let Some(err) = err {
+            s.spawn(|| tee(err, io::stderr(), &file));
+        }
+        child.wait()
+    });
+
+    let code = match status {
+        Ok(s) => s.code().unwrap_or(1) as u8,
+        Err(e) => {
+            log.error(format!("failed to wait for ./xbps-src: {e}"));
+            1
+        }
+    };
+
+    if code == 0 {
+        let _ = fs::remove_file(dir.join(BUILDDIR_FILE));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        stats::record(log, pkg, time, now.saturating_sub(time));
+    }
+
+    let suffix = if code == 0 { "ok" } else { "failed" };
+    let done = dir.join(format!("{time}-{suffix}.log"));
+    let done = if fs::rename(&path, &done).is_ok() { done } else { path };
+    if code != 0 {
+        log.error(format!(
+            "{pkg}: build failed; log: {} (vx src log {pkg})",
+            done.display()
+        ));
+    }
+
+    for old in logs_in(&dir, pkg).iter().skip(KEEP_LOGS) {
+        let _ = fs::remove_file(&old.path);
+    }
+
+    ExitCode::from(code)
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI: ESC [ ... final byte in @..~
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) && c != '[' {
+                    break;
+                }
+            }
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The xbps-src stage a build log ends in: the phase named by its
+/// `=> ERROR: <pkgver>: <phase>: ...` line, else the last one it was running.
+fn failed_stage(text: &str) -> Option<String> {
+    let lines: Vec<String> = text.lines().map(strip_ansi).collect();
+    let phase = |s: &str| {
+        let s = s.trim();
+        (!s.is_empty() && !s.contains(' ')).then(|| s.to_string())
+    };
+    for l in lines.iter().rev() {
+        if let Some((_, rest)) = l.split_once("ERROR: ")
+            && let Some(stage) = rest.split(": ").nth(1).and_then(phase)
+        {
+            return Some(stage);
+        }
+    }
+    lines.iter().rev().find_map(|l| {
+        let (_, rest) = l.split_once(": running ")?;
+        phase(rest.trim_end_matches('.').trim_end())
+    })
+}
+
+fn tail(text: &str, n: usize) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(n)..].iter().map(|l| strip_ansi(l)).collect()
+}
+
+/// The builddir xbps-src left for `pkg`: the newest `<pkg>-<version>` under
+/// `masterdir` (or any `masterdir*` of `voidpkgs`) `/builddir`.
+fn find_builddir(voidpkgs: &Path, masterdir: Option<&Path>, pkg: &str) -> Option<PathBuf> {
+    let masterdirs: Vec<PathBuf> = match masterdir {
+        Some(m) => vec![m.to_path_buf()],
+        None => fs::read_dir(voidpkgs)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with("masterdir"))
+            .map(|e| e.path())
+            .collect(),
+    };
+    let prefix = format!("{pkg}-");
+    masterdirs
+        .iter()
+        .flat_map(|m| fs::read_dir(m.join("builddir")).into_iter().flatten().flatten())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            name.strip_prefix(&prefix)
+                .is_some_and(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max_by_key(|(t, _)| *t)
+        .map(|(_, p)| p)
+}
+
+/// After `./xbps-src pkg <pkg>` failed in `voidpkgs`: say which stage broke,
+/// where its builddir is and how the log ends. The builddir is remembered
+/// for `vx src debug`.
+pub fn report_failure(log: &Log, voidpkgs: &Path, masterdir: Option<&Path>, pkg: &str) {
+    let text = list(Some(pkg))
+        .first()
+        .and_then(|l| fs::read(&l.path).ok())
+        .map(|b| String::from_utf8_lossy(&b).into_owned())
+        .unwrap_or_default();
+
+    if let Some(stage) = failed_stage(&text) {
+        log.error(format!("{pkg}: failed in {stage}"));
+    }
+    let marker = log_root().join(pkg).join(BUILDDIR_FILE);
+    match find_builddir(voidpkgs, masterdir, pkg) {
+        Some(dir) => {
+            let _ = fs::write(&marker, dir.display().to_string());
+            log.error(format!("{pkg}: builddir: {} (vx src debug {pkg})", dir.display()));
+            log.info(format!("vx: it stays in place until {pkg} builds again."));
+        }
+        None => {
+            let _ = fs::remove_file(&marker);
+        }
+    }
+    if !log.quiet && !text.is_empty() {
+        eprintln!("==> last lines of the log:");
+        for l in tail(&text, TAIL_LINES) {
+            eprintln!("  {l}");
+        }
+    }
+}
+
+/// The builddir recorded by the last failed build of `pkg`, if it's still there.
+pub fn failed_builddir(pkg: &str) -> Option<PathBuf> {
+    let text = fs::read_to_string(log_root().join(pkg).join(BUILDDIR_FILE)).ok()?;
+    let dir = PathBuf::from(text.trim());
+    dir.is_dir().then_some(dir)
+}
+
+/// Whether the last build of `pkg` failed and left its builddir inside `tree`.
+pub fn failed_in(pkg: &str, tree: &Path) -> bool {
+    failed_builddir(pkg).is_some_and(|d| d.starts_with(tree))
+}
+
+/// Whether any failed build left its builddir inside `tree`.
+pub fn any_failed_in(tree: &Path) -> bool {
+    fs::read_dir(log_root()).is_ok_and(|rd| {
+        rd.flatten()
+            .any(|e| failed_in(&e.file_name().to_string_lossy(), tree))
+    })
+}
+
+fn print_list(logs: &[BuildLog]) {
+    let w = logs.iter().map(|l| l.pkg.len()).max().unwrap_or(0);
+    for l in logs {
+        let state = match l.ok {
+            Some(true) => "ok",
+            Some(false) => "failed",
+            None => "incomplete",
+        };
+        println!(
+            "  {:<w$}  {:<10} {:<10} {}",
+            l.pkg,
+            format_age(l.time),
+            state,
+            l.path.display()
+        );
+    }
+}
+
+/// `vx src log [pkg] [-n N] [--failed]`
+pub fn cmd_log(log: &Log, pkg: Option<&str>, nth: usize, failed: bool) -> ExitCode {
+    let mut logs = list(pkg);
+    if failed {
+        logs.retain(|l| l.ok == Some(false));
+    }
+
+    let Some(pkg) = pkg.filter(|_| !failed) else {
+        if logs.is_empty() {
+            log.info(if failed { "vx: no failed builds logged." } else { "vx: no build logs." });
+            return ExitCode::SUCCESS;
+        }
+        if !log.quiet {
+            println!("{}:", if failed { "failed builds" } else { "build logs" });
+        }
+        print_list(&logs);
+        return ExitCode::SUCCESS;
+    };
+
+    let Some(entry) = logs.get(nth) else {
+        log.error(match logs.len() {
+            0 => format!("no build logs for {pkg}"),
+            n => format!("only {n} build log(s) for {pkg}"),
+        });
+        return ExitCode::from(1);
+    };
+
+    match fs::read(&entry.path) {
+        Ok(bytes) => {
+            if !log.quiet {
+                eprintln!("==> {} ({})", entry.path.display(), format_age(entry.time));
+            }
+            let _ = io::stdout().write_all(&bytes);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log.error(format!("failed to read {}: {e}", entry.path.display()));
+            ExitCode::from(1)
+        }
+    }
+}
+
+/// `vx src debug <pkg>`: open a shell in the builddir of the last failed build.
+pub fn cmd_debug(log: &Log, voidpkgs: &Path, pkg: &str) -> ExitCode {
+    let Some(dir) = failed_builddir(pkg).or_else(|| find_builddir(voidpkgs, None, pkg)) else {
+        log.error(format!(
+            "no builddir left for {pkg}; rebuild it with --keep (vx src build --keep {pkg})"
+        ));
+        return ExitCode::from(1);
+    };
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    if let Some(l) = list(Some(pkg)).first() {
+        log.info(format!("vx: build log: {}", l.path.display()));
+    }
+    log.info(format!("vx: entering {} (exit to leave)", dir.display()));
+    if log.verbose && !log.quiet {
+        log.exec(format!("(cd {}) && {shell}", dir.display()));
+    }
+    match Command::new(&shell).current_dir(&dir).status() {
+        Ok(s) => ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(e) => {
+            log.error(format!("failed to run {shell}: {e}"));
+            ExitCode::from(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{failed_stage, parse_log_name};
+
+    #[test]
+    fn log_names_carry_time_and_outcome() {
+        assert_eq!(parse_log_name("1712345678-ok.log"), Some((1712345678, Some(true))));
+        assert_eq!(parse_log_name("1712345678-failed.log"), Some((1712345678, Some(false))));
+        assert_eq!(parse_log_name("1712345678.log"), Some((1712345678, None)));
+        assert_eq!(parse_log_name("notes.txt"), None);
+        assert_eq!(parse_log_name("x-ok.log"), None);
+    }
+
+    #[test]
+    fn failed_stage_comes_from_the_error_line() {
+        let log = "=> foo-1.0_1: running do_configure ...\n\
+                   => foo-1.0_1: running do_build ...\n\
+                   make: *** [all] Error 2\n\
+                   \x1b[31;1m=> ERROR: foo-1.0_1: do_build: 'make' exited with 2\x1b[m\n";
+        assert_eq!(failed_stage(log).as_deref(), Some("do_build"));
+
+        let log = "=> foo-1.0_1: running pre_check ...\nkilled\n";
+        assert_eq!(failed_stage(log).as_deref(), Some("pre_check"));
+        assert_eq!(failed_stage("nothing here\n"), None);
+    }
+}
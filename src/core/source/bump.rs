@@ -0,0 +1,312 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    core::{pkg, version::cmp_version, xbps::ymd},
+    log::Log,
+    managed,
+};
+use std::{
+    cmp::Ordering,
+    fs,
+    process::{Command, ExitCode, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    resolve::SrcResolved,
+    srcpkgs::local_source_pkgname,
+    template::{parse_template_var_str, parse_template_version_revision_str},
+    updatecheck, xbps_src,
+};
+
+/// Template text with `version=` set to `version` and `revision=` reset to 1.
+fn bump_template(text: &str, version: &str) -> Result<String, String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut seen_version = false;
+    let mut seen_revision = false;
+    for line in text.lines() {
+        if line.starts_with("version=") && !seen_version {
+            seen_version = true;
+            out.push(format!("version={version}"));
+        } else if line.starts_with("revision=") && !seen_revision {
+            seen_revision = true;
+            out.push("revision=1".to_string());
+        } else {
+            out.push(line.to_string());
+        }
+    }
+    if !seen_version {
+        return Err("template missing version=".to_string());
+    }
+    if !seen_revision
+        && let Some(i) = out.iter().position(|l| l.starts_with("version="))
+    {
+        out.insert(i + 1, "revision=1".to_string());
+    }
+
+    let mut text = out.join("\n");
+    text.push('\n');
+    Ok(text)
+}
+
+/// `vx src bump <pkg> <version> [--build]`: edit the template, refresh its
+/// checksums with xgensum and optionally build it.
+pub fn cmd_bump(
+    log: &Log,
+    res: &SrcResolved,
+    pkg: &str,
+    version: &str,
+    build: Option<&xbps_src::SrcRunOptions>,
+) -> ExitCode {
+    let version = version.trim();
+    if version.is_empty() || version.contains(|c: char| c.is_whitespace() || c == '-' || c == '_') {
+        log.error(format!("invalid version '{version}' (no '-', '_' or spaces)"));
+        return ExitCode::from(2);
+    }
+
+    let Some(srcpkg) = local_source_pkgname(&res.voidpkgs, pkg) else {
+        log.error(format!("no template for {pkg} in {}/srcpkgs", res.voidpkgs.display()));
+        return ExitCode::from(2);
+    };
+    let tpl = res.voidpkgs.join("srcpkgs").join(&srcpkg).join("template");
+    let before = match fs::read_to_string(&tpl) {
+        Ok(s) => s,
+        Err(e) => {
+            log.error(format!("failed to read {}: {e}", tpl.display()));
+            return ExitCode::from(1);
+        }
+    };
+
+    let current = parse_template_version_revision_str(&before).map(|(v, _)| v).ok();
+    if let Some(cur) = current.as_deref() {
+        match cmp_version(version, cur) {
+            Ordering::Equal => {
+                log.error(format!("{srcpkg} is already at {version}"));
+                return ExitCode::from(2);
+            }
+            Ordering::Less => log.warn(format!("{version} is older than {srcpkg}'s current {cur}")),
+            Ordering::Greater => {}
+        }
+    }
+
+    let after = match bump_template(&before, version) {
+        Ok(t) => t,
+        Err(e) => {
+            log.error(format!("{}: {e}", tpl.display()));
+            return ExitCode::from(1);
+        }
+    };
+    if let Err(e) = fs::write(&tpl, after) {
+        log.error(format!("failed to write {}: {e}", tpl.display()));
+        return ExitCode::from(1);
+    }
+    updatecheck::forget(log, &srcpkg);
+    log.info(format!(
+        "vx: {srcpkg}: {} → {version}_1",
+        current.as_deref().unwrap_or("?")
+    ));
+
+    let c = pkg::pkg_gensum(log, Some(res.voidpkgs.clone()), None, &srcpkg, false, false, None, None);
+    if c != ExitCode::SUCCESS {
+        log.error(format!(
+            "xgensum failed; the template was bumped but its checksums are stale ({})",
+            tpl.display()
+        ));
+        return c;
+    }
+
+    match build {
+        Some(opts) => xbps_src::build(log, res, std::slice::from_ref(&srcpkg), opts),
+        None => ExitCode::SUCCESS,
+    }
+}
+
+/// Template text with the first top-level `<var>=` line set to `value`.
+fn set_var(text: &str, var: &str, value: &str) -> Option<String> {
+    let prefix = format!("{var}=");
+    let i = text.lines().position(|l| l.starts_with(&prefix))?;
+    let mut out: Vec<String> = text.lines().map(String::from).collect();
+    out[i] = format!("{prefix}{value}");
+    let mut text = out.join("\n");
+    text.push('\n');
+    Some(text)
+}
+
+/// Version of a git snapshot taken on `date`: the date that ends the current
+/// version (`20240115`, `1.2+git20240115`, `0.3.20240115`) is replaced.
+fn snapshot_version(current: &str, date: &str) -> Option<String> {
+    let start = current.rfind(|c: char| !c.is_ascii_digit()).map_or(0, |i| i + 1);
+    (current.len() - start == 8).then(|| format!("{}{date}", &current[..start]))
+}
+
+/// Git repository a `_commit=` template builds from: where its distfiles'
+/// `/archive/` tarball comes from, else its homepage.
+fn upstream_repo(text: &str) -> Option<String> {
+    let homepage = parse_template_var_str(text, "homepage").unwrap_or_default();
+    let distfiles = parse_template_var_str(text, "distfiles")
+        .unwrap_or_default()
+        .replace("${homepage}", &homepage)
+        .replace("$homepage", &homepage);
+    let from_archive = distfiles
+        .split_whitespace()
+        .find_map(|d| d.split_once("/archive/").map(|(repo, _)| repo.to_string()));
+    from_archive
+        .or_else(|| (!homepage.is_empty()).then_some(homepage))
+        .filter(|u| u.starts_with("https://") || u.starts_with("git://"))
+}
+
+fn ls_remote(url: &str, branch: Option<&str>) -> Result<String, String> {
+    let rev = branch.map_or("HEAD".to_string(), |b| format!("refs/heads/{b}"));
+    let out = Command::new("git")
+        .args(["ls-remote", url, &rev])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    match text.split_whitespace().next() {
+        Some(sha) if out.status.success() => Ok(sha.to_string()),
+        _ => Err(format!("git ls-remote {url} {rev} found nothing")),
+    }
+}
+
+/// Move a `_commit=` template to upstream's latest commit (of `_branch`, or
+/// the default branch). Ok(false) when it's already there.
+fn bump_head(log: &Log, res: &SrcResolved, srcpkg: &str, dry_run: bool) -> Result<bool, String> {
+    let tpl = res.voidpkgs.join("srcpkgs").join(srcpkg).join("template");
+    let before = fs::read_to_string(&tpl).map_err(|e| format!("failed to read {}: {e}", tpl.display()))?;
+    let commit = parse_template_var_str(&before, "_commit")
+        .ok_or_else(|| format!("{srcpkg}: no _commit= in the template (use vx src bump)"))?;
+    let url = upstream_repo(&before)
+        .ok_or_else(|| format!("{srcpkg}: can't tell the git repository from distfiles/homepage"))?;
+    let branch = parse_template_var_str(&before, "_branch");
+    let head = ls_remote(&url, branch.as_deref())?;
+    if head == commit || (commit.len() >= 7 && head.starts_with(&commit)) {
+        log.info(format!("vx: {srcpkg}: already at {}", &head[..head.len().min(10)]));
+        return Ok(false);
+    }
+    if dry_run {
+        println!("would move {srcpkg} from {commit} to {head}");
+        return Ok(true);
+    }
+
+    let (version, revision) = parse_template_version_revision_str(&before)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let snapshot = snapshot_version(&version, &ymd(now)).ok_or_else(|| {
+        format!("{srcpkg}: version {version} doesn't end in a YYYYMMDD date; bump it with vx src bump")
+    })?;
+    // A second snapshot on the same day keeps the version and bumps revision.
+    let after = if snapshot == version {
+        let rev = revision.parse::<u32>().unwrap_or(0) + 1;
+        set_var(&before, "revision", &rev.to_string()).unwrap_or(before.clone())
+    } else {
+        bump_template(&before, &snapshot)?
+    };
+    let after = set_var(&after, "_commit", &head).ok_or("template missing _commit=")?;
+    fs::write(&tpl, &after).map_err(|e| format!("failed to write {}: {e}", tpl.display()))?;
+    updatecheck::forget(log, srcpkg);
+
+    let pkgver = parse_template_version_revision_str(&after).map(|(v, r)| format!("{v}_{r}"))?;
+    log.info(format!(
+        "vx: {srcpkg}: {} → {} ({pkgver})",
+        &commit[..commit.len().min(10)],
+        &head[..10.min(head.len())]
+    ));
+
+    let c = pkg::pkg_gensum(log, Some(res.voidpkgs.clone()), None, srcpkg, false, false, None, None);
+    if c != ExitCode::SUCCESS {
+        return Err(format!(
+            "xgensum failed; {srcpkg} was moved to {head} but its checksums are stale"
+        ));
+    }
+    Ok(true)
+}
+
+/// `vx src up --head [pkg...]`: move `_commit=` templates of `pkgs` (default:
+/// every tracked one that has `_commit=`) to upstream's latest commit.
+/// Returns the packages to rebuild; with `dry_run` nothing is edited.
+pub fn bump_heads(
+    log: &Log,
+    res: &SrcResolved,
+    pkgs: &[String],
+    dry_run: bool,
+) -> Result<Vec<String>, ExitCode> {
+    let explicit = !pkgs.is_empty();
+    let pkgs = if explicit {
+        pkgs.to_vec()
+    } else {
+        managed::load_managed().map_err(|e| {
+            log.error(e);
+            ExitCode::from(1)
+        })?
+    };
+
+    let mut failed = false;
+    let mut out = Vec::new();
+    for p in &pkgs {
+        let Some(srcpkg) = local_source_pkgname(&res.voidpkgs, p) else {
+            if explicit {
+                log.error(format!("no template for {p} in {}/srcpkgs", res.voidpkgs.display()));
+                failed = true;
+            }
+            continue;
+        };
+        let tpl = res.voidpkgs.join("srcpkgs").join(&srcpkg).join("template");
+        let tracks_git = fs::read_to_string(&tpl)
+            .is_ok_and(|t| parse_template_var_str(&t, "_commit").is_some());
+        if !explicit && !tracks_git {
+            continue;
+        }
+        match bump_head(log, res, &srcpkg, dry_run) {
+            Ok(true) => out.push(p.clone()),
+            Ok(false) => {}
+            Err(e) => {
+                log.error(e);
+                failed = true;
+            }
+        }
+    }
+    if failed && explicit {
+        return Err(ExitCode::from(1));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bump_template, set_var, snapshot_version, upstream_repo, ymd};
+
+    #[test]
+    fn bump_sets_version_and_resets_revision() {
+        let text = "# Template file for 'foo'\npkgname=foo\nversion=1.2\nrevision=3\n\nfoo-devel_package() {\n\tshort_desc+=\" - development files\"\n}\n";
+        let out = bump_template(text, "1.3").unwrap();
+        assert!(out.contains("\nversion=1.3\nrevision=1\n"));
+        assert!(out.contains("foo-devel_package() {\n"));
+        assert!(out.ends_with("}\n"));
+
+        assert_eq!(
+            bump_template("pkgname=bar\nversion=0.1\n", "0.2").unwrap(),
+            "pkgname=bar\nversion=0.2\nrevision=1\n"
+        );
+        assert!(bump_template("pkgname=baz\n", "1.0").is_err());
+    }
+
+    #[test]
+    fn git_snapshots_get_a_new_date_and_commit() {
+        assert_eq!(ymd(0), "19700101");
+        assert_eq!(ymd(1709294400), "20240301");
+
+        assert_eq!(snapshot_version("1.2+git20240115", "20240301").as_deref(), Some("1.2+git20240301"));
+        assert_eq!(snapshot_version("0.3.20240115", "20240301").as_deref(), Some("0.3.20240301"));
+        assert_eq!(snapshot_version("20240115", "20240301").as_deref(), Some("20240301"));
+        assert_eq!(snapshot_version("1.2.3", "20240301"), None);
+
+        let text = "pkgname=foo-git\nversion=0.1+git20240115\nrevision=2\n_commit=abc\n\
+                    homepage=\"https://github.com/me/foo\"\n\
+                    distfiles=\"${homepage}/archive/${_commit}.tar.gz\"\n";
+        assert_eq!(upstream_repo(text).as_deref(), Some("https://github.com/me/foo"));
+        assert!(set_var(text, "_commit", "def").unwrap().contains("\n_commit=def\n"));
+        assert_eq!(upstream_repo("homepage=\"ftp://example.org\"\n"), None);
+    }
+}
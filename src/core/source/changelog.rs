@@ -0,0 +1,308 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{config::Config, core::xbps::pkgname_from_pkgver, log::Log};
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use super::{
+    git::{self, UPSTREAM_REF},
+    resolve::resolve_voidpkgs,
+    template::{Template, parse_template_var_str},
+};
+
+/// Separates commits in the `git log -p` output we parse.
+const COMMIT_MARK: &str = "\x1ecommit\t";
+
+/// How far back `vx up --dry-run --changelog` looks per package.
+const PLAN_CHANGELOG_DEPTH: usize = 10;
+
+/// One commit touching a package template.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangelogEntry {
+    pub hash: String,
+    pub date: String,
+    pub subject: String,
+    /// `version_revision` of the template after this commit, when known.
+    pub version: Option<String>,
+}
+
+/// `version_revision` part of a pkgver (`foo-devel-1.2_3` -> `1.2_3`).
+pub fn pkgver_version(pkgver: &str) -> Option<&str> {
+    let name = pkgname_from_pkgver(pkgver)?;
+    pkgver.get(name.len() + 1..)
+}
+
+/// Source package that builds `pkg` (subpackages are symlinks in srcpkgs/).
+pub fn source_pkgname(voidpkgs: &Path, pkg: &str) -> Result<String, String> {
+    let out = git::output(voidpkgs, &["ls-tree", UPSTREAM_REF, &format!("srcpkgs/{pkg}")])?;
+    let Some(line) = out.lines().next() else {
+        return Err(format!("no srcpkgs/{pkg} in {UPSTREAM_REF}"));
+    };
+
+    // `<mode> <type> <object>\t<path>`
+    let mut it = line.split_whitespace();
+    let (mode, object) = (it.next().unwrap_or(""), it.nth(1).unwrap_or(""));
+    if mode != "120000" {
+        return Ok(pkg.to_string());
+    }
+
+    let target = git::output(voidpkgs, &["cat-file", "-p", object])?;
+    let target = target.trim().trim_end_matches('/');
+    Ok(target.rsplit('/').next().unwrap_or(target).to_string())
+}
+
+/// `git log --follow -p` of srcpkgs/<srcpkg>/template, newest first.
+pub fn changelog(voidpkgs: &Path, srcpkg: &str, limit: usize) -> Result<Vec<ChangelogEntry>, String> {
+    let template = format!("srcpkgs/{srcpkg}/template");
+    let current = git::read_template_upstream(voidpkgs, srcpkg)?;
+    let text = git::output(
+        voidpkgs,
+        &[
+            "log",
+            "--follow",
+            "-p",
+            "--no-color",
+            "--format=%x1ecommit%x09%h%x09%as%x09%s",
+            &format!("-n{limit}"),
+            UPSTREAM_REF,
+            "--",
+            &template,
+        ],
+    )?;
+    let current = Template::parse(&current).ok().map(|t| format!("{}_{}", t.version, t.revision));
+    Ok(parse_changelog(&text, current))
+}
+
+/// Parse the `git log -p` output from [`changelog`].
+///
+/// Walks newest to oldest: the first commit ends at the current template
+/// version; each commit's removed `version=`/`revision=` lines give the
+/// version the next (older) commit ended at.
+fn parse_changelog(text: &str, current: Option<String>) -> Vec<ChangelogEntry> {
+    let mut out = Vec::new();
+    let mut after = current;
+
+    for chunk in text.split(COMMIT_MARK).skip(1) {
+        let mut lines = chunk.lines();
+        let header = lines.next().unwrap_or("");
+        let mut cols = header.splitn(3, '\t');
+        let hash = cols.next().unwrap_or("").to_string();
+        let date = cols.next().unwrap_or("").to_string();
+        let subject = cols.next().unwrap_or("").to_string();
+
+        let var =
+            |line: &str, key: &str| parse_template_var_str(line, key).filter(|v| !v.is_empty());
+        let (mut old_ver, mut old_rev) = (None, None);
+        let (mut new_ver, mut new_rev) = (None, None);
+        for l in lines {
+            if l.starts_with("---") || l.starts_with("+++") {
+                continue;
+            }
+            if let Some(rest) = l.strip_prefix('-') {
+                old_ver = old_ver.or_else(|| var(rest, "version"));
+                old_rev = old_rev.or_else(|| var(rest, "revision"));
+            } else if let Some(rest) = l.strip_prefix('+') {
+                new_ver = new_ver.or_else(|| var(rest, "version"));
+                new_rev = new_rev.or_else(|| var(rest, "revision"));
+            }
+        }
+
+        let version = after.clone();
+        after = match (&version, old_ver.is_some() || old_rev.is_some()) {
+            (_, false) if new_ver.is_some() || new_rev.is_some() => None, // template created
+            (Some(v), _) => {
+                let (ver, rev) = v.rsplit_once('_').unwrap_or((v.as_str(), ""));
+                let ver = old_ver.unwrap_or_else(|| ver.to_string());
+                let rev = old_rev.unwrap_or_else(|| rev.to_string());
+                Some(format!("{ver}_{rev}"))
+            }
+            (None, _) => None,
+        };
+
+        out.push(ChangelogEntry {
+            hash,
+            date,
+            subject,
+            version,
+        });
+    }
+    out
+}
+
+/// Resolve + fetch the void-packages checkout used for changelogs.
+pub fn prepare(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+) -> Result<PathBuf, String> {
+    let resolved = resolve_voidpkgs(voidpkgs_override, cfg)?;
+    git::sync_voidpkgs(log, &resolved.voidpkgs)?;
+    Ok(resolved.voidpkgs)
+}
+
+/// Commits newer than `from` (a `version_revision`), newest first.
+pub fn entries_since<'a>(entries: &'a [ChangelogEntry], from: Option<&str>) -> &'a [ChangelogEntry] {
+    let end = from
+        .and_then(|f| entries.iter().position(|e| e.version.as_deref() == Some(f)))
+        .unwrap_or(entries.len());
+    &entries[..end]
+}
+
+pub fn print_entry(indent: &str, e: &ChangelogEntry) {
+    let ver = e.version.as_deref().map(|v| format!("  ({v})")).unwrap_or_default();
+    println!("{indent}{} {}  {}{ver}", e.hash, e.date, e.subject);
+}
+
+/// Commits each planned system update pulls in (`vx up --dry-run --changelog`).
+pub fn annotate_plan(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    plan: &[crate::core::xbps::SysUpdate],
+) {
+    if plan.is_empty() {
+        return;
+    }
+    let voidpkgs = match prepare(log, voidpkgs_override, cfg) {
+        Ok(p) => p,
+        Err(e) => {
+            log.warn(format!("changelog unavailable: {e}"));
+            return;
+        }
+    };
+
+    println!("changelog:");
+    for u in plan {
+        let entries = source_pkgname(&voidpkgs, &u.name)
+            .and_then(|src| changelog(&voidpkgs, &src, PLAN_CHANGELOG_DEPTH));
+        match entries {
+            Ok(entries) => {
+                let newer = entries_since(&entries, pkgver_version(&u.from));
+                println!("  {}  {} → {}", u.name, u.from, u.to);
+                if newer.is_empty() {
+                    println!("    (no matching commits in void-packages)");
+                }
+                for e in newer {
+                    print_entry("    ", e);
+                }
+            }
+            Err(e) => println!("  {}: {e}", u.name),
+        }
+    }
+}
+
+/// `vx changelog <pkg>`
+pub fn run(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    pkg: &str,
+    limit: usize,
+) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx changelog <pkg>");
+        return ExitCode::from(2);
+    }
+
+    let voidpkgs = match prepare(log, voidpkgs_override, cfg) {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let srcpkg = match source_pkgname(&voidpkgs, pkg) {
+        Ok(s) => s,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let entries = match changelog(&voidpkgs, &srcpkg, limit) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let installed = crate::core::xbps::installed_pkgver(pkg).ok().flatten();
+    let installed_ver = installed.as_deref().and_then(pkgver_version);
+
+    if log.json {
+        log.emit_json(&serde_json::json!({
+            "pkg": pkg,
+            "srcpkg": srcpkg,
+            "installed": installed_ver,
+            "entries": entries,
+        }));
+        return ExitCode::SUCCESS;
+    }
+
+    if entries.is_empty() {
+        log.info(format!("vx: no history for srcpkgs/{srcpkg}."));
+        return ExitCode::SUCCESS;
+    }
+
+    if !log.quiet {
+        let head = entries[0].version.as_deref().unwrap_or("?");
+        let via = if srcpkg != pkg { format!(" (srcpkgs/{srcpkg})") } else { String::new() };
+        match installed_ver {
+            Some(v) => println!("{pkg}{via}: {v} installed, {head} in {UPSTREAM_REF}"),
+            None => println!("{pkg}{via}: not installed, {head} in {UPSTREAM_REF}"),
+        }
+    }
+
+    let newer = entries_since(&entries, installed_ver).len();
+    for (i, e) in entries.iter().enumerate() {
+        if installed_ver.is_some() && i == newer && i < entries.len() {
+            println!("  -- installed ({}) --", installed_ver.unwrap_or(""));
+        }
+        print_entry("  ", e);
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changelog_tracks_template_versions() {
+        let log = "\
+\x1ecommit\ta1\t2026-02-01\tfoo: rebuild for libbar-2
+
+diff --git a/srcpkgs/foo/template b/srcpkgs/foo/template
+--- a/srcpkgs/foo/template
++++ b/srcpkgs/foo/template
+-revision=1
++revision=2
+\x1ecommit\tb2\t2026-01-10\tfoo: update to 1.1.
+
+-version=1.0
++version=1.1
+\x1ecommit\tc3\t2025-12-01\tfoo: fix homepage
+
+-homepage=\"http://x\"
++homepage=\"https://x\"
+\x1ecommit\td4\t2025-11-01\tNew package: foo-1.0
+
++version=1.0
++revision=1
+";
+        let entries = parse_changelog(log, Some("1.1_2".into()));
+        let vers: Vec<_> = entries.iter().map(|e| e.version.as_deref()).collect();
+        assert_eq!(vers, [Some("1.1_2"), Some("1.1_1"), Some("1.0_1"), Some("1.0_1")]);
+        assert_eq!(entries[1].subject, "foo: update to 1.1.");
+        assert_eq!(entries_since(&entries, Some("1.0_1")).len(), 2);
+        assert_eq!(entries_since(&entries, Some("9.9_1")).len(), 4);
+        assert_eq!(pkgver_version("foo-devel-1.0_2"), Some("1.0_2"));
+    }
+}
@@ -0,0 +1,146 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::log::Log;
+use std::process::{Command, ExitCode};
+
+use super::{
+    buildlog, git,
+    resolve::SrcResolved,
+    srcpkgs::local_source_pkgname,
+    xbps_src::{self, SrcRunOptions},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stage {
+    Passed,
+    Failed,
+    Skipped(String),
+}
+
+fn outcome(c: ExitCode) -> Stage {
+    if c == ExitCode::SUCCESS { Stage::Passed } else { Stage::Failed }
+}
+
+/// One line per stage; true when nothing failed.
+fn summary(stages: &[(&str, Stage)]) -> (String, bool) {
+    let w = stages.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (name, s) in stages {
+        let state = match s {
+            Stage::Passed => "pass".to_string(),
+            Stage::Failed => "FAIL".to_string(),
+            Stage::Skipped(why) => format!("skipped ({why})"),
+        };
+        out.push_str(&format!("  {name:<w$}  {state}\n"));
+    }
+    (out, !stages.iter().any(|(_, s)| *s == Stage::Failed))
+}
+
+fn xlint(log: &Log, res: &SrcResolved, template: &str) -> Stage {
+    let path = format!("srcpkgs/{template}/template");
+    if log.verbose && !log.quiet {
+        log.exec(format!("(cd {}) && xlint {path}", res.voidpkgs.display()));
+    }
+    match Command::new("xlint").current_dir(&res.voidpkgs).arg(&path).status() {
+        Ok(s) if s.success() => Stage::Passed,
+        Ok(_) => Stage::Failed,
+        Err(_) => Stage::Skipped("xlint not installed; it comes with xtools".to_string()),
+    }
+}
+
+/// `vx src check <pkg>`: xlint, a clean build from upstream/master with the
+/// local template overlaid, and `./xbps-src check` -- roughly what Void's CI
+/// runs on a pull request.
+pub fn cmd_check(log: &Log, res: &SrcResolved, pkg: &str, opts: &SrcRunOptions) -> ExitCode {
+    let Some(template) = local_source_pkgname(&res.voidpkgs, pkg.trim()) else {
+        log.error(format!("no template for {pkg} in {}/srcpkgs", res.voidpkgs.display()));
+        return ExitCode::from(2);
+    };
+    let pkgs = std::slice::from_ref(&template);
+
+    let mut stages = vec![("xlint", xlint(log, res, &template))];
+
+    let wt = match git::ensure_upstream_worktree(log, &res.voidpkgs, opts.keep) {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if let Err(e) = xbps_src::ensure_xbps_conf(log, &wt, res) {
+        log.warn(format!("failed to ensure etc/conf in worktree: {e}"));
+    }
+    if let Err(e) = xbps_src::overlay_dirs(log, res, &wt) {
+        log.warn(format!("failed to apply source.overlays: {e}"));
+    }
+    if let Err(e) = xbps_src::overlay_srcpkg(log, &res.voidpkgs, &wt, &template, "check") {
+        log.error(format!("failed to overlay {template} into the upstream worktree: {e}"));
+        return ExitCode::from(1);
+    }
+    let env = xbps_src::build_env_for_worktree(res);
+
+    let c = xbps_src::ensure_profile_masterdir(log, res, &wt, opts, &env);
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+
+    // Rebuild even when the repository already has this version.
+    let build_opts = SrcRunOptions {
+        force_stage: true,
+        ..opts.clone()
+    };
+    let clean = xbps_src::join_args_with_opts("clean", pkgs, opts);
+    let cleaned = if opts.keep || buildlog::failed_in(&template, &wt) {
+        ExitCode::SUCCESS
+    } else {
+        xbps_src::run_xbps_src_with_env(log, &wt, clean, &env)
+    };
+    let built = match cleaned {
+        c if c != ExitCode::SUCCESS => Stage::Failed,
+        _ => {
+            let args = xbps_src::join_args_with_opts("pkg", pkgs, &build_opts);
+            let c = buildlog::run_logged(log, &wt, args, &env, &template);
+            if c != ExitCode::SUCCESS {
+                buildlog::report_failure(log, &wt, opts.masterdir.as_deref(), &template);
+            }
+            outcome(c)
+        }
+    };
+
+    let checked = if built == Stage::Passed {
+        let args = xbps_src::join_args_with_opts("check", pkgs, opts);
+        outcome(xbps_src::run_xbps_src_with_env(log, &wt, args, &env))
+    } else {
+        Stage::Skipped("build failed".to_string())
+    };
+    stages.push(("build", built));
+    stages.push(("check", checked));
+
+    let (text, ok) = summary(&stages);
+    if !log.quiet {
+        println!();
+        println!("{template}:");
+        print!("{text}");
+    }
+    if ok { ExitCode::SUCCESS } else { ExitCode::from(1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{summary, Stage};
+
+    #[test]
+    fn summary_fails_on_any_failed_stage() {
+        let (text, ok) = summary(&[
+            ("xlint", Stage::Passed),
+            ("build", Stage::Failed),
+            ("check", Stage::Skipped("build failed".to_string())),
+        ]);
+        assert!(!ok);
+        assert_eq!(text, "  xlint  pass\n  build  FAIL\n  check  skipped (build failed)\n");
+
+        let (_, ok) = summary(&[("xlint", Stage::Skipped("missing".to_string()))]);
+        assert!(ok);
+    }
+}
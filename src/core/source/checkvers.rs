@@ -0,0 +1,146 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    core::{
+        repo::xbps_arch,
+        version::{cmp_pkgver, pkgver_version},
+        xbps,
+    },
+    log::Log,
+};
+use serde::Serialize;
+use std::{cmp::Ordering, collections::BTreeMap, fs, path::PathBuf, process::ExitCode};
+
+use super::{add::discover_local_repo_dirs, resolve::SrcResolved, template::Template};
+
+/// A package in the local repository whose template has moved on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleBuild {
+    pub name: String,
+    /// pkgver in the local repository.
+    pub built: String,
+    /// `version_revision` of srcpkgs/<name>/template.
+    pub template: String,
+    pub repo: PathBuf,
+}
+
+/// Built packages whose pkgver differs from their template's, like
+/// `xbps-checkvers -i`. Packages without a template are skipped.
+fn compare_builds(
+    built: BTreeMap<String, (String, PathBuf)>,
+    template_version: impl Fn(&str) -> Option<String>,
+) -> Vec<StaleBuild> {
+    let mut out = Vec::new();
+    for (name, (pkgver, repo)) in built {
+        let Some(template) = template_version(&name) else {
+            continue;
+        };
+        if cmp_pkgver(&pkgver, &format!("{name}-{template}")) != Ordering::Equal {
+            out.push(StaleBuild {
+                name,
+                built: pkgver,
+                template,
+                repo,
+            });
+        }
+    }
+    out
+}
+
+/// Stale packages in the local repository (hostdir/binpkgs and its subrepos)
+/// of `res`, checked against the local checkout's templates.
+pub fn stale_builds(log: &Log, res: &SrcResolved) -> Result<Vec<StaleBuild>, String> {
+    let base = res.voidpkgs.join(&res.local_repo_rel);
+    if !base.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let arch = res.profile.arch.clone().unwrap_or_else(xbps_arch);
+    let mut built: BTreeMap<String, (String, PathBuf)> = BTreeMap::new();
+    for repo in discover_local_repo_dirs(&base, res.use_nonfree, Some(&arch))? {
+        let pkgs = match xbps::local_repo_pkgvers(&repo, &arch) {
+            Ok(p) => p,
+            Err(e) => {
+                log.warn(e);
+                continue;
+            }
+        };
+        for (name, pkgver) in pkgs {
+            built.entry(name).or_insert((pkgver, repo.clone()));
+        }
+    }
+
+    // srcpkgs/<subpkg> links to its main package, so this reads the right template.
+    let srcpkgs = res.voidpkgs.join("srcpkgs");
+    Ok(compare_builds(built, |name| {
+        let text = fs::read_to_string(srcpkgs.join(name).join("template")).ok()?;
+        let t = Template::parse(&text).ok()?;
+        Some(format!("{}_{}", t.version, t.revision))
+    }))
+}
+
+/// `vx src checkvers`
+pub fn cmd_checkvers(log: &Log, res: &SrcResolved) -> ExitCode {
+    let stale = match stale_builds(log, res) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if log.json {
+        log.emit_json(&stale);
+        return ExitCode::SUCCESS;
+    }
+
+    if stale.is_empty() {
+        log.info("vx: local repository matches its templates.");
+        return ExitCode::SUCCESS;
+    }
+
+    if !log.quiet {
+        println!("stale builds ({}):", stale.len());
+    }
+    let w = stale.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for s in &stale {
+        println!("  {:<w$}  {} → {}", s.name, pkgver_version(&s.built), s.template);
+    }
+    if !log.quiet {
+        println!("rebuild tracked ones with `vx src up --stale`.");
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compare_builds;
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    #[test]
+    fn builds_differing_from_their_template_are_stale() {
+        let repo = PathBuf::from("hostdir/binpkgs");
+        let built: BTreeMap<_, _> = [
+            ("foo", "foo-1.2_1"),
+            ("bar", "bar-2.0_1"),
+            ("baz", "baz-0.9_3"),
+            ("gone", "gone-1.0_1"),
+        ]
+        .into_iter()
+        .map(|(n, v)| (n.to_string(), (v.to_string(), repo.clone())))
+        .collect();
+        let templates = |n: &str| match n {
+            "foo" => Some("1.3_1".to_string()),
+            "bar" => Some("2.0_1".to_string()),
+            "baz" => Some("0.9_4".to_string()),
+            _ => None,
+        };
+
+        let stale = compare_builds(built, templates);
+        let names: Vec<_> = stale.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["baz", "foo"]);
+        assert_eq!(stale[1].built, "foo-1.2_1");
+        assert_eq!(stale[1].template, "1.3_1");
+    }
+}
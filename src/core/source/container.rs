@@ -0,0 +1,244 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{log::Log, privilege::in_path, tempdir::TempDir};
+use std::{
+    ffi::OsString,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use super::{
+    buildlog, order,
+    resolve::SrcResolved,
+    xbps_src::{join_args_with_opts, SrcRunOptions},
+};
+
+/// Where the tree and hostdir are mounted inside the container.
+const TREE_MOUNT: &str = "/void-packages";
+const HOSTDIR_MOUNT: &str = "/hostdir";
+
+/// Void's CI build images, which come with base-chroot installed.
+pub fn default_image(arch: Option<&str>) -> String {
+    let libc = if arch.is_some_and(|a| a.ends_with("-musl")) { "musl" } else { "glibc" };
+    format!("ghcr.io/void-linux/void-buildroot-{libc}:latest")
+}
+
+/// podman, else docker.
+pub(crate) fn runtime() -> Option<&'static str> {
+    ["podman", "docker"].into_iter().find(|r| in_path(r))
+}
+
+/// etc/conf as seen inside the container: the tree's own settings, with the
+/// chroot style switched to `ethereal`.
+fn container_conf(text: &str) -> String {
+    let mut out: String = text
+        .lines()
+        .filter(|l| {
+            let l = l.trim_start();
+            !l.starts_with("XBPS_CHROOT_CMD=") && !l.starts_with("XBPS_ALLOW_CHROOT_BREAKOUT=")
+        })
+        .map(|l| format!("{l}\n"))
+        .collect();
+    out.push_str("XBPS_CHROOT_CMD=ethereal\nXBPS_ALLOW_CHROOT_BREAKOUT=yes\n");
+    out
+}
+
+/// `<runtime> run ...` arguments for one `./xbps-src <args>` run.
+///
+/// Like Void's CI the container itself is the build root: xbps-src uses the
+/// `ethereal` chroot style with the masterdir linked to `/`. `conf` is
+/// mounted over the tree's etc/conf. With `owner` (`uid:gid`) the hostdir is
+/// handed back to that user afterwards, since rootful docker writes as root.
+fn run_args(
+    image: &str,
+    tree: &Path,
+    hostdir: &Path,
+    conf: &Path,
+    owner: Option<&str>,
+    xbps_src_args: Vec<OsString>,
+) -> Vec<OsString> {
+    const BUILD: &str = "ln -sfn / /masterdir && ./xbps-src -m /masterdir -H /hostdir \"$@\"";
+    let script = match owner {
+        Some(owner) => format!("{BUILD}; rc=$?; chown -R {owner} {HOSTDIR_MOUNT}; exit $rc"),
+        None => BUILD.replacen("./xbps-src", "exec ./xbps-src", 1),
+    };
+
+    let mut out: Vec<OsString> = ["run", "--rm", "-v"].map(OsString::from).to_vec();
+    out.push(format!("{}:{TREE_MOUNT}", tree.display()).into());
+    out.push("-v".into());
+    out.push(format!("{}:{HOSTDIR_MOUNT}", hostdir.display()).into());
+    out.push("-v".into());
+    out.push(format!("{}:{TREE_MOUNT}/etc/conf:ro", conf.display()).into());
+    for a in [
+        "-w",
+        TREE_MOUNT,
+        image,
+        "sh",
+        "-c",
+        script.as_str(),
+        "sh",
+    ] {
+        out.push(a.into());
+    }
+    out.extend(xbps_src_args);
+    out
+}
+
+/// `vx src build --container`: build `pkgs` from `tree` (the checkout or the
+/// upstream worktree) in a throwaway podman/docker container, one package
+/// at a time in dependency order, into the usual local repository.
+pub fn build(
+    log: &Log,
+    res: &SrcResolved,
+    tree: &Path,
+    image: Option<&str>,
+    pkgs: &[String],
+    opts: &SrcRunOptions,
+) -> ExitCode {
+    let Some(runtime) = runtime() else {
+        log.error("--container needs podman or docker");
+        return ExitCode::from(2);
+    };
+    let image = image
+        .map(String::from)
+        .unwrap_or_else(|| default_image(res.profile.arch.as_deref()));
+
+    let hostdir: PathBuf = res
+        .voidpkgs
+        .join(&res.local_repo_rel)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| res.voidpkgs.join("hostdir"));
+    if let Err(e) = std::fs::create_dir_all(&hostdir) {
+        log.error(format!("failed to create {}: {e}", hostdir.display()));
+        return ExitCode::from(1);
+    }
+
+    let pkgs = match order::build_order(tree, pkgs) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if pkgs.len() > 1 && !log.quiet {
+        println!("build order: {}", pkgs.join(" "));
+    }
+
+    // Rootless podman maps container root to us; docker's root is the real one.
+    let owner = match std::fs::metadata(&hostdir) {
+        Ok(m) if runtime == "docker" => Some(format!("{}:{}", m.uid(), m.gid())),
+        _ => None,
+    };
+
+    let tmp = match TempDir::new("vx-container") {
+        Ok(t) => t,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    let conf = tmp.join("conf");
+    let text = std::fs::read_to_string(tree.join("etc/conf")).unwrap_or_default();
+    if let Err(e) = std::fs::write(&conf, container_conf(&text)) {
+        log.error(format!("failed to write {}: {e}", conf.display()));
+        return ExitCode::from(1);
+    }
+    let run = ContainerRun {
+        runtime,
+        image: &image,
+        tree,
+        hostdir: &hostdir,
+        conf: &conf,
+        owner: owner.as_deref(),
+    };
+    build_each(log, &run, &pkgs, opts)
+}
+
+/// Where and how each package's container runs.
+struct ContainerRun<'a> {
+    runtime: &'a str,
+    image: &'a str,
+    tree: &'a Path,
+    hostdir: &'a Path,
+    conf: &'a Path,
+    owner: Option<&'a str>,
+}
+
+fn build_each(log: &Log, run: &ContainerRun, pkgs: &[String], opts: &SrcRunOptions) -> ExitCode {
+    // -m and -H are fixed inside the container.
+    let opts = SrcRunOptions {
+        masterdir: None,
+        hostdir: None,
+        temp_masterdir: false,
+        ..opts.clone()
+    };
+    for p in pkgs {
+        let xbps_src_args = join_args_with_opts("pkg", std::slice::from_ref(p), &opts);
+        let args = run_args(run.image, run.tree, run.hostdir, run.conf, run.owner, xbps_src_args);
+        if log.verbose && !log.quiet {
+            let shown: Vec<String> = args.iter().map(|a| a.to_string_lossy().into_owned()).collect();
+            log.exec(format!("{} {}", run.runtime, shown.join(" ")));
+        }
+        let mut cmd = Command::new(run.runtime);
+        cmd.args(&args);
+        let c = buildlog::run_command_logged(log, cmd, p);
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{container_conf, default_image, run_args};
+    use std::path::Path;
+
+    #[test]
+    fn container_run_mounts_tree_and_hostdir() {
+        assert_eq!(default_image(None), "ghcr.io/void-linux/void-buildroot-glibc:latest");
+        assert_eq!(
+            default_image(Some("x86_64-musl")),
+            "ghcr.io/void-linux/void-buildroot-musl:latest"
+        );
+
+        let args: Vec<String> = run_args(
+            "img",
+            Path::new("/src/vp"),
+            Path::new("/src/vp/hostdir"),
+            Path::new("/tmp/c.conf"),
+            None,
+            vec!["pkg".into(), "foo".into()],
+        )
+        .into_iter()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+        assert_eq!(args[..5], ["run", "--rm", "-v", "/src/vp:/void-packages", "-v"]);
+        assert_eq!(args[5], "/src/vp/hostdir:/hostdir");
+        assert_eq!(args[7], "/tmp/c.conf:/void-packages/etc/conf:ro");
+        assert_eq!(args[args.len() - 3..], ["sh", "pkg", "foo"]);
+        assert!(args.contains(&"img".to_string()));
+        assert!(args.iter().any(|a| a.contains("exec ./xbps-src")));
+
+        let args: Vec<String> = run_args(
+            "img",
+            Path::new("/src/vp"),
+            Path::new("/src/vp/hostdir"),
+            Path::new("/tmp/c.conf"),
+            Some("1000:1000"),
+            vec!["pkg".into(), "foo".into()],
+        )
+        .into_iter()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+        assert!(args.iter().any(|a| a.ends_with("; rc=$?; chown -R 1000:1000 /hostdir; exit $rc")));
+
+        assert_eq!(
+            container_conf("XBPS_CCACHE=yes\nXBPS_CHROOT_CMD=bwrap\n"),
+            "XBPS_CCACHE=yes\nXBPS_CHROOT_CMD=ethereal\nXBPS_ALLOW_CHROOT_BREAKOUT=yes\n"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{log::Log, tempdir::TempDir};
+use std::{
+    fs, io,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use super::{
+    git::{self, UPSTREAM_REF, read_template_upstream},
+    resolve::SrcResolved,
+    srcpkgs::local_source_pkgname,
+    template::Template,
+};
+
+/// srcpkgs/<template> plus the subpackage links pointing at it, relative to void-packages.
+fn template_paths(voidpkgs: &Path, template: &str) -> Vec<String> {
+    let mut out = vec![format!("srcpkgs/{template}")];
+    for e in fs::read_dir(voidpkgs.join("srcpkgs"))
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let name = e.file_name().to_string_lossy().into_owned();
+        if e.path().is_symlink()
+            && local_source_pkgname(voidpkgs, &name).as_deref() == Some(template)
+        {
+            out.push(format!("srcpkgs/{name}"));
+        }
+    }
+    out
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for e in fs::read_dir(from)?.flatten() {
+        let (src, dst) = (e.path(), to.join(e.file_name()));
+        if src.is_symlink() {
+            symlink(fs::read_link(&src)?, &dst)?;
+        } else if src.is_dir() {
+            copy_dir(&src, &dst)?;
+        } else {
+            fs::copy(&src, &dst)?;
+        }
+    }
+    Ok(())
+}
+
+/// Void-style subject for a template change, from its version here and on
+/// upstream/master. None when the version didn't change.
+fn commit_message(pkg: &str, version: &str, upstream: Option<&str>) -> Option<String> {
+    match upstream {
+        None => Some(format!("New package: {pkg}-{version}")),
+        Some(v) if v != version => Some(format!("{pkg}: update to {version}.")),
+        Some(_) => None,
+    }
+}
+
+fn resolve_template(log: &Log, res: &SrcResolved, pkg: &str) -> Option<String> {
+    let t = local_source_pkgname(&res.voidpkgs, pkg.trim());
+    if t.is_none() {
+        log.error(format!(
+            "no template for {pkg} in {}/srcpkgs",
+            res.voidpkgs.display()
+        ));
+    }
+    t
+}
+
+/// `vx src branch <pkg> [--name <branch>]`: a branch off upstream/master
+/// carrying the template as it is in the working tree now.
+pub fn cmd_branch(log: &Log, res: &SrcResolved, pkg: &str, name: Option<&str>) -> ExitCode {
+    let Some(template) = resolve_template(log, res, pkg) else {
+        return ExitCode::from(2);
+    };
+    let branch = name.unwrap_or(&template);
+    let dir = &res.voidpkgs;
+
+    if git::run(
+        log,
+        dir,
+        &[
+            "rev-parse",
+            "--verify",
+            "-q",
+            &format!("refs/heads/{branch}"),
+        ],
+    )
+    .is_ok()
+    {
+        log.error(format!("branch '{branch}' already exists"));
+        return ExitCode::from(2);
+    }
+    if let Err(e) = git::fetch_voidpkgs(log, dir) {
+        log.error(e);
+        return ExitCode::from(1);
+    }
+
+    // Keep a copy of the template dir and links; the checkout replaces them.
+    let paths = template_paths(dir, &template);
+    let tmp = match TempDir::new("vx-branch") {
+        Ok(t) => t,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    let links: Vec<(String, PathBuf)> = paths[1..]
+        .iter()
+        .filter_map(|p| Some((p.clone(), fs::read_link(dir.join(p)).ok()?)))
+        .collect();
+    if let Err(e) = copy_dir(&dir.join(&paths[0]), tmp.path()) {
+        log.error(format!("failed to copy {}: {e}", paths[0]));
+        return ExitCode::from(1);
+    }
+
+    let result = (|| -> Result<bool, String> {
+        let mut args = vec!["status", "--porcelain", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        let dirty = !git::run(log, dir, &args)?.trim().is_empty();
+        if dirty {
+            let msg = format!("vx src branch {template}");
+            let mut args = vec!["stash", "push", "--include-untracked", "-m", &msg, "--"];
+            args.extend(paths.iter().map(String::as_str));
+            git::run(log, dir, &args)?;
+        }
+
+        git::run(log, dir, &["checkout", "-b", branch, UPSTREAM_REF])?;
+
+        let target = dir.join(&paths[0]);
+        if target.exists() {
+            fs::remove_dir_all(&target)
+                .map_err(|e| format!("failed to replace {}: {e}", target.display()))?;
+        }
+        copy_dir(tmp.path(), &target)
+            .map_err(|e| format!("failed to restore {}: {e}", target.display()))?;
+        for (p, to) in &links {
+            let link = dir.join(p);
+            if fs::symlink_metadata(&link).is_err() {
+                symlink(to, &link)
+                    .map_err(|e| format!("failed to link {}: {e}", link.display()))?;
+            }
+        }
+        Ok(dirty)
+    })();
+
+    match result {
+        Ok(stashed) => {
+            log.info(format!(
+                "vx: on branch '{branch}' (from {UPSTREAM_REF}) with your {template} template"
+            ));
+            if stashed {
+                log.info("vx: the uncommitted changes were also stashed on your previous branch (git stash list).");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log.error(e);
+            ExitCode::from(1)
+        }
+    }
+}
+
+/// `vx src commit <pkg> [-m <msg>]`: commit the template with a Void-style message.
+pub fn cmd_commit(log: &Log, res: &SrcResolved, pkg: &str, message: Option<&str>) -> ExitCode {
+    let Some(template) = resolve_template(log, res, pkg) else {
+        return ExitCode::from(2);
+    };
+    let dir = &res.voidpkgs;
+
+    let message = match message {
+        Some(m) => m.to_string(),
+        None => {
+            let tpl = dir.join("srcpkgs").join(&template).join("template");
+            let version = match fs::read_to_string(&tpl)
+                .map_err(|e| e.to_string())
+                .and_then(|t| Template::parse(&t))
+            {
+                Ok(t) => t.version,
+                Err(e) => {
+                    log.error(format!("{}: {e}", tpl.display()));
+                    return ExitCode::from(1);
+                }
+            };
+            let upstream = read_template_upstream(dir, &template)
+                .ok()
+                .and_then(|t| Template::parse(&t).ok())
+                .map(|t| t.version);
+            match commit_message(&template, &version, upstream.as_deref()) {
+                Some(m) => m,
+                None => {
+                    log.error(format!(
+                        "{template} is at {version} on {UPSTREAM_REF} too; pass -m \"{template}: ...\""
+                    ));
+                    return ExitCode::from(2);
+                }
+            }
+        }
+    };
+
+    let paths = template_paths(dir, &template);
+    let mut add = vec!["add", "--"];
+    add.extend(paths.iter().map(String::as_str));
+    let mut commit = vec!["commit", "-m", &message, "--"];
+    commit.extend(paths.iter().map(String::as_str));
+    let r = git::run(log, dir, &add).and_then(|_| git::run(log, dir, &commit));
+    match r {
+        Ok(_) => {
+            log.info(format!("vx: committed \"{message}\""));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log.error(e);
+            ExitCode::from(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::commit_message;
+
+    #[test]
+    fn void_style_commit_subjects() {
+        assert_eq!(
+            commit_message("foo", "1.2", None).as_deref(),
+            Some("New package: foo-1.2")
+        );
+        assert_eq!(
+            commit_message("foo", "1.3", Some("1.2")).as_deref(),
+            Some("foo: update to 1.3.")
+        );
+        assert_eq!(commit_message("foo", "1.2", Some("1.2")), None);
+    }
+}
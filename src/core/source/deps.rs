@@ -0,0 +1,189 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{log::Log, privilege};
+use std::{
+    collections::BTreeSet,
+    fs,
+    process::{ExitCode, Stdio},
+};
+
+use super::template::Template;
+use super::resolve::SrcResolved;
+use super::{confirm_once, xbps_query_pkgver};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildDeps {
+    pub hostmakedepends: Vec<String>,
+    pub makedepends: Vec<String>,
+}
+
+impl BuildDeps {
+    /// All build deps, deduplicated and sorted.
+    pub fn all(&self) -> Vec<String> {
+        let set: BTreeSet<String> = self
+            .hostmakedepends
+            .iter()
+            .chain(self.makedepends.iter())
+            .cloned()
+            .collect();
+        set.into_iter().collect()
+    }
+}
+
+pub fn parse_build_deps(text: &str) -> BuildDeps {
+    let t = Template::parse(text).unwrap_or_default();
+    BuildDeps {
+        hostmakedepends: dep_names(&t.hostmakedepends),
+        makedepends: dep_names(&t.makedepends),
+    }
+}
+
+/// Bare package names of depends-style tokens.
+///
+/// Version constraints (`foo>=1.0`) are stripped; shell expansions such as
+/// `$(vopt_if ...)` cannot be evaluated here and are skipped.
+fn dep_names(tokens: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut depth: usize = 0;
+    for tok in tokens {
+        let opens = tok.matches('(').count();
+        let closes = tok.matches(')').count();
+        let inside = depth > 0 || opens > 0 || tok.contains('$');
+        depth = (depth + opens).saturating_sub(closes);
+        if inside {
+            continue;
+        }
+        if let Some(name) = dep_pkgname(tok)
+            && !out.contains(&name)
+        {
+            out.push(name);
+        }
+    }
+    out
+}
+
+/// `foo>=1.0` -> `foo`, `virtual?awk` -> `awk`.
+pub fn dep_pkgname(tok: &str) -> Option<String> {
+    let tok = tok.trim();
+    let tok = tok.strip_prefix("virtual?").unwrap_or(tok);
+    let end = tok.find(['<', '>', '=']).unwrap_or(tok.len());
+    let name = tok[..end].trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// `vx src deps <pkg> [--install-host]`
+pub fn cmd_deps(
+    log: &Log,
+    res: &SrcResolved,
+    pkg: &str,
+    install_host: bool,
+    yes: bool,
+) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx src deps <pkg> [--install-host]");
+        return ExitCode::from(2);
+    }
+
+    let tpl = res.voidpkgs.join("srcpkgs").join(pkg).join("template");
+    let text = match fs::read_to_string(&tpl) {
+        Ok(s) => s,
+        Err(e) => {
+            log.error(format!("failed to read template {}: {e}", tpl.display()));
+            return ExitCode::from(2);
+        }
+    };
+
+    let deps = parse_build_deps(&text);
+
+    if !install_host {
+        println!("{pkg} build dependencies:");
+        println!("  hostmakedepends: {}", join_or_none(&deps.hostmakedepends));
+        println!("  makedepends:     {}", join_or_none(&deps.makedepends));
+        return ExitCode::SUCCESS;
+    }
+
+    let missing: Vec<String> = deps
+        .all()
+        .into_iter()
+        .filter(|d| xbps_query_pkgver(d).is_none())
+        .collect();
+
+    if missing.is_empty() {
+        log.info(format!("vx: build dependencies of {pkg} already installed."));
+        return ExitCode::SUCCESS;
+    }
+
+    if !log.quiet {
+        println!("host install plan ({}, marked automatic):", missing.len());
+        for d in &missing {
+            println!("  {d}");
+        }
+    }
+
+    if !yes && !confirm_once("Proceed?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut cmd = privilege::command("xbps-install");
+    cmd.args(["-S", "-A", "-y"]);
+    cmd.args(&missing);
+    cmd.stdin(Stdio::inherit());
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+
+    if log.verbose && !log.quiet {
+        log.exec(privilege::label(&format!("xbps-install -S -A -y {}", missing.join(" "))));
+    }
+
+    crate::core::xbps::with_journal(log, &format!("vx src deps --install-host {pkg}"), || {
+        match cmd.status() {
+            Ok(s) => ExitCode::from(s.code().unwrap_or(1) as u8),
+            Err(e) => {
+                log.error(format!("failed to run xbps-install: {e}"));
+                ExitCode::from(1)
+            }
+        }
+    })
+}
+
+fn join_or_none(v: &[String]) -> String {
+    if v.is_empty() {
+        "(none)".to_string()
+    } else {
+        v.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dep_pkgname, parse_build_deps};
+
+    #[test]
+    fn parses_multiline_deps_and_strips_constraints() {
+        let tpl = "pkgname=foo\n\
+                   version=1.0\n\
+                   hostmakedepends=\"pkg-config\n meson\"\n\
+                   makedepends=\"libfoo-devel>=2.0 $(vopt_if x11 libX11-devel) zlib-devel\"\n";
+        let deps = parse_build_deps(tpl);
+        assert_eq!(deps.hostmakedepends, vec!["pkg-config", "meson"]);
+        assert_eq!(deps.makedepends, vec!["libfoo-devel", "zlib-devel"]);
+        assert_eq!(
+            deps.all(),
+            vec!["libfoo-devel", "meson", "pkg-config", "zlib-devel"]
+        );
+    }
+
+    #[test]
+    fn dep_pkgname_handles_virtual_and_operators() {
+        assert_eq!(dep_pkgname("virtual?awk").as_deref(), Some("awk"));
+        assert_eq!(dep_pkgname("glibc<2.40").as_deref(), Some("glibc"));
+        assert_eq!(dep_pkgname(">=1"), None);
+    }
+}
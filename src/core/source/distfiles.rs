@@ -0,0 +1,187 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{core::xbps::format_size, log::Log};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use super::{
+    confirm_once,
+    git::upstream_worktree_path,
+    resolve::SrcResolved,
+    template::{parse_template_var_str, Template},
+};
+
+/// What the templates in srcpkgs/ still need: their checksums and
+/// `<pkgname>-<version>` directories.
+#[derive(Debug, Clone, Default)]
+struct Referenced {
+    checksums: BTreeSet<String>,
+    versions: BTreeSet<String>,
+}
+
+/// References of the checkout's templates and of its upstream worktree's,
+/// which upstream builds fetch into the same hostdir.
+fn referenced(voidpkgs: &Path) -> Result<Referenced, String> {
+    let mut out = Referenced::default();
+    add_references(&mut out, voidpkgs)?;
+    let wt = upstream_worktree_path(voidpkgs);
+    if wt.join("srcpkgs").is_dir() {
+        add_references(&mut out, &wt)?;
+    }
+    Ok(out)
+}
+
+fn add_references(out: &mut Referenced, voidpkgs: &Path) -> Result<(), String> {
+    let srcpkgs = voidpkgs.join("srcpkgs");
+    let rd = fs::read_dir(&srcpkgs).map_err(|e| format!("failed to read {}: {e}", srcpkgs.display()))?;
+
+    for e in rd.flatten() {
+        if e.path().is_symlink() {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(e.path().join("template")) else {
+            continue;
+        };
+        if let Ok(t) = Template::parse(&text) {
+            out.versions.insert(format!("{}-{}", e.file_name().to_string_lossy(), t.version));
+        }
+        // `@` marks a checksum of the extracted contents.
+        for sum in parse_template_var_str(&text, "checksum").unwrap_or_default().split_whitespace() {
+            out.checksums.insert(sum.trim_start_matches('@').to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Is a distfile entry unused? `by_sha256/<sum>_<file>` goes by checksum,
+/// `<pkgname>-<version>/` by the templates' current versions.
+fn is_obsolete(entry: &str, in_by_sha256: bool, refs: &Referenced) -> bool {
+    if in_by_sha256 {
+        return entry
+            .split_once('_')
+            .is_some_and(|(sum, _)| !refs.checksums.contains(sum));
+    }
+    entry.contains('-') && !refs.versions.contains(entry)
+}
+
+/// Where xbps-src keeps distfiles: hostdir/sources of the checkout (and of
+/// the local repository's hostdir, which upstream builds share).
+fn distfile_dirs(res: &SrcResolved) -> Vec<PathBuf> {
+    let mut out = vec![res.voidpkgs.join("hostdir").join("sources")];
+    if let Some(hostdir) = res.voidpkgs.join(&res.local_repo_rel).parent() {
+        out.push(hostdir.join("sources"));
+    }
+    out.push(res.voidpkgs.join("distfiles"));
+    out.dedup();
+    out.retain(|d| d.is_dir());
+    out
+}
+
+pub fn size_of(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| size_of(&e.path()))
+        .sum()
+}
+
+/// Distfiles no template references any more.
+fn obsolete_distfiles(res: &SrcResolved) -> Result<Vec<PathBuf>, String> {
+    let refs = referenced(&res.voidpkgs)?;
+    let mut out = Vec::new();
+    for dir in distfile_dirs(res) {
+        for e in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let name = e.file_name().to_string_lossy().into_owned();
+            if name == "by_sha256" {
+                for f in fs::read_dir(e.path()).into_iter().flatten().flatten() {
+                    if is_obsolete(&f.file_name().to_string_lossy(), true, &refs) {
+                        out.push(f.path());
+                    }
+                }
+            } else if e.path().is_dir() && is_obsolete(&name, false, &refs) {
+                out.push(e.path());
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// `vx src purge-distfiles [-n] [-y]`
+pub fn cmd_purge_distfiles(log: &Log, res: &SrcResolved, dry_run: bool, yes: bool) -> ExitCode {
+    let obsolete = match obsolete_distfiles(res) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if obsolete.is_empty() {
+        log.info("vx: no obsolete distfiles.");
+        return ExitCode::SUCCESS;
+    }
+
+    // by_sha256 entries are hardlinks of the per-version files; count them once.
+    let bytes: u64 = obsolete
+        .iter()
+        .filter(|p| !p.parent().is_some_and(|d| d.ends_with("by_sha256")))
+        .map(|p| size_of(p))
+        .sum();
+    if !log.quiet {
+        println!("obsolete distfiles ({}, {}):", obsolete.len(), format_size(bytes));
+        for p in &obsolete {
+            println!("  {}", p.display());
+        }
+    }
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+    if !yes && !confirm_once("Remove them?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut failed = false;
+    for p in &obsolete {
+        let r = if p.is_dir() {
+            fs::remove_dir_all(p)
+        } else {
+            fs::remove_file(p)
+        };
+        if let Err(e) = r {
+            log.warn(format!("failed to remove {}: {e}", p.display()));
+            failed = true;
+        }
+    }
+    log.info(format!("vx: reclaimed {}.", format_size(bytes)));
+    if failed { ExitCode::from(1) } else { ExitCode::SUCCESS }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_obsolete, Referenced};
+
+    #[test]
+    fn distfiles_of_old_versions_and_unknown_sums_are_obsolete() {
+        let refs = Referenced {
+            checksums: ["aaa".to_string()].into(),
+            versions: ["foo-bar-1.2".to_string()].into(),
+        };
+        assert!(!is_obsolete("foo-bar-1.2", false, &refs));
+        assert!(is_obsolete("foo-bar-1.1", false, &refs));
+        assert!(is_obsolete("gone-0.1", false, &refs));
+        assert!(!is_obsolete("aaa_foo-bar-1.2.tar.gz", true, &refs));
+        assert!(is_obsolete("bbb_foo-bar-1.1.tar.gz", true, &refs));
+    }
+}
@@ -0,0 +1,74 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{core::pkg, log::Log};
+use std::{
+    env, fs,
+    process::{Command, ExitCode},
+};
+
+use super::{resolve::SrcResolved, srcpkgs::local_source_pkgname, xbps_src};
+
+/// `vx src edit <pkg> [--new] [--lint] [--gensum]`: open the template in
+/// $VISUAL/$EDITOR, then optionally lint it and refresh its checksums.
+pub fn cmd_edit(log: &Log, res: &SrcResolved, pkg: &str, new: bool, lint: bool, gensum: bool) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx src edit <pkg> [--new]");
+        return ExitCode::from(2);
+    }
+
+    let srcpkg = match local_source_pkgname(&res.voidpkgs, pkg) {
+        Some(s) => s,
+        None if new => {
+            let c = pkg::pkg_new(log, Some(res.voidpkgs.clone()), None, pkg);
+            if c != ExitCode::SUCCESS {
+                return c;
+            }
+            pkg.to_string()
+        }
+        None => {
+            log.error(format!("no template for {pkg} in srcpkgs/ (use --new to create it)"));
+            return ExitCode::from(2);
+        }
+    };
+
+    let tpl = res.voidpkgs.join("srcpkgs").join(&srcpkg).join("template");
+    let before = fs::read_to_string(&tpl).unwrap_or_default();
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    if log.verbose && !log.quiet {
+        log.exec(format!("{editor} {}", tpl.display()));
+    }
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&tpl)
+        .status();
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => return ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(e) => {
+            log.error(format!("failed to run {editor}: {e}"));
+            return ExitCode::from(1);
+        }
+    }
+
+    if fs::read_to_string(&tpl).unwrap_or_default() == before {
+        log.info("template unchanged.");
+    }
+
+    if lint {
+        let c = xbps_src::lint(log, res, std::slice::from_ref(&srcpkg));
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+    }
+    if gensum {
+        return pkg::pkg_gensum(log, Some(res.voidpkgs.clone()), None, &srcpkg, false, false, None, None);
+    }
+    ExitCode::SUCCESS
+}
@@ -8,9 +8,12 @@ use std::{
     hash::{Hash, Hasher},
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-const UPSTREAM_REF: &str = "upstream/master";
+use super::buildlog;
+
+pub const UPSTREAM_REF: &str = "upstream/master";
 
 fn xdg_cache_home() -> PathBuf {
     if let Ok(v) = std::env::var("XDG_CACHE_HOME") {
@@ -27,38 +30,75 @@ fn worktree_root_dir() -> PathBuf {
     xdg_cache_home().join("vx").join("worktrees")
 }
 
+/// `<bucket>/source`: the void-packages checkout a worktree was made from.
+const SOURCE_FILE: &str = "source";
+
 fn stable_hash(s: &str) -> String {
     let mut h = DefaultHasher::new();
     s.hash(&mut h);
     format!("{:016x}", h.finish())
 }
 
+/// `git <args>` in `dir`: stdout on success, stderr as the error.
+pub fn output(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let out = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        return Err(if err.is_empty() {
+            format!("git {} failed", args.join(" "))
+        } else {
+            format!("git {} failed: {err}", args.join(" "))
+        });
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// `output`, traced with -v.
+pub fn run(log: &Log, dir: &Path, args: &[&str]) -> Result<String, String> {
+    if log.verbose && !log.quiet {
+        log.exec(format!("(cd {}) && git {}", dir.display(), args.join(" ")));
+    }
+    output(dir, args)
+}
+
 /// Fetch upstream refs without modifying the current branch/working tree.
 ///
-/// - TTL-cached (default 10m). Set VX_FRESH=1 to bypass.
+/// - TTL-cached (`cache.git_ttl`, default 10m). `--fresh` bypasses it.
 /// - Does NOT merge/rebase your branch — your checkout is untouched.
 pub fn sync_voidpkgs(log: &Log, voidpkgs: &Path) -> Result<(), String> {
-    let ttl = cache::sync_ttl_secs();
+    let ttl = cache::git_ttl_secs();
     let cache_key = format!("voidpkgs.fetch:{}", voidpkgs.display());
 
-    let git_dir = voidpkgs.join(".git");
-    if !git_dir.exists() {
-        return Err(format!(
-            "void-packages at {} is not a git repo (missing .git); cannot sync",
-            voidpkgs.display()
-        ));
-    }
-
     if cache::is_fresh(&cache_key, ttl) {
         if log.verbose && !log.quiet {
             log.exec(format!(
-                "cache hit: skip git fetch (ttl={}s); set VX_FRESH=1 to force",
+                "cache hit: skip git fetch (ttl={}s); use --fresh to force",
                 ttl
             ));
         }
         return Ok(());
     }
 
+    fetch_voidpkgs(log, voidpkgs)
+}
+
+/// `git fetch upstream master` regardless of the TTL, then mark the fetch stamp.
+pub fn fetch_voidpkgs(log: &Log, voidpkgs: &Path) -> Result<(), String> {
+    let cache_key = format!("voidpkgs.fetch:{}", voidpkgs.display());
+
+    let git_dir = voidpkgs.join(".git");
+    if !git_dir.exists() {
+        return Err(format!(
+            "void-packages at {} is not a git repo (missing .git); cannot sync",
+            voidpkgs.display()
+        ));
+    }
+
     let has_upstream = Command::new("git")
         .current_dir(voidpkgs)
         .args(["remote", "get-url", "upstream"])
@@ -105,7 +145,7 @@ pub fn sync_voidpkgs(log: &Log, voidpkgs: &Path) -> Result<(), String> {
         .map_err(|e| format!("failed to run git fetch: {e}"))?;
 
     if status.success() {
-        cache::mark(&cache_key);
+        cache::mark(&cache_key, cache::git_ttl_secs());
         Ok(())
     } else {
         Err(format!(
@@ -165,23 +205,101 @@ pub fn read_template_upstream(voidpkgs: &Path, pkg: &str) -> Result<String, Stri
     Ok(String::from_utf8_lossy(&out.stdout).to_string())
 }
 
+/// Commit upstream/master points at; None when the ref is missing.
+pub fn upstream_head(voidpkgs: &Path) -> Option<String> {
+    let out = Command::new("git")
+        .current_dir(voidpkgs)
+        .args(["rev-parse", "--verify", "-q", UPSTREAM_REF])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let head = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (out.status.success() && !head.is_empty()).then_some(head)
+}
+
+/// Commit checked out in `dir` (a void-packages checkout or worktree).
+pub fn head_commit(dir: &Path) -> Option<String> {
+    let out = Command::new("git")
+        .current_dir(dir)
+        .args(["rev-parse", "--verify", "-q", "HEAD"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let head = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (out.status.success() && !head.is_empty()).then_some(head)
+}
+
+/// Commits upstream/master is ahead of `commit`; None when either is unknown
+/// (e.g. a commit from a checkout that was since garbage-collected).
+pub fn commits_behind_upstream(voidpkgs: &Path, commit: &str) -> Option<u64> {
+    let out = Command::new("git")
+        .current_dir(voidpkgs)
+        .args(["rev-list", "--count", &format!("{commit}..{UPSTREAM_REF}")])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+}
+
+/// Whether `path` differs between `commit` and upstream/master.
+pub fn changed_since(voidpkgs: &Path, commit: &str, path: &str) -> Option<bool> {
+    let status = Command::new("git")
+        .current_dir(voidpkgs)
+        .args(["diff", "--quiet", commit, UPSTREAM_REF, "--", path])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?;
+    match status.code() {
+        Some(0) => Some(false),
+        Some(1) => Some(true),
+        _ => None,
+    }
+}
+
 /// Ensure a reusable worktree is checked out at upstream/master and return its path.
 ///
 /// - Lives in ~/.cache/vx/worktrees/<hash>/upstream-master.
 /// - Creates it via `git worktree add --detach` if missing.
-/// - Hard-resets and cleans on each call so it's always at upstream/master.
-pub fn ensure_upstream_worktree(log: &Log, voidpkgs: &Path) -> Result<PathBuf, String> {
+/// - Hard-resets and cleans on each call so it's always at upstream/master;
+///   `keep_masterdirs`, or a failed build's builddir in them, spares its
+///   masterdirs.
+pub fn ensure_upstream_worktree(
+    log: &Log,
+    voidpkgs: &Path,
+    keep_masterdirs: bool,
+) -> Result<PathBuf, String> {
     sync_voidpkgs(log, voidpkgs)?;
+    upstream_worktree(log, voidpkgs, keep_masterdirs)
+}
 
-    let root = worktree_root_dir();
-    fs::create_dir_all(&root).map_err(|e| format!("failed to create worktree dir: {e}"))?;
+/// Where the upstream worktree of `voidpkgs` lives (whether or not it exists).
+pub fn upstream_worktree_path(voidpkgs: &Path) -> PathBuf {
+    worktree_root_dir()
+        .join(stable_hash(&voidpkgs.display().to_string()))
+        .join("upstream-master")
+}
 
-    let h = stable_hash(&voidpkgs.display().to_string());
-    let repo_bucket = root.join(h);
+/// `ensure_upstream_worktree` without the fetch: checks out upstream/master as
+/// it was last fetched (for building exactly what a plan was made against).
+pub fn upstream_worktree(log: &Log, voidpkgs: &Path, keep_masterdirs: bool) -> Result<PathBuf, String> {
+    let wt = upstream_worktree_path(voidpkgs);
+    let repo_bucket = wt.parent().unwrap_or(&wt).to_path_buf();
     fs::create_dir_all(&repo_bucket)
         .map_err(|e| format!("failed to create worktree bucket: {e}"))?;
 
-    let wt = repo_bucket.join("upstream-master");
+    // Which checkout the bucket belongs to; its mtime is the last use.
+    let _ = fs::write(repo_bucket.join(SOURCE_FILE), voidpkgs.display().to_string());
 
     if !wt.exists() {
         if log.verbose && !log.quiet {
@@ -214,11 +332,16 @@ pub fn ensure_upstream_worktree(log: &Log, voidpkgs: &Path) -> Result<PathBuf, S
     }
 
     // Reset to upstream/master and clean untracked files.
+    let mut clean_args = vec!["clean", "-fdx"];
+    if keep_masterdirs || buildlog::any_failed_in(&wt) {
+        clean_args.extend(["-e", "/masterdir*"]);
+    }
     if log.verbose && !log.quiet {
         log.exec(format!(
-            "(cd {}) && git reset --hard {} && git clean -fdx",
+            "(cd {}) && git reset --hard {} && git {}",
             wt.display(),
-            UPSTREAM_REF
+            UPSTREAM_REF,
+            clean_args.join(" ")
         ));
     }
 
@@ -242,7 +365,7 @@ pub fn ensure_upstream_worktree(log: &Log, voidpkgs: &Path) -> Result<PathBuf, S
 
     let clean_ok = Command::new("git")
         .current_dir(&wt)
-        .args(["clean", "-fdx"])
+        .args(&clean_args)
         .stdin(Stdio::null())
         .stdout(if log.verbose && !log.quiet { Stdio::inherit() } else { Stdio::null() })
         .stderr(if log.verbose && !log.quiet { Stdio::inherit() } else { Stdio::null() })
@@ -256,3 +379,130 @@ pub fn ensure_upstream_worktree(log: &Log, voidpkgs: &Path) -> Result<PathBuf, S
 
     Ok(wt)
 }
+
+/// An upstream worktree under ~/.cache/vx/worktrees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorktreeInfo {
+    /// `<hash>` directory holding the worktree.
+    pub bucket: PathBuf,
+    /// The void-packages checkout it came from (unknown for old buckets).
+    pub source: Option<PathBuf>,
+    /// Unix time of the last build/fetch through it.
+    pub last_used: u64,
+}
+
+impl WorktreeInfo {
+    pub fn worktree(&self) -> PathBuf {
+        self.bucket.join("upstream-master")
+    }
+
+    /// Unused for longer than `max_age_days`, or its checkout is gone. Old
+    /// buckets without a `source` stamp go by age alone.
+    pub fn is_stale(&self, now: u64, max_age_days: u64) -> bool {
+        let gone = self.source.as_ref().is_some_and(|s| !s.join(".git").exists());
+        worktree_stale(gone, self.last_used, now, max_age_days)
+    }
+}
+
+fn worktree_stale(source_gone: bool, last_used: u64, now: u64, max_age_days: u64) -> bool {
+    source_gone || now.saturating_sub(last_used) > max_age_days * 86400
+}
+
+fn mtime_secs(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// All worktree buckets, most recently used first.
+pub fn list_worktrees() -> Vec<WorktreeInfo> {
+    let mut out: Vec<WorktreeInfo> = fs::read_dir(worktree_root_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| {
+            let bucket = e.path();
+            let stamp = bucket.join(SOURCE_FILE);
+            WorktreeInfo {
+                source: fs::read_to_string(&stamp)
+                    .ok()
+                    .map(|s| PathBuf::from(s.trim()))
+                    .filter(|p| !p.as_os_str().is_empty()),
+                last_used: mtime_secs(&stamp).or_else(|| mtime_secs(&bucket)).unwrap_or(0),
+                bucket,
+            }
+        })
+        .collect();
+    out.sort_by_key(|w| std::cmp::Reverse(w.last_used));
+    out
+}
+
+/// Delete a worktree bucket and unregister it from its checkout.
+pub fn remove_worktree(log: &Log, w: &WorktreeInfo) -> Result<(), String> {
+    if log.verbose && !log.quiet {
+        log.exec(format!("rm -rf {}", w.bucket.display()));
+    }
+    fs::remove_dir_all(&w.bucket)
+        .map_err(|e| format!("failed to remove {}: {e}", w.bucket.display()))?;
+
+    if let Some(src) = w.source.as_ref().filter(|s| s.join(".git").exists()) {
+        if log.verbose && !log.quiet {
+            log.exec(format!("(cd {}) && git worktree prune", src.display()));
+        }
+        let _ = Command::new("git")
+            .current_dir(src)
+            .args(["worktree", "prune"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    Ok(())
+}
+
+/// Stamp of the last `auto_prune_worktrees`.
+pub const AUTO_PRUNE_KEY: &str = "worktrees.auto-prune";
+
+/// Remove stale worktrees of other checkouts, at most once a day.
+/// `max_age_days == 0` turns this off.
+pub fn auto_prune_worktrees(log: &Log, voidpkgs: &Path, max_age_days: u64) {
+    if max_age_days == 0 || cache::is_fresh(AUTO_PRUNE_KEY, 86400) {
+        return;
+    }
+    cache::mark(AUTO_PRUNE_KEY, 86400);
+
+    let current = worktree_root_dir().join(stable_hash(&voidpkgs.display().to_string()));
+    let now = now_secs();
+    for w in list_worktrees() {
+        if w.bucket == current || !w.is_stale(now, max_age_days) {
+            continue;
+        }
+        match remove_worktree(log, &w) {
+            Ok(()) => log.info(format!("vx: pruned unused worktree {}", w.bucket.display())),
+            Err(e) => log.warn(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::worktree_stale;
+
+    #[test]
+    fn worktrees_go_stale_by_age_or_missing_checkout() {
+        let day = 86400;
+        assert!(!worktree_stale(false, 100 * day, 120 * day, 30));
+        assert!(worktree_stale(false, 100 * day, 131 * day, 30));
+        assert!(worktree_stale(true, 100 * day, 100 * day, 30));
+    }
+}
@@ -0,0 +1,247 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cli::SrcRepoCmd,
+    core::xbps::{local_repo_pkgvers, pkgname_from_pkgver, split_xbps_filename},
+    log::Log,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+};
+
+use super::{add::discover_local_repo_dirs, resolve::SrcResolved};
+
+/// Index problems of one local repository directory and architecture.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RepoReport {
+    pub dir: PathBuf,
+    pub arch: String,
+    /// Indexed pkgvers whose .xbps file is gone (the cause of
+    /// "failed to checksum" errors on install).
+    pub missing_files: Vec<String>,
+    /// .xbps files of packages the index doesn't list at all.
+    pub unindexed: Vec<String>,
+}
+
+impl RepoReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing_files.is_empty() && self.unindexed.is_empty()
+    }
+}
+
+/// Compare an index (`pkgname -> pkgver`) with the .xbps files next to it.
+fn compare_index(
+    index: &BTreeMap<String, String>,
+    arch: &str,
+    files: &BTreeSet<String>,
+) -> (Vec<String>, Vec<String>) {
+    let has_file = |pkgver: &str| {
+        files.contains(&format!("{pkgver}.{arch}.xbps"))
+            || files.contains(&format!("{pkgver}.noarch.xbps"))
+    };
+    let missing = index.values().filter(|v| !has_file(v)).cloned().collect();
+
+    let unindexed = files
+        .iter()
+        .filter(|f| {
+            split_xbps_filename(f).is_some_and(|(pkgver, a)| {
+                (a == arch || a == "noarch")
+                    && pkgname_from_pkgver(&pkgver).is_some_and(|n| !index.contains_key(&n))
+            })
+        })
+        .cloned()
+        .collect();
+    (missing, unindexed)
+}
+
+/// Architectures `dir` has an index for (`x86_64-repodata` -> `x86_64`).
+fn repodata_arches(dir: &Path) -> Vec<String> {
+    let mut out: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            name.strip_suffix("-repodata").map(String::from)
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+fn xbps_files(dir: &Path) -> BTreeSet<String> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|n| n.ends_with(".xbps"))
+        .collect()
+}
+
+fn repo_dirs(log: &Log, res: &SrcResolved) -> Option<Vec<PathBuf>> {
+    let base = res.voidpkgs.join(&res.local_repo_rel);
+    if !base.is_dir() {
+        log.error(format!("local repo not found at {} (build packages first)", base.display()));
+        return None;
+    }
+    match discover_local_repo_dirs(&base, res.use_nonfree, None) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log.error(e);
+            None
+        }
+    }
+}
+
+fn report(log: &Log, dir: &Path) -> Vec<RepoReport> {
+    let files = xbps_files(dir);
+    let mut out = Vec::new();
+    for arch in repodata_arches(dir) {
+        let index = match local_repo_pkgvers(dir, &arch) {
+            Ok(i) => i,
+            Err(e) => {
+                log.warn(e);
+                continue;
+            }
+        };
+        let (missing_files, unindexed) = compare_index(&index, &arch, &files);
+        out.push(RepoReport {
+            dir: dir.to_path_buf(),
+            arch,
+            missing_files,
+            unindexed,
+        });
+    }
+    out
+}
+
+fn rindex(log: &Log, dir: &Path, args: &[&str], files: &[String], arch: Option<&str>) -> ExitCode {
+    if log.verbose && !log.quiet {
+        let files = if files.is_empty() { String::new() } else { " *.xbps".to_string() };
+        let env = arch.map(|a| format!("XBPS_TARGET_ARCH={a} ")).unwrap_or_default();
+        log.exec(format!("(cd {}) && {env}xbps-rindex {}{files}", dir.display(), args.join(" ")));
+    }
+    let mut cmd = Command::new("xbps-rindex");
+    cmd.args(args).args(files).current_dir(dir).stdin(Stdio::null());
+    if let Some(a) = arch {
+        cmd.env("XBPS_TARGET_ARCH", a);
+    }
+    match cmd.status() {
+        Ok(s) => ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(e) => {
+            log.error(format!("failed to run xbps-rindex: {e}"));
+            ExitCode::from(1)
+        }
+    }
+}
+
+/// `vx src repo check|index|clean`
+pub fn dispatch(log: &Log, res: &SrcResolved, cmd: SrcRepoCmd) -> ExitCode {
+    let Some(dirs) = repo_dirs(log, res) else {
+        return ExitCode::from(2);
+    };
+
+    match cmd {
+        SrcRepoCmd::Check => check(log, &dirs),
+        SrcRepoCmd::Index => {
+            for dir in &dirs {
+                // Drop entries whose files are gone, then (re)add every package file.
+                for arch in repodata_arches(dir) {
+                    let c = rindex(log, dir, &["-c", "."], &[], Some(&arch));
+                    if c != ExitCode::SUCCESS {
+                        return c;
+                    }
+                }
+                let files: Vec<String> = xbps_files(dir).into_iter().collect();
+                if !files.is_empty() {
+                    let c = rindex(log, dir, &["-a"], &files, None);
+                    if c != ExitCode::SUCCESS {
+                        return c;
+                    }
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        SrcRepoCmd::Clean => {
+            for dir in &dirs {
+                for arch in repodata_arches(dir) {
+                    for args in [["-r", "."], ["-c", "."]] {
+                        let c = rindex(log, dir, &args, &[], Some(&arch));
+                        if c != ExitCode::SUCCESS {
+                            return c;
+                        }
+                    }
+                }
+            }
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Exits 1 when any index has problems, so scripts can act on it.
+fn check(log: &Log, dirs: &[PathBuf]) -> ExitCode {
+    let reports: Vec<RepoReport> = dirs.iter().flat_map(|d| report(log, d)).collect();
+    let clean = reports.iter().all(RepoReport::is_ok);
+    let code = if clean {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    };
+    if log.json {
+        log.emit_json(&reports);
+        return code;
+    }
+
+    for r in &reports {
+        if r.is_ok() {
+            if !log.quiet {
+                println!("{} ({}): ok", r.dir.display(), r.arch);
+            }
+            continue;
+        }
+        println!("{} ({}):", r.dir.display(), r.arch);
+        for p in &r.missing_files {
+            println!("  {p}  (indexed, file missing)");
+        }
+        for f in &r.unindexed {
+            println!("  {f}  (not indexed)");
+        }
+    }
+    if !clean && !log.quiet {
+        println!("fix with `vx src repo index`.");
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compare_index;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    #[test]
+    fn index_entries_without_files_and_files_without_entries() {
+        let index: BTreeMap<String, String> = [("foo", "foo-1.1_1"), ("doc", "doc-2_1"), ("gone", "gone-1_1")]
+            .into_iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+        let files: BTreeSet<String> = [
+            "foo-1.0_1.x86_64.xbps",
+            "foo-1.1_1.x86_64.xbps",
+            "doc-2_1.noarch.xbps",
+            "bar-3_1.aarch64.xbps",
+            "baz-1_1.x86_64.xbps",
+        ]
+        .map(String::from)
+        .into();
+
+        let (missing, unindexed) = compare_index(&index, "x86_64", &files);
+        assert_eq!(missing, ["gone-1_1"]);
+        assert_eq!(unindexed, ["baz-1_1.x86_64.xbps"]);
+    }
+}
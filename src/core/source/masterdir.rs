@@ -0,0 +1,157 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cli::MasterdirCmd,
+    log::Log,
+    masterdirs::{self, Masterdir},
+};
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use super::{
+    confirm_once,
+    resolve::SrcResolved,
+    xbps_src::{join_args_with_opts, run_xbps_src_with_env, SrcRunOptions},
+};
+
+/// `-m` for xbps-src: a registered masterdir name resolves to its path,
+/// anything else is taken as a path.
+pub fn resolve_arg(value: &Path) -> PathBuf {
+    if let Some(name) = value.to_str().filter(|n| masterdirs::valid_name(n))
+        && let Ok(Some(m)) = masterdirs::find(name)
+    {
+        return m.path;
+    }
+    value.to_path_buf()
+}
+
+/// `-m <masterdir>` for xbps-src.
+fn with_masterdir(masterdir: &Path) -> SrcRunOptions {
+    SrcRunOptions {
+        masterdir: Some(masterdir.to_path_buf()),
+        ..SrcRunOptions::default()
+    }
+}
+
+pub fn dispatch(log: &Log, res: &SrcResolved, cmd: MasterdirCmd) -> ExitCode {
+    match cmd {
+        MasterdirCmd::List => list(log),
+        MasterdirCmd::Create { arch, path, name } => create(log, res, &name, arch, path),
+        MasterdirCmd::Remove { yes, name } => remove(log, res, &name, yes),
+    }
+}
+
+fn list(log: &Log) -> ExitCode {
+    let all = match masterdirs::load() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if all.is_empty() {
+        if !log.quiet {
+            println!("no masterdirs registered. use `vx src masterdir create <name>` to add one.");
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let w = all.iter().map(|m| m.name.len()).max().unwrap_or(0);
+    for m in &all {
+        let state = if m.path.join(".xbps_chroot_init").exists() {
+            ""
+        } else {
+            "  (not bootstrapped)"
+        };
+        println!("  {:<w$}  {:<14} {}{state}", m.name, m.arch, m.path.display());
+    }
+    ExitCode::SUCCESS
+}
+
+fn create(
+    log: &Log,
+    res: &SrcResolved,
+    name: &str,
+    arch: Option<String>,
+    path: Option<PathBuf>,
+) -> ExitCode {
+    if !masterdirs::valid_name(name) {
+        log.error(format!(
+            "invalid masterdir name '{name}' (use a-z, 0-9 and _; not \"names\")"
+        ));
+        return ExitCode::from(2);
+    }
+    match masterdirs::find(name) {
+        Ok(Some(m)) => {
+            log.error(format!("masterdir '{name}' already exists at {}", m.path.display()));
+            return ExitCode::from(2);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    }
+
+    let arch = arch.unwrap_or_else(crate::core::repo::xbps_arch);
+    let path = path.unwrap_or_else(|| res.voidpkgs.join(format!("masterdir-{name}")));
+
+    let args = join_args_with_opts(
+        "binary-bootstrap",
+        std::slice::from_ref(&arch),
+        &with_masterdir(&path),
+    );
+    let c = run_xbps_src_with_env(log, &res.voidpkgs, args, &[]);
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+
+    let entry = Masterdir {
+        name: name.to_string(),
+        path,
+        arch,
+    };
+    if let Err(e) = masterdirs::add(entry) {
+        log.error(e);
+        return ExitCode::from(1);
+    }
+    log.info(format!("vx: created masterdir '{name}' (build with --masterdir {name})"));
+    ExitCode::SUCCESS
+}
+
+fn remove(log: &Log, res: &SrcResolved, name: &str, yes: bool) -> ExitCode {
+    let m = match masterdirs::find(name) {
+        Ok(Some(m)) => m,
+        Ok(None) => {
+            log.error(format!("no masterdir named '{name}' (see `vx src masterdir list`)"));
+            return ExitCode::from(2);
+        }
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if !yes && !confirm_once(&format!("Remove masterdir '{name}' ({})?", m.path.display())) {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    if m.path.exists() {
+        let args = join_args_with_opts("zap", &[], &with_masterdir(&m.path));
+        let c = run_xbps_src_with_env(log, &res.voidpkgs, args, &[]);
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+    }
+
+    if let Err(e) = masterdirs::remove(name) {
+        log.error(e);
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
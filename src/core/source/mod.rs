@@ -3,24 +3,50 @@
 
 use crate::{
     cli::{SrcBuildFlags, SrcCmd},
-    config::Config,
+    config::{Config, DEFAULT_WORKTREE_MAX_AGE_DAYS},
     log::Log,
-    managed,
+    managed, privilege,
 };
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     io::{self, Write},
     path::PathBuf,
-    process::{Command, ExitCode, Stdio},
+    process::{ExitCode, Stdio},
 };
 
 pub mod add;
+pub mod adopt;
+pub mod buildlog;
+pub mod bump;
+pub mod changelog;
+pub mod check;
+pub mod checkvers;
+pub mod container;
+pub mod contribute;
+pub mod deps;
+pub mod distfiles;
+pub mod edit;
 pub mod git;
+pub mod localrepo;
+pub mod masterdir;
+pub mod order;
 pub mod plan;
+pub mod pr;
+pub mod profile;
+pub mod push;
 pub mod resolve;
+pub mod revdeps;
+pub mod shell;
+pub mod srcpkgs;
+pub mod stamp;
+pub mod stats;
+pub mod template;
+pub mod tracked;
+pub mod updatecheck;
+pub mod worktree;
 pub mod xbps_src;
 
-pub use plan::{plan_src_updates, SrcUpdate};
+pub use plan::{plan_src_updates, rebuild_suggestions, RebuildSuggestion, SrcUpdate};
 
 /// Print a combined system + source update summary for `vx up -a`.
 pub fn print_up_all_summary(
@@ -41,7 +67,19 @@ pub fn print_up_all_summary(
             println!("  system ({}):", sys.len());
         }
         for u in sys {
-            println!("    {}  {} → {}", u.name, u.from, u.to);
+            let mut line = format!("    {}  {} → {}", u.name, u.from, u.to);
+            if u.action != "update" {
+                line.push_str(&format!("  ({})", u.action));
+            }
+            if log.verbose
+                && let Some(repo) = &u.repository
+            {
+                line.push_str(&format!("  [{repo}]"));
+            }
+            println!("{line}");
+        }
+        if !log.quiet {
+            println!("  {}", crate::core::xbps::plan_totals(sys));
         }
     }
 
@@ -51,7 +89,178 @@ pub fn print_up_all_summary(
         }
         for u in src {
             let inst = u.installed.as_deref().unwrap_or("(not installed)");
-            println!("    {}  {} → {}", u.name, inst, u.candidate);
+            println!("    {}  {} → {}{}", u.name, inst, u.candidate, action_note(&u.action));
+        }
+    }
+}
+
+/// `  (downgrade)` etc. after a plan line; nothing for plain updates.
+pub fn action_note(action: &str) -> String {
+    if action == "update" {
+        String::new()
+    } else {
+        format!("  ({action})")
+    }
+}
+
+/// Sonames the system plan removes, when any managed package could care.
+/// Empty when nothing is tracked, to keep `vx up` from querying every package.
+pub fn removed_shlibs_for_managed(
+    log: &Log,
+    sys: &[crate::core::xbps::SysUpdate],
+    repos: &[String],
+) -> BTreeMap<String, String> {
+    if managed::load_managed().map(|l| l.is_empty()).unwrap_or(true) {
+        return BTreeMap::new();
+    }
+    crate::core::xbps::removed_shlibs(log, sys, repos)
+}
+
+/// Print `vx src up` suggestions for managed packages a system update broke.
+pub fn print_rebuild_suggestions(log: &Log, list: &[RebuildSuggestion]) {
+    if list.is_empty() || log.quiet {
+        return;
+    }
+    println!("source packages linked against removed libraries ({}):", list.len());
+    for r in list {
+        println!("  {}  ({})", r.name, r.sonames.join(", "));
+    }
+    let names: Vec<&str> = list.iter().map(|r| r.name.as_str()).collect();
+    println!("hint: rebuild them with: vx src up -f {}", names.join(" "));
+    println!("      (or pass --rebuild-broken to vx up to do it automatically)");
+}
+
+/// After `vx up`: suggest, or with `rebuild` run, `vx src up` for managed
+/// packages linked against sonames in `removed`.
+pub fn rebuild_after_update(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    removed: &BTreeMap<String, String>,
+    rebuild: bool,
+    remote: bool,
+) -> ExitCode {
+    let list = match rebuild_suggestions(removed) {
+        Ok(v) => v,
+        Err(e) => {
+            log.warn(format!("could not check source packages for rebuilds: {e}"));
+            return ExitCode::SUCCESS;
+        }
+    };
+    if list.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    if !rebuild {
+        print_rebuild_suggestions(log, &list);
+        return ExitCode::SUCCESS;
+    }
+
+    log.info(format!(
+        "vx: rebuilding source packages linked against removed libraries: {}",
+        list.iter().map(|r| r.name.as_str()).collect::<Vec<_>>().join(" ")
+    ));
+    dispatch_src(
+        log,
+        voidpkgs_override,
+        cfg,
+        None,
+        SrcCmd::Up {
+            dry_run: false,
+            force: true,
+            yes: true,
+            local: !remote,
+            remote,
+            stale: false,
+            head: false,
+            group: None,
+            revdeps: false,
+            build: SrcBuildFlags::default(),
+            pkgs: list.into_iter().map(|r| r.name).collect(),
+            xbps_src_args: Vec::new(),
+        },
+    )
+}
+
+/// Build from upstream/master? `--remote`/`--local` win over `source.default_remote`.
+pub fn use_remote(cfg: Option<&Config>, local: bool, remote: bool) -> bool {
+    remote || (!local && cfg.is_none_or(|c| c.default_remote))
+}
+
+/// upstream/master of the resolved void-packages, for `apply_up_all`.
+pub fn planned_upstream_head(voidpkgs_override: Option<PathBuf>, cfg: Option<&Config>) -> Option<String> {
+    let resolved = resolve::resolve_voidpkgs(voidpkgs_override, cfg).ok()?;
+    git::upstream_head(&resolved.voidpkgs)
+}
+
+/// Source half of `vx up -a` once the combined plan was confirmed.
+///
+/// Builds from the upstream/master the plan was made against instead of
+/// fetching and planning again; the candidates are only re-planned when
+/// upstream/master moved in between (e.g. a `vx sync` from cron), and the
+/// new plan is confirmed again unless `yes`.
+pub fn apply_up_all(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    pkgs: &[String],
+    remote: bool,
+    planned_head: Option<&str>,
+    yes: bool,
+) -> ExitCode {
+    let resolved = match resolve::resolve_voidpkgs(voidpkgs_override, cfg) {
+        Ok(r) => r,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut pkgs = pkgs.to_vec();
+    if remote && git::upstream_head(&resolved.voidpkgs).as_deref() != planned_head {
+        log.info("vx: upstream/master moved since the plan; re-planning source updates.");
+        let replanned = plan::plan_src_updates_with_resolved(log, &resolved, &pkgs, true, remote);
+        let updates = match replanned {
+            Ok(v) => v,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        };
+        if updates.is_empty() {
+            log.info("vx: nothing left to build.");
+            return ExitCode::SUCCESS;
+        }
+        if !log.quiet {
+            println!("source update plan ({}):", updates.len());
+            for u in &updates {
+                let inst = u.installed.as_deref().unwrap_or("(not installed)");
+                println!("  {}  {} → {}{}", u.name, inst, u.candidate, action_note(&u.action));
+            }
+        }
+        if !yes && !confirm_once("build the updated source plan?") {
+            log.info("aborted.");
+            return ExitCode::SUCCESS;
+        }
+        pkgs = updates.into_iter().map(|u| u.name).collect();
+    }
+
+    let run_opts = to_src_run_options(&resolved, &SrcBuildFlags::default(), &[]);
+    xbps_src::src_up(log, &resolved, true, remote, false, &pkgs, &run_opts)
+}
+
+/// Source half of `vx clean --src`: obsolete distfiles.
+pub fn clean_src(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    dry_run: bool,
+    yes: bool,
+) -> ExitCode {
+    match resolve::resolve_voidpkgs(voidpkgs_override, cfg) {
+        Ok(res) => distfiles::cmd_purge_distfiles(log, &res, dry_run, yes),
+        Err(e) => {
+            log.error(e);
+            ExitCode::from(2)
         }
     }
 }
@@ -69,15 +278,29 @@ pub fn dispatch_src(
     log: &Log,
     voidpkgs_override: Option<PathBuf>,
     cfg: Option<&Config>,
+    profile: Option<&str>,
     cmd: SrcCmd,
 ) -> ExitCode {
     match cmd {
-        // List doesn't need void-packages resolution.
+        // List, Log and Worktree don't need void-packages resolution.
         SrcCmd::List => return cmd_list(log),
+        SrcCmd::Log {
+            previous,
+            failed,
+            pkg,
+        } => return buildlog::cmd_log(log, pkg.as_deref(), previous, failed),
+        SrcCmd::Worktree { cmd } => return worktree::dispatch(log, cfg, cmd),
+        SrcCmd::Stats { top } => return stats::cmd_stats(log, top),
+        SrcCmd::Untrack { pkgs } => return tracked::cmd_untrack(log, &pkgs),
+        SrcCmd::Pin { pkg, version } => return tracked::cmd_pin(log, &pkg, version.as_deref()),
+        SrcCmd::Unpin { pkg } => return tracked::cmd_unpin(log, &pkg),
+        SrcCmd::Export => return tracked::cmd_export(log),
+        SrcCmd::Tag { pkg, groups } => return tracked::cmd_tag(log, &pkg, &groups, false),
+        SrcCmd::Untag { pkg, groups } => return tracked::cmd_tag(log, &pkg, &groups, true),
 
         // Search needs resolution but we handle it inline.
         SrcCmd::Search { installed, term } => {
-            let resolved = match resolve::resolve_voidpkgs(voidpkgs_override, cfg) {
+            let resolved = match resolve::resolve_voidpkgs_profile(voidpkgs_override, cfg, profile) {
                 Ok(r) => r,
                 Err(e) => {
                     log.error(e);
@@ -90,7 +313,7 @@ pub fn dispatch_src(
         _ => {}
     }
 
-    let resolved = match resolve::resolve_voidpkgs(voidpkgs_override, cfg) {
+    let resolved = match resolve::resolve_voidpkgs_profile(voidpkgs_override, cfg, profile) {
         Ok(r) => r,
         Err(e) => {
             log.error(e);
@@ -98,11 +321,70 @@ pub fn dispatch_src(
         }
     };
 
+    let max_age = cfg.map_or(DEFAULT_WORKTREE_MAX_AGE_DAYS, |c| c.worktree_max_age_days);
+    git::auto_prune_worktrees(log, &resolved.voidpkgs, max_age);
+
     match cmd {
-        SrcCmd::List | SrcCmd::Search { .. } => unreachable!(),
+        SrcCmd::List
+        | SrcCmd::Export
+        | SrcCmd::Log { .. }
+        | SrcCmd::Pin { .. }
+        | SrcCmd::Search { .. }
+        | SrcCmd::Stats { .. }
+        | SrcCmd::Tag { .. }
+        | SrcCmd::Unpin { .. }
+        | SrcCmd::Untag { .. }
+        | SrcCmd::Untrack { .. }
+        | SrcCmd::Worktree { .. } => unreachable!(),
+
+        SrcCmd::Tracked {
+            outdated_tree: true,
+            ..
+        } => tracked::cmd_outdated_tree(log, &resolved),
+        SrcCmd::Tracked { local, remote, .. } => {
+            tracked::cmd_tracked(log, &resolved, use_remote(cfg, local, remote))
+        }
+
+        SrcCmd::Adopt { dry_run, yes } => adopt::cmd_adopt(log, &resolved, yes, dry_run),
+
+        SrcCmd::Import {
+            file,
+            apply,
+            dry_run,
+            yes,
+            local,
+            remote,
+        } => {
+            let run_opts = to_src_run_options(&resolved, &SrcBuildFlags::default(), &[]);
+            let import = tracked::Import {
+                apply,
+                dry_run,
+                yes,
+                remote: use_remote(cfg, local, remote),
+            };
+            tracked::cmd_import(log, &resolved, &file, &import, &run_opts)
+        }
+
+        SrcCmd::Debug { pkg } => buildlog::cmd_debug(log, &resolved.voidpkgs, &pkg),
+
+        SrcCmd::Env {
+            local,
+            remote,
+            masterdir,
+            pkg,
+        } => {
+            let build = SrcBuildFlags {
+                masterdir,
+                ..SrcBuildFlags::default()
+            };
+            let run_opts = to_src_run_options(&resolved, &build, &[]);
+            shell::cmd_env(log, &resolved, use_remote(cfg, local, remote), &pkg, &run_opts)
+        }
 
         SrcCmd::Build {
             local,
+            remote,
+            container,
             build,
             pkgs,
             xbps_src_args,
@@ -111,43 +393,110 @@ pub fn dispatch_src(
                 log.warn("usage: vx src build <pkg> [pkg...]");
                 return ExitCode::from(2);
             }
-            let run_opts = to_src_run_options(&build, &xbps_src_args);
-            let remote = !local;
+            let run_opts = to_src_run_options(&resolved, &build, &xbps_src_args);
+            let remote = use_remote(cfg, local, remote);
+            let image = cfg.and_then(|c| c.container_image.as_deref());
             if remote {
                 // Build from upstream worktree
-                let wt = match git::ensure_upstream_worktree(log, &resolved.voidpkgs) {
+                let wt = match git::ensure_upstream_worktree(log, &resolved.voidpkgs, run_opts.keep) {
                     Ok(p) => p,
                     Err(e) => {
                         log.error(e);
                         return ExitCode::from(1);
                     }
                 };
-                if let Err(e) = xbps_src::ensure_xbps_conf(log, &wt, resolved.use_nonfree) {
+                if let Err(e) = xbps_src::ensure_xbps_conf(log, &wt, &resolved) {
                     log.warn(format!("failed to ensure etc/conf: {e}"));
                 }
+                if let Err(e) = xbps_src::overlay_dirs(log, &resolved, &wt) {
+                    log.warn(format!("failed to apply source.overlays: {e}"));
+                }
                 if let Err(e) =
                     xbps_src::overlay_local_srcpkgs(log, &resolved.voidpkgs, &wt, &pkgs)
                 {
                     log.warn(format!("failed to overlay local srcpkgs: {e}"));
                 }
+                if container {
+                    return container::build(log, &resolved, &wt, image, &pkgs, &run_opts);
+                }
                 let env = xbps_src::build_env_for_worktree(&resolved);
-                xbps_src::run_xbps_src_with_env(
-                    log,
-                    &wt,
-                    xbps_src::join_args_with_opts("pkg", &pkgs, &run_opts),
-                    &env,
-                )
+                let c = xbps_src::ensure_profile_masterdir(log, &resolved, &wt, &run_opts, &env);
+                if c != ExitCode::SUCCESS {
+                    return c;
+                }
+                xbps_src::build_logged(log, &wt, &pkgs, &run_opts, &env)
+            } else if container {
+                container::build(log, &resolved, &resolved.voidpkgs, image, &pkgs, &run_opts)
             } else {
                 xbps_src::build(log, &resolved, &pkgs, &run_opts)
             }
         }
 
-        SrcCmd::Clean { pkgs } => {
+        SrcCmd::Clean { pkgs, xbps_src_args } => {
             if pkgs.is_empty() {
                 log.warn("usage: vx src clean <pkg> [pkg...]");
                 return ExitCode::from(2);
             }
-            xbps_src::clean(log, &resolved, &pkgs)
+            xbps_src::clean(log, &resolved, &pkgs, &xbps_src_args)
+        }
+
+        SrcCmd::Fetch {
+            extract,
+            patch,
+            local,
+            remote,
+            pkgs,
+            xbps_src_args,
+        } => {
+            if pkgs.is_empty() {
+                log.warn("usage: vx src fetch <pkg> [pkg...]");
+                return ExitCode::from(2);
+            }
+            let step = if patch {
+                "patch"
+            } else if extract {
+                "extract"
+            } else {
+                "fetch"
+            };
+            let remote = use_remote(cfg, local, remote);
+            xbps_src::fetch(log, &resolved, remote, step, &pkgs, &xbps_src_args)
+        }
+
+        SrcCmd::Push {
+            dry_run,
+            delete,
+            no_index,
+            dest,
+        } => push::cmd_push(log, &resolved, cfg, &dest, dry_run, delete, no_index),
+
+        SrcCmd::Check {
+            build,
+            pkg,
+            xbps_src_args,
+        } => {
+            let run_opts = to_src_run_options(&resolved, &build, &xbps_src_args);
+            check::cmd_check(log, &resolved, &pkg, &run_opts)
+        }
+
+        SrcCmd::Branch { name, pkg } => contribute::cmd_branch(log, &resolved, &pkg, name.as_deref()),
+
+        SrcCmd::Commit { message, pkg } => {
+            contribute::cmd_commit(log, &resolved, &pkg, message.as_deref())
+        }
+
+        SrcCmd::Pr {
+            remote,
+            draft,
+            no_lint,
+            dry_run,
+            pkg,
+        } => pr::cmd_pr(log, &resolved, &pkg, &remote, draft, no_lint, dry_run),
+
+        SrcCmd::Repo { cmd } => localrepo::dispatch(log, &resolved, cmd),
+
+        SrcCmd::PurgeDistfiles { dry_run, yes } => {
+            distfiles::cmd_purge_distfiles(log, &resolved, dry_run, yes)
         }
 
         SrcCmd::Lint { pkgs } => {
@@ -158,9 +507,40 @@ pub fn dispatch_src(
             xbps_src::lint(log, &resolved, &pkgs)
         }
 
+        SrcCmd::Masterdir { cmd } => masterdir::dispatch(log, &resolved, cmd),
+
+        SrcCmd::Bump {
+            then_build,
+            build,
+            pkg,
+            version,
+        } => {
+            let run_opts = to_src_run_options(&resolved, &build, &[]);
+            bump::cmd_bump(log, &resolved, &pkg, &version, then_build.then_some(&run_opts))
+        }
+
+        SrcCmd::Edit {
+            new,
+            lint,
+            gensum,
+            pkg,
+        } => edit::cmd_edit(log, &resolved, &pkg, new, lint, gensum),
+
+        SrcCmd::Show { remote, pkg } => template::cmd_show(log, &resolved, &pkg, remote),
+
+        SrcCmd::Checkvers => checkvers::cmd_checkvers(log, &resolved),
+
+        SrcCmd::UpdateCheck { all, pkgs } => {
+            updatecheck::cmd_update_check(log, &resolved, &pkgs, all)
+        }
+
+        SrcCmd::Zap { rebootstrap, yes, .. } => xbps_src::zap(log, &resolved, rebootstrap, yes),
+
         SrcCmd::Add {
             yes,
+            force,
             local,
+            remote,
             build,
             pkgs,
             xbps_src_args,
@@ -169,11 +549,24 @@ pub fn dispatch_src(
                 log.warn("usage: vx src add <pkg> [pkg...]");
                 return ExitCode::from(2);
             }
-            let run_opts = to_src_run_options(&build, &xbps_src_args);
-            let remote = !local;
-            xbps_src::src_up(log, &resolved, yes, remote, &pkgs, &run_opts)
+            let run_opts = xbps_src::SrcRunOptions {
+                rebuild: force,
+                ..to_src_run_options(&resolved, &build, &xbps_src_args)
+            };
+            let remote = use_remote(cfg, local, remote);
+            let all = plan::with_installed_subpackages(log, &resolved, &pkgs, remote);
+            if all.len() > pkgs.len() && !log.quiet {
+                println!("also rebuilding installed subpackages: {}", all[pkgs.len()..].join(" "));
+            }
+            xbps_src::src_up(log, &resolved, yes, remote, true, &all, &run_opts)
         }
 
+        SrcCmd::Deps {
+            install_host,
+            yes,
+            pkg,
+        } => deps::cmd_deps(log, &resolved, &pkg, install_host, yes),
+
         SrcCmd::Rm { yes, pkgs } => {
             if pkgs.is_empty() {
                 log.warn("usage: vx src rm <pkg> [pkg...]");
@@ -187,15 +580,58 @@ pub fn dispatch_src(
             force,
             yes,
             local,
+            remote,
+            stale,
+            head,
+            group,
+            revdeps,
             build,
             pkgs,
             xbps_src_args,
         } => {
-            let remote = !local;
-            let run_opts = to_src_run_options(&build, &xbps_src_args);
+            // --head edits templates in the checkout, so build from there.
+            let remote = !head && use_remote(cfg, local, remote);
+            let run_opts = xbps_src::SrcRunOptions {
+                rebuild: force,
+                ..to_src_run_options(&resolved, &build, &xbps_src_args)
+            };
 
             // Determine which packages to update.
-            let targets: Option<Vec<String>> = if pkgs.is_empty() {
+            let targets: Option<Vec<String>> = if stale {
+                match stale_managed(log, &resolved) {
+                    Ok(v) if v.is_empty() => {
+                        log.info("vx: no stale builds of tracked packages.");
+                        return ExitCode::SUCCESS;
+                    }
+                    Ok(v) => Some(v),
+                    Err(e) => {
+                        log.error(e);
+                        return ExitCode::from(1);
+                    }
+                }
+            } else if let Some(group) = &group {
+                match managed::group_members(group) {
+                    Ok(v) if v.is_empty() => {
+                        log.warn(format!("no tracked packages in group {group}."));
+                        return ExitCode::from(1);
+                    }
+                    Ok(v) => Some(v),
+                    Err(e) => {
+                        log.error(format!("failed to load managed list: {e}"));
+                        return ExitCode::from(1);
+                    }
+                }
+            } else if head {
+                match bump::bump_heads(log, &resolved, &pkgs, dry_run) {
+                    Ok(v) if v.is_empty() && pkgs.is_empty() => {
+                        log.info("vx: git packages already at upstream's latest commit.");
+                        return ExitCode::SUCCESS;
+                    }
+                    Ok(v) if pkgs.is_empty() => Some(v),
+                    Ok(_) => Some(pkgs.clone()),
+                    Err(c) => return c,
+                }
+            } else if pkgs.is_empty() {
                 None // plan_src_updates will load all managed
             } else {
                 Some(pkgs.clone())
@@ -217,7 +653,33 @@ pub fn dispatch_src(
                 }
             };
 
-            if updates.is_empty() {
+            // Installed packages built from templates that depend on the targets.
+            let rebuild: Vec<String> = if revdeps {
+                let names: Vec<String> = if pkgs.is_empty() {
+                    updates.iter().map(|u| u.name.clone()).collect()
+                } else {
+                    pkgs.clone()
+                };
+                match revdep_rebuilds(log, &resolved, &names) {
+                    Ok(v) => v
+                        .into_iter()
+                        .filter(|p| !updates.iter().any(|u| u.name == *p))
+                        .collect(),
+                    Err(e) => {
+                        log.error(e);
+                        return ExitCode::from(1);
+                    }
+                }
+            } else {
+                Vec::new()
+            };
+
+            if dry_run && log.json {
+                log.emit_json(&serde_json::json!({ "source": updates, "revdeps": rebuild }));
+                return ExitCode::SUCCESS;
+            }
+
+            if updates.is_empty() && rebuild.is_empty() {
                 if !log.quiet {
                     println!("vx: source already up to date.");
                 }
@@ -225,10 +687,22 @@ pub fn dispatch_src(
             }
 
             if !log.quiet {
-                println!("source update plan ({}):", updates.len());
+                if !updates.is_empty() {
+                    println!("source update plan ({}):", updates.len());
+                }
                 for u in &updates {
                     let inst = u.installed.as_deref().unwrap_or("(not installed)");
-                    println!("  {}  {} → {}", u.name, inst, u.candidate);
+                    println!("  {}  {} → {}{}", u.name, inst, u.candidate, action_note(&u.action));
+                }
+                if !rebuild.is_empty() {
+                    println!("reverse dependencies to rebuild ({}):", rebuild.len());
+                    println!("  {}", rebuild.join(" "));
+                }
+                let names: Vec<String> =
+                    updates.iter().map(|u| u.name.clone()).chain(rebuild.iter().cloned()).collect();
+                let templates = srcpkgs::source_templates(&resolved.voidpkgs, &names);
+                if let Some(line) = stats::estimate_line(&templates) {
+                    println!("{line}");
                 }
             }
 
@@ -241,24 +715,64 @@ pub fn dispatch_src(
                 return ExitCode::SUCCESS;
             }
 
-            let pkgs_to_update: Vec<String> = updates.iter().map(|u| u.name.clone()).collect();
-            xbps_src::src_up(log, &resolved, yes, remote, &pkgs_to_update, &run_opts)
+            let pkgs_to_update: Vec<String> = updates
+                .iter()
+                .map(|u| u.name.clone())
+                .chain(rebuild.iter().cloned())
+                .collect();
+            // Rebuilt reverse dependencies stay untracked unless they already were.
+            let run_opts = xbps_src::SrcRunOptions {
+                untracked: rebuild,
+                ..run_opts
+            };
+            xbps_src::src_up(log, &resolved, yes, remote, true, &pkgs_to_update, &run_opts)
         }
     }
 }
 
-fn to_src_run_options(build: &SrcBuildFlags, passthrough: &[String]) -> xbps_src::SrcRunOptions {
+/// Installed packages whose templates depend on `pkgs`, for `vx src up --revdeps`.
+fn revdep_rebuilds(log: &Log, res: &resolve::SrcResolved, pkgs: &[String]) -> Result<Vec<String>, String> {
+    let index = revdeps::load_index(log, &res.voidpkgs)?;
+    let templates = srcpkgs::source_templates(&res.voidpkgs, pkgs);
+    Ok(index
+        .reverse_deps(&templates)
+        .iter()
+        .flat_map(|t| index.packages_of(t))
+        .filter(|p| xbps_query_pkgver(p).is_some())
+        .collect())
+}
+
+/// Tracked packages `vx src checkvers` reports as stale, for `vx src up --stale`.
+fn stale_managed(log: &Log, res: &resolve::SrcResolved) -> Result<Vec<String>, String> {
+    let managed = managed::load_managed()?;
+    Ok(checkvers::stale_builds(log, res)?
+        .into_iter()
+        .map(|s| s.name)
+        .filter(|n| managed.contains(n))
+        .collect())
+}
+
+/// xbps-src options from the command line; -m/-H fall back to the build profile's.
+fn to_src_run_options(
+    res: &resolve::SrcResolved,
+    build: &SrcBuildFlags,
+    passthrough: &[String],
+) -> xbps_src::SrcRunOptions {
+    let in_voidpkgs = |p: &Option<PathBuf>| p.as_ref().map(|p| res.voidpkgs.join(p));
     xbps_src::SrcRunOptions {
         host: build.host.clone(),
         target: build.target.clone(),
-        jobs: build.jobs,
+        jobs: build.jobs.or(res.jobs),
         build_options: build.build_options.clone(),
         check: build.check,
         check_long: build.check_long,
         no_remote: build.no_remote,
         temp_masterdir: build.temp_masterdir,
-        hostdir: build.hostdir.clone(),
-        masterdir: build.masterdir.clone(),
+        hostdir: build.hostdir.clone().or_else(|| in_voidpkgs(&res.profile.hostdir)),
+        masterdir: match &build.masterdir {
+            Some(m) => Some(masterdir::resolve_arg(m)),
+            None => in_voidpkgs(&res.profile.masterdir),
+        },
         config_name: build.config_name.clone(),
         force_stage: build.force_stage,
         skip_existing: build.skip_existing,
@@ -272,6 +786,9 @@ fn to_src_run_options(build: &SrcBuildFlags, passthrough: &[String]) -> xbps_src
         fail_missing_deps: build.fail_missing_deps,
         strict_warnings: build.strict_warnings,
         passthrough: passthrough.to_vec(),
+        keep: build.keep,
+        rebuild: false,
+        untracked: Vec::new(),
     }
 }
 
@@ -320,8 +837,7 @@ fn cmd_src_rm(log: &Log, _cfg: Option<&Config>, yes: bool, pkgs: &[String]) -> E
     }
 
     // xbps-remove
-    let mut cmd = Command::new("sudo");
-    cmd.arg("xbps-remove");
+    let mut cmd = privilege::command("xbps-remove");
     if yes {
         cmd.arg("-y");
     }
@@ -330,17 +846,17 @@ fn cmd_src_rm(log: &Log, _cfg: Option<&Config>, yes: bool, pkgs: &[String]) -> E
     cmd.stdout(Stdio::inherit());
     cmd.stderr(Stdio::inherit());
 
-    match cmd.status() {
-        Ok(status) => {
-            let code = status.code().unwrap_or(1) as u8;
-            if code != 0 {
-                return ExitCode::from(code);
+    let code = crate::core::xbps::with_journal(log, &format!("vx src rm {}", pkgs.join(" ")), || {
+        match cmd.status() {
+            Ok(status) => ExitCode::from(status.code().unwrap_or(1) as u8),
+            Err(e) => {
+                log.error(format!("failed to run xbps-remove: {e}"));
+                ExitCode::from(1)
             }
         }
-        Err(e) => {
-            log.error(format!("failed to run sudo xbps-remove: {e}"));
-            return ExitCode::from(1);
-        }
+    });
+    if code != ExitCode::SUCCESS {
+        return code;
     }
 
     // Ask before untracking, defaulting to yes.
@@ -370,7 +886,7 @@ fn cmd_src_rm(log: &Log, _cfg: Option<&Config>, yes: bool, pkgs: &[String]) -> E
         return ExitCode::SUCCESS;
     }
 
-    if let Err(e) = managed::remove_managed(&to_untrack) {
+    if let Err(e) = managed::remove_managed(log, &to_untrack) {
         log.warn(format!("removed packages but failed to update managed list: {e}"));
     } else if log.verbose && !log.quiet {
         log.exec(format!("untracked: {}", to_untrack.join(", ")));
@@ -470,7 +986,7 @@ fn cmd_search(
 }
 
 fn xbps_query_pkgver(pkg: &str) -> Option<String> {
-    let out = Command::new("xbps-query")
+    let out = crate::rootdir::command("xbps-query")
         .args(["-p", "pkgver", pkg])
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -0,0 +1,88 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use super::{deps::dep_pkgname, srcpkgs::local_source_pkgname, template::Template};
+
+/// Order `pkgs` so each comes after the ones it depends on; otherwise the
+/// given order is kept. `deps` maps a package to those of `pkgs` it needs.
+fn topo_sort(pkgs: &[String], deps: &BTreeMap<String, Vec<String>>) -> Result<Vec<String>, String> {
+    let mut left: Vec<&String> = pkgs.iter().collect();
+    let mut out: Vec<String> = Vec::with_capacity(pkgs.len());
+    while !left.is_empty() {
+        let ready = left.iter().position(|p| {
+            deps.get(*p)
+                .is_none_or(|d| d.iter().all(|d| d == *p || out.contains(d)))
+        });
+        let Some(i) = ready else {
+            let names: Vec<&str> = left.iter().map(|p| p.as_str()).collect();
+            return Err(format!("dependency cycle between: {}", names.join(" ")));
+        };
+        out.push(left.remove(i).clone());
+    }
+    Ok(out)
+}
+
+/// Build order for `pkgs` in the void-packages tree at `dir`, from the
+/// depends/hostmakedepends/makedepends of their templates.
+///
+/// Subpackages count as their source package, so `foo` needing `libbar-devel`
+/// is built after `bar`. Packages without a readable template keep their place.
+pub fn build_order(dir: &Path, pkgs: &[String]) -> Result<Vec<String>, String> {
+    let template_of = |p: &str| local_source_pkgname(dir, p).unwrap_or_else(|| p.to_string());
+
+    let mut deps: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for p in pkgs {
+        let path = dir.join("srcpkgs").join(p).join("template");
+        let Some(t) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| Template::parse(&text).ok())
+        else {
+            continue;
+        };
+
+        let own = template_of(p);
+        let needs: Vec<String> = t
+            .depends
+            .iter()
+            .chain(&t.hostmakedepends)
+            .chain(&t.makedepends)
+            .filter(|d| !d.contains('$'))
+            .filter_map(|d| dep_pkgname(d))
+            .map(|d| template_of(&d))
+            .filter(|d| *d != own)
+            .flat_map(|d| pkgs.iter().filter(move |q| template_of(q) == d).cloned())
+            .collect();
+        deps.insert(p.clone(), needs);
+    }
+
+    topo_sort(pkgs, &deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::topo_sort;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn dependencies_build_first_and_cycles_fail() {
+        let pkgs = ["app", "libfoo", "tool", "libbar"].map(String::from);
+        let deps: BTreeMap<String, Vec<String>> = [
+            ("app", vec!["libfoo", "tool"]),
+            ("libfoo", vec!["libbar"]),
+            ("tool", vec![]),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.into_iter().map(String::from).collect()))
+        .collect();
+        assert_eq!(topo_sort(&pkgs, &deps).unwrap(), ["tool", "libbar", "libfoo", "app"]);
+
+        let deps: BTreeMap<String, Vec<String>> = [("a", "b"), ("b", "a")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), vec![v.to_string()]))
+            .collect();
+        let pkgs = ["c", "a", "b"].map(String::from);
+        assert_eq!(topo_sort(&pkgs, &deps).unwrap_err(), "dependency cycle between: a b");
+    }
+}
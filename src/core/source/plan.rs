@@ -1,21 +1,62 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use crate::{config::Config, log::Log, managed};
+use crate::{config::Config, core::version, log::Log, managed};
+use serde::Serialize;
 use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
-    process::{Command, Stdio},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+    process::Stdio,
 };
 
-use super::git;
+use super::{git, srcpkgs};
+use super::template::Template;
 use super::resolve::{resolve_voidpkgs, SrcResolved};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct SrcUpdate {
     pub name: String,
+    /// Source template it is built from (differs from `name` for subpackages).
+    pub template: String,
     pub installed: Option<String>,
     pub candidate: String,
+    /// install | update | reinstall | downgrade
+    pub action: String,
+}
+
+/// A managed source package linked against a soname a system update removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RebuildSuggestion {
+    pub name: String,
+    pub sonames: Vec<String>,
+}
+
+/// Managed packages whose shlib-requires include a soname in `removed`
+/// (from `xbps::removed_shlibs`, computed before the system update).
+pub fn rebuild_suggestions(removed: &BTreeMap<String, String>) -> Result<Vec<RebuildSuggestion>, String> {
+    if removed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut out = Vec::new();
+    for name in managed::load_managed()? {
+        let requires = crate::core::xbps::installed_property(&name, "shlib-requires")?.unwrap_or_default();
+        let sonames = affected_sonames(&requires, removed);
+        if !sonames.is_empty() {
+            out.push(RebuildSuggestion { name, sonames });
+        }
+    }
+    Ok(out)
+}
+
+/// Lines of a shlib-requires property that name a removed soname.
+fn affected_sonames(requires: &str, removed: &BTreeMap<String, String>) -> Vec<String> {
+    requires
+        .lines()
+        .map(str::trim)
+        .filter(|so| removed.contains_key(*so))
+        .map(str::to_string)
+        .collect()
 }
 
 /// Plan which source packages need updating.
@@ -62,73 +103,139 @@ pub fn plan_src_updates_with_resolved(
         HashMap::new()
     });
 
+    let pins = managed::load_pins().unwrap_or_else(|e| {
+        log.warn(format!("failed to load pins: {e}"));
+        managed::Pins::new()
+    });
+
     let mut out = Vec::new();
+    let mut seen: BTreeSet<String> = pkgs.iter().cloned().collect();
 
     for name in pkgs {
-        let local_tpl = res.voidpkgs.join("srcpkgs").join(name).join("template");
-
-        let (ver, rev) = if remote {
-            // Remote: prefer upstream template, fall back to local for fork-only packages.
-            match git::read_template_upstream(&res.voidpkgs, name) {
-                Ok(text) => match parse_template_version_revision_str(&text) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        log.warn(format!("{name}: upstream template parse failed: {e}"));
-                        continue;
-                    }
-                },
-                Err(_) => {
-                    if local_tpl.is_file() {
-                        match parse_template_version_revision_file(&local_tpl) {
-                            Ok(v) => v,
-                            Err(e) => {
-                                log.warn(format!("{name}: {e}"));
-                                continue;
-                            }
-                        }
-                    } else {
-                        log.warn(format!(
-                            "{name}: not found in upstream/master and no local template at {}",
-                            local_tpl.display()
-                        ));
-                        continue;
-                    }
-                }
-            }
-        } else {
-            // Local: read from checkout.
-            match parse_template_version_revision_file(&local_tpl) {
-                Ok(v) => v,
-                Err(e) => {
-                    log.warn(format!("{name}: {e}"));
-                    continue;
-                }
+        let Some(text) = read_template(log, res, name, remote) else {
+            continue;
+        };
+        let t = match Template::parse(&text) {
+            Ok(t) => t,
+            Err(e) => {
+                log.warn(format!("{name}: template parse failed: {e}"));
+                continue;
             }
         };
+        let (ver, rev, template) = (t.version, t.revision, t.pkgname);
 
-        let candidate = format!("{name}-{ver}_{rev}");
-        let installed = installed_map.get(name).cloned();
+        if let Some(pin) = pins.get(name).or_else(|| pins.get(&template))
+            && !pin_allows(pin.as_deref(), &ver, &rev)
+        {
+            log.info(format!(
+                "vx: {name} pinned{}; holding back {ver}_{rev} (vx src unpin {name})",
+                pin.as_deref().map(|v| format!(" at {v}")).unwrap_or_default()
+            ));
+            continue;
+        }
 
-        if !force {
-            if let Some(inst) = installed.as_deref() {
-                if inst == candidate {
-                    continue;
-                }
+        // Subpackages built from the same template that are installed move
+        // with it; a newer main package next to an old -devel/lib split
+        // would not satisfy their exact-version dependencies.
+        let mut names = vec![name.clone()];
+        for sub in std::iter::once(template.clone()).chain(t.subpackages) {
+            if installed_map.contains_key(&sub) && seen.insert(sub.clone()) {
+                names.push(sub);
             }
         }
 
-        out.push(SrcUpdate {
-            name: name.clone(),
-            installed,
-            candidate,
-        });
+        for name in names {
+            let candidate = format!("{name}-{ver}_{rev}");
+            let installed = installed_map.get(&name).cloned();
+
+            let action = version::plan_action(installed.as_deref(), &candidate);
+            if action == "reinstall" && !force {
+                continue;
+            }
+
+            out.push(SrcUpdate {
+                name,
+                template: template.clone(),
+                installed,
+                candidate,
+                action: action.to_string(),
+            });
+        }
     }
 
     Ok(out)
 }
 
+/// Whether a template at `ver`_`rev` may be built under a pin: never for a
+/// bare pin, up to the pinned version otherwise (the revision only counts
+/// when the pin names one).
+fn pin_allows(pin: Option<&str>, ver: &str, rev: &str) -> bool {
+    let Some(pin) = pin else {
+        return false;
+    };
+    let candidate = if pin.contains('_') { format!("{ver}_{rev}") } else { ver.to_string() };
+    version::cmp_version(&candidate, pin).is_le()
+}
+
+/// Template text for `name`.
+///
+/// - remote=true: upstream/master, falling back to the local checkout for
+///   fork-only packages.
+/// - remote=false: the local checkout.
+pub(super) fn read_template(log: &Log, res: &SrcResolved, name: &str, remote: bool) -> Option<String> {
+    let local_tpl = res.voidpkgs.join("srcpkgs").join(name).join("template");
+
+    if remote {
+        // git show doesn't follow the srcpkgs/<subpkg> symlinks.
+        let src = srcpkgs::source_pkgname(&res.voidpkgs, name).unwrap_or_else(|| name.to_string());
+        if let Ok(text) = git::read_template_upstream(&res.voidpkgs, &src) {
+            return Some(text);
+        }
+    }
+    if remote && !local_tpl.is_file() {
+        log.warn(format!(
+            "{name}: not found in upstream/master and no local template at {}",
+            local_tpl.display()
+        ));
+        return None;
+    }
+
+    match std::fs::read_to_string(&local_tpl) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            log.warn(format!("{name}: failed to read template {}: {e}", local_tpl.display()));
+            None
+        }
+    }
+}
+
+/// `names` plus the installed subpackages of their templates, for `vx src add`.
+pub fn with_installed_subpackages(
+    log: &Log,
+    res: &SrcResolved,
+    names: &[String],
+    remote: bool,
+) -> Vec<String> {
+    let installed = load_installed_pkgver_map().unwrap_or_default();
+    let mut out = names.to_vec();
+    for name in names {
+        let Some(text) = read_template(log, res, name, remote) else {
+            continue;
+        };
+        let Ok(t) = Template::parse(&text) else {
+            continue;
+        };
+        for sub in std::iter::once(t.pkgname).chain(t.subpackages) {
+            if installed.contains_key(&sub) && !out.contains(&sub) {
+                out.push(sub);
+            }
+        }
+    }
+    out
+}
+
 fn load_installed_pkgver_map() -> Result<HashMap<String, String>, String> {
-    let out = Command::new("xbps-query")
+    let out = crate::rootdir::command("xbps-query")
         .arg("-l")
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -173,41 +280,27 @@ fn pkgname_from_pkgver(pkgver: &str) -> Option<String> {
     }
 }
 
-pub fn parse_template_version_revision_file(path: &Path) -> Result<(String, String), String> {
-    let text = std::fs::read_to_string(path)
-        .map_err(|e| format!("failed to read template {}: {e}", path.display()))?;
-    parse_template_version_revision_str(&text)
-}
-
-pub fn parse_template_version_revision_str(text: &str) -> Result<(String, String), String> {
-    let mut version: Option<String> = None;
-    let mut revision: Option<String> = None;
+#[cfg(test)]
+mod tests {
+    use super::{affected_sonames, pin_allows};
+    use std::collections::BTreeMap;
 
-    for line in text.lines() {
-        let line = line.trim();
-        if line.starts_with('#') || line.is_empty() {
-            continue;
-        }
-        if let Some(v) = line.strip_prefix("version=") {
-            version = Some(unquote(v.trim()));
-        } else if let Some(r) = line.strip_prefix("revision=") {
-            revision = Some(unquote(r.trim()));
-        }
-        if version.is_some() && revision.is_some() {
-            break;
-        }
+    #[test]
+    fn affected_sonames_matches_whole_lines() {
+        let removed: BTreeMap<String, String> =
+            [("libicuuc.so.74".to_string(), "icu-libs-74.2_1".to_string())].into();
+        let requires = "libc.so.6\nlibicuuc.so.74\nlibicuuc.so.745\n";
+        assert_eq!(affected_sonames(requires, &removed), ["libicuuc.so.74"]);
+        assert!(affected_sonames("libc.so.6", &removed).is_empty());
     }
 
-    let version = version.ok_or("template missing version=")?;
-    let revision = revision.unwrap_or_else(|| "1".to_string());
-    Ok((version, revision))
-}
-
-fn unquote(s: &str) -> String {
-    let s = s.trim();
-    if (s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')) {
-        s[1..s.len() - 1].to_string()
-    } else {
-        s.to_string()
+    #[test]
+    fn pins_hold_templates_at_the_pinned_version() {
+        assert!(!pin_allows(None, "1.0", "1"));
+        assert!(pin_allows(Some("1.2"), "1.2", "3"));
+        assert!(pin_allows(Some("1.2"), "1.1", "1"));
+        assert!(!pin_allows(Some("1.2"), "1.3", "1"));
+        assert!(pin_allows(Some("1.2_1"), "1.2", "1"));
+        assert!(!pin_allows(Some("1.2_1"), "1.2", "2"));
     }
 }
@@ -0,0 +1,175 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{core::xbps::format_age, log::Log};
+use std::process::{Command, ExitCode, Stdio};
+
+use super::{
+    buildlog,
+    git::{self, UPSTREAM_REF},
+    resolve::SrcResolved,
+    srcpkgs::local_source_pkgname,
+    xbps_src,
+};
+
+const UPSTREAM_REPO: &str = "void-linux/void-packages";
+
+/// GitHub owner of a remote URL (`git@github.com:me/void-packages.git`,
+/// `https://github.com/me/void-packages`).
+fn github_owner(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.split_once("github.com/").map(|(_, r)| r))?;
+    let owner = rest.split('/').next()?;
+    (!owner.is_empty()).then_some(owner)
+}
+
+/// Pull request description in the layout of the void-packages PR template.
+fn pr_body(commits: &[String], build: Option<&str>, lint: Option<bool>) -> String {
+    let mut out = String::from("#### Changes\n");
+    for c in commits {
+        out.push_str(&format!("- {c}\n"));
+    }
+    out.push_str("\n#### Testing the changes\n- I tested the changes in this PR: **briefly**\n");
+    out.push_str("\n#### Local build testing\n");
+    out.push_str(&format!(
+        "- I built this PR locally for my native architecture: {}\n",
+        build.unwrap_or("**NO**")
+    ));
+    if let Some(ok) = lint {
+        out.push_str(&format!("- xlint: {}\n", if ok { "clean" } else { "**has warnings**" }));
+    }
+    out
+}
+
+/// `vx src pr <pkg> [--remote <name>] [--draft] [--no-lint] [-n]`
+///
+/// Pushes the current package branch (see `vx src branch`) to the fork and
+/// opens a pull request against void-linux/void-packages with `gh`.
+pub fn cmd_pr(
+    log: &Log,
+    res: &SrcResolved,
+    pkg: &str,
+    remote: &str,
+    draft: bool,
+    no_lint: bool,
+    dry_run: bool,
+) -> ExitCode {
+    let dir = &res.voidpkgs;
+    let Some(template) = local_source_pkgname(dir, pkg.trim()) else {
+        log.error(format!("no template for {pkg} in {}/srcpkgs", dir.display()));
+        return ExitCode::from(2);
+    };
+
+    let branch = match git::run(log, dir, &["rev-parse", "--abbrev-ref", "HEAD"]) {
+        Ok(b) => b.trim().to_string(),
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if branch == "master" || branch == "HEAD" {
+        log.error(format!("on {branch}; start a package branch with: vx src branch {template}"));
+        return ExitCode::from(2);
+    }
+
+    let range = format!("{UPSTREAM_REF}..HEAD");
+    let commits: Vec<String> = git::run(log, dir, &["log", "--reverse", "--format=%s", &range])
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect();
+    let Some(title) = commits.first().cloned() else {
+        log.error(format!(
+            "'{branch}' has no commits over {UPSTREAM_REF} (vx src commit {template})"
+        ));
+        return ExitCode::from(2);
+    };
+
+    let owner = match git::run(log, dir, &["remote", "get-url", remote]) {
+        Ok(url) => match github_owner(url.trim()) {
+            Some(o) => o.to_string(),
+            None => {
+                log.error(format!("remote '{remote}' ({}) is not a GitHub fork", url.trim()));
+                return ExitCode::from(2);
+            }
+        },
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let build = buildlog::list(Some(&template)).into_iter().next().map(|l| match l.ok {
+        Some(true) => format!("**YES** (built {})", format_age(l.time)),
+        Some(false) => format!("**NO** (last build failed {})", format_age(l.time)),
+        None => "**NO** (last build did not finish)".to_string(),
+    });
+    let lint = (!no_lint && !dry_run)
+        .then(|| xbps_src::lint(log, res, std::slice::from_ref(&template)) == ExitCode::SUCCESS);
+    let body = pr_body(&commits, build.as_deref(), lint);
+
+    let head = format!("{owner}:{branch}");
+    if log.verbose && !log.quiet {
+        log.exec(format!("git push -u {remote} {branch}"));
+        log.exec(format!(
+            "gh pr create --repo {UPSTREAM_REPO} --base master --head {head}{}",
+            if draft { " --draft" } else { "" }
+        ));
+    }
+    if dry_run {
+        println!("push {branch} to {remote}, then open {head} -> {UPSTREAM_REPO}:master\n");
+        println!("{title}\n");
+        print!("{body}");
+        return ExitCode::SUCCESS;
+    }
+
+    let pushed = Command::new("git")
+        .current_dir(dir)
+        .args(["push", "-u", remote, &branch])
+        .status();
+    match pushed {
+        Ok(s) if s.success() => {}
+        Ok(s) => return ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(e) => {
+            log.error(format!("failed to run git push: {e}"));
+            return ExitCode::from(1);
+        }
+    }
+
+    let mut gh = Command::new("gh");
+    gh.current_dir(dir)
+        .args(["pr", "create", "--repo", UPSTREAM_REPO, "--base", "master", "--head", &head])
+        .args(["--title", &title, "--body", &body]);
+    if draft {
+        gh.arg("--draft");
+    }
+    match gh.status() {
+        Ok(s) if s.success() => ExitCode::SUCCESS,
+        Ok(s) => ExitCode::from(s.code().unwrap_or(1) as u8),
+        Err(_) => {
+            log.warn("gh not found; open the pull request in a browser:");
+            println!("https://github.com/{UPSTREAM_REPO}/compare/master...{head}?expand=1");
+            println!();
+            println!("{body}");
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{github_owner, pr_body};
+
+    #[test]
+    fn fork_owner_and_pr_body() {
+        assert_eq!(github_owner("git@github.com:me/void-packages.git"), Some("me"));
+        assert_eq!(github_owner("https://github.com/me/void-packages"), Some("me"));
+        assert_eq!(github_owner("https://gitlab.com/me/void-packages"), None);
+
+        let body = pr_body(&["foo: update to 1.1.".to_string()], None, Some(true));
+        assert!(body.starts_with("#### Changes\n- foo: update to 1.1.\n"));
+        assert!(body.contains("native architecture: **NO**\n"));
+        assert!(body.ends_with("- xlint: clean\n"));
+    }
+}
@@ -0,0 +1,89 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use std::path::PathBuf;
+
+/// Profiles `--profile` / `source.profile` accept.
+pub const PROFILES: [&str; 3] = ["default", "glibc", "musl"];
+
+/// Where and for which libc xbps-src builds.
+///
+/// The default profile leaves everything to xbps-src. The libc profiles get
+/// their own masterdir (`masterdir-x86_64-musl`), and when they differ from
+/// the host also their own hostdir, so the packages they produce never mix
+/// with native ones in the local repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildProfile {
+    pub name: String,
+    /// xbps-src -m, relative to void-packages. None = xbps-src's default.
+    pub masterdir: Option<PathBuf>,
+    /// Architecture the masterdir is bootstrapped for (`binary-bootstrap <arch>`).
+    pub arch: Option<String>,
+    /// xbps-src -H, relative to void-packages. None = the configured local repo.
+    pub hostdir: Option<PathBuf>,
+}
+
+impl BuildProfile {
+    /// Packages built here can't be installed on the host as-is.
+    pub fn is_foreign(&self, host_arch: &str) -> bool {
+        self.arch.as_deref().is_some_and(|a| a != host_arch)
+    }
+}
+
+/// Resolve a profile name against the host architecture (`x86_64`, `aarch64-musl`, ...).
+pub fn build_profile(name: &str, host_arch: &str) -> Result<BuildProfile, String> {
+    let name = name.trim();
+    let machine = host_arch.trim_end_matches("-musl");
+    let arch = match name {
+        "" | "default" => {
+            return Ok(BuildProfile {
+                name: "default".to_string(),
+                ..BuildProfile::default()
+            });
+        }
+        "glibc" => machine.to_string(),
+        "musl" => format!("{machine}-musl"),
+        other => {
+            return Err(format!(
+                "unknown build profile '{other}' (expected one of: {})",
+                PROFILES.join(", ")
+            ));
+        }
+    };
+
+    Ok(arch_profile(name, &arch, host_arch))
+}
+
+/// A profile named `name` building for `arch`: its own masterdir, and its own
+/// hostdir unless `arch` is the host's.
+pub fn arch_profile(name: &str, arch: &str, host_arch: &str) -> BuildProfile {
+    BuildProfile {
+        name: name.to_string(),
+        masterdir: Some(PathBuf::from(format!("masterdir-{arch}"))),
+        hostdir: (arch != host_arch).then(|| PathBuf::from(format!("hostdir-{arch}"))),
+        arch: Some(arch.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::build_profile;
+    use std::path::PathBuf;
+
+    #[test]
+    fn musl_profile_gets_its_own_masterdir_and_hostdir() {
+        let p = build_profile("musl", "x86_64").unwrap();
+        assert_eq!(p.arch.as_deref(), Some("x86_64-musl"));
+        assert_eq!(p.masterdir, Some(PathBuf::from("masterdir-x86_64-musl")));
+        assert_eq!(p.hostdir, Some(PathBuf::from("hostdir-x86_64-musl")));
+        assert!(p.is_foreign("x86_64"));
+
+        // Native libc: same output location as the default profile.
+        let p = build_profile("musl", "aarch64-musl").unwrap();
+        assert_eq!(p.masterdir, Some(PathBuf::from("masterdir-aarch64-musl")));
+        assert_eq!(p.hostdir, None);
+
+        assert_eq!(build_profile("", "x86_64").unwrap().masterdir, None);
+        assert!(build_profile("uclibc", "x86_64").is_err());
+    }
+}
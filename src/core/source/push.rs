@@ -0,0 +1,156 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    config::{Config, PushDest},
+    core::xbps,
+    log::Log,
+};
+use std::process::{Command, ExitCode};
+
+use super::resolve::SrcResolved;
+
+/// `user@host:/path` -> (Some(user@host), /path); a plain path has no host.
+fn split_dest(dest: &str) -> (Option<&str>, &str) {
+    match dest.split_once(':') {
+        Some((host, path)) if !host.is_empty() && !host.contains('/') => (Some(host), path),
+        _ => (None, dest),
+    }
+}
+
+pub(super) fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// `shell_quote` for a path, leaving a leading `~/` outside the quotes so
+/// the shell still expands it to the home directory.
+fn quote_path(p: &str) -> String {
+    match p.strip_prefix("~/") {
+        Some(rest) => format!("~/{}", shell_quote(rest)),
+        None => shell_quote(p),
+    }
+}
+
+/// Shell script run at the destination: index (and sign) every repository
+/// directory under `path`.
+fn remote_script(path: &str, d: &PushDest) -> String {
+    let mut each = String::from("xbps-rindex -a *.xbps");
+    if let Some(key) = &d.sign_key {
+        let by = d.signed_by.as_deref().unwrap_or(&d.name);
+        each.push_str(&format!(
+            " && xbps-rindex --sign --signedby {} --privkey {k} . && xbps-rindex --sign-pkg --privkey {k} *.xbps",
+            shell_quote(by),
+            k = quote_path(key)
+        ));
+    }
+    format!(
+        "find {} -name '*.xbps' -exec dirname {{}} \\; | sort -u | while read -r d; do (cd \"$d\" && {each}) || exit 1; done",
+        quote_path(path)
+    )
+}
+
+/// `vx src push <dest> [-n] [--delete] [--no-index]`
+///
+/// `dest` is a `push.names` entry from the config or a literal rsync target.
+pub fn cmd_push(
+    log: &Log,
+    res: &SrcResolved,
+    cfg: Option<&Config>,
+    dest: &str,
+    dry_run: bool,
+    delete: bool,
+    no_index: bool,
+) -> ExitCode {
+    let configured = cfg.and_then(|c| c.push_destinations.iter().find(|d| d.name == dest));
+    let d = match configured {
+        Some(d) => d.clone(),
+        None if dest.contains([':', '/']) => PushDest {
+            name: dest.to_string(),
+            dest: dest.to_string(),
+            reindex: true,
+            ..PushDest::default()
+        },
+        None => {
+            log.error(format!("unknown push destination '{dest}' (add it to push.names in the config)"));
+            return ExitCode::from(2);
+        }
+    };
+
+    let base = res.voidpkgs.join(&res.local_repo_rel);
+    if !base.is_dir() {
+        log.error(format!("local repo not found at {} (build packages first)", base.display()));
+        return ExitCode::from(2);
+    }
+
+    // Trailing slash: copy the contents, not the directory itself.
+    let src = format!("{}/", base.display());
+    let mut rsync = Command::new("rsync");
+    rsync.args(["-a", "--info=progress2"]);
+    if dry_run {
+        rsync.arg("--dry-run").arg("-v");
+    }
+    if delete {
+        rsync.arg("--delete");
+    }
+    rsync.arg(&src).arg(&d.dest);
+    let shown = format!(
+        "rsync -a{}{} {src} {}",
+        if dry_run { " --dry-run" } else { "" },
+        if delete { " --delete" } else { "" },
+        d.dest
+    );
+    let c = xbps::run(log, rsync, &shown);
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+
+    if dry_run || no_index || !d.reindex {
+        return ExitCode::SUCCESS;
+    }
+
+    let (host, path) = split_dest(&d.dest);
+    let script = remote_script(path, &d);
+    let c = match host {
+        Some(h) => {
+            let mut ssh = Command::new("ssh");
+            ssh.arg(h).arg(&script);
+            xbps::run(log, ssh, &format!("ssh {h} {script}"))
+        }
+        None => {
+            let mut sh = Command::new("sh");
+            sh.arg("-c").arg(&script);
+            xbps::run(log, sh, &script)
+        }
+    };
+    if c == ExitCode::SUCCESS {
+        log.info(format!("vx: pushed {} to {}", base.display(), d.dest));
+    }
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote_path, remote_script, split_dest};
+    use crate::config::PushDest;
+
+    #[test]
+    fn destinations_split_and_signing_script() {
+        assert_eq!(split_dest("me@box:/srv/repo"), (Some("me@box"), "/srv/repo"));
+        assert_eq!(split_dest("/mnt/usb/repo"), (None, "/mnt/usb/repo"));
+        assert_eq!(split_dest("./rel:dir"), (None, "./rel:dir"));
+
+        let d = PushDest {
+            name: "box".to_string(),
+            dest: "me@box:/srv/repo".to_string(),
+            reindex: true,
+            sign_key: Some("/k.pem".to_string()),
+            signed_by: None,
+        };
+        let s = remote_script("/srv/repo", &d);
+        assert!(s.starts_with("find '/srv/repo' -name '*.xbps'"));
+        assert!(s.contains("--signedby 'box' --privkey '/k.pem' ."));
+
+        assert_eq!(quote_path("~/srv/it's"), r"~/'srv/it'\''s'");
+        assert_eq!(quote_path("/srv/~x"), "'/srv/~x'");
+    }
+}
@@ -2,66 +2,142 @@
 // License: MIT
 
 use crate::config::Config;
-use std::{env, path::PathBuf};
+use std::path::PathBuf;
+
+use super::profile::{arch_profile, build_profile, BuildProfile};
 
 #[derive(Debug, Clone)]
 pub struct SrcResolved {
     pub voidpkgs: PathBuf,
     pub local_repo_rel: PathBuf,
     pub use_nonfree: bool,
+    pub profile: BuildProfile,
+    /// build.jobs: default for `-j`.
+    pub jobs: Option<usize>,
+    /// build.env: `NAME=value` lines kept in etc/conf.
+    pub build_env: Vec<String>,
+    /// source.overlays: directories of extra templates copied into the build tree.
+    pub overlays: Vec<PathBuf>,
+}
+
+impl SrcResolved {
+    /// Switch to a build profile (`--profile`); its hostdir, if any, becomes
+    /// the local repository.
+    pub fn set_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = build_profile(name, &crate::core::repo::xbps_arch())?;
+        self.use_build_profile(profile);
+        Ok(())
+    }
+
+    fn use_build_profile(&mut self, profile: BuildProfile) {
+        if let Some(h) = &profile.hostdir {
+            self.local_repo_rel = h.join("binpkgs");
+        }
+        self.profile = profile;
+    }
 }
 
 pub fn resolve_voidpkgs(
     voidpkgs_override: Option<PathBuf>,
     cfg: Option<&Config>,
 ) -> Result<SrcResolved, String> {
-    let mut local_repo_rel = PathBuf::from("hostdir/binpkgs");
-    let mut use_nonfree = true;
+    resolve_voidpkgs_profile(voidpkgs_override, cfg, None)
+}
 
+/// Resolve for a profile (`--profile`, else `source.profile`): a
+/// `source.profiles` entry brings its own checkout, local repo, arch and
+/// nonfree setting; default/glibc/musl only pick the build profile.
+pub fn resolve_voidpkgs_profile(
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    profile: Option<&str>,
+) -> Result<SrcResolved, String> {
+    let name = profile
+        .or(cfg.map(|c| c.build_profile.as_str()))
+        .map(str::trim)
+        .filter(|n| !n.is_empty() && *n != "default");
+    let source = name.and_then(|n| cfg?.source_profiles.iter().find(|p| p.name == n));
+
+    // --voidpkgs still wins over the profile's checkout.
+    let voidpkgs_override = voidpkgs_override.or_else(|| source.and_then(|s| s.path.clone()));
+    let mut resolved = resolve_path(voidpkgs_override, cfg)?;
     if let Some(c) = cfg {
-        if !c.local_repo_rel.as_os_str().is_empty() {
-            local_repo_rel = c.local_repo_rel.clone();
+        resolved.jobs = c.build_jobs;
+        resolved.build_env = c.build_env.clone();
+        if let Some(m) = &c.chroot_cmd {
+            resolved.build_env.push(format!("XBPS_CHROOT_CMD={m}"));
         }
-        use_nonfree = c.use_nonfree;
-    }
-
-    if let Some(p) = voidpkgs_override {
-        return Ok(SrcResolved {
-            voidpkgs: p,
-            local_repo_rel,
-            use_nonfree,
-        });
+        resolved.overlays = c.source_overlays.clone();
     }
 
-    if let Ok(v) = env::var("VX_VOIDPKGS") {
-        let p = PathBuf::from(v);
-        if !p.as_os_str().is_empty() {
-            return Ok(SrcResolved {
-                voidpkgs: p,
-                local_repo_rel,
-                use_nonfree,
-            });
+    match (name, source) {
+        (_, Some(s)) => {
+            if let Some(arch) = &s.arch {
+                let host = crate::core::repo::xbps_arch();
+                resolved.use_build_profile(arch_profile(&s.name, arch, &host));
+            } else {
+                resolved.profile.name = s.name.clone();
+            }
+            if let Some(r) = &s.local_repo {
+                resolved.local_repo_rel = r.clone();
+            }
+            if let Some(n) = s.use_nonfree {
+                resolved.use_nonfree = n;
+            }
         }
+        (Some(n), None) => resolved.set_profile(n).map_err(|e| match cfg {
+            Some(c) if !c.source_profiles.is_empty() => {
+                let names: Vec<&str> = c.source_profiles.iter().map(|p| p.name.as_str()).collect();
+                format!("{e}, or a source profile: {}", names.join(", "))
+            }
+            _ => e,
+        })?,
+        (None, None) => {}
     }
+    Ok(resolved)
+}
+
+/// The void-packages checkout to use: --voidpkgs, else `void_packages.path`
+/// (which VX_VOIDPKGS overrides). None when none of them is set.
+pub fn voidpkgs_path(voidpkgs_override: Option<PathBuf>, cfg: Option<&Config>) -> Option<PathBuf> {
+    let set = |p: &PathBuf| !p.as_os_str().is_empty();
+    voidpkgs_override
+        .filter(set)
+        .or_else(|| cfg?.void_packages_path.clone().filter(set))
+}
+
+fn resolve_path(
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+) -> Result<SrcResolved, String> {
+    let mut local_repo_rel = PathBuf::from("hostdir/binpkgs");
+    let mut use_nonfree = true;
 
     if let Some(c) = cfg {
-        if let Some(p) = &c.void_packages_path {
-            if !p.as_os_str().is_empty() {
-                return Ok(SrcResolved {
-                    voidpkgs: p.clone(),
-                    local_repo_rel,
-                    use_nonfree,
-                });
-            }
+        if !c.local_repo_rel.as_os_str().is_empty() {
+            local_repo_rel = c.local_repo_rel.clone();
         }
+        use_nonfree = c.use_nonfree;
     }
 
-    Err(
-        "vx src requires a void-packages path.\n\
-         Provide one of:\n\
-         - --voidpkgs /path/to/void-packages\n\
-         - VX_VOIDPKGS=/path/to/void-packages\n\
-         - ~/.config/vx/vx.rune with void_packages.path\n"
-            .to_string(),
-    )
+    let Some(voidpkgs) = voidpkgs_path(voidpkgs_override, cfg) else {
+        return Err(
+            "vx src requires a void-packages path.\n\
+             Provide one of:\n\
+             - --voidpkgs /path/to/void-packages\n\
+             - VX_VOIDPKGS=/path/to/void-packages\n\
+             - ~/.config/vx/vx.rune with void_packages.path\n"
+                .to_string(),
+        );
+    };
+
+    Ok(SrcResolved {
+        voidpkgs,
+        local_repo_rel,
+        use_nonfree,
+        profile: BuildProfile::default(),
+        jobs: None,
+        build_env: Vec::new(),
+        overlays: Vec::new(),
+    })
 }
@@ -0,0 +1,174 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{cache, log::Log};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use super::{deps::dep_pkgname, srcpkgs::local_source_pkgname, template::Template};
+
+/// ~/.cache/vx/srcpkgs-deps<checkout>: `<template>\t<mtime>\t<deps>` per
+/// template, so only templates changed since the last scan are parsed again.
+const DEP_INDEX_FILE: &str = "srcpkgs-deps";
+
+/// Cache file of the index of `voidpkgs`; checkouts don't share one, their
+/// templates differ.
+fn index_file(voidpkgs: &Path) -> String {
+    format!("{DEP_INDEX_FILE}{}", voidpkgs.display().to_string().replace('/', "_"))
+}
+
+/// What every template in srcpkgs/ depends on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepIndex {
+    /// template -> (template mtime, package names from depends/hostmakedepends/makedepends)
+    deps: BTreeMap<String, (u64, Vec<String>)>,
+    /// subpackage -> template (the srcpkgs/ symlinks)
+    subs: BTreeMap<String, String>,
+}
+
+impl DepIndex {
+    fn template_of<'a>(&'a self, pkg: &'a str) -> &'a str {
+        self.subs.get(pkg).map(String::as_str).unwrap_or(pkg)
+    }
+
+    /// Templates that directly depend on any package built by `templates`.
+    pub fn reverse_deps(&self, templates: &[String]) -> Vec<String> {
+        let targets: BTreeSet<&str> = templates.iter().map(|t| self.template_of(t)).collect();
+        self.deps
+            .iter()
+            .filter(|(t, _)| !targets.contains(t.as_str()))
+            .filter(|(_, (_, deps))| deps.iter().any(|d| targets.contains(self.template_of(d))))
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+
+    /// A template and its subpackages.
+    pub fn packages_of(&self, template: &str) -> Vec<String> {
+        std::iter::once(template.to_string())
+            .chain(
+                self.subs
+                    .iter()
+                    .filter(|(_, t)| *t == template)
+                    .map(|(s, _)| s.clone()),
+            )
+            .collect()
+    }
+}
+
+fn parse_cache(text: &str) -> BTreeMap<String, (u64, Vec<String>)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut f = line.split('\t');
+            let name = f.next()?.to_string();
+            let mtime = f.next()?.parse().ok()?;
+            let deps = f.next().unwrap_or("").split_whitespace().map(String::from).collect();
+            Some((name, (mtime, deps)))
+        })
+        .collect()
+}
+
+fn render_cache(deps: &BTreeMap<String, (u64, Vec<String>)>) -> String {
+    deps.iter()
+        .map(|(t, (mtime, d))| format!("{t}\t{mtime}\t{}\n", d.join(" ")))
+        .collect()
+}
+
+fn template_deps(text: &str) -> Vec<String> {
+    let Ok(t) = Template::parse(text) else {
+        return Vec::new();
+    };
+    let mut out: Vec<String> = Vec::new();
+    for d in t.depends.iter().chain(&t.hostmakedepends).chain(&t.makedepends) {
+        if let Some(name) = dep_pkgname(d).filter(|n| !n.contains('$'))
+            && !out.contains(&name)
+        {
+            out.push(name);
+        }
+    }
+    out
+}
+
+/// Scan srcpkgs/ of `voidpkgs`, reusing cached entries of unchanged templates.
+pub fn load_index(log: &Log, voidpkgs: &Path) -> Result<DepIndex, String> {
+    let srcpkgs = voidpkgs.join("srcpkgs");
+    let rd = fs::read_dir(&srcpkgs).map_err(|e| format!("failed to read {}: {e}", srcpkgs.display()))?;
+
+    let cache_file = index_file(voidpkgs);
+    let cache_path = cache::data_path(&cache_file);
+    let cached = fs::read_to_string(&cache_path)
+        .map(|t| parse_cache(&t))
+        .unwrap_or_default();
+
+    let mut index = DepIndex::default();
+    let mut parsed = 0usize;
+    for e in rd.flatten() {
+        let name = e.file_name().to_string_lossy().into_owned();
+        let path = e.path();
+        if path.is_symlink() {
+            if let Some(t) = local_source_pkgname(voidpkgs, &name) {
+                index.subs.insert(name, t);
+            }
+            continue;
+        }
+
+        let tpl = path.join("template");
+        let Some(mtime) = fs::metadata(&tpl)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+        else {
+            continue;
+        };
+
+        let deps = match cached.get(&name) {
+            Some((m, deps)) if *m == mtime => deps.clone(),
+            _ => {
+                parsed += 1;
+                fs::read_to_string(&tpl).map(|t| template_deps(&t)).unwrap_or_default()
+            }
+        };
+        index.deps.insert(name, (mtime, deps));
+    }
+
+    if log.verbose && !log.quiet {
+        log.exec(format!(
+            "dependency index: {} templates ({parsed} parsed, rest cached)",
+            index.deps.len()
+        ));
+    }
+    if (parsed > 0 || cached.len() != index.deps.len())
+        && let Err(e) = cache::write_data(&cache_file, &render_cache(&index.deps))
+    {
+        log.warn(format!("failed to cache dependency index: {e}"));
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cache, render_cache, DepIndex};
+
+    #[test]
+    fn reverse_deps_go_through_subpackages() {
+        let text = "app\t1\tlibfoo-devel pkg-config\nfoo\t1\tzlib-devel\nother\t1\t\ntool\t1\tfoo\n";
+        let index = DepIndex {
+            deps: parse_cache(text),
+            subs: [("libfoo", "foo"), ("libfoo-devel", "foo")]
+                .into_iter()
+                .map(|(s, t)| (s.to_string(), t.to_string()))
+                .collect(),
+        };
+        assert_eq!(render_cache(&index.deps), text);
+
+        assert_eq!(index.reverse_deps(&["foo".to_string()]), ["app", "tool"]);
+        assert_eq!(index.reverse_deps(&["libfoo".to_string()]), ["app", "tool"]);
+        assert!(index.reverse_deps(&["app".to_string()]).is_empty());
+        assert_eq!(index.packages_of("foo"), ["foo", "libfoo", "libfoo-devel"]);
+    }
+}
@@ -2,10 +2,10 @@
 // License: MIT
 
 use crate::log::Log;
-use std::process::{Command, ExitCode, Stdio};
+use std::process::{ExitCode, Stdio};
 
 use super::resolve::SrcResolved;
-use super::plan::parse_template_version_revision_file;
+use super::template::Template;
 
 pub fn src_search(log: &Log, res: &SrcResolved, installed_only: bool, term: &str) -> ExitCode {
     let needle = term.trim();
@@ -58,10 +58,10 @@ pub fn src_search(log: &Log, res: &SrcResolved, installed_only: bool, term: &str
         }
 
         let tpl = ent.path().join("template");
-        let ver = match parse_template_version_revision_file(&tpl) {
-            Ok((v, r)) => Some(format!("{v}_{r}")),
-            Err(_) => None,
-        };
+        let ver = std::fs::read_to_string(&tpl)
+            .ok()
+            .and_then(|text| Template::parse(&text).ok())
+            .map(|t| format!("{}_{}", t.version, t.revision));
 
         hits.push((name, ver, installed));
     }
@@ -86,7 +86,7 @@ pub fn src_search(log: &Log, res: &SrcResolved, installed_only: bool, term: &str
 }
 
 fn is_installed_system(pkg: &str) -> Result<bool, String> {
-    let status = Command::new("xbps-query")
+    let status = crate::rootdir::command("xbps-query")
         .arg("-p")
         .arg("pkgver")
         .arg(pkg)
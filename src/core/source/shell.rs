@@ -0,0 +1,148 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::log::Log;
+use std::{fs, path::Path, process::ExitCode};
+
+use super::{
+    git,
+    push::shell_quote,
+    resolve::SrcResolved,
+    srcpkgs::local_source_pkgname,
+    template::Template,
+    xbps_src::{self, SrcRunOptions},
+};
+
+/// Where xbps-src's chroot styles mount the tree, builddir and destdir.
+const CHROOT_DISTDIR: &str = "/void-packages";
+
+/// Script run inside the chroot: the template's variables as xbps-src sets
+/// them, then an interactive shell in `$wrksrc` (or the template's directory
+/// when nothing was extracted yet).
+fn env_script(t: &Template) -> String {
+    let name_ver = format!("{}-{}", t.pkgname, t.version);
+    let srcdir = format!("{CHROOT_DISTDIR}/srcpkgs/{}", t.pkgname);
+    let vars = [
+        ("pkgname", t.pkgname.clone()),
+        ("version", t.version.clone()),
+        ("revision", t.revision.clone()),
+        ("pkgver", t.pkgver()),
+        ("build_style", t.build_style.clone().unwrap_or_default()),
+        ("wrksrc", format!("/builddir/{name_ver}")),
+        ("DESTDIR", format!("/destdir/{name_ver}")),
+        ("PKGDESTDIR", format!("/destdir/{name_ver}")),
+        ("FILESDIR", format!("{srcdir}/files")),
+        ("XBPS_SRCPKGDIR", format!("{CHROOT_DISTDIR}/srcpkgs")),
+        ("PS1", format!("[{}] \\w \\$ ", t.pkgver())),
+    ];
+
+    let mut out = String::new();
+    for (k, v) in vars {
+        out.push_str(&format!("export {k}={}\n", shell_quote(&v)));
+    }
+    out.push_str(&format!("cd \"$wrksrc\" 2>/dev/null || cd {}\n", shell_quote(&srcdir)));
+    out.push_str("command -v bash >/dev/null && exec bash --norc -i\nexec sh -i\n");
+    out
+}
+
+/// `vx src env <pkg> [--local|--remote] [-m DIR|NAME]`
+///
+/// Opens a shell in the masterdir chroot (`./xbps-src chroot`) with the
+/// package's variables set, in its builddir when a (failed) build left one.
+/// The upstream worktree is used as is: no fetch, and its masterdir stays.
+pub fn cmd_env(
+    log: &Log,
+    res: &SrcResolved,
+    remote: bool,
+    pkg: &str,
+    opts: &SrcRunOptions,
+) -> ExitCode {
+    let Some(name) = local_source_pkgname(&res.voidpkgs, pkg.trim()) else {
+        log.error(format!("no template for {pkg} in {}/srcpkgs", res.voidpkgs.display()));
+        return ExitCode::from(2);
+    };
+
+    let (dir, env) = if remote {
+        let wt = match git::upstream_worktree(log, &res.voidpkgs, true) {
+            Ok(p) => p,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        };
+        if let Err(e) = xbps_src::ensure_xbps_conf(log, &wt, res) {
+            log.warn(format!("failed to ensure etc/conf in worktree: {e}"));
+        }
+        if let Err(e) = xbps_src::overlay_dirs(log, res, &wt) {
+            log.warn(format!("failed to apply source.overlays: {e}"));
+        }
+        if let Err(e) = xbps_src::overlay_srcpkg(log, &res.voidpkgs, &wt, &name, "env") {
+            log.warn(format!("failed to overlay {name} into the upstream worktree: {e}"));
+        }
+        (wt, xbps_src::build_env_for_worktree(res))
+    } else {
+        (res.voidpkgs.clone(), Vec::new())
+    };
+
+    let template = match fs::read_to_string(dir.join("srcpkgs").join(&name).join("template"))
+        .map_err(|e| e.to_string())
+        .and_then(|text| Template::parse(&text))
+    {
+        Ok(t) => t,
+        Err(e) => {
+            log.error(format!("{name}: failed to read template: {e}"));
+            return ExitCode::from(1);
+        }
+    };
+
+    let c = xbps_src::ensure_profile_masterdir(log, res, &dir, opts, &env);
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+
+    let pkgver = template.pkgver();
+    if has_builddir(&dir, opts.masterdir.as_deref(), &template) {
+        log.info(format!("vx: entering the chroot in the builddir of {pkgver} (exit to leave)"));
+    } else {
+        log.info(format!("vx: entering the chroot; {pkgver} has no builddir yet (exit to leave)"));
+    }
+
+    let mut args = xbps_src::join_args_with_opts("chroot", &[], opts);
+    args.extend(["/bin/sh".into(), "-c".into(), env_script(&template).into()]);
+    xbps_src::run_xbps_src_with_env(log, &dir, args, &env)
+}
+
+/// Whether a `<pkgname>-<version>` builddir exists in the masterdir to be used.
+fn has_builddir(dir: &Path, masterdir: Option<&Path>, t: &Template) -> bool {
+    let rel = Path::new("builddir").join(format!("{}-{}", t.pkgname, t.version));
+    match masterdir {
+        Some(m) => m.join(rel).is_dir(),
+        None => fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with("masterdir"))
+            .any(|e| e.path().join(&rel).is_dir()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::env_script;
+    use crate::core::source::template::Template;
+
+    #[test]
+    fn env_script_exports_template_vars() {
+        let t = Template {
+            pkgname: "foo".to_string(),
+            version: "1.2".to_string(),
+            revision: "3".to_string(),
+            ..Template::default()
+        };
+        let s = env_script(&t);
+        assert!(s.contains("export pkgver='foo-1.2_3'\n"));
+        assert!(s.contains("export wrksrc='/builddir/foo-1.2'\n"));
+        assert!(s.contains("cd \"$wrksrc\" 2>/dev/null || cd '/void-packages/srcpkgs/foo'\n"));
+        assert!(s.ends_with("exec sh -i\n"));
+    }
+}
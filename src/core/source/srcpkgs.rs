@@ -0,0 +1,168 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{config::Config, core::xbps, log::Log, managed};
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use super::{
+    changelog, git,
+    template::Template,
+    resolve::resolve_voidpkgs,
+};
+
+/// Source package that builds `pkg` in the local checkout.
+///
+/// Subpackages are `srcpkgs/<sub>` symlinks to their main package (possibly
+/// through other links); None when there is no `srcpkgs/<pkg>`.
+pub fn local_source_pkgname(voidpkgs: &Path, pkg: &str) -> Option<String> {
+    let srcpkgs = voidpkgs.join("srcpkgs");
+    let dir = srcpkgs.join(pkg);
+    if !dir.is_symlink() {
+        return dir.is_dir().then(|| pkg.to_string());
+    }
+    let real = fs::canonicalize(&dir).ok()?;
+    if real.parent() != fs::canonicalize(&srcpkgs).ok().as_deref() {
+        return None;
+    }
+    Some(real.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Source package that builds `pkg`: the local checkout first, then
+/// upstream/master (for packages not checked out, e.g. with a sparse tree).
+pub fn source_pkgname(voidpkgs: &Path, pkg: &str) -> Option<String> {
+    local_source_pkgname(voidpkgs, pkg).or_else(|| changelog::source_pkgname(voidpkgs, pkg).ok())
+}
+
+/// Source template each package is built from, deduplicated.
+pub fn source_templates(voidpkgs: &Path, pkgs: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for p in pkgs {
+        let t = local_source_pkgname(voidpkgs, p).unwrap_or_else(|| p.clone());
+        if !out.contains(&t) {
+            out.push(t);
+        }
+    }
+    out
+}
+
+/// Source package recorded by xbps for an installed package
+/// (`source-revisions` is `<srcpkg>:<commit>`).
+pub fn installed_source_pkgname(pkg: &str) -> Option<String> {
+    let revs = xbps::installed_property(pkg, "source-revisions").ok()??;
+    let (src, _) = revs.split_once(':')?;
+    let src = src.trim();
+    (!src.is_empty()).then(|| src.to_string())
+}
+
+/// `version_revision` from template text.
+fn template_version(text: &str) -> Option<String> {
+    Template::parse(text)
+        .ok()
+        .map(|t| format!("{}_{}", t.version, t.revision))
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct WhichTemplate {
+    pkg: String,
+    installed: Option<String>,
+    srcpkg: String,
+    subpackage: bool,
+    path: PathBuf,
+    local_version: Option<String>,
+    upstream_version: Option<String>,
+    managed: bool,
+}
+
+/// `vx which-template <pkg>`
+pub fn cmd_which_template(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    pkg: &str,
+) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx which-template <pkg>");
+        return ExitCode::from(2);
+    }
+
+    let resolved = match resolve_voidpkgs(voidpkgs_override, cfg) {
+        Ok(r) => r,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(2);
+        }
+    };
+    let voidpkgs = &resolved.voidpkgs;
+
+    let installed = xbps::installed_pkgver(pkg).ok().flatten();
+    let Some(srcpkg) = installed_source_pkgname(pkg).or_else(|| source_pkgname(voidpkgs, pkg)) else {
+        log.error(format!("no template builds {pkg} (not in srcpkgs/ or upstream/master)"));
+        return ExitCode::from(1);
+    };
+
+    let path = voidpkgs.join("srcpkgs").join(&srcpkg);
+    let local = fs::read_to_string(path.join("template")).ok();
+    let upstream = git::read_template_upstream(voidpkgs, &srcpkg).ok();
+    let managed = managed::load_managed()
+        .map(|m| m.iter().any(|n| n == pkg || *n == srcpkg))
+        .unwrap_or(false);
+
+    let info = WhichTemplate {
+        pkg: pkg.to_string(),
+        installed,
+        subpackage: srcpkg != pkg,
+        path,
+        local_version: local.as_deref().and_then(template_version),
+        upstream_version: upstream.as_deref().and_then(template_version),
+        managed,
+        srcpkg,
+    };
+
+    if log.json {
+        log.emit_json(&info);
+        return ExitCode::SUCCESS;
+    }
+
+    let or_none = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    match &info.installed {
+        Some(v) => println!("{v}"),
+        None => println!("{} (not installed)", info.pkg),
+    }
+    let kind = if info.subpackage { "  (subpackage)" } else { "" };
+    println!("  template:  {}{kind}", info.srcpkg);
+    println!("  path:      {}", info.path.display());
+    println!("  local:     {}", or_none(&info.local_version));
+    println!("  upstream:  {}", or_none(&info.upstream_version));
+    println!("  managed:   {}", if info.managed { "yes" } else { "no" });
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{local_source_pkgname, source_templates};
+    use std::{fs, os::unix::fs::symlink};
+
+    #[test]
+    fn subpackage_links_resolve_to_their_template() {
+        let root = std::env::temp_dir().join(format!("vx-srcpkgs-{}", std::process::id()));
+        let srcpkgs = root.join("srcpkgs");
+        fs::create_dir_all(srcpkgs.join("foo")).unwrap();
+        symlink("foo", srcpkgs.join("foo-devel")).unwrap();
+        symlink("foo-devel", srcpkgs.join("libfoo")).unwrap();
+
+        assert_eq!(local_source_pkgname(&root, "foo").as_deref(), Some("foo"));
+        assert_eq!(local_source_pkgname(&root, "libfoo").as_deref(), Some("foo"));
+        assert_eq!(local_source_pkgname(&root, "bar"), None);
+        let names = ["foo-devel", "foo", "bar"].map(String::from);
+        assert_eq!(source_templates(&root, &names), ["foo", "bar"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{cache, log::Log};
+use std::{fs, os::unix::ffi::OsStrExt, path::Path};
+
+use super::{resolve::SrcResolved, template::Template, xbps_src::SrcRunOptions};
+
+/// `<void-packages>\t<template>\t<hash>` per built template.
+const STAMPS_FILE: &str = "build-stamps";
+
+/// 64-bit FNV-1a. The stamps outlive vx builds, so the hash must not change
+/// between Rust releases the way `DefaultHasher` may.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// `bytes` with its length first, so neighbouring fields can't run together.
+    fn field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    fn opt(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.write(&[1]);
+                self.field(s.as_bytes());
+            }
+            None => self.write(&[0]),
+        }
+    }
+
+    fn list(&mut self, items: &[String]) {
+        self.write(&(items.len() as u64).to_le_bytes());
+        for i in items {
+            self.field(i.as_bytes());
+        }
+    }
+}
+
+fn hash_dir(h: &mut Fnv, root: &Path, dir: &Path) {
+    let mut entries: Vec<_> = fs::read_dir(dir).into_iter().flatten().flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+    for e in entries {
+        let path = e.path();
+        h.field(path.strip_prefix(root).unwrap_or(&path).as_os_str().as_bytes());
+        let Ok(ft) = e.file_type() else { continue };
+        if ft.is_symlink() {
+            h.write(b"l");
+            h.field(fs::read_link(&path).unwrap_or_default().as_os_str().as_bytes());
+        } else if ft.is_dir() {
+            h.write(b"d");
+            hash_dir(h, root, &path);
+        } else {
+            h.write(b"f");
+            h.field(&fs::read(&path).unwrap_or_default());
+        }
+    }
+}
+
+/// Hash of `srcpkgs/<template>` in `tree` and of the options and build
+/// environment that change what gets built.
+fn build_hash(
+    tree: &Path,
+    template: &str,
+    arch: Option<&str>,
+    env: &[String],
+    opts: &SrcRunOptions,
+) -> String {
+    let mut h = Fnv::new();
+    let dir = tree.join("srcpkgs").join(template);
+    hash_dir(&mut h, &dir, &dir);
+    h.opt(arch);
+    h.opt(opts.host.as_deref());
+    h.opt(opts.target.as_deref());
+    h.list(&opts.build_options);
+    h.list(&opts.passthrough);
+    h.list(env);
+    h.write(&[opts.debug_symbols, opts.git_revs, opts.check, opts.check_long].map(u8::from));
+    format!("{:016x}", h.0)
+}
+
+fn load() -> Vec<(String, String, String)> {
+    fs::read_to_string(cache::data_path(STAMPS_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| {
+            let mut f = l.splitn(3, '\t');
+            Some((f.next()?.to_string(), f.next()?.to_string(), f.next()?.to_string()))
+        })
+        .collect()
+}
+
+/// A `<pkgver>.<arch>.xbps` of the template somewhere in the local repository.
+fn in_local_repo(dir: &Path, prefix: &str, depth: usize) -> bool {
+    fs::read_dir(dir).into_iter().flatten().flatten().any(|e| {
+        let name = e.file_name().to_string_lossy().into_owned();
+        if name.starts_with(prefix) && name.ends_with(".xbps") {
+            return true;
+        }
+        depth > 0 && e.path().is_dir() && in_local_repo(&e.path(), prefix, depth - 1)
+    })
+}
+
+/// True when `template` was last built from the same files and options and
+/// its package is still in the local repository.
+pub fn is_current(res: &SrcResolved, tree: &Path, template: &str, opts: &SrcRunOptions) -> bool {
+    let Some(t) = fs::read_to_string(tree.join("srcpkgs").join(template).join("template"))
+        .ok()
+        .and_then(|text| Template::parse(&text).ok())
+    else {
+        return false;
+    };
+    let voidpkgs = res.voidpkgs.display().to_string();
+    let hash = build_hash(tree, template, res.profile.arch.as_deref(), &res.build_env, opts);
+    load().iter().any(|(v, n, h)| *v == voidpkgs && n == template && *h == hash)
+        && in_local_repo(&res.voidpkgs.join(&res.local_repo_rel), &format!("{}.", t.pkgver()), 2)
+}
+
+/// Remember that `templates` were just built from `tree` with `opts`.
+pub fn record(
+    log: &Log,
+    res: &SrcResolved,
+    tree: &Path,
+    templates: &[String],
+    opts: &SrcRunOptions,
+) {
+    let voidpkgs = res.voidpkgs.display().to_string();
+    let _lock = cache::lock(log, STAMPS_FILE).ok();
+    let mut all = load();
+    all.retain(|(v, n, _)| *v != voidpkgs || !templates.contains(n));
+    for t in templates {
+        let hash = build_hash(tree, t, res.profile.arch.as_deref(), &res.build_env, opts);
+        all.push((voidpkgs.clone(), t.clone(), hash));
+    }
+    let text: String = all.iter().map(|(v, n, h)| format!("{v}\t{n}\t{h}\n")).collect();
+    let _ = cache::write_data(STAMPS_FILE, &text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::build_hash;
+    use crate::core::source::xbps_src::SrcRunOptions;
+    use std::fs;
+
+    #[test]
+    fn build_hash_follows_template_files_and_options() {
+        let tree = std::env::temp_dir().join(format!("vx-stamp-test-{}", std::process::id()));
+        let dir = tree.join("srcpkgs/foo/patches");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(tree.join("srcpkgs/foo/template"), "pkgname=foo\nversion=1\n").unwrap();
+        fs::write(dir.join("a.patch"), "one").unwrap();
+
+        let opts = SrcRunOptions::default();
+        let first = build_hash(&tree, "foo", None, &[], &opts);
+        assert_eq!(first, build_hash(&tree, "foo", None, &[], &opts));
+
+        fs::write(dir.join("a.patch"), "two").unwrap();
+        let patched = build_hash(&tree, "foo", None, &[], &opts);
+        assert_ne!(first, patched);
+
+        let cross = SrcRunOptions {
+            target: Some("aarch64".to_string()),
+            ..SrcRunOptions::default()
+        };
+        assert_ne!(patched, build_hash(&tree, "foo", None, &[], &cross));
+        assert_ne!(patched, build_hash(&tree, "foo", Some("x86_64-musl"), &[], &opts));
+
+        let passthrough = SrcRunOptions {
+            passthrough: vec!["-E".to_string()],
+            ..SrcRunOptions::default()
+        };
+        assert_ne!(patched, build_hash(&tree, "foo", None, &[], &passthrough));
+        let env = ["XBPS_CCACHE=yes".to_string()];
+        assert_ne!(patched, build_hash(&tree, "foo", None, &env, &opts));
+
+        let _ = fs::remove_dir_all(&tree);
+    }
+
+    #[test]
+    fn fnv_matches_the_reference_values() {
+        let mut h = super::Fnv::new();
+        assert_eq!(h.0, 0xcbf2_9ce4_8422_2325);
+        h.write(b"a");
+        assert_eq!(h.0, 0xaf63_dc4c_8601_ec8c);
+    }
+}
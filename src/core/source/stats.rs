@@ -0,0 +1,132 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{cache, log::Log};
+use std::{collections::BTreeMap, fs, path::PathBuf, process::ExitCode};
+
+use super::buildlog;
+
+/// Successful builds remembered per package.
+const KEEP_TIMES: usize = 5;
+
+/// `<pkg>\t<unix time>\t<seconds>` per successful build, next to the build logs.
+fn stats_path() -> PathBuf {
+    buildlog::log_root().with_file_name("build-times")
+}
+
+fn load() -> BTreeMap<String, Vec<(u64, u64)>> {
+    let mut out: BTreeMap<String, Vec<(u64, u64)>> = BTreeMap::new();
+    for l in fs::read_to_string(stats_path()).unwrap_or_default().lines() {
+        let mut f = l.split('\t');
+        let (Some(pkg), Some(Ok(time)), Some(Ok(secs))) =
+            (f.next(), f.next().map(str::parse), f.next().map(str::parse))
+        else {
+            continue;
+        };
+        out.entry(pkg.to_string()).or_default().push((time, secs));
+    }
+    out
+}
+
+/// Remember that `pkg` built successfully in `secs` seconds.
+pub fn record(log: &Log, pkg: &str, time: u64, secs: u64) {
+    let _lock = cache::lock(log, "build-times").ok();
+    let mut all = load();
+    let times = all.entry(pkg.to_string()).or_default();
+    times.push((time, secs));
+    times.sort_by_key(|(t, _)| std::cmp::Reverse(*t));
+    times.truncate(KEEP_TIMES);
+
+    let mut text = String::new();
+    for (p, times) in &all {
+        for (t, s) in times {
+            text.push_str(&format!("{p}\t{t}\t{s}\n"));
+        }
+    }
+    let path = stats_path();
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = cache::write_atomic(&path, &text);
+}
+
+fn average(times: &[(u64, u64)]) -> u64 {
+    times.iter().map(|(_, s)| s).sum::<u64>() / times.len().max(1) as u64
+}
+
+/// `~45 min`, `~1 h 20 min`, `<1 min`.
+pub fn format_duration(secs: u64) -> String {
+    let min = (secs + 30) / 60;
+    match min {
+        0 => "<1 min".to_string(),
+        m if m < 60 => format!("~{m} min"),
+        m if m % 60 == 0 => format!("~{} h", m / 60),
+        m => format!("~{} h {} min", m / 60, m % 60),
+    }
+}
+
+/// Expected build time of `templates` from their previous builds, and how
+/// many of them were never built before.
+pub fn estimate(templates: &[String]) -> (u64, usize) {
+    let all = load();
+    let mut total = 0;
+    let mut unknown = 0;
+    for t in templates {
+        match all.get(t) {
+            Some(times) if !times.is_empty() => total += average(times),
+            _ => unknown += 1,
+        }
+    }
+    (total, unknown)
+}
+
+/// Plan line for `vx src up`; None when nothing was built before.
+pub fn estimate_line(templates: &[String]) -> Option<String> {
+    let (total, unknown) = estimate(templates);
+    if unknown == templates.len() {
+        return None;
+    }
+    let mut line = format!("estimated build time: {} based on previous builds", format_duration(total));
+    if unknown > 0 {
+        line.push_str(&format!(" (+{unknown} never built)"));
+    }
+    Some(line)
+}
+
+/// `vx src stats [--top N]`: the slowest packages by average build time.
+pub fn cmd_stats(log: &Log, top: usize) -> ExitCode {
+    let mut rows: Vec<(String, u64, u64, usize)> = load()
+        .into_iter()
+        .map(|(p, times)| {
+            let last = times.iter().max_by_key(|(t, _)| *t).map_or(0, |(_, s)| *s);
+            (p, average(&times), last, times.len())
+        })
+        .collect();
+    if rows.is_empty() {
+        log.info("vx: no build times recorded yet.");
+        return ExitCode::SUCCESS;
+    }
+    rows.sort_by_key(|r| std::cmp::Reverse(r.1));
+
+    let w = rows.iter().take(top).map(|r| r.0.len()).max().unwrap_or(0).max(7);
+    if !log.quiet {
+        println!("  {:<w$}  {:>14}  {:>14}  builds", "package", "average", "last");
+    }
+    for (p, avg, last, n) in rows.iter().take(top) {
+        println!("  {p:<w$}  {:>14}  {:>14}  {n}", format_duration(*avg), format_duration(*last));
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_duration;
+
+    #[test]
+    fn durations_are_rounded_to_minutes() {
+        assert_eq!(format_duration(20), "<1 min");
+        assert_eq!(format_duration(45 * 60 + 10), "~45 min");
+        assert_eq!(format_duration(3600), "~1 h");
+        assert_eq!(format_duration(80 * 60), "~1 h 20 min");
+    }
+}
@@ -0,0 +1,299 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::log::Log;
+use serde::Serialize;
+use std::process::ExitCode;
+
+use super::{plan::read_template, resolve::SrcResolved, srcpkgs::local_source_pkgname};
+
+/// The metadata of a srcpkgs template that vx cares about.
+///
+/// Only top-level assignments are read (not those inside `<sub>_package()`),
+/// and `$pkgname`/`$version`/`$revision` are expanded; other shell expansions
+/// are kept as written.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Template {
+    pub pkgname: String,
+    pub version: String,
+    pub revision: String,
+    pub short_desc: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    pub maintainer: Option<String>,
+    pub build_style: Option<String>,
+    pub depends: Vec<String>,
+    pub hostmakedepends: Vec<String>,
+    pub makedepends: Vec<String>,
+    pub subpackages: Vec<String>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Template, String> {
+        let top = top_level(text);
+        let (version, revision) = parse_template_version_revision_str(&top)?;
+        let pkgname = parse_template_var_str(&top, "pkgname").ok_or("template missing pkgname=")?;
+
+        let expand = |s: String| {
+            let s = s
+                .replace("${pkgname}", &pkgname)
+                .replace("$pkgname", &pkgname)
+                .replace("${version}", &version)
+                .replace("$version", &version)
+                .replace("${revision}", &revision)
+                .replace("$revision", &revision);
+            s.split_whitespace().collect::<Vec<_>>().join(" ")
+        };
+        let var = |name: &str| parse_template_var_str(&top, name).map(expand).filter(|v| !v.is_empty());
+        let list = |name: &str| -> Vec<String> {
+            var(name)
+                .map(|v| v.split_whitespace().map(String::from).collect())
+                .unwrap_or_default()
+        };
+
+        Ok(Template {
+            short_desc: var("short_desc"),
+            homepage: var("homepage"),
+            license: var("license"),
+            maintainer: var("maintainer"),
+            build_style: var("build_style"),
+            depends: list("depends"),
+            hostmakedepends: list("hostmakedepends"),
+            makedepends: list("makedepends"),
+            subpackages: parse_template_subpackages_str(text),
+            pkgname: pkgname.clone(),
+            version: version.clone(),
+            revision: revision.clone(),
+        })
+    }
+
+    /// `pkgname-version_revision`.
+    pub fn pkgver(&self) -> String {
+        format!("{}-{}_{}", self.pkgname, self.version, self.revision)
+    }
+}
+
+/// `vx src show <pkg> [--remote]`
+pub fn cmd_show(log: &Log, res: &SrcResolved, pkg: &str, remote: bool) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx src show <pkg>");
+        return ExitCode::from(2);
+    }
+
+    // Not checked out (e.g. a sparse tree): show upstream/master's.
+    let remote = remote || local_source_pkgname(&res.voidpkgs, pkg).is_none();
+    let Some(text) = read_template(log, res, pkg, remote) else {
+        return ExitCode::from(1);
+    };
+    let t = match Template::parse(&text) {
+        Ok(t) => t,
+        Err(e) => {
+            log.error(format!("{pkg}: {e}"));
+            return ExitCode::from(1);
+        }
+    };
+
+    if log.json {
+        log.emit_json(&t);
+        return ExitCode::SUCCESS;
+    }
+
+    let field = |label: &str, value: Option<&str>| {
+        if let Some(v) = value {
+            println!("  {label:<17}{v}");
+        }
+    };
+    let list = |label: &str, values: &[String]| {
+        if !values.is_empty() {
+            field(label, Some(&values.join(" ")));
+        }
+    };
+    println!("{}{}", t.pkgver(), if remote { "  (upstream/master)" } else { "" });
+    field("short_desc:", t.short_desc.as_deref());
+    field("homepage:", t.homepage.as_deref());
+    field("license:", t.license.as_deref());
+    field("maintainer:", t.maintainer.as_deref());
+    field("build_style:", t.build_style.as_deref());
+    list("depends:", &t.depends);
+    list("hostmakedepends:", &t.hostmakedepends);
+    list("makedepends:", &t.makedepends);
+    list("subpackages:", &t.subpackages);
+    ExitCode::SUCCESS
+}
+
+/// Template text without function bodies, so subpackage and build-phase
+/// assignments don't shadow the main package's.
+fn top_level(text: &str) -> String {
+    let mut out = String::new();
+    let mut in_fn = false;
+    for line in text.lines() {
+        if in_fn {
+            in_fn = !line.starts_with('}');
+            continue;
+        }
+        let t = line.trim_end();
+        if !line.starts_with(char::is_whitespace) && (t.ends_with("() {") || t.ends_with("(){")) {
+            in_fn = true;
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+pub fn parse_template_version_revision_str(text: &str) -> Result<(String, String), String> {
+    let mut version: Option<String> = None;
+    let mut revision: Option<String> = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        if let Some(v) = line.strip_prefix("version=") {
+            version = Some(unquote(v.trim()));
+        } else if let Some(r) = line.strip_prefix("revision=") {
+            revision = Some(unquote(r.trim()));
+        }
+        if version.is_some() && revision.is_some() {
+            break;
+        }
+    }
+
+    let version = version.ok_or("template missing version=")?;
+    let revision = revision.unwrap_or_else(|| "1".to_string());
+    Ok((version, revision))
+}
+
+/// Subpackages a template defines: `subpackages=` when set, otherwise its
+/// `<name>_package()` functions. `$pkgname`/`${pkgname}` are expanded.
+pub fn parse_template_subpackages_str(text: &str) -> Vec<String> {
+    let pkgname = parse_template_var_str(text, "pkgname").unwrap_or_default();
+    let expand = |s: &str| s.replace("${pkgname}", &pkgname).replace("$pkgname", &pkgname);
+
+    let mut out: Vec<String> = match parse_template_var_str(text, "subpackages") {
+        Some(v) => v.split_whitespace().map(expand).collect(),
+        None => text
+            .lines()
+            .filter_map(|l| {
+                let (name, _) = l.trim_end().split_once("_package()")?;
+                (!name.is_empty() && !name.contains(char::is_whitespace)).then(|| expand(name))
+            })
+            .collect(),
+    };
+    out.retain(|s| !s.is_empty() && *s != pkgname);
+    out.dedup();
+    out
+}
+
+/// Read a (possibly multi-line, quoted) shell variable assignment from a template.
+///
+/// Handles `name="a
+///  b"`, `name='a'` and bare `name=a`. Returns the raw value with quotes removed;
+/// shell expansions are left untouched for the caller to deal with.
+pub fn parse_template_var_str(text: &str, var: &str) -> Option<String> {
+    let prefix = format!("{var}=");
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let line = line.trim_start();
+        let Some(rest) = line.strip_prefix(&prefix) else {
+            continue;
+        };
+
+        let quote = match rest.chars().next() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => return Some(unquote(rest.split('#').next().unwrap_or("").trim())),
+        };
+
+        let body = &rest[1..];
+        if let Some(end) = body.find(quote) {
+            return Some(body[..end].to_string());
+        }
+
+        let mut value = body.to_string();
+        for next in lines.by_ref() {
+            value.push('\n');
+            if let Some(end) = next.find(quote) {
+                value.push_str(&next[..end]);
+                return Some(value);
+            }
+            value.push_str(next);
+        }
+        return Some(value);
+    }
+
+    None
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    if (s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')) {
+        s[1..s.len() - 1].to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_template_subpackages_str, Template};
+
+    #[test]
+    fn subpackages_from_functions_and_variable() {
+        let tpl = r#"pkgname=foo
+version=1.2
+revision=1
+
+libfoo_package() {
+	short_desc+=" - library"
+}
+
+${pkgname}-devel_package() {
+	depends="libfoo>=${version}_${revision}"
+}
+"#;
+        assert_eq!(parse_template_subpackages_str(tpl), ["libfoo", "foo-devel"]);
+
+        let tpl = "pkgname=bar\nsubpackages=\"$pkgname-doc libbar\"\nbar-doc_package() {\n}\n";
+        assert_eq!(parse_template_subpackages_str(tpl), ["bar-doc", "libbar"]);
+    }
+
+    #[test]
+    fn template_metadata_skips_function_bodies() {
+        let tpl = r#"# Template file for 'foo'
+pkgname=foo
+version=1.2
+revision=3
+build_style=gnu-configure
+hostmakedepends="pkg-config"
+makedepends="libbar-devel
+ zlib-devel"
+depends="libfoo>=${version}_${revision}"
+short_desc="Foo tool"
+maintainer="Jane Doe <jane@example.org>"
+license="MIT"
+homepage="https://example.org/${pkgname}"
+
+post_install() {
+	vlicense LICENSE
+}
+
+libfoo_package() {
+	short_desc+=" - library"
+	depends="libbar"
+}
+"#;
+        let t = Template::parse(tpl).unwrap();
+        assert_eq!(t.pkgver(), "foo-1.2_3");
+        assert_eq!(t.short_desc.as_deref(), Some("Foo tool"));
+        assert_eq!(t.homepage.as_deref(), Some("https://example.org/foo"));
+        assert_eq!(t.build_style.as_deref(), Some("gnu-configure"));
+        assert_eq!(t.depends, ["libfoo>=1.2_3"]);
+        assert_eq!(t.makedepends, ["libbar-devel", "zlib-devel"]);
+        assert_eq!(t.subpackages, ["libfoo"]);
+        assert!(Template::parse("version=1\n").is_err());
+    }
+}
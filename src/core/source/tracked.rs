@@ -0,0 +1,389 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{log::Log, managed};
+use serde::Serialize;
+use std::{path::Path, process::ExitCode};
+
+use super::{confirm_once, git, plan, resolve::SrcResolved, srcpkgs, xbps_src};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Tracked {
+    name: String,
+    installed: Option<String>,
+    candidate: Option<String>,
+    drift: &'static str,
+    groups: Vec<String>,
+}
+
+/// How the installed package relates to its template (a `plan_action`).
+fn drift(action: Option<&str>) -> &'static str {
+    match action {
+        Some("reinstall") => "up to date",
+        Some("update") => "update available",
+        Some("downgrade") => "newer than template",
+        Some("install") => "not installed",
+        _ => "no template",
+    }
+}
+
+/// `vx src tracked [--local|--remote]`: every tracked package with its
+/// installed version, the template's version and how far apart they are.
+pub fn cmd_tracked(log: &Log, res: &SrcResolved, remote: bool) -> ExitCode {
+    let records = match managed::load_records() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(format!("failed to load managed list: {e}"));
+            return ExitCode::from(1);
+        }
+    };
+    let managed: Vec<String> = records.iter().map(|r| r.name.clone()).collect();
+    if managed.is_empty() {
+        if log.json {
+            log.emit_json(&Vec::<Tracked>::new());
+        } else if !log.quiet {
+            println!("no source packages tracked. use `vx src add <pkg>` to start.");
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if remote && let Err(e) = git::sync_voidpkgs(log, &res.voidpkgs) {
+        log.warn(e);
+    }
+    let pins = managed::load_pins().unwrap_or_default();
+    let plan = match plan::plan_src_updates_with_resolved(log, res, &managed, true, remote) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let rows: Vec<Tracked> = records
+        .iter()
+        .map(|r| {
+            let name = &r.name;
+            let u = plan.iter().find(|u| u.name == *name);
+            Tracked {
+                name: name.clone(),
+                installed: u.and_then(|u| u.installed.clone()),
+                candidate: u.map(|u| u.candidate.clone()),
+                drift: match u {
+                    None if pins.contains_key(name) => "pinned",
+                    _ => drift(u.map(|u| u.action.as_str())),
+                },
+                groups: r.groups.clone(),
+            }
+        })
+        .collect();
+
+    if log.json {
+        log.emit_json(&rows);
+        return ExitCode::SUCCESS;
+    }
+
+    let from = if remote { "upstream/master" } else { "local checkout" };
+    if !log.quiet {
+        println!("tracked source packages ({}, templates from {from}):", rows.len());
+    }
+    let w = rows.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    let vw = rows
+        .iter()
+        .flat_map(|r| [&r.installed, &r.candidate])
+        .map(|v| v.as_deref().map_or(1, str::len))
+        .max()
+        .unwrap_or(0);
+    for r in &rows {
+        let groups = if r.groups.is_empty() { String::new() } else { format!("  [{}]", r.groups.join(", ")) };
+        println!(
+            "  {:<w$}  {:<vw$}  {:<vw$}  {}{groups}",
+            r.name,
+            r.installed.as_deref().unwrap_or("-"),
+            r.candidate.as_deref().unwrap_or("-"),
+            r.drift
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+/// `vx src untrack <pkg...>`: stop tracking packages but leave them installed.
+pub fn cmd_untrack(log: &Log, pkgs: &[String]) -> ExitCode {
+    if pkgs.is_empty() {
+        log.warn("usage: vx src untrack <pkg> [pkg...]");
+        return ExitCode::from(2);
+    }
+    let managed = match managed::load_managed() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(format!("failed to load managed list: {e}"));
+            return ExitCode::from(1);
+        }
+    };
+
+    let (tracked, unknown): (Vec<String>, Vec<String>) =
+        pkgs.iter().cloned().partition(|p| managed.contains(p));
+    for p in &unknown {
+        log.warn(format!("{p} is not tracked."));
+    }
+    if tracked.is_empty() {
+        return ExitCode::from(1);
+    }
+
+    if let Err(e) = managed::remove_managed(log, &tracked) {
+        log.error(e);
+        return ExitCode::from(1);
+    }
+    log.info(format!("vx: no longer tracking {} (still installed).", tracked.join(" ")));
+    ExitCode::SUCCESS
+}
+
+/// `vx src pin <pkg> [version]`: hold a tracked package back from updates.
+pub fn cmd_pin(log: &Log, pkg: &str, version: Option<&str>) -> ExitCode {
+    match managed::load_managed() {
+        Ok(m) if m.iter().any(|p| p == pkg) => {}
+        Ok(_) => {
+            log.warn(format!("{pkg} is not tracked."));
+            return ExitCode::from(1);
+        }
+        Err(e) => {
+            log.error(format!("failed to load managed list: {e}"));
+            return ExitCode::from(1);
+        }
+    }
+
+    if let Err(e) = managed::set_pin(log, pkg, version) {
+        log.error(e);
+        return ExitCode::from(1);
+    }
+    match version {
+        Some(v) => log.info(format!("vx: {pkg} pinned; updates stop at {v}.")),
+        None => log.info(format!("vx: {pkg} pinned; vx src up will skip it.")),
+    }
+    ExitCode::SUCCESS
+}
+
+/// `vx src unpin <pkg>`: let a pinned package update again.
+pub fn cmd_unpin(log: &Log, pkg: &str) -> ExitCode {
+    match managed::remove_pin(log, pkg) {
+        Ok(true) => {
+            log.info(format!("vx: {pkg} unpinned."));
+            ExitCode::SUCCESS
+        }
+        Ok(false) => {
+            log.warn(format!("{pkg} is not pinned."));
+            ExitCode::from(1)
+        }
+        Err(e) => {
+            log.error(e);
+            ExitCode::from(1)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct OutdatedTree {
+    name: String,
+    commit: String,
+    behind: u64,
+    /// srcpkgs/<template> changed since the build (patches, files, template).
+    template_changed: Option<bool>,
+}
+
+/// `12 commits behind, template changed`.
+fn tree_note(behind: u64, template_changed: Option<bool>) -> String {
+    let commits = if behind == 1 { "commit" } else { "commits" };
+    match template_changed {
+        Some(true) => format!("{behind} {commits} behind, template changed"),
+        Some(false) => format!("{behind} {commits} behind, template unchanged"),
+        None => format!("{behind} {commits} behind"),
+    }
+}
+
+/// `vx src tracked --outdated-tree`: tracked packages built from a
+/// void-packages commit upstream/master has since moved past.
+pub fn cmd_outdated_tree(log: &Log, res: &SrcResolved) -> ExitCode {
+    let records = match managed::load_records() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(format!("failed to load managed list: {e}"));
+            return ExitCode::from(1);
+        }
+    };
+    if let Err(e) = git::sync_voidpkgs(log, &res.voidpkgs) {
+        log.warn(e);
+    }
+
+    let mut rows = Vec::new();
+    let mut unknown = Vec::new();
+    for r in &records {
+        let Some(behind) = r.commit.as_deref().and_then(|c| git::commits_behind_upstream(&res.voidpkgs, c))
+        else {
+            unknown.push(r.name.clone());
+            continue;
+        };
+        if behind == 0 {
+            continue;
+        }
+        let commit = r.commit.clone().unwrap_or_default();
+        let template = srcpkgs::source_pkgname(&res.voidpkgs, &r.name).unwrap_or_else(|| r.name.clone());
+        rows.push(OutdatedTree {
+            name: r.name.clone(),
+            template_changed: git::changed_since(&res.voidpkgs, &commit, &format!("srcpkgs/{template}")),
+            commit,
+            behind,
+        });
+    }
+    rows.sort_by_key(|r| std::cmp::Reverse(r.behind));
+
+    if log.json {
+        log.emit_json(&rows);
+        return ExitCode::SUCCESS;
+    }
+    if rows.is_empty() {
+        log.info("vx: every tracked build is from upstream/master's current commit.");
+    } else if !log.quiet {
+        println!("built from an older void-packages tree ({}):", rows.len());
+    }
+    let w = rows.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    for r in &rows {
+        let short = &r.commit[..r.commit.len().min(10)];
+        println!("  {:<w$}  {short}  {}", r.name, tree_note(r.behind, r.template_changed));
+    }
+    if !unknown.is_empty() && log.verbose && !log.quiet {
+        log.info(format!("vx: no build commit recorded for {}", unknown.join(" ")));
+    }
+    ExitCode::SUCCESS
+}
+
+/// `vx src tag <pkg> <group...>` / `vx src untag <pkg> [group...]`.
+pub fn cmd_tag(log: &Log, pkg: &str, groups: &[String], remove: bool) -> ExitCode {
+    if let Some(g) = groups.iter().find(|g| g.is_empty() || g.contains([',', ' ', '\t', '"'])) {
+        log.error(format!("invalid group name: {g:?}"));
+        return ExitCode::from(2);
+    }
+    match managed::tag(log, pkg, groups, remove) {
+        Ok(true) if remove && groups.is_empty() => log.info(format!("vx: {pkg} is in no group now.")),
+        Ok(true) if remove => log.info(format!("vx: {pkg} removed from {}.", groups.join(" "))),
+        Ok(true) => log.info(format!("vx: {pkg} added to {}.", groups.join(" "))),
+        Ok(false) => {
+            log.warn(format!("{pkg} is not tracked."));
+            return ExitCode::from(1);
+        }
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// `vx src export`: the managed manifest on stdout.
+pub fn cmd_export(log: &Log) -> ExitCode {
+    match managed::export_manifest() {
+        Ok(text) => {
+            print!("{text}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log.error(format!("failed to load managed list: {e}"));
+            ExitCode::from(1)
+        }
+    }
+}
+
+pub struct Import {
+    pub apply: bool,
+    pub dry_run: bool,
+    pub yes: bool,
+    pub remote: bool,
+}
+
+/// `vx src import <file> [--apply]`: track an exported package set and, with
+/// --apply, build and install what isn't installed here.
+pub fn cmd_import(
+    log: &Log,
+    res: &SrcResolved,
+    file: &Path,
+    import: &Import,
+    opts: &xbps_src::SrcRunOptions,
+) -> ExitCode {
+    let (pkgs, pins) = match managed::load_manifest_file(file) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if pkgs.is_empty() {
+        log.warn(format!("{}: no packages to import.", file.display()));
+        return ExitCode::SUCCESS;
+    }
+    let names: Vec<String> = pkgs.iter().map(|p| p.name.clone()).collect();
+
+    if !import.dry_run {
+        if let Err(e) = managed::import_managed(log, &pkgs, &pins) {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+        log.info(format!("vx: tracking {} source package(s) from {}.", names.len(), file.display()));
+    }
+    if !import.apply && !import.dry_run {
+        log.info("vx: `vx src import --apply` builds the ones not installed yet.");
+        return ExitCode::SUCCESS;
+    }
+
+    if import.remote && let Err(e) = git::sync_voidpkgs(log, &res.voidpkgs) {
+        log.warn(e);
+    }
+    let missing: Vec<plan::SrcUpdate> =
+        match plan::plan_src_updates_with_resolved(log, res, &names, false, import.remote) {
+            Ok(v) => v.into_iter().filter(|u| u.installed.is_none()).collect(),
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        };
+
+    if log.json {
+        log.emit_json(&missing);
+    } else if !log.quiet {
+        if missing.is_empty() {
+            println!("vx: every imported package is already installed.");
+        } else {
+            println!("import plan ({}):", missing.len());
+            for u in &missing {
+                println!("  {}  {}", u.name, u.candidate);
+            }
+        }
+    }
+    if missing.is_empty() || import.dry_run {
+        return ExitCode::SUCCESS;
+    }
+
+    if !import.yes && !confirm_once("Build and install?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+    let pkgs: Vec<String> = missing.into_iter().map(|u| u.name).collect();
+    xbps_src::src_up(log, res, import.yes, import.remote, true, &pkgs, opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{drift, tree_note};
+
+    #[test]
+    fn drift_follows_plan_action() {
+        assert_eq!(drift(Some("reinstall")), "up to date");
+        assert_eq!(drift(Some("update")), "update available");
+        assert_eq!(drift(Some("downgrade")), "newer than template");
+        assert_eq!(drift(None), "no template");
+    }
+
+    #[test]
+    fn tree_note_reads_naturally() {
+        assert_eq!(tree_note(1, None), "1 commit behind");
+        assert_eq!(tree_note(12, Some(true)), "12 commits behind, template changed");
+        assert_eq!(tree_note(3, Some(false)), "3 commits behind, template unchanged");
+    }
+}
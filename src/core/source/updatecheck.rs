@@ -0,0 +1,231 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cache,
+    core::{version::pkgver_version, xbps::format_age},
+    log::Log,
+    managed,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    process::{ExitCode, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{resolve::SrcResolved, srcpkgs::source_templates, xbps_src::xbps_src_command};
+
+/// Results live in ~/.cache/vx/update-check, one `<pkg>\t<time>\t<versions>` line each.
+const UPDATE_CHECK_FILE: &str = "update-check";
+
+/// Re-check a template only after this long (--fresh forces it).
+const UPDATE_CHECK_TTL_SECS: u64 = 6 * 3600;
+
+/// What `./xbps-src update-check` last found for a template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpstreamCheck {
+    pub pkg: String,
+    pub checked: u64,
+    /// Newer upstream versions; empty when the template is current.
+    pub versions: Vec<String>,
+}
+
+/// Versions offered by `update-check` output (`foo-1.0 -> foo-1.1` lines).
+fn parse_update_check(out: &str) -> Vec<String> {
+    let mut versions: Vec<String> = Vec::new();
+    for line in out.lines() {
+        let Some((_, new)) = line.split_once(" -> ") else {
+            continue;
+        };
+        let v = pkgver_version(new.trim()).to_string();
+        if !v.is_empty() && !versions.contains(&v) {
+            versions.push(v);
+        }
+    }
+    versions
+}
+
+fn parse_cache(text: &str) -> BTreeMap<String, UpstreamCheck> {
+    text.lines()
+        .filter_map(|line| {
+            let mut f = line.split('\t');
+            let pkg = f.next()?.to_string();
+            let checked = f.next()?.parse().ok()?;
+            let versions = f.next().unwrap_or("").split_whitespace().map(String::from).collect();
+            Some((pkg.clone(), UpstreamCheck { pkg, checked, versions }))
+        })
+        .collect()
+}
+
+fn render_cache(all: &BTreeMap<String, UpstreamCheck>) -> String {
+    all.values()
+        .map(|c| format!("{}\t{}\t{}\n", c.pkg, c.checked, c.versions.join(" ")))
+        .collect()
+}
+
+/// Cached results, by template name.
+pub fn cached() -> BTreeMap<String, UpstreamCheck> {
+    fs::read_to_string(cache::data_path(UPDATE_CHECK_FILE))
+        .map(|t| parse_cache(&t))
+        .unwrap_or_default()
+}
+
+/// Drop the cached result for a template (e.g. after `vx src bump`).
+pub fn forget(log: &Log, pkg: &str) {
+    let _lock = cache::lock(log, UPDATE_CHECK_FILE).ok();
+    let mut all = cached();
+    if all.remove(pkg).is_some() {
+        let _ = cache::write_data(UPDATE_CHECK_FILE, &render_cache(&all));
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Run `./xbps-src update-check <pkg>` quietly and collect its versions.
+/// None when the check itself failed (warned about, with its stderr).
+fn run_update_check(
+    log: &Log,
+    res: &SrcResolved,
+    pkg: &str,
+) -> Result<Option<Vec<String>>, ExitCode> {
+    let mut cmd = xbps_src_command(log, &res.voidpkgs, vec!["update-check".into(), pkg.into()], &[])?;
+    match cmd.stdin(Stdio::null()).stderr(Stdio::piped()).output() {
+        Ok(o) if o.status.success() => {
+            Ok(Some(parse_update_check(&String::from_utf8_lossy(&o.stdout))))
+        }
+        Ok(o) => {
+            let err = String::from_utf8_lossy(&o.stderr).trim().to_string();
+            log.warn(if err.is_empty() {
+                format!("update-check {pkg} failed (exit={})", o.status.code().unwrap_or(1))
+            } else {
+                format!("update-check {pkg} failed: {err}")
+            });
+            Ok(None)
+        }
+        Err(e) => {
+            log.error(format!("failed to run ./xbps-src update-check: {e}"));
+            Err(ExitCode::from(1))
+        }
+    }
+}
+
+/// `vx src update-check [pkg...|--all]`
+pub fn cmd_update_check(log: &Log, res: &SrcResolved, pkgs: &[String], all: bool) -> ExitCode {
+    let names = if all {
+        match managed::load_managed() {
+            Ok(v) => v,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        }
+    } else {
+        pkgs.to_vec()
+    };
+    if names.is_empty() {
+        if all {
+            log.info("vx: no tracked source packages.");
+            return ExitCode::SUCCESS;
+        }
+        log.error("usage: vx src update-check <pkg> [pkg...] | --all");
+        return ExitCode::from(2);
+    }
+
+    let results = cached();
+    let now = now_secs();
+    let templates = source_templates(&res.voidpkgs, &names);
+    let mut checked: Vec<UpstreamCheck> = Vec::new();
+    let mut failed = 0usize;
+    for t in &templates {
+        if !cache::force_fresh()
+            && let Some(c) = results.get(t)
+            && now.saturating_sub(c.checked) <= UPDATE_CHECK_TTL_SECS
+        {
+            if log.verbose && !log.quiet {
+                log.exec(format!("cache hit: update-check {t} (ttl={UPDATE_CHECK_TTL_SECS}s)"));
+            }
+            checked.push(c.clone());
+            continue;
+        }
+
+        if !log.quiet && !log.json {
+            eprintln!("checking {t}...");
+        }
+        // A failed check is not cached: it says nothing about upstream.
+        let versions = match run_update_check(log, res, t) {
+            Ok(Some(v)) => v,
+            Ok(None) => {
+                failed += 1;
+                continue;
+            }
+            Err(c) => return c,
+        };
+        let c = UpstreamCheck {
+            pkg: t.clone(),
+            checked: now,
+            versions,
+        };
+        checked.push(c);
+    }
+
+    // Merge into what is cached now; another vx may have checked others meanwhile.
+    let _lock = cache::lock(log, UPDATE_CHECK_FILE).ok();
+    let mut results = cached();
+    results.extend(checked.iter().map(|c| (c.pkg.clone(), c.clone())));
+    if let Err(e) = cache::write_data(UPDATE_CHECK_FILE, &render_cache(&results)) {
+        log.warn(format!("failed to cache update-check results: {e}"));
+    }
+
+    let code = if failed > 0 {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    };
+    if log.json {
+        log.emit_json(&checked);
+        return code;
+    }
+
+    let newer: Vec<&UpstreamCheck> = checked.iter().filter(|c| !c.versions.is_empty()).collect();
+    if newer.is_empty() {
+        if failed == 0 {
+            log.info("vx: no new upstream releases.");
+        }
+        return code;
+    }
+
+    if !log.quiet {
+        println!("new upstream releases ({}):", newer.len());
+    }
+    let w = newer.iter().map(|c| c.pkg.len()).max().unwrap_or(0);
+    for c in newer {
+        println!("  {:<w$}  {}  (checked {})", c.pkg, c.versions.join(" "), format_age(c.checked));
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cache, parse_update_check, render_cache};
+
+    #[test]
+    fn update_check_output_and_cache_round_trip() {
+        let out = "foo-1.0_1 -> foo-1.1\nfoo-1.0_1 -> foo-1.2\nfoo-1.0_1 -> foo-1.1\nnoise\n";
+        assert_eq!(parse_update_check(out), ["1.1", "1.2"]);
+        assert!(parse_update_check("").is_empty());
+
+        let text = "bar\t1700000000\t\nfoo\t1700000100\t1.1 1.2\nbroken\n";
+        let cache = parse_cache(text);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache["foo"].versions, ["1.1", "1.2"]);
+        assert!(cache["bar"].versions.is_empty());
+        assert_eq!(render_cache(&cache), "bar\t1700000000\t\nfoo\t1700000100\t1.1 1.2\n");
+    }
+}
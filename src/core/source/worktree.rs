@@ -0,0 +1,108 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cli::SrcWorktreeCmd,
+    config::{Config, DEFAULT_WORKTREE_MAX_AGE_DAYS},
+    core::xbps::{format_age, format_size},
+    log::Log,
+};
+use std::{
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    distfiles::size_of,
+    git::{self, WorktreeInfo},
+};
+
+pub fn dispatch(log: &Log, cfg: Option<&Config>, cmd: SrcWorktreeCmd) -> ExitCode {
+    match cmd {
+        SrcWorktreeCmd::List => list(log),
+        SrcWorktreeCmd::Prune {
+            all,
+            older_than,
+            dry_run,
+        } => {
+            let days = older_than
+                .or_else(|| cfg.map(|c| c.worktree_max_age_days).filter(|d| *d > 0))
+                .unwrap_or(DEFAULT_WORKTREE_MAX_AGE_DAYS);
+            prune(log, all, days, dry_run)
+        }
+    }
+}
+
+fn source_label(w: &WorktreeInfo) -> String {
+    match &w.source {
+        Some(s) if s.join(".git").exists() => s.display().to_string(),
+        Some(s) => format!("{} (gone)", s.display()),
+        None => "(unknown)".to_string(),
+    }
+}
+
+fn list(log: &Log) -> ExitCode {
+    let all = git::list_worktrees();
+    if all.is_empty() {
+        if !log.quiet {
+            println!("no upstream worktrees.");
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let mut total = 0;
+    for w in &all {
+        let size = size_of(&w.bucket);
+        total += size;
+        println!(
+            "  {:<10} {:>9}  {}\n    from {}",
+            format_age(w.last_used),
+            format_size(size),
+            w.worktree().display(),
+            source_label(w)
+        );
+    }
+    if all.len() > 1 {
+        println!("total: {}", format_size(total));
+    }
+    ExitCode::SUCCESS
+}
+
+pub fn prune(log: &Log, all: bool, max_age_days: u64, dry_run: bool) -> ExitCode {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let doomed: Vec<WorktreeInfo> = git::list_worktrees()
+        .into_iter()
+        .filter(|w| all || w.is_stale(now, max_age_days))
+        .collect();
+    if doomed.is_empty() {
+        log.info("vx: no stale worktrees.");
+        return ExitCode::SUCCESS;
+    }
+
+    let (mut removed, mut freed) = (0, 0);
+    let mut failed = false;
+    for w in &doomed {
+        let size = size_of(&w.bucket);
+        if dry_run {
+            println!("would remove {} ({}, {})", w.bucket.display(), format_size(size), source_label(w));
+            continue;
+        }
+        match git::remove_worktree(log, w) {
+            Ok(()) => {
+                removed += 1;
+                freed += size;
+            }
+            Err(e) => {
+                log.warn(e);
+                failed = true;
+            }
+        }
+    }
+    if !dry_run {
+        log.info(format!("vx: removed {removed} worktree(s), freed {}.", format_size(freed)));
+    }
+    if failed { ExitCode::from(1) } else { ExitCode::SUCCESS }
+}
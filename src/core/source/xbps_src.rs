@@ -1,7 +1,7 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use crate::{log::Log, managed};
+use crate::{core::xbps::ymd, log::Log, managed};
 use std::{
     ffi::OsString,
     fs,
@@ -10,8 +10,15 @@ use std::{
 };
 
 use super::add;
+use super::buildlog;
+use super::confirm_once;
 use super::git;
+use super::order;
+use super::push::shell_quote;
+use super::srcpkgs;
+use super::stamp;
 use super::resolve::SrcResolved;
+use super::template::Template;
 
 #[derive(Debug, Clone, Default)]
 pub struct SrcRunOptions {
@@ -38,18 +45,165 @@ pub struct SrcRunOptions {
     pub fail_missing_deps: bool,
     pub strict_warnings: bool,
     pub passthrough: Vec<String>,
+    /// Not for xbps-src: leave the builddirs of earlier builds alone.
+    pub keep: bool,
+    /// Not for xbps-src: build in `src_up` even when the template is unchanged
+    /// since its last build.
+    pub rebuild: bool,
+    /// Not for xbps-src: packages `src_up` installs without starting to track
+    /// them (reverse dependencies rebuilt by `--revdeps`).
+    pub untracked: Vec<String>,
 }
 
 pub fn build(log: &Log, res: &SrcResolved, pkgs: &[String], opts: &SrcRunOptions) -> ExitCode {
-    run_xbps_src(log, &res.voidpkgs, join_args_with_opts("pkg", pkgs, opts))
+    if let Err(e) = ensure_xbps_conf(log, &res.voidpkgs, res) {
+        log.warn(format!("failed to update etc/conf: {e}"));
+    }
+    let c = ensure_profile_masterdir(log, res, &res.voidpkgs, opts, &[]);
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+    build_logged(log, &res.voidpkgs, pkgs, opts, &[])
 }
 
-pub fn clean(log: &Log, res: &SrcResolved, pkgs: &[String]) -> ExitCode {
-    run_xbps_src(log, &res.voidpkgs, join_args("clean", pkgs))
+/// `./xbps-src pkg` once per package, in dependency order and each into its
+/// own build log (`vx src log <pkg>`). Stops at the first failure.
+pub fn build_logged(
+    log: &Log,
+    dir: &Path,
+    pkgs: &[String],
+    opts: &SrcRunOptions,
+    env: &[(String, String)],
+) -> ExitCode {
+    let pkgs = match order::build_order(dir, pkgs) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if pkgs.len() > 1 && !log.quiet {
+        println!("build order: {}", pkgs.join(" "));
+    }
+
+    for p in &pkgs {
+        let args = join_args_with_opts("pkg", std::slice::from_ref(p), opts);
+        let c = buildlog::run_logged(log, dir, args, env, p);
+        if c != ExitCode::SUCCESS {
+            buildlog::report_failure(log, dir, opts.masterdir.as_deref(), p);
+            return c;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Bootstrap a build profile's masterdir for its architecture before the
+/// first build; left alone, xbps-src would bootstrap it for the host's.
+pub fn ensure_profile_masterdir(
+    log: &Log,
+    res: &SrcResolved,
+    dir: &Path,
+    opts: &SrcRunOptions,
+    env: &[(String, String)],
+) -> ExitCode {
+    let (Some(arch), Some(masterdir)) = (&res.profile.arch, &opts.masterdir) else {
+        return ExitCode::SUCCESS;
+    };
+    if dir.join(masterdir).join(".xbps_chroot_init").exists() {
+        return ExitCode::SUCCESS;
+    }
+
+    log.info(format!(
+        "vx: bootstrapping {} for the {} profile ({arch})",
+        masterdir.display(),
+        res.profile.name
+    ));
+    let bootstrap = SrcRunOptions {
+        masterdir: Some(masterdir.clone()),
+        hostdir: opts.hostdir.clone(),
+        ..SrcRunOptions::default()
+    };
+    run_xbps_src_with_env(
+        log,
+        dir,
+        join_args_with_opts("binary-bootstrap", std::slice::from_ref(arch), &bootstrap),
+        env,
+    )
+}
+
+pub fn clean(log: &Log, res: &SrcResolved, pkgs: &[String], raw: &[String]) -> ExitCode {
+    run_xbps_src(log, &res.voidpkgs, join_args("clean", pkgs, raw))
+}
+
+/// `vx src fetch`: `./xbps-src fetch|extract|patch` without building.
+///
+/// Upstream builds share `<void-packages>/distfiles` (XBPS_DISTDIR), so
+/// sources fetched through the worktree are there for a later offline build;
+/// with remote=false they go where the checkout's own builds look.
+pub fn fetch(
+    log: &Log,
+    res: &SrcResolved,
+    remote: bool,
+    step: &str,
+    pkgs: &[String],
+    raw: &[String],
+) -> ExitCode {
+    if !remote {
+        return run_xbps_src(log, &res.voidpkgs, join_args(step, pkgs, raw));
+    }
+
+    let wt = match git::ensure_upstream_worktree(log, &res.voidpkgs, false) {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if let Err(e) = ensure_xbps_conf(log, &wt, res) {
+        log.warn(format!("failed to ensure etc/conf in worktree: {e}"));
+    }
+    if let Err(e) = overlay_dirs(log, res, &wt) {
+        log.warn(format!("failed to apply source.overlays: {e}"));
+    }
+    if let Err(e) = overlay_local_srcpkgs(log, &res.voidpkgs, &wt, pkgs) {
+        log.warn(format!("failed to overlay local srcpkgs: {e}"));
+    }
+    run_xbps_src_with_env(log, &wt, join_args(step, pkgs, raw), &build_env_for_worktree(res))
 }
 
 pub fn lint(log: &Log, res: &SrcResolved, pkgs: &[String]) -> ExitCode {
-    run_xbps_src(log, &res.voidpkgs, join_args("lint", pkgs))
+    run_xbps_src(log, &res.voidpkgs, join_args("lint", pkgs, &[]))
+}
+
+/// `vx src zap`: remove the masterdir (build chroot) of the local checkout,
+/// optionally bootstrapping a fresh one right after.
+///
+/// Upstream worktree builds don't need this; their masterdir is wiped with
+/// the worktree on every run.
+pub fn zap(log: &Log, res: &SrcResolved, rebootstrap: bool, yes: bool) -> ExitCode {
+    if !yes {
+        let what = if rebootstrap { "Remove and re-bootstrap" } else { "Remove" };
+        let masterdir = match &res.profile.masterdir {
+            Some(m) => format!("{} ({} profile)", m.display(), res.profile.name),
+            None => "masterdir".to_string(),
+        };
+        let prompt = format!("{what} the xbps-src {masterdir} in {}?", res.voidpkgs.display());
+        if !confirm_once(&prompt) {
+            log.info("aborted.");
+            return ExitCode::SUCCESS;
+        }
+    }
+
+    let opts = SrcRunOptions {
+        masterdir: res.profile.masterdir.as_ref().map(|m| res.voidpkgs.join(m)),
+        ..SrcRunOptions::default()
+    };
+    let c = run_xbps_src(log, &res.voidpkgs, join_args_with_opts("zap", &[], &opts));
+    if c != ExitCode::SUCCESS || !rebootstrap {
+        return c;
+    }
+    let arch: Vec<String> = res.profile.arch.iter().cloned().collect();
+    run_xbps_src(log, &res.voidpkgs, join_args_with_opts("binary-bootstrap", &arch, &opts))
 }
 
 /// Build + install source packages, then track them in the managed list.
@@ -57,11 +211,13 @@ pub fn lint(log: &Log, res: &SrcResolved, pkgs: &[String]) -> ExitCode {
 /// - remote=true (default): builds from upstream/master via git worktree.
 ///   Does not touch your local branch.
 /// - remote=false (--local): builds from your local void-packages checkout.
+/// - fetch=false: uses upstream/master as already fetched (no git fetch).
 pub fn src_up(
     log: &Log,
     res: &SrcResolved,
     yes: bool,
     remote: bool,
+    fetch: bool,
     pkgs: &[String],
     opts: &SrcRunOptions,
 ) -> ExitCode {
@@ -71,7 +227,12 @@ pub fn src_up(
     }
 
     let (dir, env) = if remote {
-        let wt = match git::ensure_upstream_worktree(log, &res.voidpkgs) {
+        let wt = if fetch {
+            git::ensure_upstream_worktree(log, &res.voidpkgs, opts.keep)
+        } else {
+            git::upstream_worktree(log, &res.voidpkgs, opts.keep)
+        };
+        let wt = match wt {
             Ok(p) => p,
             Err(e) => {
                 log.error(e);
@@ -79,10 +240,14 @@ pub fn src_up(
             }
         };
 
-        if let Err(e) = ensure_xbps_conf(log, &wt, res.use_nonfree) {
+        if let Err(e) = ensure_xbps_conf(log, &wt, res) {
             log.warn(format!("failed to ensure etc/conf in worktree: {e}"));
         }
 
+        if let Err(e) = overlay_dirs(log, res, &wt) {
+            log.warn(format!("failed to apply source.overlays: {e}"));
+        }
+
         if let Err(e) = overlay_local_srcpkgs(log, &res.voidpkgs, &wt, pkgs) {
             log.warn(format!(
                 "failed to overlay local srcpkgs into upstream worktree: {e}"
@@ -91,26 +256,62 @@ pub fn src_up(
 
         (wt, build_env_for_worktree(res))
     } else {
-        if let Err(e) = ensure_xbps_conf(log, &res.voidpkgs, res.use_nonfree) {
+        if let Err(e) = ensure_xbps_conf(log, &res.voidpkgs, res) {
             log.warn(format!("failed to ensure etc/conf in local repo: {e}"));
         }
         (res.voidpkgs.clone(), Vec::new())
     };
 
-    let c = run_xbps_src_with_env(log, &dir, join_args_with_opts("clean", pkgs, opts), &env);
+    let c = ensure_profile_masterdir(log, res, &dir, opts, &env);
     if c != ExitCode::SUCCESS {
         return c;
     }
 
-    let c = run_xbps_src_with_env(log, &dir, join_args_with_opts("pkg", pkgs, opts), &env);
-    if c != ExitCode::SUCCESS {
-        return c;
+    // Subpackages come out of their main template's build; build each
+    // template once and install every requested package from the result.
+    // Templates built before from the same files and options are only
+    // reinstalled from the local repository.
+    let (unchanged, templates): (Vec<String>, Vec<String>) = srcpkgs::source_templates(&dir, pkgs)
+        .into_iter()
+        .partition(|t| !opts.rebuild && !opts.force_stage && stamp::is_current(res, &dir, t, opts));
+    if !unchanged.is_empty() {
+        log.info(format!(
+            "vx: unchanged since the last build, not rebuilding: {} (--force rebuilds)",
+            unchanged.join(" ")
+        ));
+    }
+
+    if !templates.is_empty() {
+        // A failed build's builddir stays until the package builds again.
+        let clean: Vec<String> = templates
+            .iter()
+            .filter(|t| !buildlog::failed_in(t, &dir))
+            .cloned()
+            .collect();
+        if !opts.keep && !clean.is_empty() {
+            let c = run_xbps_src_with_env(log, &dir, join_args_with_opts("clean", &clean, opts), &env);
+            if c != ExitCode::SUCCESS {
+                return c;
+            }
+        }
+
+        let c = build_logged(log, &dir, &templates, opts, &env);
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+        stamp::record(log, res, &dir, &templates, opts);
     }
 
     let c = add::add_from_local_repo(log, res, true, yes, pkgs);
 
     if c == ExitCode::SUCCESS {
-        if let Err(e) = managed::add_managed(&pkgs.to_vec()) {
+        let tracked = managed::load_managed().unwrap_or_default();
+        let track: Vec<String> = pkgs
+            .iter()
+            .filter(|p| !opts.untracked.contains(p) || tracked.contains(p))
+            .cloned()
+            .collect();
+        if let Err(e) = managed::add_managed(log, &build_records(res, &dir, remote, &track)) {
             log.warn(format!("failed to update managed list: {e}"));
         }
     }
@@ -118,8 +319,35 @@ pub fn src_up(
     c
 }
 
-pub fn join_args(sub: &str, pkgs: &[String]) -> Vec<OsString> {
-    let mut out = Vec::with_capacity(1 + pkgs.len());
+/// Manifest records of `pkgs`, just installed from the templates in `dir`.
+fn build_records(res: &SrcResolved, dir: &Path, remote: bool, pkgs: &[String]) -> Vec<managed::ManagedPkg> {
+    let installed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| ymd(d.as_secs()))
+        .ok();
+    let commit = git::head_commit(dir);
+    let arch = res.profile.arch.clone().unwrap_or_else(crate::core::repo::xbps_arch);
+    pkgs.iter()
+        .map(|p| managed::ManagedPkg {
+            name: p.clone(),
+            installed: installed.clone(),
+            version: fs::read_to_string(dir.join("srcpkgs").join(p).join("template"))
+                .ok()
+                .and_then(|text| Template::parse(&text).ok())
+                .map(|t| format!("{}_{}", t.version, t.revision)),
+            commit: commit.clone(),
+            mode: Some(if remote { "remote" } else { "local" }.to_string()),
+            arch: Some(arch.clone()).filter(|a| !a.is_empty()),
+            groups: Vec::new(),
+        })
+        .collect()
+}
+
+/// `[raw...] <sub> <pkgs...>`; `raw` is what the user passed after `--`,
+/// forwarded to ./xbps-src as-is.
+pub fn join_args(sub: &str, pkgs: &[String], raw: &[String]) -> Vec<OsString> {
+    let mut out = Vec::with_capacity(1 + raw.len() + pkgs.len());
+    out.extend(raw.iter().map(OsString::from));
     out.push(OsString::from(sub));
     out.extend(pkgs.iter().cloned().map(OsString::from));
     out
@@ -218,12 +446,36 @@ pub fn run_xbps_src_with_env(
     args: Vec<OsString>,
     env: &[(String, String)],
 ) -> ExitCode {
+    let mut cmd = match xbps_src_command(log, voidpkgs, args, env) {
+        Ok(c) => c,
+        Err(c) => return c,
+    };
+    cmd.stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+
+    match cmd.status() {
+        Ok(status) => ExitCode::from(status.code().unwrap_or(1) as u8),
+        Err(e) => {
+            log.error(format!("failed to run ./xbps-src: {e}"));
+            ExitCode::from(1)
+        }
+    }
+}
+
+/// `./xbps-src <args>` in `voidpkgs` with `env` set (traced with -v).
+pub fn xbps_src_command(
+    log: &Log,
+    voidpkgs: &Path,
+    args: Vec<OsString>,
+    env: &[(String, String)],
+) -> Result<Command, ExitCode> {
     if !voidpkgs.join("xbps-src").is_file() {
         log.error(format!(
             "not a void-packages directory (missing ./xbps-src): {}",
             voidpkgs.display()
         ));
-        return ExitCode::from(2);
+        return Err(ExitCode::from(2));
     }
 
     if log.verbose && !log.quiet {
@@ -244,65 +496,93 @@ pub fn run_xbps_src_with_env(
     }
 
     let mut cmd = Command::new("./xbps-src");
-    cmd.current_dir(voidpkgs)
-        .args(args)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
-
+    cmd.current_dir(voidpkgs).args(args);
     for (k, v) in env {
         cmd.env(k, v);
     }
+    Ok(cmd)
+}
 
-    match cmd.status() {
-        Ok(status) => ExitCode::from(status.code().unwrap_or(1) as u8),
-        Err(e) => {
-            log.error(format!("failed to run ./xbps-src: {e}"));
-            ExitCode::from(1)
+const CONF_BLOCK_BEGIN: &str = "# --- vx: build.env (generated from vx.rune; edits here are replaced) ---";
+const CONF_BLOCK_END: &str = "# --- vx: end build.env ---";
+
+/// `etc/conf` with the vx-managed `build.env` block replaced by `env`
+/// (dropped when empty) and XBPS_ALLOW_RESTRICTED=yes added when asked for.
+///
+/// xbps-src sources etc/conf, so values are single-quoted: they are taken
+/// literally, never expanded or run.
+fn render_xbps_conf(text: &str, allow_restricted: bool, env: &[String]) -> String {
+    let mut out = String::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        match line.trim() {
+            CONF_BLOCK_BEGIN => in_block = true,
+            CONF_BLOCK_END => in_block = false,
+            _ if !in_block => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            _ => {}
         }
     }
-}
 
-/// Ensure `etc/conf` contains XBPS_ALLOW_RESTRICTED=yes when allow_restricted=true.
-pub fn ensure_xbps_conf(log: &Log, voidpkgs: &Path, allow_restricted: bool) -> Result<(), String> {
-    if !allow_restricted {
-        return Ok(());
+    if allow_restricted && !out.lines().any(|l| l.trim() == "XBPS_ALLOW_RESTRICTED=yes") {
+        out.push_str("XBPS_ALLOW_RESTRICTED=yes\n");
     }
+    if !env.is_empty() {
+        out.push_str(CONF_BLOCK_BEGIN);
+        out.push('\n');
+        for kv in env {
+            let (k, v) = kv.split_once('=').unwrap_or((kv, ""));
+            out.push_str(&format!("{k}={}\n", shell_quote(v)));
+        }
+        out.push_str(CONF_BLOCK_END);
+        out.push('\n');
+    }
+    out
+}
+
+/// `build.env` entries that are `NAME=value` with a shell-safe NAME.
+fn valid_env(log: &Log, env: &[String]) -> Vec<String> {
+    env.iter()
+        .filter(|kv| {
+            let ok = kv.split_once('=').is_some_and(|(k, _)| {
+                !k.is_empty()
+                    && !k.starts_with(|c: char| c.is_ascii_digit())
+                    && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+            if !ok {
+                log.warn(format!("build.env: ignoring '{kv}' (expected NAME=value)"));
+            }
+            ok
+        })
+        .cloned()
+        .collect()
+}
 
+/// Bring `etc/conf` in line with the config: XBPS_ALLOW_RESTRICTED=yes when
+/// nonfree is in use, plus the `build.env` entries.
+pub fn ensure_xbps_conf(log: &Log, voidpkgs: &Path, res: &SrcResolved) -> Result<(), String> {
     let etc_dir = voidpkgs.join("etc");
     let conf = etc_dir.join("conf");
 
-    fs::create_dir_all(&etc_dir)
-        .map_err(|e| format!("failed to create {}: {e}", etc_dir.display()))?;
+    let text = if conf.is_file() {
+        fs::read_to_string(&conf).map_err(|e| format!("failed to read {}: {e}", conf.display()))?
+    } else {
+        String::new()
+    };
 
-    let mut needs_write = true;
-    if conf.is_file() {
-        let text = fs::read_to_string(&conf)
-            .map_err(|e| format!("failed to read {}: {e}", conf.display()))?;
-        if text.lines().any(|l| l.trim() == "XBPS_ALLOW_RESTRICTED=yes") {
-            needs_write = false;
-        }
+    let out = render_xbps_conf(&text, res.use_nonfree, &valid_env(log, &res.build_env));
+    if out.trim_end() == text.trim_end() {
+        return Ok(());
     }
 
-    if needs_write {
-        if log.verbose && !log.quiet {
-            log.exec(format!("write {}", conf.display()));
-        }
-        let mut out = String::new();
-        if conf.is_file() {
-            out.push_str(
-                &fs::read_to_string(&conf)
-                    .map_err(|e| format!("failed to read {}: {e}", conf.display()))?,
-            );
-            if !out.ends_with('\n') {
-                out.push('\n');
-            }
-        }
-        out.push_str("XBPS_ALLOW_RESTRICTED=yes\n");
-        fs::write(&conf, out).map_err(|e| format!("failed to write {}: {e}", conf.display()))?;
+    if log.verbose && !log.quiet {
+        log.exec(format!("write {}", conf.display()));
     }
-
-    Ok(())
+    fs::create_dir_all(&etc_dir)
+        .map_err(|e| format!("failed to create {}: {e}", etc_dir.display()))?;
+    fs::write(&conf, out).map_err(|e| format!("failed to write {}: {e}", conf.display()))
 }
 
 pub fn build_env_for_worktree(res: &SrcResolved) -> Vec<(String, String)> {
@@ -356,25 +636,70 @@ pub fn overlay_local_srcpkgs(
             continue;
         }
 
-        let wt_dir = worktree.join("srcpkgs").join(pkg);
+        let why = if marker.is_file() { "marker .vx-overlay" } else { "fork-only" };
+        overlay_srcpkg(log, local_repo, worktree, pkg, why)?;
+    }
 
-        if wt_dir.exists() {
-            fs::remove_dir_all(&wt_dir)
-                .map_err(|e| format!("failed to remove {}: {e}", wt_dir.display()))?;
-        }
+    Ok(())
+}
 
-        if log.verbose && !log.quiet {
-            let why = if marker.is_file() { "marker .vx-overlay" } else { "fork-only" };
-            log.exec(format!(
-                "overlay ({why}): {} -> {}",
-                local_dir.display(),
-                wt_dir.display()
-            ));
-        }
+/// Replace `srcpkgs/<pkg>` of the worktree with the local one, unconditionally.
+pub fn overlay_srcpkg(
+    log: &Log,
+    local_repo: &Path,
+    worktree: &Path,
+    pkg: &str,
+    why: &str,
+) -> Result<(), String> {
+    let local_dir = local_repo.join("srcpkgs").join(pkg);
+    let wt_dir = worktree.join("srcpkgs").join(pkg);
 
-        copy_dir_all(&local_dir, &wt_dir)?;
+    if wt_dir.exists() {
+        fs::remove_dir_all(&wt_dir)
+            .map_err(|e| format!("failed to remove {}: {e}", wt_dir.display()))?;
+    }
+
+    if log.verbose && !log.quiet {
+        log.exec(format!(
+            "overlay ({why}): {} -> {}",
+            local_dir.display(),
+            wt_dir.display()
+        ));
     }
 
+    copy_dir_all(&local_dir, &wt_dir)
+}
+
+/// Copy the templates of the `source.overlays` directories into the upstream
+/// worktree `wt`, replacing upstream's. Your own checkout is never touched.
+pub fn overlay_dirs(log: &Log, res: &SrcResolved, wt: &Path) -> Result<(), String> {
+    for dir in &res.overlays {
+        let srcpkgs = if dir.join("srcpkgs").is_dir() { dir.join("srcpkgs") } else { dir.clone() };
+        let rd = fs::read_dir(&srcpkgs)
+            .map_err(|e| format!("failed to read overlay {}: {e}", srcpkgs.display()))?;
+
+        for e in rd.flatten() {
+            let (from, name) = (e.path(), e.file_name());
+            let to = wt.join("srcpkgs").join(&name);
+
+            if from.is_symlink() {
+                let target = fs::read_link(&from)
+                    .map_err(|e| format!("failed to read link {}: {e}", from.display()))?;
+                let _ = fs::remove_file(&to);
+                std::os::unix::fs::symlink(&target, &to)
+                    .map_err(|e| format!("failed to link {}: {e}", to.display()))?;
+            } else if from.join("template").is_file() {
+                if to.exists() {
+                    fs::remove_dir_all(&to)
+                        .map_err(|e| format!("failed to remove {}: {e}", to.display()))?;
+                }
+                if log.verbose && !log.quiet {
+                    log.exec(format!("overlay (source.overlays): {} -> {}", from.display(), to.display()));
+                }
+                copy_dir_all(&from, &to)?;
+            }
+        }
+    }
     Ok(())
 }
 
@@ -411,7 +736,7 @@ fn copy_dir_all(src: &Path, dst: &Path) -> Result<(), String> {
 
 #[cfg(test)]
 mod tests {
-    use super::{SrcRunOptions, join_args_with_opts};
+    use super::{SrcRunOptions, join_args, join_args_with_opts, render_xbps_conf};
     use std::{ffi::OsString, path::PathBuf};
 
     fn s(args: Vec<OsString>) -> Vec<String> {
@@ -430,6 +755,13 @@ mod tests {
         assert_eq!(out, vec!["pkg", "ripgrep"]);
     }
 
+    #[test]
+    fn join_args_puts_raw_flags_before_subcommand() {
+        let raw = ["-K".to_string(), "-j8".to_string()];
+        let out = s(join_args("fetch", &["ripgrep".to_string()], &raw));
+        assert_eq!(out, vec!["-K", "-j8", "fetch", "ripgrep"]);
+    }
+
     #[test]
     fn join_args_with_opts_emits_selected_flags_and_passthrough_before_subcommand() {
         let opts = SrcRunOptions {
@@ -456,6 +788,9 @@ mod tests {
             fail_missing_deps: true,
             strict_warnings: true,
             passthrough: vec!["--foo".to_string(), "--bar".to_string()],
+            keep: true,
+            rebuild: true,
+            untracked: Vec::new(),
         };
 
         let out = s(join_args_with_opts("pkg", &["hello".to_string()], &opts));
@@ -498,4 +833,65 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn xbps_conf_build_env_block_is_replaced() {
+        let env = ["XBPS_CCACHE=yes".to_string()];
+        let conf = render_xbps_conf("XBPS_CHROOT_CMD=uchroot\n", true, &env);
+        assert_eq!(
+            conf,
+            "XBPS_CHROOT_CMD=uchroot\nXBPS_ALLOW_RESTRICTED=yes\n\
+             # --- vx: build.env (generated from vx.rune; edits here are replaced) ---\n\
+             XBPS_CCACHE='yes'\n# --- vx: end build.env ---\n"
+        );
+
+        // Re-rendering is stable, and an empty build.env drops the block.
+        assert_eq!(render_xbps_conf(&conf, true, &env), conf);
+        assert_eq!(
+            render_xbps_conf(&conf, true, &[]),
+            "XBPS_CHROOT_CMD=uchroot\nXBPS_ALLOW_RESTRICTED=yes\n"
+        );
+
+        // Values are data, not shell.
+        let env = ["XBPS_MAKEJOBS=$(id) it's".to_string()];
+        assert!(render_xbps_conf("", false, &env).contains("XBPS_MAKEJOBS='$(id) it'\\''s'\n"));
+    }
+
+    #[test]
+    fn overlays_replace_templates_of_the_worktree() {
+        use super::overlay_dirs;
+        use crate::{core::source::{profile::BuildProfile, resolve::SrcResolved}, log::Log};
+        use std::{fs, os::unix::fs::symlink};
+
+        let root = std::env::temp_dir().join(format!("vx-overlays-{}", std::process::id()));
+        let (voidpkgs, wt, overlay) = (root.join("void-packages"), root.join("wt"), root.join("mine"));
+        for d in [wt.join("srcpkgs/foo"), overlay.join("srcpkgs/foo"), overlay.join("srcpkgs/bar")] {
+            fs::create_dir_all(&d).unwrap();
+        }
+        fs::write(wt.join("srcpkgs/foo/template"), "version=1\n").unwrap();
+        fs::write(overlay.join("srcpkgs/foo/template"), "version=2\n").unwrap();
+        fs::write(overlay.join("srcpkgs/bar/template"), "version=3\n").unwrap();
+        symlink("bar", overlay.join("srcpkgs/bar-devel")).unwrap();
+
+        let res = SrcResolved {
+            voidpkgs: voidpkgs.clone(),
+            local_repo_rel: PathBuf::from("hostdir/binpkgs"),
+            use_nonfree: false,
+            profile: BuildProfile::default(),
+            jobs: None,
+            build_env: Vec::new(),
+            overlays: vec![overlay.clone()],
+        };
+        let log = Log { quiet: true, verbose: false, json: false };
+        overlay_dirs(&log, &res, &wt).unwrap();
+        fs::write(overlay.join("srcpkgs/bar/template"), "version=4\n").unwrap();
+        overlay_dirs(&log, &res, &wt).unwrap();
+
+        let read = |p: &str| fs::read_to_string(wt.join(p)).unwrap();
+        assert_eq!(read("srcpkgs/foo/template"), "version=2\n");
+        assert_eq!(read("srcpkgs/bar/template"), "version=4\n");
+        assert_eq!(read("srcpkgs/bar-devel/template"), "version=4\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
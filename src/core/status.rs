@@ -1,50 +1,188 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use crate::{cli::Cli, config::Config, managed, paths::user_config_path};
-use std::{env, path::PathBuf, process::ExitCode};
-
-pub fn run_status(_log: &crate::log::Log, cli: &Cli, cfg: Option<&Config>) -> ExitCode {
-    println!("version: {}", env!("CARGO_PKG_VERSION"));
-
-    match user_config_path() {
-        Ok(p) => {
-            if p.exists() {
-                println!("config: loaded ({})", p.display());
-            } else {
-                println!("config: none (expected at {})", p.display());
-            }
+use crate::{
+    cli::Cli,
+    config::{Config, Origin},
+    core::{
+        source::updatecheck::{self, UpstreamCheck},
+        xbps::{self, PkgStats},
+    },
+    managed,
+    paths::{project_config_path, user_config_path},
+};
+use serde::Serialize;
+use std::{path::PathBuf, process::ExitCode};
+
+/// Everything `vx status` reports; printed as text or, with `--json`, as JSON.
+#[derive(Debug, Serialize)]
+struct Status {
+    version: &'static str,
+    config: PathBuf,
+    config_loaded: bool,
+    /// The `.vx.rune` of the current directory or an ancestor, if any.
+    project_config: Option<PathBuf>,
+    debug: bool,
+    voidpkgs: Option<PathBuf>,
+    voidpkgs_source: &'static str,
+    local_repo: PathBuf,
+    use_nonfree: bool,
+    privilege: &'static str,
+    privilege_source: &'static str,
+    root: PathBuf,
+    managed: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    managed_error: Option<String>,
+    /// Cached `vx src update-check` results with new releases.
+    upstream_releases: Vec<UpstreamCheck>,
+    packages: Option<PkgStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    packages_error: Option<String>,
+    quiet: bool,
+    verbose: bool,
+}
+
+/// `vx status --resolved`: each setting's effective value and its source.
+fn run_resolved(log: &crate::log::Log, cli: &Cli, cfg: Option<&Config>) -> ExitCode {
+    let defaults;
+    let cfg = match cfg {
+        Some(c) => c,
+        None => {
+            defaults = Config::defaults();
+            &defaults
+        }
+    };
+
+    // The global flags beat everything else.
+    let mut rows = cfg.resolved();
+    for r in &mut rows {
+        let flag = match r.key {
+            "void_packages.path" => cli.voidpkgs.as_ref(),
+            "xbps.root" => cli.root.as_ref(),
+            _ => None,
+        };
+        if let Some(p) = flag.filter(|p| !p.as_os_str().is_empty()) {
+            r.value = p.display().to_string();
+            r.origin = Origin::Cli;
         }
+    }
+
+    if log.json {
+        log.emit_json(&rows);
+        return ExitCode::SUCCESS;
+    }
+    let w = rows.iter().map(|r| r.key.len()).max().unwrap_or(0);
+    for r in &rows {
+        let from = match r.origin {
+            Origin::Env => format!("env {}", r.env),
+            o => o.as_str().to_string(),
+        };
+        println!("{:<w$}  {}  ({from})", r.key, r.value);
+    }
+    ExitCode::SUCCESS
+}
+
+pub fn run_status(log: &crate::log::Log, cli: &Cli, cfg: Option<&Config>, resolved: bool) -> ExitCode {
+    if resolved {
+        return run_resolved(log, cli, cfg);
+    }
+
+    let config = match user_config_path() {
+        Ok(p) => p,
         Err(e) => {
             eprintln!("error: failed to resolve config path: {e}");
             return ExitCode::from(2);
         }
+    };
+
+    let (voidpkgs, voidpkgs_source) = resolve_voidpkgs_for_status(cli, cfg);
+    let privilege_source = match cfg.filter(|c| c.privilege_command.is_some()) {
+        Some(c) => match c.origins.get("privilege.command") {
+            Some(Origin::Env) => "env",
+            _ => "config",
+        },
+        None => "auto",
+    };
+
+    let (managed, managed_error) = match managed::load_managed() {
+        Ok(list) => (list, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+
+    let (packages, packages_error) = match xbps::package_stats() {
+        Ok(s) => (Some(s), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    let status = Status {
+        version: env!("CARGO_PKG_VERSION"),
+        config_loaded: config.exists(),
+        project_config: project_config_path(),
+        config,
+        debug: cfg.is_some_and(|c| c.debug),
+        voidpkgs,
+        voidpkgs_source,
+        local_repo: cfg
+            .map(|c| c.local_repo_rel.clone())
+            .unwrap_or_else(|| PathBuf::from("hostdir/binpkgs")),
+        use_nonfree: cfg.is_none_or(|c| c.use_nonfree),
+        privilege: crate::privilege::current().program().unwrap_or("none"),
+        privilege_source,
+        root: crate::rootdir::current()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/")),
+        managed,
+        managed_error,
+        upstream_releases: updatecheck::cached()
+            .into_values()
+            .filter(|c| !c.versions.is_empty())
+            .collect(),
+        packages,
+        packages_error,
+        quiet: cli.quiet,
+        verbose: cli.verbose,
+    };
+
+    if log.json {
+        log.emit_json(&status);
+    } else {
+        print_status(&status);
     }
+    ExitCode::SUCCESS
+}
 
-    if let Some(c) = cfg {
-        println!("debug: {}", c.debug);
+fn print_status(s: &Status) {
+    println!("version: {}", s.version);
+
+    if s.config_loaded {
+        println!("config: loaded ({})", s.config.display());
     } else {
-        println!("debug: false");
+        println!("config: none (expected at {})", s.config.display());
     }
 
-    let (voidpkgs, source) = resolve_voidpkgs_for_status(cli, cfg);
-    match voidpkgs {
-        Some(p) => println!("voidpkgs: {} ({})", p.display(), source),
-        None => println!("voidpkgs: unset (needed for `vx src ...`)"),
+    if let Some(p) = &s.project_config {
+        println!("project config: {}", p.display());
     }
 
-    if let Some(c) = cfg {
-        println!(
-            "src repo: {} (use_nonfree={})",
-            c.local_repo_rel.display(),
-            c.use_nonfree
-        );
-    } else {
-        println!("src repo: hostdir/binpkgs (use_nonfree=true)");
+    println!("debug: {}", s.debug);
+
+    match &s.voidpkgs {
+        Some(p) => println!("voidpkgs: {} ({})", p.display(), s.voidpkgs_source),
+        None => println!("voidpkgs: unset (needed for `vx src ...`)"),
     }
 
-    match managed::load_managed() {
-        Ok(list) => {
+    println!(
+        "src repo: {} (use_nonfree={})",
+        s.local_repo.display(),
+        s.use_nonfree
+    );
+
+    println!("privilege: {} ({})", s.privilege, s.privilege_source);
+    println!("root: {}", s.root.display());
+
+    match &s.managed_error {
+        None => {
+            let list = &s.managed;
             println!("managed: {} package(s)", list.len());
             if !list.is_empty() {
                 let show = 10usize;
@@ -55,14 +193,37 @@ pub fn run_status(_log: &crate::log::Log, cli: &Cli, cfg: Option<&Config>) -> Ex
                 }
             }
         }
-        Err(e) => {
+        Some(e) => {
             println!("managed: unavailable ({e})");
         }
     }
 
-    println!("flags: quiet={} verbose={}", cli.quiet, cli.verbose);
+    for c in &s.upstream_releases {
+        println!(
+            "upstream release: {} {} (checked {})",
+            c.pkg,
+            c.versions.join(" "),
+            xbps::format_age(c.checked)
+        );
+    }
 
-    ExitCode::SUCCESS
+    match (&s.packages, &s.packages_error) {
+        (Some(p), _) => {
+            println!(
+                "packages: {} installed, {} manual ({} leaves), {} orphans, {} held",
+                p.installed, p.manual, p.leaves, p.orphans, p.held
+            );
+            println!("package cache: {}", xbps::format_size(p.cache_bytes));
+            match p.last_update {
+                Some(t) => println!("last update: {}", xbps::format_age(t)),
+                None => println!("last update: none journaled"),
+            }
+        }
+        (None, Some(e)) => println!("packages: unavailable ({e})"),
+        (None, None) => {}
+    }
+
+    println!("flags: quiet={} verbose={}", s.quiet, s.verbose);
 }
 
 fn resolve_voidpkgs_for_status(cli: &Cli, cfg: Option<&Config>) -> (Option<PathBuf>, &'static str) {
@@ -72,17 +233,15 @@ fn resolve_voidpkgs_for_status(cli: &Cli, cfg: Option<&Config>) -> (Option<PathB
         }
     }
 
-    if let Ok(v) = env::var("VX_VOIDPKGS") {
-        let p = PathBuf::from(v);
-        if !p.as_os_str().is_empty() {
-            return (Some(p), "env");
-        }
-    }
-
     if let Some(c) = cfg {
         if let Some(p) = &c.void_packages_path {
             if !p.as_os_str().is_empty() {
-                return (Some(p.clone()), "config");
+                let from = match c.origins.get("void_packages.path") {
+                    Some(Origin::Env) => "env",
+                    Some(Origin::Project) => "project",
+                    _ => "config",
+                };
+                return (Some(p.clone()), from);
             }
         }
     }
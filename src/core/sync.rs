@@ -0,0 +1,71 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    config::Config,
+    core::{
+        source::{
+            git,
+            resolve::{resolve_voidpkgs, voidpkgs_path},
+        },
+        xbps,
+    },
+    log::Log,
+};
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+/// `vx sync` — refresh repodata and the void-packages upstream refs, and
+/// reset their TTL stamps so the next interactive command skips both.
+pub fn run_sync(
+    log: &Log,
+    voidpkgs_override: Option<PathBuf>,
+    cfg: Option<&Config>,
+    repos: &[String],
+) -> ExitCode {
+    let mut failed = false;
+
+    // Cron has no terminal to type a password into.
+    if io::stdin().is_terminal() {
+        match xbps::sync_repodata(log, repos) {
+            Ok(()) => log.info("repodata: synced"),
+            Err(e) => {
+                log.error(e);
+                failed = true;
+            }
+        }
+    } else if xbps::sync_repodata_noninteractive(repos) {
+        log.info("repodata: synced");
+    } else {
+        log.error(
+            "xbps-install -S failed or needs a password.\n\
+             hint: run vx sync as root, or allow `xbps-install -S` without a password.",
+        );
+        failed = true;
+    }
+
+    if voidpkgs_path(voidpkgs_override.clone(), cfg).is_none() {
+        log.info("void-packages: not configured, skipped");
+    } else {
+        let fetched = resolve_voidpkgs(voidpkgs_override, cfg).and_then(|r| {
+            git::fetch_voidpkgs(log, &r.voidpkgs)?;
+            Ok(r.voidpkgs)
+        });
+        match fetched {
+            Ok(p) => log.info(format!("void-packages: fetched upstream ({})", p.display())),
+            Err(e) => {
+                log.error(format!("void-packages: {e}"));
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,157 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use std::{
+    cmp::Ordering,
+    process::{Command, Stdio},
+};
+
+/// Component values for the modifiers xbps' dewey rules understand (lib/dewey.c).
+const ALPHA: i64 = -3;
+const BETA: i64 = -2;
+const RC: i64 = -1;
+const DOT: i64 = 0;
+
+const MODIFIERS: &[(&str, i64)] = &[
+    ("alpha", ALPHA),
+    ("beta", BETA),
+    ("pre", RC),
+    ("rc", RC),
+    ("pl", DOT),
+    (".", DOT),
+];
+
+/// A version split into dewey components plus its `_N` revision.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Dewey {
+    parts: Vec<i64>,
+    revision: i64,
+}
+
+fn digits(s: &[u8]) -> (i64, usize) {
+    let len = s.iter().take_while(|b| b.is_ascii_digit()).count();
+    let n = s[..len]
+        .iter()
+        .fold(0i64, |n, b| n.saturating_mul(10).saturating_add(i64::from(b - b'0')));
+    (n, len)
+}
+
+fn parse(version: &str) -> Dewey {
+    let s = version.as_bytes();
+    let mut d = Dewey::default();
+    let mut i = 0;
+
+    'outer: while i < s.len() {
+        let rest = &s[i..];
+
+        if rest[0].is_ascii_digit() {
+            let (n, len) = digits(rest);
+            d.parts.push(n);
+            i += len;
+            continue;
+        }
+
+        for (m, v) in MODIFIERS {
+            if rest.len() >= m.len() && rest[..m.len()].eq_ignore_ascii_case(m.as_bytes()) {
+                d.parts.push(*v);
+                i += m.len();
+                continue 'outer;
+            }
+        }
+
+        if rest[0] == b'_' {
+            let (n, len) = digits(&rest[1..]);
+            d.revision = n;
+            i += 1 + len;
+            continue;
+        }
+
+        // A lone letter counts as ".N" where a=1, b=2, ... (1.0a < 1.0b).
+        if rest[0].is_ascii_alphabetic() {
+            d.parts.push(DOT);
+            d.parts.push(i64::from(rest[0].to_ascii_lowercase() - b'a') + 1);
+        }
+        i += 1;
+    }
+    d
+}
+
+/// Compare two versions (`1.2.3_1`, `2.0rc1_2`), as `xbps_cmp_ver` does.
+pub fn cmp_version(a: &str, b: &str) -> Ordering {
+    let (a, b) = (parse(a), parse(b));
+    let n = a.parts.len().max(b.parts.len());
+    (0..n)
+        .map(|i| {
+            let x = a.parts.get(i).copied().unwrap_or(0);
+            let y = b.parts.get(i).copied().unwrap_or(0);
+            x.cmp(&y)
+        })
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.revision.cmp(&b.revision))
+}
+
+/// Version part of a pkgver (`foo-bar-1.0_1` -> `1.0_1`); the whole string
+/// when it carries no name.
+pub fn pkgver_version(pkgver: &str) -> &str {
+    match pkgver.rsplit_once('-') {
+        Some((_, v)) if v.starts_with(|c: char| c.is_ascii_digit()) => v,
+        _ => pkgver,
+    }
+}
+
+/// Compare the versions of two pkgvers of the same package.
+pub fn cmp_pkgver(a: &str, b: &str) -> Ordering {
+    cmp_version(pkgver_version(a), pkgver_version(b))
+}
+
+/// Compare two pkgvers with `xbps-uhelper cmpver`, which exits 0 when
+/// they're equal, 1 when `a` is newer and 255 when `b` is; `cmp_pkgver`
+/// when xbps-uhelper can't be run.
+pub fn cmpver(a: &str, b: &str) -> Ordering {
+    let status = Command::new("xbps-uhelper")
+        .args(["cmpver", a, b])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status.ok().and_then(|s| s.code()) {
+        Some(0) => Ordering::Equal,
+        Some(1) => Ordering::Greater,
+        Some(255) => Ordering::Less,
+        _ => cmp_pkgver(a, b),
+    }
+}
+
+/// Plan action for moving from `installed` to `candidate`:
+/// install | update | reinstall | downgrade.
+pub fn plan_action(installed: Option<&str>, candidate: &str) -> &'static str {
+    match installed.map(|i| cmp_pkgver(candidate, i)) {
+        None => "install",
+        Some(Ordering::Greater) => "update",
+        Some(Ordering::Equal) => "reinstall",
+        Some(Ordering::Less) => "downgrade",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cmp_pkgver, cmp_version, plan_action};
+    use std::cmp::Ordering::{Equal, Greater, Less};
+
+    #[test]
+    fn dewey_ordering() {
+        assert_eq!(cmp_version("1.10_1", "1.9_1"), Greater);
+        assert_eq!(cmp_version("1.0_2", "1.0_10"), Less);
+        assert_eq!(cmp_version("1.0", "1.0.0"), Equal);
+        assert_eq!(cmp_version("2.0rc1_1", "2.0_1"), Less);
+        assert_eq!(cmp_version("2.0alpha_1", "2.0beta_1"), Less);
+        assert_eq!(cmp_version("2.0beta2_1", "2.0pre1_1"), Less);
+        assert_eq!(cmp_version("1.0pl1_1", "1.0_1"), Greater);
+        assert_eq!(cmp_version("1.0a_1", "1.0b_1"), Less);
+        assert_eq!(cmp_version("1.0a_1", "1.0_1"), Greater);
+        assert_eq!(cmp_pkgver("font-misc-1.0.4_1", "font-misc-1.0.10_1"), Less);
+        assert_eq!(plan_action(Some("foo-2.1_1"), "foo-2.0_3"), "downgrade");
+        assert_eq!(plan_action(Some("foo-2.0_1"), "foo-2.0_1"), "reinstall");
+        assert_eq!(plan_action(None, "foo-2.0_1"), "install");
+    }
+}
@@ -0,0 +1,183 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{log::Log, tempdir::TempDir};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::SystemTime,
+};
+
+use super::parse::pkgname_from_pkgver;
+
+/// A binary package file sitting in the xbps cache.
+#[derive(Debug, Clone)]
+pub struct CachedPkg {
+    pub pkgver: String,
+    pub arch: String,
+    pub path: PathBuf,
+    pub mtime: SystemTime,
+}
+
+/// Split `foo-1.0_1.x86_64.xbps` into (`foo-1.0_1`, `x86_64`).
+pub fn split_xbps_filename(file: &str) -> Option<(String, String)> {
+    let stem = file.strip_suffix(".xbps")?;
+    let (pkgver, arch) = stem.rsplit_once('.')?;
+    pkgname_from_pkgver(pkgver)?;
+    Some((pkgver.to_string(), arch.to_string()))
+}
+
+/// All cached `.xbps` files for `pkg`, newest first.
+pub fn cached_versions(dir: &Path, pkg: &str) -> Vec<CachedPkg> {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut out = Vec::new();
+    for entry in rd.flatten() {
+        let file = entry.file_name().to_string_lossy().to_string();
+        let Some((pkgver, arch)) = split_xbps_filename(&file) else {
+            continue;
+        };
+        if pkgname_from_pkgver(&pkgver).as_deref() != Some(pkg) {
+            continue;
+        }
+        let mtime = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        out.push(CachedPkg {
+            pkgver,
+            arch,
+            path: entry.path(),
+            mtime,
+        });
+    }
+
+    out.sort_by(|a, b| b.mtime.cmp(&a.mtime).then_with(|| b.pkgver.cmp(&a.pkgver)));
+    out
+}
+
+/// Default xbps package cache.
+pub const DEFAULT_CACHEDIR: &str = "/var/cache/xbps";
+
+/// `over`, else the default cache of the target root (`--root /mnt` ->
+/// `/mnt/var/cache/xbps`).
+pub fn cachedir(over: Option<&Path>) -> PathBuf {
+    match over {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => crate::rootdir::path(DEFAULT_CACHEDIR),
+    }
+}
+
+/// Total size of regular files under `dir` (recursive, symlinks not followed).
+/// Unreadable entries are skipped; a missing dir is 0.
+pub fn dir_size(dir: &Path) -> u64 {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    let mut total = 0u64;
+    for entry in rd.flatten() {
+        let Ok(meta) = entry.path().symlink_metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            total += dir_size(&entry.path());
+        } else if meta.is_file() {
+            total += meta.len();
+        }
+    }
+    total
+}
+
+/// Symlink cached package files into a fresh private temp dir and index them
+/// (`xbps-rindex -a`) so they can be installed with `xbps-install -R <dir>`.
+/// The directory goes away when the returned guard is dropped.
+pub fn temp_repo(log: &Log, pkgfiles: &[PathBuf]) -> Result<TempDir, String> {
+    let dir = TempDir::new("vx-repo")?;
+
+    let mut links = Vec::with_capacity(pkgfiles.len());
+    for f in pkgfiles {
+        let name = f
+            .file_name()
+            .ok_or_else(|| format!("invalid package path: {}", f.display()))?;
+        let link = dir.join(name);
+        std::os::unix::fs::symlink(f, &link)
+            .map_err(|e| format!("failed to link {}: {e}", link.display()))?;
+        links.push(link);
+    }
+
+    if log.verbose && !log.quiet {
+        log.exec(format!("(cd {}) && xbps-rindex -a *.xbps", dir.path().display()));
+    }
+
+    let status = Command::new("xbps-rindex")
+        .arg("-a")
+        .args(&links)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|e| format!("failed to run xbps-rindex: {e}"))?;
+
+    if !status.success() {
+        return Err(format!("xbps-rindex -a failed in {}", dir.path().display()));
+    }
+
+    Ok(dir)
+}
+
+/// Whether `file` may be missing from its directory's index: there is no
+/// repodata yet, or the file was written after the newest one.
+fn needs_index(dir: &Path, file: &Path) -> bool {
+    let mtime = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    let indexed = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().ends_with("-repodata"))
+        .filter_map(|e| mtime(&e.path()))
+        .max();
+    match (indexed, mtime(file)) {
+        (Some(indexed), Some(built)) => built > indexed,
+        _ => true,
+    }
+}
+
+/// `vx add ./foo-1.0_1.x86_64.xbps`: make the file's directory usable as a `-R`
+/// repository (indexing it when it is newer than the repodata) and return (dir, pkgver).
+pub fn local_pkgfile(log: &Log, file: &Path) -> Result<(PathBuf, String), String> {
+    let name = file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("invalid package path: {}", file.display()))?;
+    let (pkgver, _arch) = split_xbps_filename(name)
+        .ok_or_else(|| format!("not an xbps package file name: {name}"))?;
+
+    let file = fs::canonicalize(file).map_err(|e| format!("{}: {e}", file.display()))?;
+    let dir = file
+        .parent()
+        .ok_or_else(|| format!("invalid package path: {}", file.display()))?
+        .to_path_buf();
+
+    if needs_index(&dir, &file) {
+        if log.verbose && !log.quiet {
+            log.exec(format!("xbps-rindex -a {}", file.display()));
+        }
+        let status = Command::new("xbps-rindex")
+            .arg("-a")
+            .arg(&file)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .status()
+            .map_err(|e| format!("failed to run xbps-rindex: {e}"))?;
+        if !status.success() {
+            return Err(format!("xbps-rindex -a failed in {}", dir.display()));
+        }
+    }
+
+    Ok((dir, pkgver))
+}
@@ -0,0 +1,307 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{log::Log, privilege, tempdir::TempDir};
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+};
+
+use super::install::run;
+
+pub const DEFAULT_ETC: &str = "/etc";
+
+/// A pending config update left by xbps: `foo.conf.new-1.2_1` next to `foo.conf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfUpdate {
+    pub current: PathBuf,
+    pub new: PathBuf,
+    pub version: String,
+}
+
+/// `/etc/foo.conf.new-1.2_1` -> (`/etc/foo.conf`, `1.2_1`)
+pub fn split_new_file(path: &Path) -> Option<(PathBuf, String)> {
+    let name = path.file_name()?.to_str()?;
+    let idx = name.rfind(".new-")?;
+    let (base, ver) = (&name[..idx], &name[idx + ".new-".len()..]);
+    if base.is_empty() || ver.is_empty() {
+        return None;
+    }
+    Some((path.with_file_name(base), ver.to_string()))
+}
+
+pub fn find_updates(root: &Path) -> Vec<ConfUpdate> {
+    let mut out = Vec::new();
+    walk(root, &mut out);
+    out.sort_by(|a, b| a.new.cmp(&b.new));
+    out
+}
+
+fn walk(dir: &Path, out: &mut Vec<ConfUpdate>) {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return;
+    };
+    for ent in rd.flatten() {
+        let path = ent.path();
+        let Ok(ft) = ent.file_type() else {
+            continue;
+        };
+        if ft.is_dir() {
+            walk(&path, out);
+        } else if ft.is_file()
+            && let Some((current, version)) = split_new_file(&path)
+        {
+            out.push(ConfUpdate {
+                current,
+                new: path,
+                version,
+            });
+        }
+    }
+}
+
+/// `diff -u current new`; empty when identical or on failure.
+fn unified_diff(u: &ConfUpdate) -> String {
+    let out = Command::new("diff")
+        .arg("-u")
+        .arg(&u.current)
+        .arg(&u.new)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    match out {
+        Ok(o) => String::from_utf8_lossy(&o.stdout).into_owned(),
+        Err(_) => String::new(),
+    }
+}
+
+fn print_diff(diff: &str) {
+    let color = io::stdout().is_terminal();
+    for line in diff.lines() {
+        if !color {
+            println!("{line}");
+            continue;
+        }
+        let code = if line.starts_with("+++") || line.starts_with("---") {
+            "1"
+        } else if line.starts_with('+') {
+            "32"
+        } else if line.starts_with('-') {
+            "31"
+        } else if line.starts_with("@@") {
+            "36"
+        } else {
+            ""
+        };
+        if code.is_empty() {
+            println!("{line}");
+        } else {
+            println!("\x1b[{code}m{line}\x1b[0m");
+        }
+    }
+}
+
+/// Current file with the new version's changes inlined as conflict markers.
+fn merge_text(u: &ConfUpdate) -> Result<String, String> {
+    let out = Command::new("diff")
+        .arg("--changed-group-format=<<<<<<< current\n%<=======\n%>>>>>>>> new\n")
+        .arg("--new-group-format=<<<<<<< current\n=======\n%>>>>>>>> new\n")
+        .arg("--old-group-format=<<<<<<< current\n%<=======\n>>>>>>> new\n")
+        .arg("--unchanged-group-format=%=")
+        .arg(&u.current)
+        .arg(&u.new)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("failed to run diff: {e}"))?;
+
+    // diff exits 1 when files differ; only 2 is trouble.
+    if out.status.code() == Some(2) {
+        let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        return Err(format!("diff failed: {err}"));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+fn prompt(msg: &str) -> String {
+    print!("{msg} ");
+    io::stdout().flush().ok();
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).ok();
+    line.trim().to_lowercase()
+}
+
+enum Action {
+    Keep,
+    Replace,
+    Merge,
+    Skip,
+    Quit,
+}
+
+fn ask_action() -> Action {
+    loop {
+        match prompt("[k]eep current, [r]eplace with new, [m]erge in $EDITOR, [s]kip, [q]uit?")
+            .as_str()
+        {
+            "k" | "keep" => return Action::Keep,
+            "r" | "replace" => return Action::Replace,
+            "m" | "merge" => return Action::Merge,
+            "s" | "skip" | "" => return Action::Skip,
+            "q" | "quit" => return Action::Quit,
+            _ => continue,
+        }
+    }
+}
+
+fn keep(log: &Log, u: &ConfUpdate) -> ExitCode {
+    let mut cmd = privilege::command("rm");
+    cmd.arg("-f").arg(&u.new);
+    run(log, cmd, &privilege::label(&format!("rm -f {}", u.new.display())))
+}
+
+fn replace(log: &Log, u: &ConfUpdate) -> ExitCode {
+    let mut cmd = privilege::command("mv");
+    cmd.arg("-f").arg(&u.new).arg(&u.current);
+    run(
+        log,
+        cmd,
+        &privilege::label(&format!("mv -f {} {}", u.new.display(), u.current.display())),
+    )
+}
+
+fn merge(log: &Log, u: &ConfUpdate) -> ExitCode {
+    let text = match merge_text(u) {
+        Ok(t) => t,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let name = u
+        .current
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "conf".into());
+    let dir = match TempDir::new("vx-merge") {
+        Ok(d) => d,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    let tmp = dir.join(name);
+    if let Err(e) = fs::write(&tmp, text) {
+        log.error(format!("failed to write {}: {e}", tmp.display()));
+        return ExitCode::from(1);
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(format!("{editor} \"$1\"")).arg("sh").arg(&tmp);
+    let c = run(log, cmd, &format!("{editor} {}", tmp.display()));
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+
+    let merged = fs::read_to_string(&tmp).unwrap_or_default();
+    if merged.lines().any(|l| l.starts_with("<<<<<<< ") || l == "=======") {
+        log.warn(format!(
+            "the merge still has conflict markers; left {} untouched",
+            u.current.display()
+        ));
+        return ExitCode::from(1);
+    }
+
+    if !crate::core::source::confirm_once(&format!("Install merged {}?", u.current.display())) {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    // cp onto the existing file keeps its owner and mode.
+    let mut cmd = privilege::command("cp");
+    cmd.arg(&tmp).arg(&u.current);
+    let c = run(
+        log,
+        cmd,
+        &privilege::label(&format!("cp {} {}", tmp.display(), u.current.display())),
+    );
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+    keep(log, u)
+}
+
+/// `vx confdiff [--list]`
+pub fn confdiff(log: &Log, root: &Path, list_only: bool) -> ExitCode {
+    let updates = find_updates(root);
+    if updates.is_empty() {
+        log.info(format!("vx: no pending .new-* config files under {}.", root.display()));
+        return ExitCode::SUCCESS;
+    }
+
+    if list_only {
+        println!("pending config updates ({}):", updates.len());
+        for u in &updates {
+            println!("  {}  ({})", u.current.display(), u.version);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    for (i, u) in updates.iter().enumerate() {
+        println!(
+            "[{}/{}] {} ({})",
+            i + 1,
+            updates.len(),
+            u.current.display(),
+            u.version
+        );
+
+        if !u.current.exists() {
+            println!("  current file is missing; the new file can simply be moved into place.");
+        } else {
+            let diff = unified_diff(u);
+            if diff.is_empty() {
+                println!("  identical to current file.");
+            } else {
+                print_diff(&diff);
+            }
+        }
+
+        let c = match ask_action() {
+            Action::Keep => keep(log, u),
+            Action::Replace => replace(log, u),
+            Action::Merge => merge(log, u),
+            Action::Skip => ExitCode::SUCCESS,
+            Action::Quit => return ExitCode::SUCCESS,
+        };
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_new_file;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn splits_new_suffix() {
+        assert_eq!(
+            split_new_file(Path::new("/etc/ssh/sshd_config.new-9.8p1_1")),
+            Some((PathBuf::from("/etc/ssh/sshd_config"), "9.8p1_1".to_string()))
+        );
+        assert_eq!(split_new_file(Path::new("/etc/fstab")), None);
+        assert_eq!(split_new_file(Path::new("/etc/.new-1.0_1")), None);
+    }
+}
@@ -0,0 +1,149 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{config::Config, core::source::confirm_once, log::Log, privilege};
+use std::{
+    io::{self, IsTerminal, Write},
+    process::ExitCode,
+};
+
+use super::cachedir::{self, CachedPkg};
+use super::install::run;
+use super::journal::with_journal;
+use super::query;
+
+/// `vx downgrade <pkg> [version]`
+///
+/// Picks an older `.xbps` from the package cache, indexes it into a throwaway
+/// repository and installs exactly that pkgver with `xbps-install -f`.
+pub fn downgrade(
+    log: &Log,
+    _cfg: Option<&Config>,
+    pkg: &str,
+    version: Option<&str>,
+    yes: bool,
+) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx downgrade <pkg> [version]");
+        return ExitCode::from(2);
+    }
+
+    let installed = match query::installed_pkgver(pkg) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let cache = cachedir::cachedir(None);
+    let candidates: Vec<CachedPkg> = cachedir::cached_versions(&cache, pkg)
+        .into_iter()
+        .filter(|c| Some(&c.pkgver) != installed.as_ref())
+        .collect();
+
+    if candidates.is_empty() {
+        log.error(format!(
+            "no other cached versions of {pkg} in {}",
+            cache.display()
+        ));
+        return ExitCode::from(1);
+    }
+
+    let chosen = match version.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) => match match_version(&candidates, pkg, v) {
+            Some(c) => c,
+            None => {
+                log.error(format!("version {v} of {pkg} is not in the cache"));
+                print_candidates(&candidates);
+                return ExitCode::from(1);
+            }
+        },
+        None => {
+            if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+                log.error(format!("specify a version: vx downgrade {pkg} <version>"));
+                print_candidates(&candidates);
+                return ExitCode::from(2);
+            }
+            println!(
+                "installed: {}",
+                installed.as_deref().unwrap_or("(not installed)")
+            );
+            print_candidates(&candidates);
+            match pick_index(candidates.len()) {
+                Some(i) => &candidates[i],
+                None => {
+                    log.info("aborted.");
+                    return ExitCode::SUCCESS;
+                }
+            }
+        }
+    };
+
+    if !log.quiet {
+        println!(
+            "downgrade plan: {}  {} → {}",
+            pkg,
+            installed.as_deref().unwrap_or("(not installed)"),
+            chosen.pkgver
+        );
+    }
+
+    if !yes && !confirm_once("Proceed?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    let repo = match cachedir::temp_repo(log, std::slice::from_ref(&chosen.path)) {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let mut cmd = privilege::command("xbps-install");
+    cmd.arg("-R").arg(repo.path());
+    cmd.args(["-f", "-y"]);
+    cmd.arg(&chosen.pkgver);
+
+    let label = privilege::label(&format!(
+        "xbps-install -R {} -f -y {}",
+        repo.path().display(),
+        chosen.pkgver
+    ));
+    with_journal(log, &format!("vx downgrade {}", chosen.pkgver), || run(log, cmd, &label))
+}
+
+/// Accept a full pkgver (`foo-1.0_1`), a version+revision (`1.0_1`) or a bare version (`1.0`).
+fn match_version<'a>(cands: &'a [CachedPkg], pkg: &str, v: &str) -> Option<&'a CachedPkg> {
+    let full = if v.starts_with(&format!("{pkg}-")) {
+        v.to_string()
+    } else {
+        format!("{pkg}-{v}")
+    };
+    cands
+        .iter()
+        .find(|c| c.pkgver == full)
+        .or_else(|| {
+            let prefix = format!("{full}_");
+            cands.iter().find(|c| c.pkgver.starts_with(&prefix))
+        })
+}
+
+fn print_candidates(cands: &[CachedPkg]) {
+    println!("cached versions:");
+    for (i, c) in cands.iter().enumerate() {
+        println!("  {:>2}) {} ({})", i + 1, c.pkgver, c.arch);
+    }
+}
+
+fn pick_index(n: usize) -> Option<usize> {
+    print!("Select version [1-{n}] (empty to abort): ");
+    let _ = io::stdout().flush();
+    let mut s = String::new();
+    io::stdin().read_line(&mut s).ok()?;
+    let i: usize = s.trim().parse().ok()?;
+    if (1..=n).contains(&i) { Some(i - 1) } else { None }
+}
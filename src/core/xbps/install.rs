@@ -3,43 +3,208 @@
 
 use crate::{
     config::Config,
-    core::xbps::{AddOptions, RmOptions},
+    core::{
+        source::confirm_once,
+        xbps::{AddOptions, RmOptions, UpOptions},
+    },
     log::Log,
-    managed,
+    managed, privilege,
 };
 use std::{
     ffi::OsString,
     collections::BTreeSet,
     io::{self, IsTerminal, Write},
+    path::Path,
     process::{Command, ExitCode, Stdio},
 };
 
+use super::journal::with_journal;
+use super::{cachedir, parse, query, repository_args, virtualpkg};
+
 pub fn add(log: &Log, _cfg: Option<&Config>, opts: AddOptions, pkgs: &[String]) -> ExitCode {
     if pkgs.is_empty() {
         log.error("usage: vx add <pkgs...>");
         return ExitCode::from(2);
     }
 
-    let mut cmd = Command::new("sudo");
-    cmd.arg("xbps-install");
-    cmd.args(xbps_install_args(&opts, pkgs));
+    let mut opts = opts;
+    let pkgs = match resolve_local_files(log, &mut opts, pkgs) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(2);
+        }
+    };
+    let pkgs = match virtualpkg::resolve_add_targets(log, &pkgs, &opts.repositories, opts.yes) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(2);
+        }
+    };
+    if pkgs.is_empty() {
+        log.info("nothing to do.");
+        return ExitCode::SUCCESS;
+    }
+    let pkgs = pkgs.as_slice();
+
+    // Reinstall/download/update requests go straight to xbps.
+    let (install, update) = if opts.force > 0 || opts.download_only || opts.update {
+        (pkgs.to_vec(), Vec::new())
+    } else {
+        match split_installed(log, pkgs) {
+            Ok(v) => v,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        }
+    };
+
+    if install.is_empty() && update.is_empty() {
+        log.info("nothing to do.");
+        return ExitCode::SUCCESS;
+    }
+
+    let requested: Vec<String> = install.iter().chain(&update).cloned().collect();
+    crate::core::repo::keys::warn_unsigned_sources(log, &requested, &opts.repositories);
+
+    with_journal(log, &format!("vx add {}", pkgs.join(" ")), || {
+        if !install.is_empty() {
+            let mut cmd = privilege::command("xbps-install");
+            cmd.args(xbps_install_args(&opts, &install));
+            let c = run(log, cmd, &privilege::label("xbps-install ..."));
+            if c != ExitCode::SUCCESS {
+                return c;
+            }
+        }
+
+        if !update.is_empty() {
+            let mut opts = opts.clone();
+            opts.update = true;
+            let mut cmd = privilege::command("xbps-install");
+            cmd.args(xbps_install_args(&opts, &update));
+            return run(log, cmd, &privilege::label("xbps-install -u ..."));
+        }
+
+        ExitCode::SUCCESS
+    })
+}
+
+/// Replace `.xbps` file arguments with their pkgver and add each file's
+/// directory as a `-R` repository.
+fn resolve_local_files(
+    log: &Log,
+    opts: &mut AddOptions,
+    pkgs: &[String],
+) -> Result<Vec<String>, String> {
+    let mut out = Vec::with_capacity(pkgs.len());
+    for arg in pkgs {
+        let path = Path::new(arg);
+        if !arg.ends_with(".xbps") || !path.is_file() {
+            out.push(arg.clone());
+            continue;
+        }
+
+        let (dir, pkgver) = cachedir::local_pkgfile(log, path)?;
+        let repo = dir.to_string_lossy().into_owned();
+        if !opts.repositories.contains(&repo) {
+            opts.repositories.push(repo);
+        }
+        out.push(pkgver);
+    }
+    Ok(out)
+}
+
+/// Split `vx add` args into (to install, installed but failing their constraint).
+/// Installed packages that already satisfy the request are skipped with a warning.
+fn split_installed(log: &Log, pkgs: &[String]) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut install = Vec::new();
+    let mut update = Vec::new();
+
+    for arg in pkgs {
+        let spec = parse::parse_pkg_spec(arg);
+        let Some(pkgver) = query::installed_pkgver(&spec.name)? else {
+            install.push(spec.pattern());
+            continue;
+        };
+
+        match &spec.constraint {
+            None => log.warn(format!("package '{}' already installed.", spec.name)),
+            Some(c) => {
+                if query::pkgver_matches(&pkgver, &spec.pattern())? {
+                    log.warn(format!("{pkgver} already installed (satisfies {c})."));
+                } else {
+                    log.info(format!("{pkgver} does not satisfy {c}; updating."));
+                    update.push(spec.pattern());
+                }
+            }
+        }
+    }
 
-    run(log, cmd, "sudo xbps-install ...")
+    Ok((install, update))
 }
 
-pub fn rm(log: &Log, _cfg: Option<&Config>, opts: RmOptions, pkgs: &[String]) -> ExitCode {
+pub fn rm(log: &Log, cfg: Option<&Config>, opts: RmOptions, pkgs: &[String]) -> ExitCode {
     if pkgs.is_empty() && !opts.orphans {
         log.error("usage: vx rm <pkgs...> [--orphans]");
         return ExitCode::from(2);
     }
 
+    if !opts.force_protected {
+        let hits = protected_matches(pkgs, &protected_packages(cfg));
+        if !hits.is_empty() {
+            log.error(format!(
+                "refusing to remove protected package(s): {}\n\
+                 hint: pass --force-protected if you really mean it.",
+                hits.join(" ")
+            ));
+            return ExitCode::from(2);
+        }
+    }
+
+    with_journal(log, &format!("vx rm {}", pkgs.join(" ")), || {
+        rm_inner(log, &opts, pkgs)
+    })
+}
+
+fn rm_inner(log: &Log, opts: &RmOptions, pkgs: &[String]) -> ExitCode {
     // 1) Remove requested packages (if any)
     if !pkgs.is_empty() {
-        let mut cmd = Command::new("sudo");
-        cmd.arg("xbps-remove");
-        cmd.args(xbps_remove_args(&opts, pkgs));
+        warn_revdeps(log, opts, pkgs);
+
+        // Show everything that goes (dependencies included) and ask once,
+        // then let xbps-remove run without its own prompt.
+        let mut opts = opts.clone();
+        if !opts.dry_run {
+            match removal_plan(&opts, pkgs) {
+                Ok(plan) if !plan.is_empty() => {
+                    if !log.quiet {
+                        println!("removal plan ({}):", plan.len());
+                        for p in &plan {
+                            println!("  {p}");
+                        }
+                    }
+                    if !opts.yes && !confirm_once("Proceed?") {
+                        log.info("aborted.");
+                        return ExitCode::SUCCESS;
+                    }
+                    opts.yes = true;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    if log.verbose && !log.quiet {
+                        log.exec(format!("removal plan unavailable: {e}"));
+                    }
+                }
+            }
+        }
+        let opts = &opts;
+
+        let mut cmd = privilege::command("xbps-remove");
+        cmd.args(xbps_remove_args(opts, pkgs));
 
-        let code = run(log, cmd, "sudo xbps-remove ...");
+        let code = run(log, cmd, &privilege::label("xbps-remove ..."));
         if code != ExitCode::SUCCESS {
             return code;
         }
@@ -49,28 +214,166 @@ pub fn rm(log: &Log, _cfg: Option<&Config>, opts: RmOptions, pkgs: &[String]) ->
 
     // 2) Optional orphan cleanup pass
     if opts.orphans {
-        let mut cmd = Command::new("sudo");
-        cmd.arg("xbps-remove");
-        cmd.args(xbps_remove_orphan_args(&opts));
+        let mut cmd = privilege::command("xbps-remove");
+        cmd.args(xbps_remove_orphan_args(opts));
+
+        return run(log, cmd, &privilege::label("xbps-remove -o"));
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// `vx orphans` — show what `xbps-remove -o` would remove, then remove it.
+pub fn orphans(log: &Log, _cfg: Option<&Config>, dry_run: bool, yes: bool) -> ExitCode {
+    let orphans = match query::orphan_pkgvers() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if orphans.is_empty() {
+        log.info("vx: no orphaned packages.");
+        return ExitCode::SUCCESS;
+    }
+
+    if !log.quiet {
+        println!("orphan removal plan ({}):", orphans.len());
+        for p in &orphans {
+            println!("  {p}");
+        }
+    }
+
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+
+    if !yes && !confirm_once("Proceed?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut cmd = privilege::command("xbps-remove");
+    cmd.args(["-y", "-o"]);
 
-        return run(log, cmd, "sudo xbps-remove -o");
+    with_journal(log, "vx orphans", || run(log, cmd, &privilege::label("xbps-remove -y -o")))
+}
+
+/// `vx clean [--all]` — purge obsolete cached packages (xbps-remove -O),
+/// optionally removing orphans too, and report reclaimed space.
+pub fn clean(log: &Log, _cfg: Option<&Config>, all: bool, dry_run: bool, yes: bool) -> ExitCode {
+    let cache = cachedir::cachedir(None);
+    let cache_before = cachedir::dir_size(&cache);
+
+    let orphans = if all {
+        match query::orphan_pkgvers() {
+            Ok(v) => v,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    let orphan_bytes: u64 = orphans
+        .iter()
+        .filter_map(|pv| {
+            query::installed_property(pv, "installed_size")
+                .ok()
+                .flatten()
+                .and_then(|s| parse::parse_human_size(&s))
+        })
+        .sum();
+
+    if !log.quiet {
+        println!("clean plan:");
+        println!(
+            "  cache: remove obsolete packages from {} (currently {})",
+            cache.display(),
+            parse::format_size(cache_before)
+        );
+        if all {
+            if orphans.is_empty() {
+                println!("  orphans: none");
+            } else {
+                println!(
+                    "  orphans ({}, {}):",
+                    orphans.len(),
+                    parse::format_size(orphan_bytes)
+                );
+                for p in &orphans {
+                    println!("    {p}");
+                }
+            }
+        }
+    }
+
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+
+    if !yes && !confirm_once("Proceed?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut cmd = privilege::command("xbps-remove");
+    cmd.args(["-y", "-O"]);
+    let c = run(log, cmd, &privilege::label("xbps-remove -y -O"));
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+
+    if !orphans.is_empty() {
+        let mut cmd = privilege::command("xbps-remove");
+        cmd.args(["-y", "-o"]);
+        let c = with_journal(log, "vx clean --all", || {
+            run(log, cmd, &privilege::label("xbps-remove -y -o"))
+        });
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+    }
+
+    let cache_freed = cache_before.saturating_sub(cachedir::dir_size(&cache));
+    if all {
+        log.info(format!(
+            "vx: reclaimed {} (cache {}, orphans {})",
+            parse::format_size(cache_freed + orphan_bytes),
+            parse::format_size(cache_freed),
+            parse::format_size(orphan_bytes)
+        ));
+    } else {
+        log.info(format!("vx: reclaimed {}", parse::format_size(cache_freed)));
     }
 
     ExitCode::SUCCESS
 }
 
-pub fn up_with_yes(log: &Log, _cfg: Option<&Config>, yes: bool) -> ExitCode {
-    let mut cmd = Command::new("sudo");
-    cmd.arg("xbps-install");
-    if yes {
+pub fn up(log: &Log, _cfg: Option<&Config>, opts: &UpOptions) -> ExitCode {
+    let mut cmd = privilege::command("xbps-install");
+    cmd.args(repository_args(&opts.repositories));
+    if opts.yes {
         cmd.arg("-y");
     }
     cmd.arg("-u");
+    cmd.args(&opts.pkgs);
 
-    run(log, cmd, "sudo xbps-install -u")
+    // Download-only leaves the installed set alone; nothing to journal.
+    if opts.download_only {
+        cmd.arg("-D");
+        return run(log, cmd, &privilege::label("xbps-install -u -D"));
+    }
+
+    let what = format!("vx up {}", opts.pkgs.join(" "));
+    with_journal(log, what.trim_end(), || run(log, cmd, &privilege::label("xbps-install -u")))
 }
 
-fn run(log: &Log, mut cmd: Command, label: &str) -> ExitCode {
+/// Run `cmd` on the terminal (shown as `label` with -v); its exit code is vx's.
+pub fn run(log: &Log, mut cmd: Command, label: &str) -> ExitCode {
     cmd.stdin(Stdio::inherit());
     cmd.stdout(Stdio::inherit());
     cmd.stderr(Stdio::inherit());
@@ -203,11 +506,35 @@ fn xbps_remove_args(opts: &RmOptions, pkgs: &[String]) -> Vec<OsString> {
     if opts.recursive {
         out.push("-R".into());
     }
+    if opts.clean_deps {
+        out.push("-o".into());
+    }
     out.extend(opts.xbps_args.iter().cloned().map(OsString::from));
     out.extend(pkgs.iter().cloned().map(OsString::from));
     out
 }
 
+/// `xbps-remove -n` with the same flags: the pkgvers the removal would take out.
+fn removal_plan(opts: &RmOptions, pkgs: &[String]) -> Result<Vec<String>, String> {
+    let plan_opts = RmOptions {
+        yes: false,
+        dry_run: true,
+        ..opts.clone()
+    };
+    let out = crate::rootdir::command("xbps-remove")
+        .args(xbps_remove_args(&plan_opts, pkgs))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("failed to run xbps-remove -n: {e}"))?;
+
+    if !out.status.success() {
+        return Err(String::from_utf8_lossy(&out.stderr).trim().to_string());
+    }
+    Ok(parse::parse_remove_plan(&String::from_utf8_lossy(&out.stdout)))
+}
+
 fn xbps_remove_orphan_args(opts: &RmOptions) -> Vec<OsString> {
     let mut out = xbps_rm_common_args(opts);
     out.extend(opts.xbps_args.iter().cloned().map(OsString::from));
@@ -215,6 +542,65 @@ fn xbps_remove_orphan_args(opts: &RmOptions) -> Vec<OsString> {
     out
 }
 
+/// Configured protected packages plus the running kernel.
+fn protected_packages(cfg: Option<&Config>) -> Vec<String> {
+    let mut out = match cfg {
+        Some(c) => c.protected_packages.clone(),
+        None => crate::config::DEFAULT_PROTECTED_PACKAGES.map(String::from).to_vec(),
+    };
+    out.extend(super::kernels::running_kernel_pkg());
+    out
+}
+
+/// Requested names that are on the protected list.
+fn protected_matches(pkgs: &[String], protected: &[String]) -> Vec<String> {
+    pkgs.iter()
+        .map(|p| p.trim())
+        .filter(|p| protected.iter().any(|q| q == p))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Warn about installed packages that still depend on what is being removed.
+fn warn_revdeps(log: &Log, opts: &RmOptions, pkgs: &[String]) {
+    let removing: BTreeSet<&str> = pkgs.iter().map(String::as_str).collect();
+
+    for p in pkgs {
+        let deps = match query::revdeps(p, false) {
+            Ok(v) => v,
+            Err(e) => {
+                if log.verbose && !log.quiet {
+                    log.exec(format!("revdeps check skipped for {p}: {e}"));
+                }
+                continue;
+            }
+        };
+
+        let dependents: Vec<String> = deps
+            .into_iter()
+            .filter(|pv| {
+                let name = pv.rsplit_once('-').map(|(n, _)| n).unwrap_or(pv);
+                !removing.contains(name)
+            })
+            .collect();
+
+        if dependents.is_empty() {
+            continue;
+        }
+
+        let hint = if opts.force_revdeps {
+            ""
+        } else {
+            " (xbps-remove will refuse without --force-revdeps)"
+        };
+        log.warn(format!(
+            "{p} is required by {} installed package(s){hint}: {}",
+            dependents.len(),
+            dependents.join(" ")
+        ));
+    }
+}
+
 fn maybe_untrack_managed(log: &Log, yes: bool, pkgs: &[String]) {
     let managed = match managed::load_managed() {
         Ok(v) => v,
@@ -256,7 +642,7 @@ fn maybe_untrack_managed(log: &Log, yes: bool, pkgs: &[String]) {
         return;
     }
 
-    if let Err(e) = managed::remove_managed(&to_untrack) {
+    if let Err(e) = managed::remove_managed(log, &to_untrack) {
         log.warn(format!(
             "removed packages but failed to update managed list: {e}"
         ));
@@ -279,7 +665,7 @@ fn confirm_yes_default(prompt: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{xbps_install_args, xbps_remove_args, xbps_remove_orphan_args};
+    use super::{protected_matches, xbps_install_args, xbps_remove_args, xbps_remove_orphan_args};
     use crate::core::xbps::{AddOptions, RmOptions};
     use std::{ffi::OsString, path::PathBuf};
 
@@ -329,9 +715,18 @@ mod tests {
             rootdir: None,
             xbps_verbose: false,
             xbps_args: Vec::new(),
+            force_protected: false,
+            clean_deps: false,
         }
     }
 
+    #[test]
+    fn protected_matches_exact_names_only() {
+        let protected = vec!["base-system".to_string(), "linux6.6".to_string()];
+        let pkgs = vec!["base-system".to_string(), "linux6.6-headers".to_string(), "fd".to_string()];
+        assert_eq!(protected_matches(&pkgs, &protected), vec!["base-system"]);
+    }
+
     #[test]
     fn install_args_keep_sync_default_and_append_pkgs() {
         let opts = add_opts();
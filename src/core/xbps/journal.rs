@@ -0,0 +1,119 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{history, log::Log};
+use std::{
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::query;
+
+/// Run `f` and journal whatever it changed in the installed package set.
+///
+/// Snapshot failures only disable journaling; they never block the operation.
+pub fn with_journal(log: &Log, what: &str, f: impl FnOnce() -> ExitCode) -> ExitCode {
+    let before = match query::installed_snapshot() {
+        Ok(v) => Some(v),
+        Err(e) => {
+            if log.verbose && !log.quiet {
+                log.exec(format!("journal disabled for this run: {e}"));
+            }
+            None
+        }
+    };
+
+    let code = f();
+
+    let Some(before) = before else {
+        return code;
+    };
+
+    match query::installed_snapshot() {
+        Ok(after) => match history::record(what, history::diff(&before, &after)) {
+            Ok(Some(id)) => {
+                if log.verbose && !log.quiet {
+                    log.exec(format!("journal: recorded transaction {id}"));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log.warn(format!("failed to write history journal: {e}")),
+        },
+        Err(e) => log.warn(format!("failed to snapshot packages for journal: {e}")),
+    }
+
+    code
+}
+
+/// `vx history [id]` — list journaled transactions, or show one in detail.
+pub fn history(log: &Log, id: Option<u64>, limit: usize) -> ExitCode {
+    let txns = match history::load() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if let Some(id) = id {
+        let Some(t) = txns.iter().find(|t| t.id == id) else {
+            log.error(format!("no transaction {id} in history"));
+            return ExitCode::from(1);
+        };
+        println!("transaction {} ({}, {})", t.id, t.command, format_age(t.time));
+        for c in &t.changes {
+            println!(
+                "  {}  {} → {}",
+                c.name,
+                c.from.as_deref().unwrap_or("(not installed)"),
+                c.to.as_deref().unwrap_or("(removed)")
+            );
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if txns.is_empty() {
+        log.info("vx: no transactions recorded yet.");
+        return ExitCode::SUCCESS;
+    }
+
+    for t in txns.iter().rev().take(limit) {
+        println!(
+            "  {:>4}  {:<10}  {:<30}  {} change(s)",
+            t.id,
+            format_age(t.time),
+            t.command,
+            t.changes.len()
+        );
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// `YYYYMMDD` (UTC) of a unix time.
+pub fn ymd(secs: u64) -> String {
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{y:04}{m:02}{d:02}")
+}
+
+pub fn format_age(epoch: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let d = now.saturating_sub(epoch);
+    match d {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", d / 60),
+        3600..86400 => format!("{}h ago", d / 3600),
+        _ => format!("{}d ago", d / 86400),
+    }
+}
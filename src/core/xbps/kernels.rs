@@ -0,0 +1,240 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::core::source::confirm_once;
+use crate::log::Log;
+use crate::privilege;
+use std::{
+    fs,
+    process::{Command, ExitCode, Stdio},
+};
+
+use super::install::run;
+use super::journal::with_journal;
+use super::query;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kernel {
+    pub name: String,
+    pub pkgver: String,
+    pub series: (u32, u32),
+    pub running: bool,
+    pub newest: bool,
+    pub required_by: Vec<String>,
+}
+
+impl Kernel {
+    /// Kernels that are running, newest, or pulled in by a meta package stay.
+    pub fn keep(&self) -> bool {
+        self.running || self.newest || !self.required_by.is_empty()
+    }
+}
+
+/// `linux6.6` -> (6, 6). Headers/meta packages (`linux6.6-headers`, `linux`) are not series.
+pub fn kernel_series(name: &str) -> Option<(u32, u32)> {
+    let rest = name.strip_prefix("linux")?;
+    let (maj, min) = rest.split_once('.')?;
+    Some((maj.parse().ok()?, min.parse().ok()?))
+}
+
+/// `6.6.52_1` -> (6, 6)
+pub fn release_series(release: &str) -> Option<(u32, u32)> {
+    let mut it = release.trim().split(['.', '-', '_']);
+    Some((it.next()?.parse().ok()?, it.next()?.parse().ok()?))
+}
+
+fn running_series() -> Option<(u32, u32)> {
+    // The host's running kernel says nothing about an alternate root.
+    if crate::rootdir::current().is_some() {
+        return None;
+    }
+    release_series(&fs::read_to_string("/proc/sys/kernel/osrelease").ok()?)
+}
+
+/// Package name of the running kernel series (`linux6.6`).
+pub fn running_kernel_pkg() -> Option<String> {
+    running_series().map(|(maj, min)| format!("linux{maj}.{min}"))
+}
+
+pub fn installed_kernels() -> Result<Vec<Kernel>, String> {
+    let running = running_series();
+    let snapshot = query::installed_snapshot()?;
+
+    let mut out: Vec<Kernel> = snapshot
+        .iter()
+        .filter_map(|(name, pkgver)| {
+            let series = kernel_series(name)?;
+            Some(Kernel {
+                name: name.clone(),
+                pkgver: pkgver.clone(),
+                series,
+                running: Some(series) == running,
+                newest: false,
+                required_by: Vec::new(),
+            })
+        })
+        .collect();
+
+    out.sort_by_key(|k| std::cmp::Reverse(k.series));
+    if let Some(k) = out.first_mut() {
+        k.newest = true;
+    }
+
+    for k in &mut out {
+        // Headers depend on their kernel; they go together, so they don't pin it.
+        k.required_by = query::revdeps(&k.name, false)?
+            .into_iter()
+            .filter(|pv| !pv.starts_with(&format!("{}-headers-", k.name)))
+            .collect();
+    }
+
+    Ok(out)
+}
+
+/// `vkpurge list` — old kernel versions still on disk within kept series.
+fn vkpurge_list() -> Vec<String> {
+    // vkpurge only knows the host's /boot.
+    if crate::rootdir::current().is_some() {
+        return Vec::new();
+    }
+    let out = Command::new("vkpurge")
+        .arg("list")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+
+    match out {
+        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout)
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `vx kernels`
+pub fn list(log: &Log) -> ExitCode {
+    let kernels = match installed_kernels() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if kernels.is_empty() {
+        log.info("vx: no linuxX.Y kernel packages installed.");
+        return ExitCode::SUCCESS;
+    }
+
+    println!("installed kernels ({}):", kernels.len());
+    for k in &kernels {
+        let mut notes = Vec::new();
+        if k.running {
+            notes.push("running".to_string());
+        }
+        if k.newest {
+            notes.push("newest".to_string());
+        }
+        if !k.required_by.is_empty() {
+            notes.push(format!("required by {}", k.required_by.join(", ")));
+        }
+        if notes.is_empty() {
+            println!("  {}  (stale)", k.pkgver);
+        } else {
+            println!("  {}  ({})", k.pkgver, notes.join(", "));
+        }
+    }
+
+    let old = vkpurge_list();
+    if !old.is_empty() {
+        println!("old kernel versions on disk ({}): {}", old.len(), old.join(" "));
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// `vx kernels --purge` — remove stale kernel series and old versions via vkpurge.
+pub fn purge(log: &Log, dry_run: bool, yes: bool) -> ExitCode {
+    let kernels = match installed_kernels() {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let snapshot = query::installed_snapshot().unwrap_or_default();
+    let mut remove: Vec<String> = Vec::new();
+    for k in kernels.iter().filter(|k| !k.keep()) {
+        remove.push(k.name.clone());
+        let headers = format!("{}-headers", k.name);
+        if snapshot.contains_key(&headers) {
+            remove.push(headers);
+        }
+    }
+    let old = vkpurge_list();
+
+    if remove.is_empty() && old.is_empty() {
+        log.info("vx: no stale kernels.");
+        return ExitCode::SUCCESS;
+    }
+
+    if !log.quiet {
+        println!("kernel purge plan:");
+        if !remove.is_empty() {
+            println!("  remove ({}):", remove.len());
+            for p in &remove {
+                println!("    {p}");
+            }
+        }
+        if !old.is_empty() {
+            println!("  vkpurge ({}): {}", old.len(), old.join(" "));
+        }
+    }
+
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+
+    if !yes && !confirm_once("Proceed?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    if !remove.is_empty() {
+        let mut cmd = privilege::command("xbps-remove");
+        cmd.arg("-y");
+        cmd.args(&remove);
+        let label = privilege::label(&format!("xbps-remove -y {}", remove.join(" ")));
+        let c = with_journal(log, "vx kernels --purge", || run(log, cmd, &label));
+        if c != ExitCode::SUCCESS {
+            return c;
+        }
+    }
+
+    if !old.is_empty() {
+        let mut cmd = privilege::command("vkpurge");
+        cmd.args(["rm", "all"]);
+        return run(log, cmd, &privilege::label("vkpurge rm all"));
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kernel_series, release_series};
+
+    #[test]
+    fn series_from_package_name_and_release() {
+        assert_eq!(kernel_series("linux6.6"), Some((6, 6)));
+        assert_eq!(kernel_series("linux6.12"), Some((6, 12)));
+        assert_eq!(kernel_series("linux6.6-headers"), None);
+        assert_eq!(kernel_series("linux"), None);
+        assert_eq!(kernel_series("linux-firmware"), None);
+        assert_eq!(release_series("6.6.52_1\n"), Some((6, 6)));
+    }
+}
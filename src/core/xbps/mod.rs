@@ -2,17 +2,43 @@
 // License: MIT
 
 use crate::{config::Config, log::Log};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
+mod cachedir;
+mod confdiff;
+mod downgrade;
 mod install;
+mod journal;
+mod kernels;
 mod parse;
+mod pick;
 mod plan;
 mod query;
+mod repodata;
+mod restart;
+mod rollback;
+mod shlibs;
+mod stats;
+mod virtualpkg;
+mod why;
 
-pub use plan::{plan_system_updates_fresh, SysUpdate};
+pub use install::run;
+pub use journal::{format_age, with_journal, ymd};
+pub use parse::{format_size, pkgname_from_pkgver};
+pub use cachedir::split_xbps_filename;
+pub use repodata::local_repo_pkgvers;
+pub use stats::{package_stats, PkgStats};
+pub use query::{
+    available_pkgnames, installed_pkgver, installed_properties, installed_property,
+    installed_snapshot,
+};
+pub use plan::{
+    download_summary, plan_totals, plan_up, removed_shlibs, sync_repodata,
+    sync_repodata_noninteractive, update_targets, SysUpdate,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct AddOptions {
     pub yes: bool,
     pub automatic: bool,
@@ -51,28 +77,116 @@ pub struct RmOptions {
     pub rootdir: Option<PathBuf>,
     pub xbps_verbose: bool,
     pub xbps_args: Vec<String>,
+    /// Allow removing packages on the protected list.
+    pub force_protected: bool,
+    /// Also remove orphans in the same transaction (`-o` alongside the packages).
+    pub clean_deps: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UpOptions {
+    pub yes: bool,
+    /// Only planning (`--dry-run`): never ask for a password.
+    pub dry_run: bool,
+    /// Repodata was just synced by the caller; plan without syncing again.
+    pub synced: bool,
+    pub download_only: bool,
+    pub repositories: Vec<String>,
+    /// Package names to hold back (`--ignore` + config `ignore_packages`).
+    pub ignore: Vec<String>,
+    /// Explicit update targets; empty means a full `xbps-install -u`.
+    pub pkgs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub manual: bool,
+    pub auto: bool,
+    pub hold: bool,
+    pub repo: Option<String>,
+    pub orphans: bool,
+}
+
+impl ListOptions {
+    pub fn is_filtered(&self) -> bool {
+        self.manual || self.auto || self.hold || self.repo.is_some() || self.orphans
+    }
 }
 
-pub fn search(log: &Log, cfg: Option<&Config>, installed: bool, term: &[String]) -> ExitCode {
-    query::search(log, cfg, installed, term)
+/// `--repository=<url>` args for extra repositories given with `--repo`.
+pub(crate) fn repository_args(repos: &[String]) -> Vec<String> {
+    repos.iter().map(|r| format!("--repository={r}")).collect()
 }
 
-pub fn info(log: &Log, cfg: Option<&Config>, pkg: &str) -> ExitCode {
-    query::info(log, cfg, pkg)
+pub fn search(
+    log: &Log,
+    cfg: Option<&Config>,
+    installed: bool,
+    term: &[String],
+    repos: &[String],
+    names_only: bool,
+) -> ExitCode {
+    query::search(log, cfg, installed, term, repos, names_only)
+}
+
+/// `vx search --pick <term>` — choose matches interactively, then `vx add` them.
+pub fn search_pick(log: &Log, cfg: Option<&Config>, term: &[String], repos: &[String]) -> ExitCode {
+    if term.is_empty() {
+        log.error("usage: vx search --pick <term>");
+        return ExitCode::from(2);
+    }
+    let pkgs = match query::search_text(term, repos).and_then(|t| pick::pick_from_search(log, &t)) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    if pkgs.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    let opts = AddOptions {
+        repositories: repos.to_vec(),
+        ..AddOptions::default()
+    };
+    install::add(log, cfg, opts, &pkgs)
+}
+
+pub fn info(log: &Log, cfg: Option<&Config>, pkg: &str, repos: &[String], raw: bool) -> ExitCode {
+    query::info(log, cfg, pkg, repos, raw)
 }
 
 pub fn files(log: &Log, cfg: Option<&Config>, pkg: &str) -> ExitCode {
     query::files(log, cfg, pkg)
 }
 
-/// `vx owns <path>` — who owns this file (xbps-query -o)
-pub fn owns(log: &Log, cfg: Option<&Config>, path: &str) -> ExitCode {
-    query::owns(log, cfg, path)
+/// `vx owns <path> [--remote]` — who owns this file (xbps-query -o / xlocate)
+pub fn owns(log: &Log, cfg: Option<&Config>, path: &str, remote: bool) -> ExitCode {
+    if remote {
+        query::owns_remote(log, cfg, path)
+    } else {
+        query::owns(log, cfg, path)
+    }
+}
+
+/// `vx rdeps <pkg> [--remote]` — reverse dependencies (xbps-query -X / -RX)
+pub fn rdeps(log: &Log, cfg: Option<&Config>, pkg: &str, remote: bool) -> ExitCode {
+    query::rdeps(log, cfg, pkg, remote)
 }
 
-/// `vx list [term]` — list installed packages (optionally filtered)
-pub fn list(log: &Log, cfg: Option<&Config>, term: Option<&str>) -> ExitCode {
-    query::list(log, cfg, term)
+/// `vx why <pkg>` — explain why a package is installed
+pub fn why(log: &Log, cfg: Option<&Config>, pkg: &str) -> ExitCode {
+    why::why(log, cfg, pkg)
+}
+
+/// `vx mark --auto|--manual <pkgs...>` — change the install mode (xbps-pkgdb -m)
+pub fn mark(log: &Log, automatic: bool, pkgs: &[String]) -> ExitCode {
+    why::mark(log, automatic, pkgs)
+}
+
+/// `vx list [term] [filters]` — list installed packages (optionally filtered)
+pub fn list(log: &Log, cfg: Option<&Config>, term: Option<&str>, opts: &ListOptions) -> ExitCode {
+    query::list(log, cfg, term, opts)
 }
 
 pub fn add(log: &Log, cfg: Option<&Config>, opts: AddOptions, pkgs: &[String]) -> ExitCode {
@@ -87,6 +201,109 @@ pub fn rm(log: &Log, cfg: Option<&Config>, opts: RmOptions, pkgs: &[String]) ->
     install::rm(log, cfg, opts, pkgs)
 }
 
-pub fn up_with_yes(log: &Log, cfg: Option<&Config>, yes: bool) -> ExitCode {
-    install::up_with_yes(log, cfg, yes)
+/// `vx orphans [-n] [-y]` — show orphaned packages, then remove them (xbps-remove -o).
+pub fn orphans(log: &Log, cfg: Option<&Config>, dry_run: bool, yes: bool) -> ExitCode {
+    install::orphans(log, cfg, dry_run, yes)
+}
+
+/// `vx clean [--all] [-n] [-y]` — purge obsolete cached packages (xbps-remove -O).
+pub fn clean(log: &Log, cfg: Option<&Config>, all: bool, dry_run: bool, yes: bool) -> ExitCode {
+    install::clean(log, cfg, all, dry_run, yes)
+}
+
+/// `vx downgrade <pkg> [version]` — reinstall an older version from the package cache.
+pub fn downgrade(
+    log: &Log,
+    cfg: Option<&Config>,
+    pkg: &str,
+    version: Option<&str>,
+    yes: bool,
+) -> ExitCode {
+    downgrade::downgrade(log, cfg, pkg, version, yes)
+}
+
+/// `vx kernels [--purge]`
+pub fn kernels(log: &Log, _cfg: Option<&Config>, purge: bool, dry_run: bool, yes: bool) -> ExitCode {
+    if purge || dry_run {
+        kernels::purge(log, dry_run, yes)
+    } else {
+        kernels::list(log)
+    }
+}
+
+/// Opt-in pre-update check (`vx up --check-shlibs`): report sonames the plan
+/// would remove from under installed packages. Err carries the exit code when
+/// the update should not go ahead.
+pub fn shlib_guard(log: &Log, plan: &[SysUpdate], opts: &UpOptions, dry_run: bool) -> Result<(), ExitCode> {
+    let broken = shlibs::find_broken(log, plan, &opts.repositories);
+    if broken.is_empty() {
+        if log.verbose && !log.quiet {
+            log.info("shlib check: no installed package loses a shared library.");
+        }
+        return Ok(());
+    }
+
+    shlibs::print_broken(&broken);
+    if dry_run {
+        return Ok(());
+    }
+    if opts.yes {
+        log.error("refusing to update with unresolved shared libraries (run without --yes to confirm)");
+        return Err(ExitCode::from(1));
+    }
+    if !crate::core::source::confirm_once("Update anyway?") {
+        log.info("aborted.");
+        return Err(ExitCode::SUCCESS);
+    }
+    Ok(())
+}
+
+/// `vx restartcheck`
+pub fn restartcheck(log: &Log, _cfg: Option<&Config>) -> ExitCode {
+    restart::check(log)
+}
+
+/// Report processes still running replaced binaries/libraries (after `vx up`).
+pub fn restart_hint(log: &Log) {
+    restart::after_update(log);
+}
+
+/// `vx up --check [--list]`
+pub fn check_updates(log: &Log, cfg: Option<&Config>, list: bool, opts: &UpOptions) -> ExitCode {
+    plan::check(log, cfg, list, opts)
+}
+
+/// `vx size [pkg...] [--top N]`
+pub fn size(log: &Log, cfg: Option<&Config>, pkgs: &[String], top: Option<usize>) -> ExitCode {
+    query::size(log, cfg, pkgs, top)
+}
+
+/// `vx confdiff [--list] [dir]`
+pub fn confdiff(log: &Log, _cfg: Option<&Config>, dir: Option<&Path>, list: bool) -> ExitCode {
+    let dir = match dir {
+        Some(d) => d.to_path_buf(),
+        None => crate::rootdir::path(confdiff::DEFAULT_ETC),
+    };
+    confdiff::confdiff(log, &dir, list)
+}
+
+/// `vx history [id]` — show journaled transactions
+pub fn history(log: &Log, id: Option<u64>, limit: usize) -> ExitCode {
+    journal::history(log, id, limit)
+}
+
+/// `vx rollback [id]` — undo a journaled transaction from the package cache
+pub fn rollback(
+    log: &Log,
+    cfg: Option<&Config>,
+    id: Option<u64>,
+    dry_run: bool,
+    yes: bool,
+) -> ExitCode {
+    rollback::rollback(log, cfg, id, dry_run, yes)
+}
+
+/// System update (`xbps-install -u`), or prefetch only with `download_only`.
+pub fn up(log: &Log, cfg: Option<&Config>, opts: &UpOptions) -> ExitCode {
+    install::up(log, cfg, opts)
 }
@@ -2,6 +2,9 @@
 // License: MIT
 
 use super::plan::SysUpdate;
+use crate::core::version::cmp_pkgver;
+use serde::Serialize;
+use std::{cmp::Ordering, collections::BTreeMap};
 
 /// Parse `xbps-install -Sun` (or `-un`) output.
 ///
@@ -74,8 +77,18 @@ where
 
             let from = format!("{name}-{oldver}");
             let to = format!("{name}-{newver}");
+            let download_size = cols.get(4..).and_then(|c| parse_human_size(&c.concat()));
 
-            out.push(SysUpdate { name, from, to });
+            out.push(SysUpdate {
+                name,
+                from,
+                to,
+                action: action.to_string(),
+                repository: None,
+                download_size,
+                installed_size: None,
+                size_delta: None,
+            });
             saw_table_row = true;
             continue;
         }
@@ -103,20 +116,68 @@ where
             None => "<not installed>".to_string(),
         };
 
+        // <pkgver> <action> <arch> <repo> <installed_size> <download_size>
+        let repository = cols.get(3).map(|s| s.to_string());
+        let installed_size = cols.get(4).and_then(|s| parse_human_size(s));
+        let download_size = cols.get(5).and_then(|s| parse_human_size(s));
+
         out.push(SysUpdate {
             name,
             from,
             to: pkgver.to_string(),
+            action: action.to_string(),
+            repository,
+            download_size,
+            installed_size,
+            size_delta: None,
         });
     }
 
     out.sort_by(|a, b| a.name.cmp(&b.name));
     out.dedup_by(|a, b| a.name == b.name);
 
+    // xbps reports some version drops (repo rollbacks, held-back mirrors) as
+    // "update"; label them by what the versions actually do.
+    for u in &mut out {
+        if u.action == "update" && cmp_pkgver(&u.to, &u.from) == Ordering::Less {
+            u.action = "downgrade".to_string();
+        }
+    }
+
     Ok(out)
 }
 
-fn pkgname_from_pkgver(pkgver: &str) -> Option<String> {
+/// One row of `xbps-query -l`: `<state> <pkgver> <short_desc>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPkg {
+    pub state: String,
+    pub pkgver: String,
+    pub name: String,
+    pub desc: String,
+}
+
+pub fn parse_installed_list(text: &str) -> Vec<InstalledPkg> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let mut it = line.splitn(3, char::is_whitespace);
+        let (Some(state), Some(pkgver)) = (it.next(), it.next()) else {
+            continue;
+        };
+        let Some(name) = pkgname_from_pkgver(pkgver) else {
+            continue;
+        };
+        out.push(InstalledPkg {
+            state: state.to_string(),
+            pkgver: pkgver.to_string(),
+            name,
+            desc: it.next().unwrap_or("").trim().to_string(),
+        });
+    }
+    out
+}
+
+pub fn pkgname_from_pkgver(pkgver: &str) -> Option<String> {
     let (name, ver) = pkgver.rsplit_once('-')?;
     if ver.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false) {
         Some(name.to_string())
@@ -125,6 +186,215 @@ fn pkgname_from_pkgver(pkgver: &str) -> Option<String> {
     }
 }
 
+/// `<pkgver>: <value> (<prop>)` rows of `xbps-query -p <prop> -s ""`, by
+/// package name.
+pub fn parse_property_rows(text: &str, prop: &str) -> BTreeMap<String, String> {
+    let suffix = format!(" ({prop})");
+    text.lines()
+        .filter_map(|l| {
+            let (pkgver, v) = l.trim().split_once(": ")?;
+            let v = v.strip_suffix(&suffix).unwrap_or(v).trim();
+            Some((pkgname_from_pkgver(pkgver.trim())?, v.to_string()))
+        })
+        .collect()
+}
+
+/// pkgvers from `xbps-remove -n` output (`<pkgver> remove <arch> <repo> ...`).
+pub fn parse_remove_plan(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|l| {
+            let mut it = l.split_whitespace();
+            let pkgver = it.next()?;
+            (it.next() == Some("remove") && pkgname_from_pkgver(pkgver).is_some())
+                .then(|| pkgver.to_string())
+        })
+        .collect()
+}
+
+/// One row of `xbps-query -Rs`: `[*] <pkgver> <short_desc>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchHit {
+    pub name: String,
+    pub pkgver: String,
+    pub installed: bool,
+    pub desc: String,
+}
+
+pub fn parse_search_results(text: &str) -> Vec<SearchHit> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let mut line = line.trim();
+        let mut installed = false;
+        if line.len() >= 3 && line.starts_with('[') && line.as_bytes()[2] == b']' {
+            installed = line.as_bytes()[1] == b'*';
+            line = line[3..].trim_start();
+        }
+        let (pkgver, desc) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let Some(name) = pkgname_from_pkgver(pkgver) else {
+            continue;
+        };
+        out.push(SearchHit {
+            name,
+            pkgver: pkgver.to_string(),
+            installed,
+            desc: desc.trim().to_string(),
+        });
+    }
+    out
+}
+
+/// A property value from `xbps-query -R <pkg>`: scalar, or an indented list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum InfoValue {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// `key: value` lines; a key with no value is followed by indented list items.
+pub fn parse_pkg_info(text: &str) -> BTreeMap<String, InfoValue> {
+    let mut out = BTreeMap::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            if let Some(key) = &current
+                && let Some(InfoValue::Many(items)) = out.get_mut(key)
+            {
+                items.push(line.trim().to_string());
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_string(), value.trim());
+        let v = if value.is_empty() {
+            InfoValue::Many(Vec::new())
+        } else {
+            InfoValue::One(value.to_string())
+        };
+        out.insert(key.clone(), v);
+        current = Some(key);
+    }
+    out
+}
+
+/// The fields `vx info` shows, picked out of `parse_pkg_info`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PkgInfo {
+    pub name: String,
+    pub version: String,
+    pub short_desc: String,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    pub maintainer: Option<String>,
+    pub repository: Option<String>,
+    pub architecture: Option<String>,
+    pub installed_size: Option<u64>,
+    pub download_size: Option<u64>,
+    pub run_depends: Vec<String>,
+}
+
+impl PkgInfo {
+    pub fn from_props(props: &BTreeMap<String, InfoValue>) -> Option<Self> {
+        let one = |k: &str| match props.get(k) {
+            Some(InfoValue::One(v)) => Some(v.clone()),
+            _ => None,
+        };
+        let pkgver = one("pkgver")?;
+        let name = pkgname_from_pkgver(&pkgver)?;
+        Some(Self {
+            version: pkgver[name.len() + 1..].to_string(),
+            name,
+            short_desc: one("short_desc").unwrap_or_default(),
+            homepage: one("homepage"),
+            license: one("license"),
+            maintainer: one("maintainer"),
+            repository: one("repository"),
+            architecture: one("architecture"),
+            installed_size: one("installed_size").and_then(|s| parse_human_size(&s)),
+            download_size: one("filename-size").and_then(|s| parse_human_size(&s)),
+            run_depends: match props.get("run_depends") {
+                Some(InfoValue::Many(v)) => v.clone(),
+                Some(InfoValue::One(v)) => vec![v.clone()],
+                None => Vec::new(),
+            },
+        })
+    }
+}
+
+/// A package argument with an optional version constraint: `firefox>=120`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PkgSpec {
+    pub name: String,
+    /// Operator and version, e.g. `>=120`.
+    pub constraint: Option<String>,
+}
+
+impl PkgSpec {
+    /// xbps package pattern (`firefox>=120`), or just the name.
+    pub fn pattern(&self) -> String {
+        match &self.constraint {
+            Some(c) => format!("{}{c}", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+pub fn parse_pkg_spec(arg: &str) -> PkgSpec {
+    let arg = arg.trim();
+    match arg.find(['<', '>', '=']) {
+        Some(i) if i > 0 && i + 1 < arg.len() => PkgSpec {
+            name: arg[..i].to_string(),
+            constraint: Some(arg[i..].to_string()),
+        },
+        _ => PkgSpec {
+            name: arg.to_string(),
+            constraint: None,
+        },
+    }
+}
+
+/// Parse a humanized xbps size ("12MB", "850 KB", "1.5GB", "4096B") into bytes.
+pub fn parse_human_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: f64 = num.parse().ok()?;
+
+    let mult: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "K" | "KIB" => 1 << 10,
+        "MB" | "M" | "MIB" => 1 << 20,
+        "GB" | "G" | "GIB" => 1 << 30,
+        "TB" | "T" | "TIB" => 1 << 40,
+        _ => return None,
+    };
+
+    Some((num * mult as f64) as u64)
+}
+
+/// Format bytes the way xbps does (binary units, one decimal above KB).
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut v = bytes as f64;
+    let mut i = 0;
+    while v >= 1024.0 && i < UNITS.len() - 1 {
+        v /= 1024.0;
+        i += 1;
+    }
+    if i == 0 {
+        format!("{bytes}B")
+    } else {
+        format!("{v:.1}{}", UNITS[i])
+    }
+}
+
 pub fn strip_ansi(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     let mut it = s.chars().peekable();
@@ -145,3 +415,117 @@ pub fn strip_ansi(s: &str) -> String {
     out
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_size, parse_human_size, parse_installed_list, parse_pkg_info, parse_pkg_spec,
+        parse_property_rows, parse_remove_plan, parse_search_results, parse_xbps_sun_plan,
+        InfoValue, PkgInfo,
+    };
+
+    #[test]
+    fn property_rows_map_names_to_values() {
+        let text = "\
+foo-1.0_1: /home/me/void-packages/hostdir/binpkgs (repository)
+linux6.6-6.6.30_1: https://repo-default.voidlinux.org/current (repository)
+";
+        let rows = parse_property_rows(text, "repository");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows["foo"], "/home/me/void-packages/hostdir/binpkgs");
+        assert_eq!(rows["linux6.6"], "https://repo-default.voidlinux.org/current");
+    }
+
+    #[test]
+    fn remove_plan_lists_removed_pkgvers() {
+        let text = "\
+gimp-2.10.38_1 remove x86_64 https://repo-default.voidlinux.org/current 120MB
+babl-0.1.108_1 remove x86_64 https://repo-default.voidlinux.org/current 4MB
+
+Size freed on disk:            124MB
+";
+        assert_eq!(parse_remove_plan(text), ["gimp-2.10.38_1", "babl-0.1.108_1"]);
+    }
+
+    #[test]
+    fn installed_list_rows_split_state_pkgver_and_desc() {
+        let text = "ii ripgrep-14.1.0_1    Fast line-oriented search tool\n\
+                    uu linux6.6-6.6.30_1 Linux kernel and modules (6.6 series)\n\
+                    \n";
+        let rows = parse_installed_list(text);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].state, "ii");
+        assert_eq!(rows[0].name, "ripgrep");
+        assert_eq!(rows[0].pkgver, "ripgrep-14.1.0_1");
+        assert_eq!(rows[0].desc, "Fast line-oriented search tool");
+        assert_eq!(rows[1].name, "linux6.6");
+    }
+
+    #[test]
+    fn human_sizes_round_trip() {
+        assert_eq!(parse_human_size("4096B"), Some(4096));
+        assert_eq!(parse_human_size("2KB"), Some(2048));
+        assert_eq!(parse_human_size("1.5 MB"), Some(1572864));
+        assert_eq!(parse_human_size("lots"), None);
+        assert_eq!(format_size(512), "512B");
+        assert_eq!(format_size(1572864), "1.5MB");
+    }
+
+    #[test]
+    fn pkg_spec_splits_name_and_constraint() {
+        let s = parse_pkg_spec("firefox>=120");
+        assert_eq!(s.name, "firefox");
+        assert_eq!(s.constraint.as_deref(), Some(">=120"));
+        assert_eq!(s.pattern(), "firefox>=120");
+
+        let s = parse_pkg_spec("glibc<2.40_1");
+        assert_eq!((s.name.as_str(), s.constraint.as_deref()), ("glibc", Some("<2.40_1")));
+
+        let s = parse_pkg_spec("htop");
+        assert_eq!((s.name.as_str(), s.constraint), ("htop", None));
+    }
+
+    #[test]
+    fn sun_plan_captures_sizes_and_repository() {
+        let text = "foo-1.1_1 update x86_64 https://repo 2097152 1048576\n\
+                    bar-2.0_1 update x86_64 https://repo 4096\n";
+        let plan = parse_xbps_sun_plan(text, |n| Ok(Some(format!("{n}-1.0_1")))).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].name, "bar");
+        assert_eq!(plan[0].download_size, None);
+        assert_eq!(plan[0].installed_size, Some(4096));
+        assert_eq!(plan[1].from, "foo-1.0_1");
+        assert_eq!(plan[1].action, "update");
+        assert_eq!(plan[1].repository.as_deref(), Some("https://repo"));
+        assert_eq!(plan[1].download_size, Some(1 << 20));
+    }
+
+    #[test]
+    fn search_and_info_output_parse_into_structs() {
+        let hits = parse_search_results(
+            "[*] ripgrep-14.1.0_1      Fast line-oriented search tool\n\
+             [-] ripgrep-all-0.10.6_1  Ripgrep, but also search in PDFs\n",
+        );
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].installed);
+        assert_eq!(hits[0].name, "ripgrep");
+        assert_eq!(hits[1].name, "ripgrep-all");
+        assert!(!hits[1].installed);
+        assert_eq!(hits[1].desc, "Ripgrep, but also search in PDFs");
+
+        let info = parse_pkg_info(
+            "pkgver: ripgrep-14.1.0_1\nrun_depends:\n\tglibc>=2.39_1\n\tpcre2>=10.22_1\nshort_desc: Fast search\n",
+        );
+        assert_eq!(info["pkgver"], InfoValue::One("ripgrep-14.1.0_1".into()));
+        assert_eq!(
+            info["run_depends"],
+            InfoValue::Many(vec!["glibc>=2.39_1".into(), "pcre2>=10.22_1".into()])
+        );
+        assert_eq!(info["short_desc"], InfoValue::One("Fast search".into()));
+
+        let typed = PkgInfo::from_props(&info).unwrap();
+        assert_eq!((typed.name.as_str(), typed.version.as_str()), ("ripgrep", "14.1.0_1"));
+        assert_eq!(typed.run_depends.len(), 2);
+        assert_eq!(typed.homepage, None);
+    }
+}
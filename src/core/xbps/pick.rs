@@ -0,0 +1,336 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::log::Log;
+use std::{
+    collections::BTreeSet,
+    io::{self, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+};
+
+use super::parse::SearchHit;
+
+/// Subsequence match of `needle` in `hay` (case-insensitive).
+/// Lower is better: the number of skipped chars between matches, with a bonus
+/// for a match at the start. None when `needle` isn't a subsequence.
+pub fn fuzzy_score(needle: &str, hay: &str) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    let hay: Vec<char> = hay.to_lowercase().chars().collect();
+    let mut score = 0usize;
+    let mut pos = 0usize;
+    let mut first = None;
+    for n in needle.to_lowercase().chars() {
+        let found = hay[pos..].iter().position(|&c| c == n)?;
+        if first.is_none() {
+            first = Some(pos + found);
+        } else {
+            score += found;
+        }
+        pos += found + 1;
+    }
+    Some(score * 2 + first.unwrap_or(0).min(1))
+}
+
+/// Indices of `hits` whose name matches `query`, best first.
+pub fn filter(hits: &[SearchHit], query: &str) -> Vec<usize> {
+    let mut scored: Vec<(usize, usize)> = hits
+        .iter()
+        .enumerate()
+        .filter_map(|(i, h)| fuzzy_score(query, &h.name).map(|s| (s, i)))
+        .collect();
+    scored.sort();
+    scored.into_iter().map(|(_, i)| i).collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Up,
+    Down,
+    Toggle,
+    Enter,
+    Backspace,
+    Cancel,
+    Char(char),
+}
+
+/// Decode one chunk read from a raw-mode tty.
+fn decode(buf: &[u8]) -> Vec<Key> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < buf.len() {
+        match buf[i] {
+            // CSI: ESC [ <parameter/intermediate bytes> <final byte>, e.g. `ESC[3~`
+            // (Delete); all of it is consumed, only arrows mean anything here.
+            0x1b if buf.get(i + 1) == Some(&b'[') => {
+                let mut j = i + 2;
+                while j < buf.len() && (0x20..=0x3f).contains(&buf[j]) {
+                    j += 1;
+                }
+                if j == i + 2 {
+                    match buf.get(j) {
+                        Some(b'A') => out.push(Key::Up),
+                        Some(b'B') => out.push(Key::Down),
+                        _ => {}
+                    }
+                }
+                i = j + 1;
+                continue;
+            }
+            // SS3 arrows (application cursor mode): ESC O A / ESC O B.
+            0x1b if buf.get(i + 1) == Some(&b'O') => {
+                match buf.get(i + 2) {
+                    Some(b'A') => out.push(Key::Up),
+                    Some(b'B') => out.push(Key::Down),
+                    _ => {}
+                }
+                i += 3;
+                continue;
+            }
+            // A lone Esc (nothing else arrived with it).
+            0x1b => out.push(Key::Cancel),
+            0x03 | 0x07 => out.push(Key::Cancel),
+            0x10 => out.push(Key::Up),   // Ctrl-P
+            0x0e => out.push(Key::Down), // Ctrl-N
+            b' ' | b'\t' => out.push(Key::Toggle),
+            b'\r' | b'\n' => out.push(Key::Enter),
+            0x7f | 0x08 => out.push(Key::Backspace),
+            b if b.is_ascii_graphic() => out.push(Key::Char(b as char)),
+            _ => {}
+        }
+        i += 1;
+    }
+    out
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let out = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Restores the saved tty settings when dropped.
+struct RawMode(String);
+
+impl RawMode {
+    fn enter() -> Result<Self, String> {
+        let saved = stty(&["-g"]).ok_or("failed to read terminal settings (stty -g)")?;
+        // min 0 / time 1: reads return after 0.1s, so a lone Esc can be told apart.
+        stty(&["raw", "-echo", "min", "0", "time", "1"])
+            .ok_or("failed to switch terminal to raw mode")?;
+        Ok(Self(saved))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[self.0.as_str()]);
+        print!("\x1b[?25h");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// (rows, cols) of the controlling terminal, from `stty size`.
+pub fn term_size() -> Option<(usize, usize)> {
+    let s = stty(&["size"])?;
+    let mut it = s.split_whitespace();
+    Some((it.next()?.parse().ok()?, it.next()?.parse().ok()?))
+}
+
+/// `line` cut to `cols` characters, so a long description can't wrap and
+/// push the list off screen.
+fn fit(line: &str, cols: usize) -> String {
+    match line.char_indices().nth(cols) {
+        Some((i, _)) => line[..i].to_string(),
+        None => line.to_string(),
+    }
+}
+
+struct State<'a> {
+    hits: &'a [SearchHit],
+    query: String,
+    shown: Vec<usize>,
+    cursor: usize,
+    offset: usize,
+    selected: BTreeSet<usize>,
+}
+
+impl State<'_> {
+    fn refilter(&mut self) {
+        self.shown = filter(self.hits, &self.query);
+        self.cursor = 0;
+        self.offset = 0;
+    }
+
+    fn render(&mut self, rows: usize, cols: usize) {
+        let height = rows.saturating_sub(3).max(1);
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + height {
+            self.offset = self.cursor + 1 - height;
+        }
+
+        // Raw mode: lines need an explicit carriage return.
+        let mut s = String::from("\x1b[?25l\x1b[H\x1b[2J");
+        let status = format!(
+            "{}/{} matches, {} selected — space: select, enter: install, esc: cancel",
+            self.shown.len(),
+            self.hits.len(),
+            self.selected.len()
+        );
+        s.push_str(&format!(
+            "{}\r\n\x1b[2m{}\x1b[0m\r\n",
+            fit(&format!("> {}", self.query), cols),
+            fit(&status, cols)
+        ));
+        for (row, &i) in self.shown.iter().enumerate().skip(self.offset).take(height) {
+            let h = &self.hits[i];
+            let mark = if self.selected.contains(&i) { "[x]" } else { "[ ]" };
+            let inst = if h.installed { " (installed)" } else { "" };
+            let line = fit(&format!("{mark} {}{inst}  {}", h.pkgver, h.desc), cols);
+            if row == self.cursor {
+                s.push_str(&format!("\x1b[7m{line}\x1b[0m\r\n"));
+            } else {
+                s.push_str(&format!("{line}\r\n"));
+            }
+        }
+        print!("{s}");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Interactive multi-select over `hits`. Returns the chosen package names
+/// (empty when cancelled).
+pub fn pick(hits: &[SearchHit]) -> Result<Vec<String>, String> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err("--pick needs an interactive terminal".into());
+    }
+
+    let mut st = State {
+        hits,
+        query: String::new(),
+        shown: Vec::new(),
+        cursor: 0,
+        offset: 0,
+        selected: BTreeSet::new(),
+    };
+    st.refilter();
+
+    let chosen = {
+        let _raw = RawMode::enter()?;
+        let (rows, cols) = term_size().unwrap_or((24, 80));
+        let mut stdin = io::stdin().lock();
+        let mut buf = [0u8; 32];
+        st.render(rows, cols);
+
+        loop {
+            let n = stdin
+                .read(&mut buf)
+                .map_err(|e| format!("failed to read terminal input: {e}"))?;
+            if n == 0 {
+                continue;
+            }
+
+            let mut done = None;
+            for key in decode(&buf[..n]) {
+                match key {
+                    Key::Up => st.cursor = st.cursor.saturating_sub(1),
+                    Key::Down => {
+                        if st.cursor + 1 < st.shown.len() {
+                            st.cursor += 1;
+                        }
+                    }
+                    Key::Toggle => {
+                        if let Some(&i) = st.shown.get(st.cursor)
+                            && !st.selected.remove(&i)
+                        {
+                            st.selected.insert(i);
+                        }
+                    }
+                    Key::Enter => {
+                        if st.selected.is_empty()
+                            && let Some(&i) = st.shown.get(st.cursor)
+                        {
+                            st.selected.insert(i);
+                        }
+                        done = Some(true);
+                    }
+                    Key::Cancel => done = Some(false),
+                    Key::Backspace => {
+                        st.query.pop();
+                        st.refilter();
+                    }
+                    Key::Char(c) => {
+                        st.query.push(c);
+                        st.refilter();
+                    }
+                }
+                if done.is_some() {
+                    break;
+                }
+            }
+
+            match done {
+                Some(ok) => break ok,
+                None => st.render(rows, cols),
+            }
+        }
+    };
+
+    print!("\x1b[H\x1b[2J");
+    let _ = io::stdout().flush();
+
+    if !chosen {
+        return Ok(Vec::new());
+    }
+    Ok(st.selected.iter().map(|&i| hits[i].name.clone()).collect())
+}
+
+/// `vx search --pick <term>`: results for `text` -> picker -> package names.
+pub fn pick_from_search(log: &Log, text: &str) -> Result<Vec<String>, String> {
+    let hits = super::parse::parse_search_results(text);
+    if hits.is_empty() {
+        log.info("vx: no matches.");
+        return Ok(Vec::new());
+    }
+    pick(&hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, fit, fuzzy_score, Key};
+
+    #[test]
+    fn decode_consumes_whole_escape_sequences() {
+        assert_eq!(decode(b"\x1b[A\x1bOB"), vec![Key::Up, Key::Down]);
+        // Delete, Page Down and a modified arrow leave nothing in the query.
+        assert_eq!(decode(b"\x1b[3~\x1b[6~\x1b[1;5Ca"), vec![Key::Char('a')]);
+        assert_eq!(decode(b"\x1b"), vec![Key::Cancel]);
+    }
+
+    #[test]
+    fn fit_cuts_to_the_terminal_width() {
+        assert_eq!(fit("[ ] foo-1.0_1  a long description", 12), "[ ] foo-1.0_");
+        assert_eq!(fit("short", 80), "short");
+        assert_eq!(fit("—é", 1), "—");
+    }
+
+    #[test]
+    fn fuzzy_prefers_tight_prefix_matches() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "firefox"), None);
+        let tight = fuzzy_score("fire", "firefox").unwrap();
+        let spread = fuzzy_score("fire", "font-ibm-plex-otf-ire").unwrap();
+        let inner = fuzzy_score("fox", "firefox").unwrap();
+        assert!(tight < spread);
+        assert!(tight < inner);
+        assert_eq!(fuzzy_score("FF", "firefox"), Some(6));
+    }
+}
@@ -1,101 +1,347 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use crate::{cache, config::Config, log::Log};
-use std::process::{Command, Stdio};
+use crate::{cache, config::Config, log::Log, privilege};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    process::{ExitCode, Stdio},
+};
 
-use super::{parse, query};
+use super::{UpOptions, parse, query, repository_args, shlibs};
 
-#[derive(Debug, Clone)]
+/// Fresh plan for `vx up`, narrowed to `opts.pkgs` when given and without
+/// ignored packages (explicitly named packages are never ignored).
+///
+/// When the plan was narrowed, `opts.pkgs` is set to the remaining names so
+/// xbps-install updates exactly those instead of running a blanket `-u`.
+pub fn plan_up(
+    log: &Log,
+    cfg: Option<&Config>,
+    opts: &mut UpOptions,
+) -> Result<Vec<SysUpdate>, String> {
+    let mut plan = if opts.dry_run {
+        plan_system_updates_rootless(log, cfg, &opts.repositories)?
+    } else if opts.synced {
+        plan_system_updates_inner(log, cfg, &opts.repositories, SyncMode::Done)?
+    } else {
+        plan_system_updates_fresh(log, cfg, &opts.repositories)?
+    };
+    let targets = std::mem::take(&mut opts.pkgs);
+
+    if !targets.is_empty() {
+        for t in &targets {
+            if plan.iter().any(|u| &u.name == t) {
+                continue;
+            }
+            if query::installed_pkgver(t)?.is_some() {
+                log.info(format!("vx: {t} is already up to date."));
+            } else {
+                log.warn(format!("{t} is not installed; use `vx add {t}`"));
+            }
+        }
+        plan.retain(|u| targets.contains(&u.name));
+    }
+
+    let ignore: Vec<String> = opts
+        .ignore
+        .iter()
+        .filter(|i| !targets.contains(i))
+        .cloned()
+        .collect();
+    let skipped = apply_ignores(&mut plan, &ignore);
+    if !skipped.is_empty() {
+        log.info(format!("vx: ignoring updates for {}", skipped.join(" ")));
+    }
+
+    if !targets.is_empty() || !skipped.is_empty() {
+        opts.pkgs = update_targets(&plan);
+    }
+
+    Ok(plan)
+}
+
+/// Names to pass to `xbps-install -u` for `plan`: installed packages only.
+/// New dependencies ("install" rows) are pulled in by xbps itself, and
+/// `-u` fails on a package that isn't installed.
+pub fn update_targets(plan: &[SysUpdate]) -> Vec<String> {
+    plan.iter()
+        .filter(|u| u.action != "install")
+        .map(|u| u.name.clone())
+        .collect()
+}
+
+/// Drop ignored packages from a plan; returns the names that were held back.
+pub fn apply_ignores(plan: &mut Vec<SysUpdate>, ignore: &[String]) -> Vec<String> {
+    let mut skipped = Vec::new();
+    plan.retain(|u| {
+        if ignore.iter().any(|i| i == &u.name) {
+            skipped.push(u.name.clone());
+            false
+        } else {
+            true
+        }
+    });
+    skipped
+}
+
+/// Exit code of `vx up --check` when updates are available.
+pub const UPDATES_AVAILABLE: u8 = 100;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct SysUpdate {
     pub name: String,
     pub from: String,
     pub to: String,
+    /// update | install | reinstall | downgrade
+    pub action: String,
+    /// Repository the new version comes from (not shown in table output).
+    pub repository: Option<String>,
+    /// Bytes still to fetch; None when xbps didn't report it (already cached).
+    pub download_size: Option<u64>,
+    /// Installed size of the new version.
+    pub installed_size: Option<u64>,
+    /// Change in disk usage once applied (new size minus current size).
+    pub size_delta: Option<i64>,
+}
+
+/// Sonames `plan` stops providing (soname -> pkgver that provided it).
+/// Queries the installed versions, so call it before applying the plan.
+pub fn removed_shlibs(log: &Log, plan: &[SysUpdate], repos: &[String]) -> BTreeMap<String, String> {
+    if plan.is_empty() {
+        return BTreeMap::new();
+    }
+    shlibs::removed_sonames(&shlibs::plan_changes(log, plan, repos))
+}
+
+/// `download 12 packages, 96.0MB`
+pub fn download_summary(plan: &[SysUpdate]) -> String {
+    let total: u64 = plan.iter().filter_map(|u| u.download_size).sum();
+    format!(
+        "download {} packages, {}",
+        plan.len(),
+        parse::format_size(total)
+    )
+}
+
+/// `14 updates, 210.0MB download, 48.0MB disk delta`
+pub fn plan_totals(plan: &[SysUpdate]) -> String {
+    let download: u64 = plan.iter().filter_map(|u| u.download_size).sum();
+    let mut s = format!(
+        "{} update{}, {} download",
+        plan.len(),
+        if plan.len() == 1 { "" } else { "s" },
+        parse::format_size(download)
+    );
+    if plan.iter().any(|u| u.size_delta.is_some()) {
+        let delta: i64 = plan.iter().filter_map(|u| u.size_delta).sum();
+        let sign = if delta < 0 { "-" } else { "" };
+        s.push_str(&format!(
+            ", {sign}{} disk delta",
+            parse::format_size(delta.unsigned_abs())
+        ));
+    }
+    s
+}
+
+/// Fill `size_delta` from the new installed size and the current one.
+fn fill_size_deltas(plan: &mut [SysUpdate]) {
+    for u in plan {
+        let Some(new) = u.installed_size else {
+            continue;
+        };
+        let old = if u.action == "install" {
+            Some(0)
+        } else {
+            query::installed_property(&u.name, "installed_size")
+                .ok()
+                .flatten()
+                .and_then(|s| parse::parse_human_size(&s))
+        };
+        u.size_delta = old.map(|old| new as i64 - old as i64);
+    }
+}
+
+/// TTL stamp for the last successful `xbps-install -S`.
+const REPODATA_SYNC_KEY: &str = "xbps.repodata.sync";
+
+/// The last system update plan, kept for `vx cache show`.
+const UPDATE_PLAN_KEY: &str = "xbps.update-plan";
+
+/// `xbps-install -S`, prompting for a password if needed; marks the sync stamp.
+pub fn sync_repodata(log: &Log, repos: &[String]) -> Result<(), String> {
+    let mut sync = privilege::command("xbps-install");
+    sync.args(repository_args(repos));
+    sync.args(["-S"]);
+    sync.env("XBPS_COLORS", "0");
+    sync.stdin(Stdio::inherit());
+    sync.stdout(Stdio::piped());
+    sync.stderr(Stdio::piped());
+
+    if log.verbose && !log.quiet {
+        log.exec(privilege::label("xbps-install -S"));
+    }
+
+    let out = sync
+        .output()
+        .map_err(|e| format!("failed to run xbps-install -S: {e}"))?;
+
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        if err.is_empty() {
+            return Err(format!(
+                "xbps-install -S failed (exit={})",
+                out.status.code().unwrap_or(1)
+            ));
+        }
+        return Err(format!("xbps-install -S failed: {err}"));
+    }
+
+    cache::mark(REPODATA_SYNC_KEY, cache::repodata_ttl_secs());
+    Ok(())
+}
+
+/// `xbps-install -S` that never prompts (`sudo -n`, `doas -n`, ...).
+/// Marks the sync stamp and returns true on success.
+pub fn sync_repodata_noninteractive(repos: &[String]) -> bool {
+    let ok = privilege::command_noninteractive("xbps-install")
+        .arg("-S")
+        .args(repository_args(repos))
+        .env("XBPS_COLORS", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if ok {
+        cache::mark(REPODATA_SYNC_KEY, cache::repodata_ttl_secs());
+    }
+    ok
+}
+
+/// How `plan_system_updates_inner` gets fresh repodata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncMode {
+    /// Always `xbps-install -S` (may prompt for a password).
+    Always,
+    /// Non-interactive `-S` when the TTL stamp is stale; if that needs a
+    /// password, the existing repodata is used as-is.
+    IfStale,
+    /// Like `IfStale`, but without a password the repodata is fetched into
+    /// memory (`xbps-install -M`): fresh, nothing written, no root.
+    IfStaleRootless,
+    /// The caller already ran `xbps-install -S`.
+    Done,
 }
 
 /// Like `plan_system_updates`, but ALWAYS syncs repodata first.
 ///
 /// This is what you want for commands that must *reliably* "find updates",
-/// e.g. `vx up -a` and `vx up -n`, where planning must not depend on TTL cache.
-pub fn plan_system_updates_fresh(log: &Log, cfg: Option<&Config>) -> Result<Vec<SysUpdate>, String> {
-    plan_system_updates_inner(log, cfg, true)
+/// e.g. `vx up` and `vx up -a`, where planning must not depend on TTL cache.
+pub fn plan_system_updates_fresh(
+    log: &Log,
+    cfg: Option<&Config>,
+    repos: &[String],
+) -> Result<Vec<SysUpdate>, String> {
+    plan_system_updates_inner(log, cfg, repos, SyncMode::Always)
+}
+
+/// Plan for `vx up --dry-run`: current repodata without ever needing root.
+///
+/// Stale repodata is synced non-interactively (`sudo -n`, `doas -n`, ...) or,
+/// failing that, fetched into memory with `xbps-install -M`.
+pub fn plan_system_updates_rootless(
+    log: &Log,
+    cfg: Option<&Config>,
+    repos: &[String],
+) -> Result<Vec<SysUpdate>, String> {
+    plan_system_updates_inner(log, cfg, repos, SyncMode::IfStaleRootless)
+}
+
+/// Non-interactive plan for `vx up --check`.
+///
+/// Never prompts: repodata is synced non-interactively (`sudo -n`, `doas -n`, ...)
+/// when the TTL cache is stale,
+/// and if that needs a password the existing repodata is used as-is.
+pub fn plan_system_updates_check(
+    log: &Log,
+    cfg: Option<&Config>,
+    repos: &[String],
+) -> Result<Vec<SysUpdate>, String> {
+    plan_system_updates_inner(log, cfg, repos, SyncMode::IfStale)
 }
 
 fn plan_system_updates_inner(
     log: &Log,
     _cfg: Option<&Config>,
-    force_sync: bool,
+    repos: &[String],
+    mode: SyncMode,
 ) -> Result<Vec<SysUpdate>, String> {
-    let ttl = cache::sync_ttl_secs();
-    let cache_key = "xbps.repodata.sync";
+    let ttl = cache::repodata_ttl_secs();
+    let repo_args = repository_args(repos);
+    let mut memory_sync = false;
 
     // 1) Sync repodata if needed (or forced)
-    if force_sync || !cache::is_fresh(cache_key, ttl) {
-        let mut sync = Command::new("sudo");
-        sync.arg("xbps-install");
-        sync.args(["-S"]);
-        sync.env("XBPS_COLORS", "0");
-        sync.stdin(Stdio::inherit());
-        sync.stdout(Stdio::piped());
-        sync.stderr(Stdio::piped());
-
-        if log.verbose && !log.quiet {
-            if force_sync {
-                log.exec("sudo xbps-install -S (forced)".to_string());
-            } else {
-                log.exec("sudo xbps-install -S".to_string());
+    match mode {
+        SyncMode::Always => sync_repodata(log, repos)?,
+        SyncMode::Done => {}
+        _ if cache::is_fresh(REPODATA_SYNC_KEY, ttl) => {
+            if log.verbose && !log.quiet {
+                log.exec(format!(
+                    "cache hit: skip repodata sync (ttl={}s); use --fresh to force",
+                    ttl
+                ));
             }
         }
-
-        let out = sync
-            .output()
-            .map_err(|e| format!("failed to run xbps-install -S: {e}"))?;
-
-        if !out.status.success() {
-            let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
-            if err.is_empty() {
-                return Err(format!(
-                    "xbps-install -S failed (exit={})",
-                    out.status.code().unwrap_or(1)
-                ));
+        SyncMode::IfStale => {
+            if !sync_repodata_noninteractive(repos) && log.verbose && !log.quiet {
+                log.exec("non-interactive xbps-install -S unavailable; using existing repodata");
+            }
+        }
+        SyncMode::IfStaleRootless => {
+            if !sync_repodata_noninteractive(repos) {
+                memory_sync = true;
+                if log.verbose && !log.quiet {
+                    log.exec("non-interactive xbps-install -S unavailable; fetching repodata into memory");
+                }
             }
-            return Err(format!("xbps-install -S failed: {err}"));
         }
-
-        cache::mark(cache_key);
-    } else if log.verbose && !log.quiet {
-        log.exec(format!(
-            "cache hit: skip repodata sync (ttl={}s); set VX_FRESH=1 to force",
-            ttl
-        ));
     }
 
-    // 2) Dry-run update plan (always)
-    let mut cmd = Command::new("sudo");
-    cmd.arg("xbps-install");
+    // 2) Dry-run update plan; a dry run never needs root.
+    let label = if memory_sync {
+        "xbps-install -Mun"
+    } else {
+        "xbps-install -un"
+    };
+    let mut cmd = crate::rootdir::command("xbps-install");
+    cmd.stdin(Stdio::null());
+    cmd.args(&repo_args);
+    if memory_sync {
+        cmd.arg("-M");
+    }
     cmd.args(["-un"]);
     cmd.env("XBPS_COLORS", "0");
-    cmd.stdin(Stdio::inherit());
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
 
     if log.verbose && !log.quiet {
-        log.exec("sudo xbps-install -un".to_string());
+        log.exec(crate::rootdir::label(label));
     }
 
     let out = cmd
         .output()
-        .map_err(|e| format!("failed to run xbps-install -un: {e}"))?;
+        .map_err(|e| format!("failed to run {label}: {e}"))?;
 
     if !out.status.success() {
         let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
         if err.is_empty() {
             return Err(format!(
-                "xbps-install -un failed (exit={})",
+                "{label} failed (exit={})",
                 out.status.code().unwrap_or(1)
             ));
         }
-        return Err(format!("xbps-install -un failed: {err}"));
+        return Err(format!("{label} failed: {err}"));
     }
 
     let text = format!(
@@ -105,7 +351,7 @@ fn plan_system_updates_inner(
     );
     let text = parse::strip_ansi(&text);
 
-    let plan = parse::parse_xbps_sun_plan(&text, |name| query::installed_pkgver(name))?;
+    let mut plan = parse::parse_xbps_sun_plan(&text, |name| query::installed_pkgver(name))?;
 
     if plan.is_empty()
         && (text.contains("Name")
@@ -119,6 +365,37 @@ fn plan_system_updates_inner(
         );
     }
 
+    fill_size_deltas(&mut plan);
+    // Another root has its own plan; only the running system's is kept, and
+    // nothing is while caching is off.
+    if crate::rootdir::current().is_none() && !cache::force_fresh() {
+        cache::mark_with(UPDATE_PLAN_KEY, cache::repodata_ttl_secs(), &plan);
+    }
     Ok(plan)
 }
 
+/// `vx up --check [--list]` — exit 0 when up to date, 100 when updates are available.
+pub fn check(log: &Log, cfg: Option<&Config>, list: bool, opts: &UpOptions) -> ExitCode {
+    let mut plan = match plan_system_updates_check(log, cfg, &opts.repositories) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+    apply_ignores(&mut plan, &opts.ignore);
+
+    if list {
+        for u in &plan {
+            println!("{} {}->{}", u.name, u.from, u.to);
+        }
+    } else {
+        println!("{}", plan.len());
+    }
+
+    if plan.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(UPDATES_AVAILABLE)
+    }
+}
@@ -1,10 +1,27 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use crate::{config::Config, log::Log};
-use std::process::{Command, ExitCode, Stdio};
+use crate::{cache, config::Config, core::version, log::Log};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    io::{self, IsTerminal},
+    process::{Command, ExitCode, Stdio},
+};
 
-pub fn search(log: &Log, _cfg: Option<&Config>, installed: bool, term: &[String]) -> ExitCode {
+use super::{
+    parse::{self, InstalledPkg},
+    pick, repodata, repository_args, virtualpkg, ListOptions,
+};
+
+pub fn search(
+    log: &Log,
+    _cfg: Option<&Config>,
+    installed: bool,
+    term: &[String],
+    repos: &[String],
+    names_only: bool,
+) -> ExitCode {
     if term.is_empty() {
         log.error("usage: vx search <term>");
         return ExitCode::from(2);
@@ -12,15 +29,261 @@ pub fn search(log: &Log, _cfg: Option<&Config>, installed: bool, term: &[String]
 
     let needle = term.join(" ");
     let opt = if installed { "-s" } else { "-Rs" };
-    run_query_cmd(log, "xbps-query", &[opt, &needle])
+    let repo_args = repository_args(repos);
+    let mut args: Vec<&str> = repo_args.iter().map(String::as_str).collect();
+    args.extend([opt, &needle]);
+
+    let native = if installed || !repos.is_empty() {
+        None
+    } else {
+        native_indexes(log)
+    };
+
+    let mut hits = if let Some(indexes) = &native {
+        repodata::search(indexes, &needle, &installed_snapshot().unwrap_or_default())
+    } else {
+        if log.verbose && !log.quiet {
+            log.exec(crate::rootdir::label(&format!("xbps-query {}", args.join(" "))));
+        }
+        match query_text(&args) {
+            Ok(text) => parse::parse_search_results(&text),
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        }
+    };
+    if installed {
+        for h in &mut hits {
+            h.installed = true;
+        }
+    }
+
+    if log.json {
+        log.emit_json(&hits);
+        return ExitCode::SUCCESS;
+    }
+
+    if hits.is_empty() {
+        let providers = if installed {
+            Vec::new()
+        } else {
+            virtualpkg::providers(&needle, repos)
+        };
+        if providers.is_empty() {
+            log.info(format!("no packages matching '{needle}'"));
+        } else {
+            virtualpkg::print_providers(&needle, &providers);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if names_only {
+        for h in &hits {
+            println!("{}", h.name);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let width = if io::stdout().is_terminal() {
+        pick::term_size().map(|(_, cols)| cols)
+    } else {
+        None
+    };
+    for line in format_search_rows(&hits, width) {
+        println!("{line}");
+    }
+    ExitCode::SUCCESS
+}
+
+/// `name  version  installed  description`, aligned; descriptions are cut to `width`.
+fn format_search_rows(hits: &[parse::SearchHit], width: Option<usize>) -> Vec<String> {
+    let version = |h: &parse::SearchHit| h.pkgver[h.name.len() + 1..].to_string();
+    let nw = hits.iter().map(|h| h.name.len()).max().unwrap_or(0);
+    let vw = hits.iter().map(|h| version(h).len()).max().unwrap_or(0);
+    let any_installed = hits.iter().any(|h| h.installed);
+
+    hits.iter()
+        .map(|h| {
+            let mut line = format!("{:nw$}  {:vw$}  ", h.name, version(h));
+            if any_installed {
+                line.push_str(if h.installed { "installed  " } else { "           " });
+            }
+            let room = width.map(|w| w.saturating_sub(line.chars().count()));
+            match room {
+                Some(r) if h.desc.chars().count() > r => {
+                    let cut: String = h.desc.chars().take(r.saturating_sub(1)).collect();
+                    line.push_str(&cut);
+                    line.push('…');
+                }
+                _ => line.push_str(&h.desc),
+            }
+            line.trim_end().to_string()
+        })
+        .collect()
 }
 
-pub fn info(log: &Log, _cfg: Option<&Config>, pkg: &str) -> ExitCode {
+/// Synced repodata of the configured repositories, read directly; None
+/// (use xbps-query) when it can't be read.
+fn native_indexes(log: &Log) -> Option<Vec<repodata::RepoIndex>> {
+    match repodata::load_indexes() {
+        Ok(v) => {
+            if log.verbose && !log.quiet {
+                log.exec(format!("reading repodata directly ({} repositories)", v.len()));
+            }
+            Some(v)
+        }
+        Err(e) => {
+            if log.verbose && !log.quiet {
+                log.exec(format!("repodata unreadable, using xbps-query: {e}"));
+            }
+            None
+        }
+    }
+}
+
+/// Raw `xbps-query -Rs <term>` output, for callers that parse it.
+pub fn search_text(term: &[String], repos: &[String]) -> Result<String, String> {
+    let needle = term.join(" ");
+    let repo_args = repository_args(repos);
+    let mut args: Vec<&str> = repo_args.iter().map(String::as_str).collect();
+    args.extend(["-Rs", &needle]);
+    query_text(&args)
+}
+
+pub fn info(log: &Log, cfg: Option<&Config>, pkg: &str, repos: &[String], raw: bool) -> ExitCode {
     if pkg.trim().is_empty() {
         log.error("usage: vx info <pkg>");
         return ExitCode::from(2);
     }
-    run_query_cmd(log, "xbps-query", &["-R", pkg])
+    let repo_args = repository_args(repos);
+    let mut args: Vec<&str> = repo_args.iter().map(String::as_str).collect();
+    args.extend(["-R", pkg]);
+
+    if raw && !log.json {
+        return run_query_cmd(log, "xbps-query", &args);
+    }
+
+    let native = if repos.is_empty() {
+        native_indexes(log).and_then(|idx| repodata::info_props(&idx, pkg))
+    } else {
+        None
+    };
+
+    let props = match native {
+        Some(props) => props,
+        None => match query_text(&args) {
+            Ok(text) => parse::parse_pkg_info(&text),
+            Err(e) => {
+                if let Some(c) = virtual_info(log, cfg, pkg, repos, raw) {
+                    return c;
+                }
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        },
+    };
+
+    if props.is_empty()
+        && let Some(c) = virtual_info(log, cfg, pkg, repos, raw)
+    {
+        return c;
+    }
+
+    if log.json {
+        log.emit_json(&props);
+        return ExitCode::SUCCESS;
+    }
+
+    let Some(info) = parse::PkgInfo::from_props(&props) else {
+        log.error(format!("package {pkg} not found in repositories"));
+        return ExitCode::from(1);
+    };
+    let installed = installed_pkgver(&info.name).ok().flatten();
+    print_info(&info, installed.as_deref());
+    ExitCode::SUCCESS
+}
+
+/// `vx info <virtual>`: list the providers and offer to show one.
+/// None when `pkg` isn't a virtual package either.
+fn virtual_info(
+    log: &Log,
+    cfg: Option<&Config>,
+    pkg: &str,
+    repos: &[String],
+    raw: bool,
+) -> Option<ExitCode> {
+    let list = virtualpkg::providers(pkg, repos);
+    if list.is_empty() {
+        return None;
+    }
+    if log.json {
+        log.emit_json(&serde_json::json!({ "virtual": pkg, "providers": list }));
+        return Some(ExitCode::SUCCESS);
+    }
+
+    virtualpkg::print_providers(pkg, &list);
+    match virtualpkg::choose(&list) {
+        Some(p) => {
+            println!();
+            Some(info(log, cfg, &p.name, repos, raw))
+        }
+        None => Some(ExitCode::SUCCESS),
+    }
+}
+
+fn print_info(info: &parse::PkgInfo, installed: Option<&str>) {
+    let color = io::stdout().is_terminal();
+    let paint = |code: &str, s: &str| {
+        if color {
+            format!("\x1b[{code}m{s}\x1b[0m")
+        } else {
+            s.to_string()
+        }
+    };
+
+    let repo_pkgver = format!("{}-{}", info.name, info.version);
+    let state = match installed.map(|v| (v, version::cmpver(&repo_pkgver, v))) {
+        None => paint("2", "not installed"),
+        Some((_, Ordering::Equal)) => paint("32", "installed"),
+        Some((v, Ordering::Greater)) => paint("33", &format!("installed: {v}, update available")),
+        Some((v, Ordering::Less)) => paint("33", &format!("installed: {v}, newer than the repository")),
+    };
+    println!("{} {}  ({state})", paint("1", &info.name), info.version);
+    if !info.short_desc.is_empty() {
+        println!("  {}", info.short_desc);
+    }
+
+    let field = |label: &str, value: &str| println!("  {}{value}", paint("36", &format!("{label:<12}")));
+    if let Some(v) = &info.homepage {
+        field("homepage", v);
+    }
+    if let Some(v) = &info.license {
+        field("license", v);
+    }
+    if let Some(v) = &info.maintainer {
+        field("maintainer", v);
+    }
+    if let Some(v) = &info.repository {
+        field("repository", v);
+    }
+    if let Some(v) = &info.architecture {
+        field("arch", v);
+    }
+
+    let mut size = Vec::new();
+    if let Some(n) = info.installed_size {
+        size.push(format!("{} installed", parse::format_size(n)));
+    }
+    if let Some(n) = info.download_size {
+        size.push(format!("{} download", parse::format_size(n)));
+    }
+    if !size.is_empty() {
+        field("size", &size.join(", "));
+    }
+    if !info.run_depends.is_empty() {
+        field("depends", &info.run_depends.join(", "));
+    }
 }
 
 pub fn files(log: &Log, _cfg: Option<&Config>, pkg: &str) -> ExitCode {
@@ -40,22 +303,186 @@ pub fn owns(log: &Log, _cfg: Option<&Config>, path: &str) -> ExitCode {
     run_query_cmd(log, "xbps-query", &["-o", path])
 }
 
-/// `vx list [term]`
-pub fn list(log: &Log, _cfg: Option<&Config>, term: Option<&str>) -> ExitCode {
-    let mut args: Vec<&str> = vec!["-l"];
-    if let Some(t) = term {
-        let t = t.trim();
-        if !t.is_empty() {
+/// xlocate's index is a large git repo; refresh it at most once a day.
+const XLOCATE_TTL_SECS: u64 = 24 * 60 * 60;
+const XLOCATE_CACHE_KEY: &str = "xlocate.index";
+
+/// `vx owns --remote <path>` — which repository package ships a path (xlocate).
+pub fn owns_remote(log: &Log, _cfg: Option<&Config>, path: &str) -> ExitCode {
+    if path.trim().is_empty() {
+        log.error("usage: vx owns --remote <path>");
+        return ExitCode::from(2);
+    }
+
+    if !cache::is_fresh(XLOCATE_CACHE_KEY, XLOCATE_TTL_SECS) {
+        if log.verbose && !log.quiet {
+            log.exec("xlocate -S");
+        }
+        let status = Command::new("xlocate")
+            .arg("-S")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .status();
+        match status {
+            Ok(s) if s.success() => cache::mark(XLOCATE_CACHE_KEY, XLOCATE_TTL_SECS),
+            Ok(s) => {
+                log.warn(format!(
+                    "xlocate -S failed (exit={}); searching the existing index",
+                    s.code().unwrap_or(1)
+                ));
+            }
+            Err(e) => {
+                log.error(format!("failed to run xlocate: {e} (it ships with xtools)"));
+                return ExitCode::from(1);
+            }
+        }
+    }
+
+    if log.verbose && !log.quiet {
+        log.exec(format!("xlocate {path}"));
+    }
+    let out = match Command::new("xlocate")
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .output()
+    {
+        Ok(o) => o,
+        Err(e) => {
+            log.error(format!("failed to run xlocate: {e} (it ships with xtools)"));
+            return ExitCode::from(1);
+        }
+    };
+
+    // xlocate prints `<pkgver>\t<path>`; it exits non-zero when nothing matches.
+    let text = String::from_utf8_lossy(&out.stdout);
+    let rows: Vec<(&str, &str)> = text
+        .lines()
+        .filter_map(|l| l.split_once('\t'))
+        .collect();
+    if rows.is_empty() {
+        log.info(format!("no repository package ships {path}"));
+        return ExitCode::from(1);
+    }
+
+    let w = rows.iter().map(|(p, _)| p.len()).max().unwrap_or(0);
+    for (pkgver, file) in rows {
+        println!("{pkgver:w$}  {file}");
+    }
+    ExitCode::SUCCESS
+}
+
+/// `vx list [term] [--manual|--auto] [--hold] [--repo NAME] [--orphans]`
+///
+/// Without filters this is plain `xbps-query -l`; with filters the list is parsed
+/// and narrowed using the manual (-m), hold (-H) and orphan (-O) sets.
+pub fn list(log: &Log, _cfg: Option<&Config>, term: Option<&str>, opts: &ListOptions) -> ExitCode {
+    let term = term.map(str::trim).filter(|t| !t.is_empty());
+
+    if !opts.is_filtered() {
+        let mut args: Vec<&str> = vec!["-l"];
+        if let Some(t) = term {
             args.push(t);
         }
+        return run_query_cmd(log, "xbps-query", &args);
     }
-    run_query_cmd(log, "xbps-query", &args)
+
+    match filtered_list(term, opts) {
+        Ok(rows) => {
+            let w = rows.iter().map(|r| r.pkgver.len()).max().unwrap_or(0);
+            for r in &rows {
+                println!("{} {:<w$} {}", r.state, r.pkgver, r.desc);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log.error(e);
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn filtered_list(term: Option<&str>, opts: &ListOptions) -> Result<Vec<InstalledPkg>, String> {
+    let mut rows = parse::parse_installed_list(&query_lines(&["-l"])?.join("\n"));
+
+    if let Some(t) = term {
+        let t = t.to_ascii_lowercase();
+        rows.retain(|r| r.name.to_ascii_lowercase().contains(&t));
+    }
+
+    if opts.manual || opts.auto {
+        let manual = name_set(query_lines(&["-m"])?);
+        rows.retain(|r| manual.contains(&r.name) == opts.manual);
+    }
+
+    if opts.hold {
+        let held = name_set(query_lines(&["-H"])?);
+        rows.retain(|r| held.contains(&r.name));
+    }
+
+    if opts.orphans {
+        let orphans = name_set(orphan_pkgvers()?);
+        rows.retain(|r| orphans.contains(&r.name));
+    }
+
+    if let Some(repo) = opts.repo.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        let mut kept = Vec::new();
+        for r in rows {
+            if let Some(url) = installed_property(&r.name, "repository")?
+                && url.contains(repo)
+            {
+                kept.push(r);
+            }
+        }
+        rows = kept;
+    }
+
+    Ok(rows)
+}
+
+/// Installed packages as `name -> pkgver`.
+pub fn installed_snapshot() -> Result<BTreeMap<String, String>, String> {
+    Ok(parse::parse_installed_list(&query_lines(&["-l"])?.join("\n"))
+        .into_iter()
+        .map(|r| (r.name, r.pkgver))
+        .collect())
+}
+
+/// Every package name in the configured repositories (`xbps-query -Rs ""`).
+pub fn available_pkgnames() -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = parse::parse_search_results(&query_text(&["-Rs", ""])?)
+        .into_iter()
+        .map(|h| h.name)
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+pub(super) fn name_set(pkgvers: Vec<String>) -> BTreeSet<String> {
+    pkgvers
+        .iter()
+        .filter_map(|pv| parse::pkgname_from_pkgver(pv))
+        .collect()
 }
 
 pub fn installed_pkgver(pkg: &str) -> Result<Option<String>, String> {
-    let out = Command::new("xbps-query")
+    installed_property(pkg, "pkgver")
+}
+
+/// `prop` of every installed package that has it, by name, from a single
+/// `xbps-query -p <prop> -s ""`.
+pub fn installed_properties(prop: &str) -> Result<BTreeMap<String, String>, String> {
+    Ok(parse::parse_property_rows(&query_text(&["-p", prop, "-s", ""])?, prop))
+}
+
+/// `xbps-query -p <prop> <pkg>` for an installed package; None if not installed/unset.
+pub fn installed_property(pkg: &str, prop: &str) -> Result<Option<String>, String> {
+    let out = crate::rootdir::command("xbps-query")
         .arg("-p")
-        .arg("pkgver")
+        .arg(prop)
         .arg(pkg)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -75,8 +502,191 @@ pub fn installed_pkgver(pkg: &str) -> Result<Option<String>, String> {
     }
 }
 
+/// `vx size [pkg...] [--top N]` — installed size per package, largest first.
+pub fn size(log: &Log, _cfg: Option<&Config>, pkgs: &[String], top: Option<usize>) -> ExitCode {
+    let targets: Vec<String> = if pkgs.is_empty() {
+        match installed_snapshot() {
+            Ok(m) => m.into_values().collect(),
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        }
+    } else {
+        pkgs.to_vec()
+    };
+
+    let mut sizes: Vec<(String, u64)> = Vec::new();
+    for pkg in &targets {
+        let size = match installed_property(pkg, "installed_size") {
+            Ok(Some(s)) => parse::parse_human_size(&s),
+            Ok(None) => None,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        };
+        match size {
+            Some(b) => {
+                let pkgver = if pkgs.is_empty() {
+                    pkg.clone()
+                } else {
+                    installed_pkgver(pkg).ok().flatten().unwrap_or_else(|| pkg.clone())
+                };
+                sizes.push((pkgver, b));
+            }
+            None if !pkgs.is_empty() => log.warn(format!("{pkg} is not installed")),
+            None => {}
+        }
+    }
+
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let total: u64 = sizes.iter().map(|(_, b)| b).sum();
+    let count = sizes.len();
+    if let Some(n) = top {
+        sizes.truncate(n);
+    }
+
+    for (pkgver, b) in &sizes {
+        println!("{:>9}  {pkgver}", parse::format_size(*b));
+    }
+    println!("{:>9}  total ({count} packages)", parse::format_size(total));
+
+    ExitCode::SUCCESS
+}
+
+/// Does an installed `pkgver` satisfy an xbps pattern like `firefox>=120`?
+pub fn pkgver_matches(pkgver: &str, pattern: &str) -> Result<bool, String> {
+    let status = Command::new("xbps-uhelper")
+        .args(["pkgmatch", pkgver, pattern])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("failed to run xbps-uhelper pkgmatch: {e}"))?;
+
+    // pkgmatch exits 1 on match, 0 on mismatch.
+    match status.code() {
+        Some(1) => Ok(true),
+        Some(0) => Ok(false),
+        _ => Err(format!("xbps-uhelper pkgmatch {pkgver} '{pattern}' failed")),
+    }
+}
+
+/// `vx rdeps <pkg>` — what depends on this package (xbps-query -X / -RX).
+pub fn rdeps(log: &Log, _cfg: Option<&Config>, pkg: &str, remote: bool) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx rdeps <pkg>");
+        return ExitCode::from(2);
+    }
+
+    let deps = match revdeps(pkg, remote) {
+        Ok(v) => v,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if deps.is_empty() {
+        log.info(format!("vx: nothing depends on {pkg}."));
+        return ExitCode::SUCCESS;
+    }
+
+    if !log.quiet {
+        let scope = if remote { "repository" } else { "installed" };
+        println!("{scope} reverse dependencies of {pkg} ({}):", deps.len());
+    }
+    for d in &deps {
+        if log.quiet {
+            println!("{d}");
+        } else {
+            println!("  {d}");
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Reverse dependencies of `pkg` as pkgvers; `remote` queries repositories instead of pkgdb.
+pub fn revdeps(pkg: &str, remote: bool) -> Result<Vec<String>, String> {
+    let opt = if remote { "-RX" } else { "-X" };
+    let out = crate::rootdir::command("xbps-query")
+        .arg(opt)
+        .arg(pkg)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("failed to run xbps-query {opt}: {e}"))?;
+
+    let text = String::from_utf8_lossy(&out.stdout);
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        // xbps-query exits non-zero with no output when there is nothing to report.
+        if err.is_empty() && text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        if err.is_empty() {
+            return Err(format!(
+                "xbps-query {opt} {pkg} failed (exit={})",
+                out.status.code().unwrap_or(1)
+            ));
+        }
+        return Err(format!("xbps-query {opt} {pkg} failed: {err}"));
+    }
+
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Orphaned packages as reported by `xbps-query -O` (pkgver per line).
+pub fn orphan_pkgvers() -> Result<Vec<String>, String> {
+    query_lines(&["-O"])
+}
+
+/// Run `xbps-query <args>` and return its non-empty stdout lines.
+pub(super) fn query_lines(args: &[&str]) -> Result<Vec<String>, String> {
+    Ok(query_text(args)?
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Run `xbps-query <args>` and return its stdout.
+fn query_text(args: &[&str]) -> Result<String, String> {
+    let label = format!("xbps-query {}", args.join(" "));
+    let out = crate::rootdir::command("xbps-query")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("failed to run {label}: {e}"))?;
+
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        if err.is_empty() {
+            return Err(format!(
+                "{label} failed (exit={})",
+                out.status.code().unwrap_or(1)
+            ));
+        }
+        return Err(format!("{label} failed: {err}"));
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
 fn run_query_cmd(log: &Log, tool: &str, args: &[&str]) -> ExitCode {
-    let mut cmd = Command::new(tool);
+    let mut cmd = crate::rootdir::command(tool);
     cmd.args(args);
     cmd.stdin(Stdio::null());
     cmd.stdout(Stdio::inherit());
@@ -89,7 +699,7 @@ fn run_query_cmd(log: &Log, tool: &str, args: &[&str]) -> ExitCode {
             s.push(' ');
             s.push_str(a);
         }
-        log.exec(s);
+        log.exec(crate::rootdir::label(&s));
     }
 
     match cmd.status() {
@@ -100,3 +710,24 @@ fn run_query_cmd(log: &Log, tool: &str, args: &[&str]) -> ExitCode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::format_search_rows;
+    use crate::core::xbps::parse::parse_search_results;
+
+    #[test]
+    fn search_rows_align_and_fit_width() {
+        let hits = parse_search_results(
+            "[*] ripgrep-14.1.0_1      Fast line-oriented search tool\n\
+             [-] ripgrep-all-0.10.6_1  Ripgrep, but also search in PDFs\n",
+        );
+        let rows = format_search_rows(&hits, None);
+        assert_eq!(rows[0], "ripgrep      14.1.0_1  installed  Fast line-oriented search tool");
+        assert_eq!(rows[1], "ripgrep-all  0.10.6_1             Ripgrep, but also search in PDFs");
+
+        let rows = format_search_rows(&hits, Some(40));
+        assert_eq!(rows[0].chars().count(), 40);
+        assert!(rows[0].ends_with('…'));
+    }
+}
@@ -0,0 +1,425 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    cache,
+    core::repo::{conf, repodata_path, xbps_arch},
+    rootdir,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+    time::UNIX_EPOCH,
+};
+
+use super::parse::{self, InfoValue, SearchHit};
+
+/// A property list value (the XML form xbps writes).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Plist {
+    Dict(BTreeMap<String, Plist>),
+    Array(Vec<Plist>),
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    /// base64, whitespace removed
+    Data(String),
+}
+
+impl Plist {
+    pub fn get(&self, key: &str) -> Option<&Plist> {
+        match self {
+            Plist::Dict(d) => d.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Plist::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn unescape(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let ent = &rest[1..end];
+        let c = match ent {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => ent
+                .strip_prefix("#x")
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .or_else(|| ent.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+struct Reader<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Next tag without the angle brackets (`dict`, `/dict`, `true/`); skips text.
+    fn next_tag(&mut self) -> Result<&'a str, String> {
+        let start = self.pos + self.s[self.pos..].find('<').ok_or("plist: unexpected end")?;
+        let end = start + self.s[start..].find('>').ok_or("plist: unterminated tag")?;
+        self.pos = end + 1;
+        Ok(self.s[start + 1..end].trim())
+    }
+
+    /// Text up to `</tag>`, consuming the closing tag.
+    fn text(&mut self, tag: &str) -> Result<&'a str, String> {
+        let close = format!("</{tag}>");
+        let end = self.pos
+            + self.s[self.pos..]
+                .find(&close)
+                .ok_or_else(|| format!("plist: missing {close}"))?;
+        let t = &self.s[self.pos..end];
+        self.pos = end + close.len();
+        Ok(t)
+    }
+
+    fn value(&mut self, tag: &str) -> Result<Plist, String> {
+        let tag = tag.trim_end_matches('/').trim_end();
+        let empty = self.s[..self.pos].ends_with("/>");
+        match (tag, empty) {
+            ("dict", true) => Ok(Plist::Dict(BTreeMap::new())),
+            ("array", true) => Ok(Plist::Array(Vec::new())),
+            ("string", true) => Ok(Plist::String(String::new())),
+            ("true", _) => Ok(Plist::Bool(true)),
+            ("false", _) => Ok(Plist::Bool(false)),
+            ("dict", false) => {
+                let mut d = BTreeMap::new();
+                loop {
+                    match self.next_tag()? {
+                        "/dict" => return Ok(Plist::Dict(d)),
+                        "key" => {
+                            let key = unescape(self.text("key")?);
+                            let t = self.next_tag()?;
+                            d.insert(key, self.value(t)?);
+                        }
+                        t => return Err(format!("plist: unexpected <{t}> in dict")),
+                    }
+                }
+            }
+            ("array", false) => {
+                let mut a = Vec::new();
+                loop {
+                    match self.next_tag()? {
+                        "/array" => return Ok(Plist::Array(a)),
+                        t => a.push(self.value(t)?),
+                    }
+                }
+            }
+            ("string", false) => Ok(Plist::String(unescape(self.text("string")?))),
+            ("integer", false) => {
+                let t = self.text("integer")?.trim();
+                let n = match t.strip_prefix("0x") {
+                    Some(h) => i64::from_str_radix(h, 16).ok(),
+                    None => t.parse().ok(),
+                };
+                n.map(Plist::Integer).ok_or_else(|| format!("plist: bad integer {t}"))
+            }
+            ("data", false) => Ok(Plist::Data(
+                self.text("data")?.split_whitespace().collect(),
+            )),
+            ("real" | "date", false) => Ok(Plist::String(self.text(tag)?.trim().to_string())),
+            (t, _) => Err(format!("plist: unsupported <{t}>")),
+        }
+    }
+}
+
+/// Parse an XML property list (`index.plist`, `pkgdb-0.38.plist`).
+pub fn parse_plist(xml: &str) -> Result<Plist, String> {
+    let mut r = Reader { s: xml, pos: 0 };
+    loop {
+        let t = r.next_tag()?;
+        if t.starts_with('?') || t.starts_with('!') {
+            continue;
+        }
+        if t == "plist" || t.starts_with("plist ") {
+            let t = r.next_tag()?;
+            return r.value(t);
+        }
+        // Bare value without the <plist> wrapper.
+        return r.value(t);
+    }
+}
+
+/// `index.plist` from a repodata archive. xbps compresses these (zstd by
+/// default); tar picks the decompressor.
+fn read_index(path: &Path) -> Result<String, String> {
+    let out = Command::new("tar")
+        .arg("-xOf")
+        .arg(path)
+        .arg("index.plist")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("failed to run tar: {e}"))?;
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr).trim().to_string();
+        return Err(format!("failed to read {}: {err}", path.display()));
+    }
+    String::from_utf8(out.stdout).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// `pkgname -> pkgver` of a local repository directory (`<dir>/<arch>-repodata`).
+pub fn local_repo_pkgvers(dir: &Path, arch: &str) -> Result<BTreeMap<String, String>, String> {
+    let path = dir.join(format!("{arch}-repodata"));
+    match parse_plist(&read_index(&path)?)? {
+        Plist::Dict(pkgs) => Ok(pkgs
+            .into_iter()
+            .filter_map(|(name, p)| Some((name, p.get("pkgver")?.as_str()?.to_string())))
+            .collect()),
+        _ => Err(format!("{}: index is not a dictionary", path.display())),
+    }
+}
+
+/// A parsed index as cached in ~/.cache/vx, with the size and mtime of the
+/// repodata file it was read from.
+#[derive(Serialize, Deserialize)]
+struct CachedIndex {
+    size: u64,
+    mtime: u64,
+    pkgs: BTreeMap<String, Plist>,
+}
+
+/// `pkgname -> package dict` of a repodata file. Unpacking and parsing an
+/// index takes a while, so the result is cached until the file changes
+/// (a sync replaces it); `--fresh` reads the file itself.
+fn read_index_cached(path: &Path) -> Result<BTreeMap<String, Plist>, String> {
+    let meta = fs::metadata(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    let name = format!("repodata{}.json", path.display().to_string().replace('/', "_"));
+
+    if !cache::force_fresh()
+        && let Ok(text) = fs::read_to_string(cache::data_path(&name))
+        && let Ok(c) = serde_json::from_str::<CachedIndex>(&text)
+        && (c.size, c.mtime) == (meta.len(), mtime)
+    {
+        return Ok(c.pkgs);
+    }
+
+    let pkgs = match parse_plist(&read_index(path)?)? {
+        Plist::Dict(pkgs) => pkgs,
+        _ => return Err(format!("{}: index is not a dictionary", path.display())),
+    };
+    if !cache::force_fresh() {
+        let c = CachedIndex {
+            size: meta.len(),
+            mtime,
+            pkgs,
+        };
+        if let Ok(text) = serde_json::to_string(&c) {
+            let _ = cache::write_data(&name, &text);
+        }
+        return Ok(c.pkgs);
+    }
+    Ok(pkgs)
+}
+
+/// One synced repository: its URL and `pkgname -> package dict`.
+#[derive(Debug, Clone)]
+pub struct RepoIndex {
+    pub url: String,
+    pub pkgs: BTreeMap<String, Plist>,
+}
+
+/// Indexes of the enabled repositories, in xbps.d order. Repositories that
+/// were never synced are skipped; Err when none could be read.
+pub fn load_indexes() -> Result<Vec<RepoIndex>, String> {
+    let etc = rootdir::path(conf::ETC_XBPS_D);
+    let share = rootdir::path(conf::SHARE_XBPS_D);
+    let arch = xbps_arch();
+
+    let mut seen = BTreeSet::new();
+    let mut out = Vec::new();
+    let mut last_err = None;
+    // The xbps.mirror repositories come first, as they do for xbps.
+    let mirrored = rootdir::repositories().iter().map(|u| (u.clone(), true));
+    let configured = conf::load_all(&etc, &share).into_iter().map(|e| (e.url, e.enabled));
+    for (url, enabled) in mirrored.chain(configured) {
+        if !enabled || !seen.insert(url.clone()) {
+            continue;
+        }
+        let path = repodata_path(&url, &arch);
+        if !path.exists() {
+            continue;
+        }
+        match read_index_cached(&path) {
+            Ok(pkgs) => out.push(RepoIndex { url, pkgs }),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    if out.is_empty() {
+        return Err(last_err.unwrap_or_else(|| "no synced repodata found".to_string()));
+    }
+    Ok(out)
+}
+
+/// `xbps-query -Rs` over the indexes: case-insensitive substring match on
+/// pkgver and short_desc; the first repository providing a name wins.
+pub fn search(indexes: &[RepoIndex], needle: &str, installed: &BTreeMap<String, String>) -> Vec<SearchHit> {
+    let needle = needle.to_ascii_lowercase();
+    let mut seen = BTreeSet::new();
+    let mut out = Vec::new();
+    for idx in indexes {
+        for (name, pkg) in &idx.pkgs {
+            let pkgver = pkg.get("pkgver").and_then(Plist::as_str).unwrap_or_default();
+            let desc = pkg.get("short_desc").and_then(Plist::as_str).unwrap_or_default();
+            if pkgver.is_empty()
+                || !(pkgver.to_ascii_lowercase().contains(&needle)
+                    || desc.to_ascii_lowercase().contains(&needle))
+                || !seen.insert(name.clone())
+            {
+                continue;
+            }
+            out.push(SearchHit {
+                name: name.clone(),
+                pkgver: pkgver.to_string(),
+                installed: installed.get(name).is_some_and(|v| v == pkgver),
+                desc: desc.to_string(),
+            });
+        }
+    }
+    out.sort_by(|a, b| a.pkgver.cmp(&b.pkgver));
+    out
+}
+
+/// `xbps-query -R <pkg>` properties for `name` from the first repository
+/// that has it, shaped like `parse::parse_pkg_info`.
+pub fn info_props(indexes: &[RepoIndex], name: &str) -> Option<BTreeMap<String, InfoValue>> {
+    let (idx, pkg) = indexes.iter().find_map(|i| Some((i, i.pkgs.get(name)?)))?;
+    let Plist::Dict(d) = pkg else {
+        return None;
+    };
+
+    let mut out = BTreeMap::new();
+    for (k, v) in d {
+        let v = match v {
+            Plist::String(s) => InfoValue::One(s.clone()),
+            Plist::Integer(n) if k.ends_with("size") => {
+                InfoValue::One(parse::format_size((*n).max(0) as u64))
+            }
+            Plist::Integer(n) => InfoValue::One(n.to_string()),
+            Plist::Bool(b) => InfoValue::One(if *b { "yes" } else { "no" }.to_string()),
+            Plist::Array(a) => InfoValue::Many(
+                a.iter().filter_map(Plist::as_str).map(str::to_string).collect(),
+            ),
+            Plist::Dict(_) | Plist::Data(_) => continue,
+        };
+        out.insert(k.clone(), v);
+    }
+    out.insert("repository".to_string(), InfoValue::One(idx.url.clone()));
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{info_props, parse_plist, search, Plist, RepoIndex};
+    use crate::core::xbps::parse::{InfoValue, PkgInfo};
+    use std::collections::BTreeMap;
+
+    const INDEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>ncurses</key>
+	<dict>
+		<key>architecture</key>
+		<string>x86_64</string>
+		<key>installed_size</key>
+		<integer>1048576</integer>
+		<key>pkgver</key>
+		<string>ncurses-6.5_1</string>
+		<key>preserve</key>
+		<true/>
+		<key>run_depends</key>
+		<array>
+			<string>glibc&gt;=2.39_1</string>
+		</array>
+		<key>short_desc</key>
+		<string>System V Release 4.0 curses emulation library</string>
+	</dict>
+	<key>xterm</key>
+	<dict>
+		<key>pkgver</key>
+		<string>xterm-393_1</string>
+		<key>short_desc</key>
+		<string>Terminal emulator &amp; curses fan</string>
+		<key>conf_files</key>
+		<array/>
+	</dict>
+</dict>
+</plist>"#;
+
+    #[test]
+    fn index_plist_feeds_search_and_info() {
+        let Plist::Dict(pkgs) = parse_plist(INDEX).unwrap() else {
+            panic!("index is not a dict");
+        };
+        let idx = vec![RepoIndex {
+            url: "https://repo-default.voidlinux.org/current".to_string(),
+            pkgs,
+        }];
+
+        let installed = BTreeMap::from([("ncurses".to_string(), "ncurses-6.5_1".to_string())]);
+        let hits = search(&idx, "CURSES", &installed);
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].installed);
+        assert_eq!(hits[1].desc, "Terminal emulator & curses fan");
+
+        let props = info_props(&idx, "ncurses").unwrap();
+        assert_eq!(props.get("preserve"), Some(&InfoValue::One("yes".to_string())));
+        let info = PkgInfo::from_props(&props).unwrap();
+        assert_eq!(info.version, "6.5_1");
+        assert_eq!(info.installed_size, Some(1048576));
+        assert_eq!(info.run_depends, ["glibc>=2.39_1"]);
+        assert_eq!(info.repository.as_deref(), Some("https://repo-default.voidlinux.org/current"));
+    }
+
+    #[test]
+    fn parsed_index_survives_the_cache() {
+        let index = parse_plist(INDEX).unwrap();
+        let text = serde_json::to_string(&index).unwrap();
+        assert_eq!(serde_json::from_str::<Plist>(&text).unwrap(), index);
+    }
+}
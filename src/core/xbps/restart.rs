@@ -0,0 +1,225 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::log::Log;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    process::ExitCode,
+};
+
+/// Deleted files under these prefixes are replaced binaries/libraries;
+/// anything else (shm, memfd, tmp files) is not something an update touched.
+const SYSTEM_PREFIXES: [&str; 6] = ["/usr/", "/lib", "/bin/", "/sbin/", "/opt/", "/etc/"];
+
+/// runit's service directory (Void's default).
+const SERVICE_DIR: &str = "/var/service";
+
+#[derive(Debug, Serialize)]
+pub struct StaleProcess {
+    pub pid: u32,
+    pub comm: String,
+    /// runit service the process runs under, if any.
+    pub service: Option<String>,
+    pub files: Vec<String>,
+}
+
+/// Deleted system files mapped in a `/proc/<pid>/maps` dump.
+pub fn deleted_files(maps: &str) -> BTreeSet<String> {
+    maps.lines()
+        .filter_map(|l| {
+            let path = l.split_once('/').map(|(_, p)| format!("/{p}"))?;
+            let path = path.strip_suffix(" (deleted)")?;
+            SYSTEM_PREFIXES
+                .iter()
+                .any(|p| path.starts_with(p))
+                .then(|| path.to_string())
+        })
+        .collect()
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // `pid (comm) state ppid ...`; comm may contain spaces/parens.
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// pid -> service name, from runit's supervise/pid files.
+fn service_pids() -> BTreeMap<u32, String> {
+    let mut out = BTreeMap::new();
+    let Ok(rd) = fs::read_dir(SERVICE_DIR) else {
+        return out;
+    };
+    for ent in rd.flatten() {
+        let name = ent.file_name().to_string_lossy().into_owned();
+        if let Some(pid) = fs::read_to_string(ent.path().join("supervise/pid"))
+            .ok()
+            .and_then(|s| s.trim().parse::<u32>().ok())
+            && pid != 0
+        {
+            out.insert(pid, name);
+        }
+    }
+    out
+}
+
+fn service_of(pid: u32, services: &BTreeMap<u32, String>) -> Option<String> {
+    let mut cur = pid;
+    // Bounded walk: pid 1 or a read failure ends it.
+    for _ in 0..64 {
+        if let Some(s) = services.get(&cur) {
+            return Some(s.clone());
+        }
+        cur = parent_pid(cur)?;
+        if cur <= 1 {
+            return None;
+        }
+    }
+    None
+}
+
+/// Scan /proc for processes still using replaced binaries or libraries.
+/// Also returns how many processes could not be inspected (not ours, not root).
+pub fn scan() -> (Vec<StaleProcess>, usize) {
+    let services = service_pids();
+    let own = std::process::id();
+    let mut out = Vec::new();
+    let mut unreadable = 0usize;
+
+    let Ok(rd) = fs::read_dir("/proc") else {
+        return (out, 0);
+    };
+    for ent in rd.flatten() {
+        let Some(pid) = ent.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+            continue;
+        };
+        if pid == own {
+            continue;
+        }
+        let dir = Path::new("/proc").join(pid.to_string());
+
+        let maps = match fs::read_to_string(dir.join("maps")) {
+            Ok(m) => m,
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                unreadable += 1;
+                continue;
+            }
+            // Exited meanwhile, or a kernel thread.
+            Err(_) => continue,
+        };
+
+        let mut files = deleted_files(&maps);
+        if let Ok(exe) = fs::read_link(dir.join("exe")) {
+            files.extend(deleted_files(&format!(" {}", exe.display())));
+        }
+        if files.is_empty() {
+            continue;
+        }
+
+        let comm = fs::read_to_string(dir.join("comm"))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_else(|_| "?".to_string());
+        out.push(StaleProcess {
+            pid,
+            comm,
+            service: service_of(pid, &services),
+            files: files.into_iter().collect(),
+        });
+    }
+    out.sort_by_key(|p| p.pid);
+    (out, unreadable)
+}
+
+fn print_report(log: &Log, procs: &[StaleProcess], unreadable: usize) {
+    println!("processes using deleted files ({}):", procs.len());
+    for p in procs {
+        let svc = p.service.as_deref().map(|s| format!("  [service {s}]")).unwrap_or_default();
+        if log.verbose {
+            println!("  {:>7}  {}{svc}", p.pid, p.comm);
+            for f in &p.files {
+                println!("           {f}");
+            }
+        } else {
+            let more = if p.files.len() > 1 {
+                format!(" (+{} more)", p.files.len() - 1)
+            } else {
+                String::new()
+            };
+            println!("  {:>7}  {}{svc}  {}{more}", p.pid, p.comm, p.files[0]);
+        }
+    }
+
+    let services: BTreeSet<&str> = procs.iter().filter_map(|p| p.service.as_deref()).collect();
+    if !services.is_empty() {
+        let list = services.into_iter().collect::<Vec<_>>().join(" ");
+        println!("hint: restart services with: sv restart {list}");
+    }
+    if procs.iter().any(|p| p.service.is_none()) {
+        println!("hint: restart the other programs (or log out and back in) to load the new versions.");
+    }
+    if unreadable > 0 {
+        log.info(format!(
+            "note: {unreadable} process(es) could not be inspected; run as root for a complete list."
+        ));
+    }
+}
+
+/// `vx restartcheck`
+pub fn check(log: &Log) -> ExitCode {
+    if crate::rootdir::current().is_some() {
+        log.error("restartcheck inspects running processes; it does not apply to --root");
+        return ExitCode::from(2);
+    }
+
+    let (procs, unreadable) = scan();
+    if log.json {
+        log.emit_json(&serde_json::json!({ "processes": procs, "unreadable": unreadable }));
+        return ExitCode::SUCCESS;
+    }
+
+    if procs.is_empty() {
+        log.info("vx: no running processes use deleted files.");
+        if unreadable > 0 {
+            log.info(format!(
+                "note: {unreadable} process(es) could not be inspected; run as root for a complete list."
+            ));
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    print_report(log, &procs, unreadable);
+    ExitCode::SUCCESS
+}
+
+/// After `vx up`: report stale processes, silent when there are none.
+pub fn after_update(log: &Log) {
+    if log.quiet || crate::rootdir::current().is_some() {
+        return;
+    }
+    let (procs, unreadable) = scan();
+    if !procs.is_empty() {
+        print_report(log, &procs, unreadable);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::deleted_files;
+
+    #[test]
+    fn deleted_files_keeps_replaced_system_files() {
+        let maps = "\
+7f00-7f01 r-xp 00000000 fe:01 1234   /usr/lib/libssl.so.3 (deleted)
+7f01-7f02 r--p 00000000 fe:01 1235   /usr/lib/libc.so.6
+7f02-7f03 rw-s 00000000 00:05 99     /dev/shm/pulse-shm-1 (deleted)
+7f03-7f04 rw-s 00000000 00:01 98     /memfd:wayland (deleted)
+7f04-7f05 r-xp 00000000 fe:01 1236   /usr/lib/libssl.so.3 (deleted)
+7f05-7f06 r--p 00000000 fe:01 1237   /usr/share/icons/my icon.png (deleted)
+";
+        let got: Vec<_> = deleted_files(maps).into_iter().collect();
+        assert_eq!(got, ["/usr/lib/libssl.so.3", "/usr/share/icons/my icon.png"]);
+    }
+}
@@ -0,0 +1,177 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{
+    config::Config,
+    core::source::confirm_once,
+    history::{self, Txn},
+    log::Log,
+    privilege,
+};
+use std::{path::PathBuf, process::ExitCode};
+
+use super::cachedir;
+use super::install::run;
+use super::journal::with_journal;
+use super::query;
+
+/// How a journaled change maps back onto the package cache.
+#[derive(Debug, Clone, Default)]
+struct RollbackPlan {
+    /// (name, current pkgver, cached pkgver, cached file)
+    reinstall: Vec<(String, Option<String>, String, PathBuf)>,
+    /// Packages the transaction newly installed.
+    remove: Vec<String>,
+    /// (name, wanted pkgver) with no cached file.
+    missing: Vec<(String, String)>,
+}
+
+fn plan_for(txn: &Txn) -> Result<RollbackPlan, String> {
+    let cache = cachedir::cachedir(None);
+    let mut plan = RollbackPlan::default();
+
+    for c in &txn.changes {
+        let current = query::installed_pkgver(&c.name)?;
+        match &c.from {
+            Some(prev) => {
+                if current.as_deref() == Some(prev.as_str()) {
+                    continue;
+                }
+                let hit = cachedir::cached_versions(&cache, &c.name)
+                    .into_iter()
+                    .find(|p| &p.pkgver == prev);
+                match hit {
+                    Some(p) => plan.reinstall.push((c.name.clone(), current, p.pkgver, p.path)),
+                    None => plan.missing.push((c.name.clone(), prev.clone())),
+                }
+            }
+            None => {
+                if current.is_some() {
+                    plan.remove.push(c.name.clone());
+                }
+            }
+        }
+    }
+
+    Ok(plan)
+}
+
+/// `vx rollback [txn-id]` — undo a journaled transaction using cached packages.
+pub fn rollback(
+    log: &Log,
+    _cfg: Option<&Config>,
+    id: Option<u64>,
+    dry_run: bool,
+    yes: bool,
+) -> ExitCode {
+    let txn = match id {
+        Some(id) => history::find(id),
+        None => history::load().map(|v| v.into_iter().last()),
+    };
+    let txn = match txn {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            match id {
+                Some(id) => log.error(format!("no transaction {id} in history")),
+                None => log.error("no transactions recorded yet"),
+            }
+            return ExitCode::from(1);
+        }
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let plan = match plan_for(&txn) {
+        Ok(p) => p,
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    if !log.quiet {
+        println!("rollback plan for transaction {} ({}):", txn.id, txn.command);
+        if !plan.reinstall.is_empty() {
+            println!("  reinstall ({}):", plan.reinstall.len());
+            for (name, cur, to, _) in &plan.reinstall {
+                let cur = cur.as_deref().unwrap_or("(not installed)");
+                println!("    {name}  {cur} → {to}");
+            }
+        }
+        if !plan.remove.is_empty() {
+            println!("  remove ({}):", plan.remove.len());
+            for name in &plan.remove {
+                println!("    {name}");
+            }
+        }
+    }
+
+    if !plan.missing.is_empty() {
+        log.warn(format!(
+            "cannot roll back {} package(s) (not in the package cache):",
+            plan.missing.len()
+        ));
+        for (name, pkgver) in &plan.missing {
+            log.warn(format!("  {name}: {pkgver}"));
+        }
+    }
+
+    if plan.reinstall.is_empty() && plan.remove.is_empty() {
+        log.info("vx: nothing to roll back.");
+        return ExitCode::SUCCESS;
+    }
+
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+
+    if !yes && !confirm_once("Proceed?") {
+        log.info("aborted.");
+        return ExitCode::SUCCESS;
+    }
+
+    with_journal(log, &format!("vx rollback {}", txn.id), || apply(log, &plan))
+}
+
+fn apply(log: &Log, plan: &RollbackPlan) -> ExitCode {
+    if !plan.reinstall.is_empty() {
+        let files: Vec<PathBuf> = plan.reinstall.iter().map(|r| r.3.clone()).collect();
+        let repo = match cachedir::temp_repo(log, &files) {
+            Ok(p) => p,
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        };
+
+        let pkgvers: Vec<&str> = plan.reinstall.iter().map(|r| r.2.as_str()).collect();
+        let mut cmd = privilege::command("xbps-install");
+        cmd.arg("-R").arg(repo.path());
+        cmd.args(["-f", "-y"]);
+        cmd.args(&pkgvers);
+
+        let label = privilege::label(&format!(
+            "xbps-install -R {} -f -y {}",
+            repo.path().display(),
+            pkgvers.join(" ")
+        ));
+        let code = run(log, cmd, &label);
+        drop(repo);
+        if code != ExitCode::SUCCESS {
+            return code;
+        }
+    }
+
+    if !plan.remove.is_empty() {
+        let mut cmd = privilege::command("xbps-remove");
+        cmd.arg("-y");
+        cmd.args(&plan.remove);
+
+        let label = privilege::label(&format!("xbps-remove -y {}", plan.remove.join(" ")));
+        return run(log, cmd, &label);
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,210 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::log::Log;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    process::Stdio,
+};
+
+use super::{parse::pkgname_from_pkgver, plan::SysUpdate};
+
+/// shlib-provides before/after one planned update.
+#[derive(Debug, Clone, Default)]
+pub struct ShlibChange {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub old: BTreeSet<String>,
+    pub new: BTreeSet<String>,
+}
+
+/// A soname the update drops while installed packages still need it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenShlib {
+    pub soname: String,
+    /// pkgver that provided it before the update.
+    pub provider: String,
+    /// Installed pkgvers that would be left without it.
+    pub consumers: Vec<String>,
+}
+
+/// Sonames some update stops providing that nothing else in the plan provides.
+/// soname -> pkgver that provided it.
+pub fn removed_sonames(changes: &[ShlibChange]) -> BTreeMap<String, String> {
+    let provided_after: BTreeSet<&String> = changes.iter().flat_map(|c| &c.new).collect();
+    let mut out = BTreeMap::new();
+    for c in changes {
+        for so in c.old.difference(&c.new) {
+            if !provided_after.contains(so) {
+                out.insert(so.clone(), c.from.clone());
+            }
+        }
+    }
+    out
+}
+
+/// Parse `xbps-query -p <prop> -s <pattern>` output (`pkgver: value (prop)`)
+/// into pkgvers whose value is exactly `value`.
+pub fn parse_property_search(text: &str, prop: &str, value: &str) -> Vec<String> {
+    let suffix = format!(" ({prop})");
+    text.lines()
+        .filter_map(|l| {
+            let (pkgver, v) = l.trim().split_once(": ")?;
+            let v = v.strip_suffix(&suffix).unwrap_or(v).trim();
+            (v == value).then(|| pkgver.trim().to_string())
+        })
+        .collect()
+}
+
+/// Values of a list property (one per line); empty when unset.
+fn prop_set(args: &[&str]) -> BTreeSet<String> {
+    let out = crate::rootdir::command("xbps-query")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    match out {
+        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => BTreeSet::new(),
+    }
+}
+
+fn repo_prop(repos: &[String], pkgver: &str, prop: &str) -> BTreeSet<String> {
+    let repo_args = super::repository_args(repos);
+    let mut args: Vec<&str> = repo_args.iter().map(String::as_str).collect();
+    args.extend(["-R", "-p", prop, pkgver]);
+    prop_set(&args)
+}
+
+/// Installed pkgvers whose `prop` contains `value`.
+fn installed_with(prop: &str, value: &str) -> Vec<String> {
+    let out = crate::rootdir::command("xbps-query")
+        .args(["-p", prop, "-s", value])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    match out {
+        Ok(o) if o.status.success() => {
+            parse_property_search(&String::from_utf8_lossy(&o.stdout), prop, value)
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// shlib-provides of each planned package: installed now vs the new version.
+/// Must run before the update is applied.
+pub fn plan_changes(log: &Log, plan: &[SysUpdate], repos: &[String]) -> Vec<ShlibChange> {
+    plan.iter()
+        .map(|u| {
+            if log.verbose && !log.quiet {
+                log.exec(format!("xbps-query -p shlib-provides {} / -R {}", u.name, u.to));
+            }
+            ShlibChange {
+                name: u.name.clone(),
+                from: u.from.clone(),
+                to: u.to.clone(),
+                old: super::query::installed_property(&u.name, "shlib-provides")
+                    .ok()
+                    .flatten()
+                    .map(|s| s.lines().map(|l| l.trim().to_string()).collect())
+                    .unwrap_or_default(),
+                new: repo_prop(repos, &u.to, "shlib-provides"),
+            }
+        })
+        .collect()
+}
+
+/// Simulate `plan` against shlib metadata: which installed packages would be
+/// left requiring a soname that no longer exists afterwards.
+pub fn find_broken(log: &Log, plan: &[SysUpdate], repos: &[String]) -> Vec<BrokenShlib> {
+    let changes = plan_changes(log, plan, repos);
+    let updating: BTreeMap<&str, &ShlibChange> =
+        changes.iter().map(|c| (c.name.as_str(), c)).collect();
+
+    let mut out = Vec::new();
+    for (soname, provider) in removed_sonames(&changes) {
+        // Another installed package that isn't being updated still ships it.
+        let kept = installed_with("shlib-provides", &soname).iter().any(|pv| {
+            pkgname_from_pkgver(pv).is_some_and(|n| !updating.contains_key(n.as_str()))
+        });
+        if kept {
+            continue;
+        }
+
+        let consumers: Vec<String> = installed_with("shlib-requires", &soname)
+            .into_iter()
+            .filter(|pv| {
+                // Consumers updated in the same transaction count by their new requirements.
+                match pkgname_from_pkgver(pv).and_then(|n| updating.get(n.as_str()).copied()) {
+                    Some(c) => repo_prop(repos, &c.to, "shlib-requires").contains(&soname),
+                    None => true,
+                }
+            })
+            .collect();
+
+        if !consumers.is_empty() {
+            out.push(BrokenShlib {
+                soname,
+                provider,
+                consumers,
+            });
+        }
+    }
+    out
+}
+
+pub fn print_broken(broken: &[BrokenShlib]) {
+    println!("shared libraries this update would break ({}):", broken.len());
+    for b in broken {
+        println!("  {}  (from {})", b.soname, b.provider);
+        println!("    needed by: {}", b.consumers.join(" "));
+    }
+    println!("hint: rebuild the affected source packages first (vx src up <pkg>), or hold the provider with --ignore.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(name: &str, old: &[&str], new: &[&str]) -> ShlibChange {
+        ShlibChange {
+            name: name.into(),
+            from: format!("{name}-1.0_1"),
+            to: format!("{name}-2.0_1"),
+            old: old.iter().map(|s| s.to_string()).collect(),
+            new: new.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn removed_sonames_ignores_moves_within_the_plan() {
+        let changes = [
+            change("libfoo", &["libfoo.so.1", "libfoo-extra.so.1"], &["libfoo.so.2"]),
+            change("libfoo-compat", &[], &["libfoo-extra.so.1"]),
+        ];
+        let removed = removed_sonames(&changes);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed["libfoo.so.1"], "libfoo-1.0_1");
+    }
+
+    #[test]
+    fn property_search_matches_exact_values() {
+        let text = "\
+mpv-0.38.0_1: libfoo.so.1 (shlib-requires)
+bar-1.0_1: libfoo.so.12 (shlib-requires)
+baz-2.1_3: libfoo.so.1 (shlib-requires)
+";
+        assert_eq!(
+            parse_property_search(text, "shlib-requires", "libfoo.so.1"),
+            ["mpv-0.38.0_1", "baz-2.1_3"]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::history;
+use serde::Serialize;
+use std::{collections::BTreeSet, process::Stdio};
+
+use super::{cachedir, parse, query};
+
+/// Package counts for `vx status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PkgStats {
+    pub installed: usize,
+    pub manual: usize,
+    /// Manually installed packages nothing else depends on.
+    pub leaves: usize,
+    pub orphans: usize,
+    pub held: usize,
+    pub cache_bytes: u64,
+    /// Unix time of the last journaled `vx up`.
+    pub last_update: Option<u64>,
+}
+
+/// Package name of a dependency pattern (`glibc>=2.39_1`, `foo-1.0_1`, `bar`).
+fn dep_name(pattern: &str) -> String {
+    if let Some(i) = pattern.find(['<', '>', '=']) {
+        return pattern[..i].to_string();
+    }
+    parse::pkgname_from_pkgver(pattern).unwrap_or_else(|| pattern.to_string())
+}
+
+/// Names that appear as a dependency in `xbps-query -p run_depends -s ""`
+/// (`<pkgver>: <pattern> (run_depends)`).
+pub fn parse_depended_names(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .filter_map(|l| {
+            let (_, v) = l.trim().split_once(": ")?;
+            let v = v.strip_suffix(" (run_depends)").unwrap_or(v).trim();
+            (!v.is_empty()).then(|| dep_name(v))
+        })
+        .collect()
+}
+
+fn depended_names() -> BTreeSet<String> {
+    let out = crate::rootdir::command("xbps-query")
+        .args(["-p", "run_depends", "-s", ""])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    match out {
+        Ok(o) => parse_depended_names(&String::from_utf8_lossy(&o.stdout)),
+        Err(_) => BTreeSet::new(),
+    }
+}
+
+pub fn package_stats() -> Result<PkgStats, String> {
+    let installed = query::installed_snapshot()?.len();
+    let manual = query::name_set(query::query_lines(&["-m"])?);
+    let depended = depended_names();
+
+    Ok(PkgStats {
+        installed,
+        manual: manual.len(),
+        leaves: manual.iter().filter(|n| !depended.contains(*n)).count(),
+        orphans: query::orphan_pkgvers()?.len(),
+        held: query::query_lines(&["-H"])?.len(),
+        cache_bytes: cachedir::dir_size(&cachedir::cachedir(None)),
+        last_update: history::last_update(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_depended_names;
+
+    #[test]
+    fn depended_names_strip_versions() {
+        let text = "\
+firefox-128.0_1: glibc>=2.39_1 (run_depends)
+firefox-128.0_1: nss-3.101_1 (run_depends)
+xterm-393_1: libXft (run_depends)
+";
+        let names: Vec<String> = parse_depended_names(text).into_iter().collect();
+        assert_eq!(names, ["glibc", "libXft", "nss"]);
+    }
+}
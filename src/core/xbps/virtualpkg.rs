@@ -0,0 +1,167 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::log::Log;
+use serde::Serialize;
+use std::{
+    io::{self, IsTerminal, Write},
+    process::Stdio,
+};
+
+use super::{parse, query, repository_args};
+
+/// A real package that provides a virtual name (`provides` property).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provider {
+    pub name: String,
+    pub pkgver: String,
+    /// What it provides, e.g. `awk-0_1`.
+    pub provides: String,
+    pub installed: bool,
+}
+
+/// `(pkgver, value)` rows of `xbps-query -p provides -Rs <name>` whose value
+/// names `virtual`.
+pub fn parse_providers(text: &str, virtual_name: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|l| {
+            let (pkgver, v) = l.trim().split_once(": ")?;
+            let v = v.strip_suffix(" (provides)").unwrap_or(v).trim();
+            (parse::pkgname_from_pkgver(v).as_deref() == Some(virtual_name))
+                .then(|| (pkgver.trim().to_string(), v.to_string()))
+        })
+        .collect()
+}
+
+/// True if the repositories have a real package called `name`.
+pub fn is_real(name: &str, repos: &[String]) -> bool {
+    let repo_args = repository_args(repos);
+    let out = crate::rootdir::command("xbps-query")
+        .args(&repo_args)
+        .args(["-R", "-p", "pkgname", name])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    out.is_ok_and(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).trim() == name)
+}
+
+/// Repository packages providing the virtual package `name`, installed ones first.
+pub fn providers(name: &str, repos: &[String]) -> Vec<Provider> {
+    let repo_args = repository_args(repos);
+    let out = crate::rootdir::command("xbps-query")
+        .args(&repo_args)
+        .args(["-p", "provides", "-Rs", name])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    let Ok(out) = out else {
+        return Vec::new();
+    };
+
+    let mut list: Vec<Provider> = parse_providers(&String::from_utf8_lossy(&out.stdout), name)
+        .into_iter()
+        .filter_map(|(pkgver, provides)| {
+            let name = parse::pkgname_from_pkgver(&pkgver)?;
+            let installed = query::installed_pkgver(&name).ok().flatten().is_some();
+            Some(Provider {
+                name,
+                pkgver,
+                provides,
+                installed,
+            })
+        })
+        .collect();
+    list.sort_by(|a, b| b.installed.cmp(&a.installed).then(a.name.cmp(&b.name)));
+    list.dedup_by(|a, b| a.name == b.name);
+    list
+}
+
+pub fn print_providers(name: &str, list: &[Provider]) {
+    println!("{name} is a virtual package, provided by:");
+    for (i, p) in list.iter().enumerate() {
+        let inst = if p.installed { "  (installed)" } else { "" };
+        println!("  {}) {}{inst}", i + 1, p.pkgver);
+    }
+}
+
+/// Ask which provider to use. None when cancelled or not interactive.
+pub fn choose(list: &[Provider]) -> Option<&Provider> {
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+    print!("choose [1-{}, empty to cancel]: ", list.len());
+    io::stdout().flush().ok();
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).ok()?;
+    let n: usize = line.trim().parse().ok()?;
+    list.get(n.checked_sub(1)?)
+}
+
+/// Map `vx add` targets that are virtual names onto a real provider.
+///
+/// - a provider is already installed: the target is dropped
+/// - one provider: it is used
+/// - several: the user picks one (with `--yes` the choice is left to the user)
+pub fn resolve_add_targets(
+    log: &Log,
+    pkgs: &[String],
+    repos: &[String],
+    yes: bool,
+) -> Result<Vec<String>, String> {
+    let mut out = Vec::with_capacity(pkgs.len());
+    for arg in pkgs {
+        let spec = parse::parse_pkg_spec(arg);
+        if spec.constraint.is_some() || is_real(&spec.name, repos) {
+            out.push(arg.clone());
+            continue;
+        }
+        let list = providers(&spec.name, repos);
+        if list.is_empty() {
+            out.push(arg.clone());
+            continue;
+        }
+
+        if let Some(p) = list.iter().find(|p| p.installed) {
+            log.info(format!("{} is already provided by {}.", spec.name, p.pkgver));
+            continue;
+        }
+        if list.len() == 1 {
+            log.info(format!("{} is provided by {}; installing it.", spec.name, list[0].name));
+            out.push(list[0].name.clone());
+            continue;
+        }
+
+        print_providers(&spec.name, &list);
+        if yes {
+            return Err(format!(
+                "{} is provided by several packages; name the one you want",
+                spec.name
+            ));
+        }
+        match choose(&list) {
+            Some(p) => out.push(p.name.clone()),
+            None => return Err(format!("no provider chosen for {}", spec.name)),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_providers;
+
+    #[test]
+    fn providers_match_the_virtual_name() {
+        let text = "\
+busybox-1.36.1_4: awk-0_1 (provides)
+gawk-5.3.0_1: awk-0_1 (provides)
+mawk-1.3.4_2: awk-0_1 (provides)
+foo-1.0_1: awkward-0_1 (provides)
+";
+        let got = parse_providers(text, "awk");
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[1], ("gawk-5.3.0_1".to_string(), "awk-0_1".to_string()));
+    }
+}
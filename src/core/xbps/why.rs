@@ -0,0 +1,135 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{config::Config, log::Log, managed, privilege};
+use std::process::ExitCode;
+
+use super::{install, query};
+
+/// Why a package is on the system.
+#[derive(Debug, Clone)]
+pub struct InstallReason {
+    pub pkgver: String,
+    pub automatic: bool,
+    pub required_by: Vec<String>,
+    pub managed_src: bool,
+}
+
+/// Combine pkgdb install mode, reverse dependencies and the vx managed list.
+pub fn resolve(pkg: &str) -> Result<Option<InstallReason>, String> {
+    let Some(pkgver) = query::installed_pkgver(pkg)? else {
+        return Ok(None);
+    };
+
+    let automatic = query::installed_property(pkg, "automatic-install")?
+        .map(|v| v.trim() == "yes")
+        .unwrap_or(false);
+
+    let required_by = query::revdeps(pkg, false)?;
+
+    let managed_src = managed::load_managed()?.iter().any(|m| m == pkg);
+
+    Ok(Some(InstallReason {
+        pkgver,
+        automatic,
+        required_by,
+        managed_src,
+    }))
+}
+
+/// `vx why <pkg>`
+pub fn why(log: &Log, _cfg: Option<&Config>, pkg: &str) -> ExitCode {
+    let pkg = pkg.trim();
+    if pkg.is_empty() {
+        log.error("usage: vx why <pkg>");
+        return ExitCode::from(2);
+    }
+
+    let r = match resolve(pkg) {
+        Ok(Some(r)) => r,
+        Ok(None) => {
+            log.error(format!("{pkg} is not installed"));
+            return ExitCode::from(1);
+        }
+        Err(e) => {
+            log.error(e);
+            return ExitCode::from(1);
+        }
+    };
+
+    println!("{}", r.pkgver);
+    if r.automatic {
+        println!("  installed: automatically (as a dependency; `vx mark --manual {pkg}` to keep it)");
+    } else {
+        println!("  installed: manually (`vx mark --auto {pkg}` if it was only a dependency)");
+    }
+
+    if r.required_by.is_empty() {
+        if r.automatic {
+            println!("  required by: nothing (orphan; `vx orphans` would remove it)");
+        } else {
+            println!("  required by: nothing");
+        }
+    } else {
+        println!("  required by ({}):", r.required_by.len());
+        for d in &r.required_by {
+            println!("    {d}");
+        }
+    }
+
+    if r.managed_src {
+        println!("  source: tracked by vx (`vx src up` rebuilds it)");
+    } else {
+        println!("  source: not tracked by vx");
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// `vx mark --auto|--manual <pkgs...>` (xbps-pkgdb -m auto|manual).
+pub fn mark(log: &Log, automatic: bool, pkgs: &[String]) -> ExitCode {
+    if pkgs.is_empty() {
+        log.error("usage: vx mark --auto|--manual <pkgs...>");
+        return ExitCode::from(2);
+    }
+    let mode = if automatic { "auto" } else { "manual" };
+
+    let mut todo = Vec::new();
+    for p in pkgs {
+        let r = match resolve(p) {
+            Ok(Some(r)) => r,
+            Ok(None) => {
+                log.error(format!("{p} is not installed"));
+                return ExitCode::from(1);
+            }
+            Err(e) => {
+                log.error(e);
+                return ExitCode::from(1);
+            }
+        };
+        if r.automatic == automatic {
+            log.info(format!("vx: {p} is already marked {mode}."));
+        } else {
+            todo.push((p.clone(), r));
+        }
+    }
+    if todo.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    let mut cmd = privilege::command("xbps-pkgdb");
+    cmd.args(["-m", mode]);
+    cmd.args(todo.iter().map(|(p, _)| p));
+    let c = install::run(log, cmd, &privilege::label(&format!("xbps-pkgdb -m {mode}")));
+    if c != ExitCode::SUCCESS {
+        return c;
+    }
+
+    for (p, r) in &todo {
+        log.info(format!("vx: {} marked {mode}.", r.pkgver));
+        if automatic && r.required_by.is_empty() {
+            log.info(format!("vx: nothing requires {p} now; `vx orphans` would remove it."));
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,209 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::paths::history_path;
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// One package change inside a transaction. `None` means "not installed".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub name: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// A journaled vx transaction.
+#[derive(Debug, Clone)]
+pub struct Txn {
+    pub id: u64,
+    pub time: u64,
+    pub command: String,
+    pub changes: Vec<Change>,
+}
+
+/// Diff two `name -> pkgver` snapshots of the installed package set.
+pub fn diff(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Vec<Change> {
+    let mut out = Vec::new();
+
+    for (name, old) in before {
+        match after.get(name) {
+            Some(new) if new == old => {}
+            new => out.push(Change {
+                name: name.clone(),
+                from: Some(old.clone()),
+                to: new.cloned(),
+            }),
+        }
+    }
+    for (name, new) in after {
+        if !before.contains_key(name) {
+            out.push(Change {
+                name: name.clone(),
+                from: None,
+                to: Some(new.clone()),
+            });
+        }
+    }
+
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    out
+}
+
+/// Append a transaction to the journal. Empty change sets are not recorded.
+pub fn record(command: &str, changes: Vec<Change>) -> Result<Option<u64>, String> {
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    let path = history_path()?;
+    let id = load()?.last().map(|t| t.id + 1).unwrap_or(1);
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut out = format!("#txn\t{id}\t{time}\t{}\n", command.replace(['\t', '\n'], " "));
+    for c in &changes {
+        out.push_str(&format!(
+            "{}\t{}\t{}\n",
+            c.name,
+            c.from.as_deref().unwrap_or("-"),
+            c.to.as_deref().unwrap_or("-")
+        ));
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(out.as_bytes()))
+        .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+
+    Ok(Some(id))
+}
+
+/// Load all journaled transactions, oldest first.
+pub fn load() -> Result<Vec<Txn>, String> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    Ok(parse(&text))
+}
+
+pub fn find(id: u64) -> Result<Option<Txn>, String> {
+    Ok(load()?.into_iter().find(|t| t.id == id))
+}
+
+/// Time of the newest journaled `vx up` (None without one or when unreadable).
+pub fn last_update() -> Option<u64> {
+    load()
+        .ok()?
+        .iter()
+        .rev()
+        .find(|t| t.command == "vx up" || t.command.starts_with("vx up "))
+        .map(|t| t.time)
+}
+
+fn parse(text: &str) -> Vec<Txn> {
+    let mut out: Vec<Txn> = Vec::new();
+
+    for line in text.lines() {
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.first() == Some(&"#txn") {
+            if cols.len() < 4 {
+                continue;
+            }
+            let (Ok(id), Ok(time)) = (cols[1].parse(), cols[2].parse()) else {
+                continue;
+            };
+            out.push(Txn {
+                id,
+                time,
+                command: cols[3].to_string(),
+                changes: Vec::new(),
+            });
+            continue;
+        }
+
+        if cols.len() != 3 {
+            continue;
+        }
+        let Some(txn) = out.last_mut() else {
+            continue;
+        };
+        let opt = |s: &str| (s != "-").then(|| s.to_string());
+        txn.changes.push(Change {
+            name: cols[0].to_string(),
+            from: opt(cols[1]),
+            to: opt(cols[2]),
+        });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, parse, Change};
+    use std::collections::BTreeMap;
+
+    fn snapshot(pkgs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pkgs.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn diff_reports_updates_installs_and_removals() {
+        let before = snapshot(&[("bash", "bash-5.2_1"), ("curl", "curl-8.5.0_1"), ("zsh", "zsh-5.9_1")]);
+        let after = snapshot(&[("bash", "bash-5.2_1"), ("curl", "curl-8.6.0_1"), ("fish", "fish-3.7_1")]);
+
+        let change = |name: &str, from: Option<&str>, to: Option<&str>| Change {
+            name: name.to_string(),
+            from: from.map(String::from),
+            to: to.map(String::from),
+        };
+        assert_eq!(
+            diff(&before, &after),
+            vec![
+                change("curl", Some("curl-8.5.0_1"), Some("curl-8.6.0_1")),
+                change("fish", None, Some("fish-3.7_1")),
+                change("zsh", Some("zsh-5.9_1"), None),
+            ]
+        );
+        assert!(diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn parse_reads_transactions_and_skips_bad_lines() {
+        let text = "#txn\t1\t1700000000\tvx up\n\
+                    curl\tcurl-8.5.0_1\tcurl-8.6.0_1\n\
+                    fish\t-\tfish-3.7_1\n\
+                    garbage\n\
+                    #txn\tx\t1700000001\tvx add foo\n\
+                    #txn\t2\t1700000002\tvx rm zsh\n\
+                    zsh\tzsh-5.9_1\t-\n";
+        let txns = parse(text);
+
+        assert_eq!(txns.len(), 2);
+        assert_eq!((txns[0].id, txns[0].time), (1, 1700000000));
+        assert_eq!(txns[0].command, "vx up");
+        assert_eq!(txns[0].changes.len(), 2);
+        assert_eq!(txns[0].changes[1].from, None);
+        assert_eq!(txns[0].changes[1].to.as_deref(), Some("fish-3.7_1"));
+        assert_eq!(txns[1].command, "vx rm zsh");
+        assert_eq!(txns[1].changes[0].to, None);
+    }
+}
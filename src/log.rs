@@ -1,12 +1,15 @@
 // Author Dustin Pilgrim
 // License: MIT
 
+use serde::Serialize;
 use std::io::{self, Write};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Log {
     pub quiet: bool,
     pub verbose: bool,
+    /// `--json`: stdout carries only the JSON document; messages go to stderr.
+    pub json: bool,
 }
 
 impl Log {
@@ -14,9 +17,21 @@ impl Log {
         if self.quiet {
             return;
         }
+        if self.json {
+            let _ = writeln!(io::stderr(), "{}", msg.as_ref());
+            return;
+        }
         println!("{}", msg.as_ref());
     }
 
+    /// Print `value` as pretty JSON on stdout.
+    pub fn emit_json(&self, value: &impl Serialize) {
+        match serde_json::to_string_pretty(value) {
+            Ok(s) => println!("{s}"),
+            Err(e) => self.error(format!("failed to encode JSON: {e}")),
+        }
+    }
+
     pub fn warn(&self, msg: impl AsRef<str>) {
         if self.quiet {
             return;
@@ -4,11 +4,17 @@
 mod app;
 mod cache;
 mod cli;
+mod completions;
 mod core;
 mod config;
+mod history;
 mod log;
 mod managed;
+mod masterdirs;
 mod paths;
+mod privilege;
+mod rootdir;
+mod tempdir;
 
 fn main() -> std::process::ExitCode {
     app::run()
@@ -1,53 +1,267 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use crate::paths::managed_src_path;
+use crate::{cache, log::Log, paths::managed_src_path};
 use rune_cfg::RuneConfig;
+use serde::Serialize;
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     fs,
     io,
     path::Path,
 };
 
-pub fn load_managed() -> Result<Vec<String>, String> {
-    let path = managed_src_path()?;
+/// Pinned package -> the version it is held at (None: never updated).
+pub type Pins = BTreeMap<String, Option<String>>;
+
+/// A tracked source package and what vx knows about its last build.
+/// Packages tracked before vx kept this (or added by hand) only have a name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ManagedPkg {
+    pub name: String,
+    /// `YYYYMMDD` (UTC) it was last installed from source.
+    pub installed: Option<String>,
+    /// `version_revision` it was built at.
+    pub version: Option<String>,
+    /// void-packages commit the template was built from.
+    pub commit: Option<String>,
+    /// remote | local
+    pub mode: Option<String>,
+    pub arch: Option<String>,
+    /// Groups it was tagged with (`vx src tag`).
+    pub groups: Vec<String>,
+}
+
+struct Manifest {
+    pkgs: Vec<ManagedPkg>,
+    pins: Pins,
+}
+
+fn load_manifest(path: &Path) -> Result<Manifest, String> {
     if !path.exists() {
-        return Ok(Vec::new());
+        return Ok(Manifest {
+            pkgs: Vec::new(),
+            pins: Pins::new(),
+        });
     }
 
     let cfg = RuneConfig::from_file(path.to_str().ok_or("invalid managed-src path")?)
         .map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
 
-    // Expect: packages ["a" "b" ...]
-    let pkgs: Vec<String> = cfg.get("packages").unwrap_or_else(|_| Vec::new());
+    // Expect: packages ["a" "b" ...], a records.<a> block per package vx
+    // built (see `record_key`), pins ["a" "b=1.2" ...]. Manifests written
+    // before records existed only have `packages`; a record whose name is
+    // missing from `packages` was untracked by hand.
+    let names: Vec<String> = cfg.get("packages").unwrap_or_else(|_| Vec::new());
+    let pins: Vec<String> = cfg.get("pins").unwrap_or_else(|_| Vec::new());
+
+    Ok(Manifest {
+        pkgs: dedupe_sorted(names).into_iter().map(|name| read_record(&cfg, name)).collect(),
+        pins: pins.iter().filter_map(|p| parse_pin(p)).collect(),
+    })
+}
 
-    Ok(dedupe_sorted(pkgs))
+/// `foo` -> (foo, None), `foo=1.2` -> (foo, Some(1.2)).
+fn parse_pin(entry: &str) -> Option<(String, Option<String>)> {
+    let (name, version) = match entry.split_once('=') {
+        Some((n, v)) => (n.trim(), Some(v.trim()).filter(|v| !v.is_empty())),
+        None => (entry.trim(), None),
+    };
+    (!name.is_empty()).then(|| (name.to_string(), version.map(str::to_string)))
 }
 
-pub fn add_managed(pkgs: &[String]) -> Result<(), String> {
+/// Block name of `pkg` under `records`: config keys are only letters, digits
+/// and `_`, so anything else (and `_` itself, or a leading digit) becomes
+/// `_<hex>`. `gtk+3` -> `gtk_2b3`, `0ad` -> `_30ad`.
+fn record_key(pkg: &str) -> String {
+    let mut out = String::new();
+    for (i, b) in pkg.bytes().enumerate() {
+        if b.is_ascii_alphabetic() || (b.is_ascii_digit() && i > 0) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("_{b:02x}"));
+        }
+    }
+    out
+}
+
+/// `name` with what its `records.<name>` block says about it.
+fn read_record(cfg: &RuneConfig, name: String) -> ManagedPkg {
+    let key = |k: &str| format!("records.{}.{k}", record_key(&name));
+    let text = |k: &str| cfg.get::<String>(&key(k)).ok().filter(|v| !v.is_empty());
+    ManagedPkg {
+        installed: text("installed"),
+        version: text("version"),
+        commit: text("commit"),
+        mode: text("mode"),
+        arch: text("arch"),
+        groups: cfg.get(&key("groups")).unwrap_or_default(),
+        name,
+    }
+}
+
+/// The `records.<name>` block of `p`; None when nothing but the name is known.
+fn record_block(p: &ManagedPkg) -> Option<String> {
+    let fields = [
+        ("installed", &p.installed),
+        ("version", &p.version),
+        ("commit", &p.commit),
+        ("mode", &p.mode),
+        ("arch", &p.arch),
+    ];
+    let mut body = String::new();
+    for (k, v) in fields {
+        if let Some(v) = v {
+            body.push_str(&format!("    {k} \"{}\"\n", escape_string(v)));
+        }
+    }
+    if !p.groups.is_empty() {
+        let groups: Vec<String> = p.groups.iter().map(|g| format!("\"{}\"", escape_string(g))).collect();
+        body.push_str(&format!("    groups [{}]\n", groups.join(", ")));
+    }
+    (!body.is_empty()).then(|| format!("  {}:\n{body}  end\n", record_key(&p.name)))
+}
+
+pub fn load_managed() -> Result<Vec<String>, String> {
+    Ok(load_records()?.into_iter().map(|p| p.name).collect())
+}
+
+/// Every tracked package, with build metadata where vx recorded it.
+pub fn load_records() -> Result<Vec<ManagedPkg>, String> {
+    let path = managed_src_path()?;
+    Ok(load_manifest(&path)?.pkgs)
+}
+
+pub fn load_pins() -> Result<Pins, String> {
+    let path = managed_src_path()?;
+    Ok(load_manifest(&path)?.pins)
+}
+
+/// Load, change and write back the manifest while holding its lock, so two
+/// vx runs (a cron `vx up --check` and a `vx src add`) can't lose each
+/// other's changes. `f` returns whether anything changed.
+fn update_manifest(log: &Log, f: impl FnOnce(&mut Manifest) -> bool) -> Result<bool, String> {
     let path = managed_src_path()?;
-    let mut existing = if path.exists() { load_managed()? } else { Vec::new() };
+    let _lock = cache::lock(log, "managed-src").map_err(|e| format!("failed to lock the managed list: {e}"))?;
+    let mut m = load_manifest(&path)?;
+    if !f(&mut m) {
+        return Ok(false);
+    }
+    write_manifest(&path, &m).map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+    Ok(true)
+}
+
+/// Track packages that were just installed from source, replacing what was
+/// recorded about their previous builds.
+pub fn add_managed(log: &Log, pkgs: &[ManagedPkg]) -> Result<(), String> {
+    update_manifest(log, |m| {
+        for p in pkgs {
+            let name = p.name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            let mut p = ManagedPkg {
+                name: name.to_string(),
+                ..p.clone()
+            };
+            match m.pkgs.iter_mut().find(|e| e.name == name) {
+                Some(e) => {
+                    // Groups are the user's, not part of the build.
+                    p.groups = std::mem::take(&mut e.groups);
+                    *e = p;
+                }
+                None => m.pkgs.push(p),
+            }
+        }
+        m.pkgs.sort_by(|a, b| a.name.cmp(&b.name));
+        true
+    })?;
+    Ok(())
+}
 
-    existing.extend(pkgs.iter().cloned());
-    let merged = dedupe_sorted(existing);
+/// Track `pkgs` and take over `pins` from an exported manifest; what was
+/// recorded about packages already tracked here is kept.
+pub fn import_managed(log: &Log, pkgs: &[ManagedPkg], pins: &Pins) -> Result<(), String> {
+    update_manifest(log, |m| {
+        for p in pkgs {
+            if !m.pkgs.iter().any(|e| e.name == p.name) {
+                m.pkgs.push(ManagedPkg {
+                    name: p.name.clone(),
+                    groups: p.groups.clone(),
+                    ..ManagedPkg::default()
+                });
+            }
+        }
+        m.pkgs.sort_by(|a, b| a.name.cmp(&b.name));
+        m.pins.extend(pins.iter().map(|(k, v)| (k.clone(), v.clone())));
+        true
+    })?;
+    Ok(())
+}
 
-    write_manifest(&path, &merged).map_err(|e| format!("failed to write {}: {e}", path.display()))
+/// Packages and pins of a manifest written by `vx src export`.
+pub fn load_manifest_file(path: &Path) -> Result<(Vec<ManagedPkg>, Pins), String> {
+    if !path.is_file() {
+        return Err(format!("{} not found", path.display()));
+    }
+    let m = load_manifest(path)?;
+    Ok((m.pkgs, m.pins))
+}
+
+/// The managed manifest as `vx src export` prints it.
+pub fn export_manifest() -> Result<String, String> {
+    let path = managed_src_path()?;
+    Ok(manifest_text(&load_manifest(&path)?))
+}
+
+/// Add `pkg` to `groups`, or with `remove` take it out of them (all of them
+/// when `groups` is empty). False when `pkg` isn't tracked.
+pub fn tag(log: &Log, pkg: &str, groups: &[String], remove: bool) -> Result<bool, String> {
+    update_manifest(log, |m| {
+        let Some(p) = m.pkgs.iter_mut().find(|p| p.name == pkg) else {
+            return false;
+        };
+        if remove {
+            p.groups.retain(|g| !groups.is_empty() && !groups.contains(g));
+        } else {
+            p.groups.extend(groups.iter().map(|g| g.trim().to_string()));
+            p.groups = dedupe_sorted(std::mem::take(&mut p.groups));
+        }
+        true
+    })
+}
+
+/// Tracked packages tagged with `group`.
+pub fn group_members(group: &str) -> Result<Vec<String>, String> {
+    Ok(load_records()?
+        .into_iter()
+        .filter(|p| p.groups.iter().any(|g| g == group))
+        .map(|p| p.name)
+        .collect())
+}
+
+/// Pin a tracked package, at `version` or entirely.
+pub fn set_pin(log: &Log, pkg: &str, version: Option<&str>) -> Result<(), String> {
+    update_manifest(log, |m| {
+        m.pins.insert(pkg.to_string(), version.map(str::to_string));
+        true
+    })?;
+    Ok(())
+}
+
+/// Drop the pin of `pkg`; false when it wasn't pinned.
+pub fn remove_pin(log: &Log, pkg: &str) -> Result<bool, String> {
+    update_manifest(log, |m| m.pins.remove(pkg).is_some())
 }
 
 /// Remove packages from the vx-managed src list.
 /// This is a no-op if the manifest doesn't exist or none of the packages are present.
-pub fn remove_managed(pkgs: &[String]) -> Result<(), String> {
+pub fn remove_managed(log: &Log, pkgs: &[String]) -> Result<(), String> {
     let path = managed_src_path()?;
     if !path.exists() {
         return Ok(());
     }
 
-    let mut existing = load_managed()?;
-    if existing.is_empty() {
-        return Ok(());
-    }
-
     let mut rmset: BTreeSet<String> = BTreeSet::new();
     for p in pkgs {
         let t = p.trim();
@@ -56,17 +270,21 @@ pub fn remove_managed(pkgs: &[String]) -> Result<(), String> {
         }
     }
 
-    let before = existing.len();
+    update_manifest(log, |m| {
+        let before = m.pkgs.len();
 
-    // Compare using trimmed entries so weird whitespace in the manifest can't block removals.
-    existing.retain(|p| !rmset.contains(p.trim()));
+        // Compare using trimmed entries so weird whitespace in the manifest can't block removals.
+        m.pkgs.retain(|p| !rmset.contains(p.name.trim()));
 
-    if existing.len() == before {
-        return Ok(());
-    }
+        if m.pkgs.len() == before {
+            return false;
+        }
 
-    write_manifest(&path, &existing)
-        .map_err(|e| format!("failed to write {}: {e}", path.display()))
+        // Pins of packages no longer tracked would only surprise a later `vx src add`.
+        m.pins.retain(|p, _| !rmset.contains(p));
+        true
+    })?;
+    Ok(())
 }
 
 fn dedupe_sorted(mut pkgs: Vec<String>) -> Vec<String> {
@@ -80,25 +298,100 @@ fn dedupe_sorted(mut pkgs: Vec<String>) -> Vec<String> {
     set.into_iter().collect()
 }
 
-fn write_manifest(path: &Path, pkgs: &[String]) -> io::Result<()> {
+fn push_list(out: &mut String, key: &str, entries: &[String]) {
+    out.push_str(key);
+    out.push_str(" [\n");
+    for e in entries {
+        out.push_str("  \"");
+        out.push_str(&escape_string(e));
+        out.push_str("\"\n");
+    }
+    out.push_str("]\n");
+}
+
+fn write_manifest(path: &Path, m: &Manifest) -> io::Result<()> {
     if let Some(dir) = path.parent() {
         fs::create_dir_all(dir)?;
     }
+    cache::write_atomic(path, &manifest_text(m))
+}
 
+fn manifest_text(m: &Manifest) -> String {
     let mut out = String::new();
     out.push_str("@author \"vx\"\n");
     out.push_str("@description \"Source packages managed by vx\"\n\n");
-    out.push_str("packages [\n");
-    for p in pkgs {
-        out.push_str("  \"");
-        out.push_str(&escape_string(p));
-        out.push_str("\"\n");
+
+    // `packages` stays a plain name list so older vx versions can still read it.
+    let names: Vec<String> = m.pkgs.iter().map(|p| p.name.clone()).collect();
+    push_list(&mut out, "packages", &names);
+
+    let records: Vec<String> = m.pkgs.iter().filter_map(record_block).collect();
+    if !records.is_empty() {
+        out.push_str("\n# what vx knows about each build, one block per package\n");
+        out.push_str("records:\n");
+        out.push_str(&records.join("\n"));
+        out.push_str("end\n");
+    }
+
+    if !m.pins.is_empty() {
+        let pins: Vec<String> = m
+            .pins
+            .iter()
+            .map(|(p, v)| match v {
+                Some(v) => format!("{p}={v}"),
+                None => p.clone(),
+            })
+            .collect();
+        out.push('\n');
+        push_list(&mut out, "pins", &pins);
     }
-    out.push_str("]\n");
 
-    fs::write(path, out)
+    out
 }
 
 fn escape_string(s: &str) -> String {
     s.replace('\\', "\\\\").replace('"', "\\\"")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{load_manifest, manifest_text, parse_pin, record_key, Manifest, ManagedPkg, Pins};
+
+    #[test]
+    fn pin_entries() {
+        assert_eq!(parse_pin("foo"), Some(("foo".to_string(), None)));
+        assert_eq!(parse_pin(" foo = 1.2_1 "), Some(("foo".to_string(), Some("1.2_1".to_string()))));
+        assert_eq!(parse_pin("foo="), Some(("foo".to_string(), None)));
+        assert_eq!(parse_pin("=1.2"), None);
+    }
+
+    #[test]
+    fn record_keys_are_config_keys() {
+        assert_eq!(record_key("foo"), "foo");
+        assert_eq!(record_key("gtk+3"), "gtk_2b3");
+        assert_eq!(record_key("linux-firmware"), "linux_2dfirmware");
+        assert_eq!(record_key("a_b"), "a_5fb");
+        assert_eq!(record_key("0ad"), "_30ad");
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let p = ManagedPkg {
+            name: "gtk+3".to_string(),
+            installed: Some("20240301".to_string()),
+            version: Some("1.2_1".to_string()),
+            commit: Some("0123abc".to_string()),
+            mode: Some("remote".to_string()),
+            arch: Some("x86_64-musl".to_string()),
+            groups: vec!["desktop".to_string(), "wayland".to_string()],
+        };
+        let bare = ManagedPkg { name: "bar".to_string(), ..ManagedPkg::default() };
+        let m = Manifest { pkgs: vec![bare.clone(), p.clone()], pins: Pins::new() };
+
+        let path = std::env::temp_dir().join(format!("vx-managed-{}.rune", std::process::id()));
+        std::fs::write(&path, manifest_text(&m)).unwrap();
+        let loaded = load_manifest(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.pkgs, vec![bare, p]);
+    }
+}
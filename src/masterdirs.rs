@@ -0,0 +1,109 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::{core::configure, paths::user_config_path};
+use rune_cfg::RuneConfig;
+use std::path::PathBuf;
+
+/// A named xbps-src masterdir registered with `vx src masterdir create`,
+/// kept in vx.rune as a `source.masterdirs.<name>` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Masterdir {
+    pub name: String,
+    pub path: PathBuf,
+    /// Architecture it was bootstrapped for (`x86_64-musl`, ...).
+    pub arch: String,
+}
+
+/// Names double as rune section names, so keep them to `[a-z0-9_]`;
+/// `names` is the list of them.
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "names"
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+pub fn load() -> Result<Vec<Masterdir>, String> {
+    let path = user_config_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let cfg = RuneConfig::from_file(path.to_str().ok_or("invalid config path")?)
+        .map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
+
+    // source.masterdirs.names + source.masterdirs.<name>.path/arch
+    let names: Vec<String> = cfg.get("source.masterdirs.names").unwrap_or_default();
+    let mut out = Vec::new();
+    for name in names {
+        let key = |k: &str| format!("source.masterdirs.{name}.{k}");
+        let Ok(p) = cfg.get::<String>(&key("path")) else {
+            continue;
+        };
+        out.push(Masterdir {
+            path: PathBuf::from(p),
+            arch: cfg.get(&key("arch")).unwrap_or_default(),
+            name,
+        });
+    }
+    Ok(out)
+}
+
+pub fn find(name: &str) -> Result<Option<Masterdir>, String> {
+    Ok(load()?.into_iter().find(|m| m.name == name))
+}
+
+/// Add or replace an entry.
+pub fn add(entry: Masterdir) -> Result<(), String> {
+    let mut all = load()?;
+    all.retain(|m| m.name != entry.name);
+    all.push(entry.clone());
+    all.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let key = |k: &str| format!("source.masterdirs.{}.{k}", entry.name);
+    let settings = [
+        ("source.masterdirs.names".to_string(), names_value(&all)),
+        (key("path"), quote(&entry.path.to_string_lossy())),
+        (key("arch"), quote(&entry.arch)),
+    ];
+    configure::write_settings(&user_config_path()?, &settings)
+}
+
+/// Drop an entry; returns whether it existed.
+pub fn remove(name: &str) -> Result<bool, String> {
+    let mut all = load()?;
+    let before = all.len();
+    all.retain(|m| m.name != name);
+    if all.len() == before {
+        return Ok(false);
+    }
+
+    let path = user_config_path()?;
+    configure::remove_setting_section(&path, &format!("source.masterdirs.{name}"))?;
+    configure::write_setting(&path, "source.masterdirs.names", &names_value(&all))?;
+    Ok(true)
+}
+
+/// `["a", "b"]`
+fn names_value(all: &[Masterdir]) -> String {
+    let names: Vec<String> = all.iter().map(|m| quote(&m.name)).collect();
+    format!("[{}]", names.join(", "))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::valid_name;
+
+    #[test]
+    fn names_are_section_names() {
+        assert!(valid_name("musl") && valid_name("x86_64_musl"));
+        assert!(!valid_name("") && !valid_name("Musl") && !valid_name("a-b"));
+        assert!(!valid_name("names"));
+    }
+}
@@ -1,15 +1,74 @@
 // Author Dustin Pilgrim
 // License: MIT
 
-use std::path::PathBuf;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 pub fn user_config_path() -> Result<PathBuf, String> {
     let base = dirs::config_dir().ok_or("could not locate config dir")?;
     Ok(base.join("vx").join("vx.rune"))
 }
 
+/// The nearest `.vx.rune` from the current directory up: a project config
+/// that overrides vx.rune while working in that tree.
+pub fn project_config_path() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|d| d.join(".vx.rune"))
+        .find(|p| p.is_file())
+}
+
+/// `$XDG_STATE_HOME/vx` (default ~/.local/state/vx): what vx keeps about this
+/// machine rather than how it is configured — the managed list, the
+/// transaction journal, build logs and build times.
+pub fn state_dir() -> Result<PathBuf, String> {
+    let base = match env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        Some(v) => PathBuf::from(v),
+        None => dirs::state_dir()
+            .or_else(|| dirs::home_dir().map(|h| h.join(".local").join("state")))
+            .ok_or("could not locate state dir")?,
+    };
+    Ok(base.join("vx"))
+}
+
+/// `<state dir>/<name>`, moved over from ~/.config/vx where older vx kept it.
+/// Stays at the old place if it can't be moved.
+fn state_file(name: &str) -> Result<PathBuf, String> {
+    let dir = state_dir()?;
+    let path = dir.join(name);
+    let Some(old) = dirs::config_dir().map(|c| c.join("vx").join(name)) else {
+        return Ok(path);
+    };
+    if path.exists() || !old.is_file() {
+        return Ok(path);
+    }
+
+    let moved = fs::create_dir_all(&dir).is_ok()
+        && (fs::rename(&old, &path).is_ok() || copy_over(&old, &path));
+    Ok(if moved { path } else { old })
+}
+
+/// Move `old` to `path` across filesystems: copy next to `path`, rename the
+/// copy into place, then drop `old`. An interrupted copy never leaves a
+/// truncated `path` behind to be taken for the real file.
+fn copy_over(old: &Path, path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let placed = fs::copy(old, &tmp).is_ok() && fs::rename(&tmp, path).is_ok();
+    if !placed {
+        let _ = fs::remove_file(&tmp);
+        return false;
+    }
+    let _ = fs::remove_file(old);
+    true
+}
+
 pub fn managed_src_path() -> Result<PathBuf, String> {
-    let base = dirs::config_dir().ok_or("could not locate config dir")?;
-    Ok(base.join("vx").join("managed-src.rune"))
+    state_file("managed-src.rune")
 }
 
+pub fn history_path() -> Result<PathBuf, String> {
+    state_file("history.log")
+}
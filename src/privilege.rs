@@ -0,0 +1,145 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use crate::rootdir;
+use std::{
+    env,
+    os::unix::fs::MetadataExt,
+    path::Path,
+    process::Command,
+    sync::OnceLock,
+};
+
+/// How vx runs commands that need root (xbps-install, xbps-remove, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escalation {
+    Sudo,
+    Doas,
+    Run0,
+    /// Run directly (already root, or the user wraps vx themselves).
+    None,
+}
+
+impl Escalation {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sudo" => Some(Self::Sudo),
+            "doas" => Some(Self::Doas),
+            "run0" => Some(Self::Run0),
+            "none" | "" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn program(self) -> Option<&'static str> {
+        match self {
+            Self::Sudo => Some("sudo"),
+            Self::Doas => Some("doas"),
+            Self::Run0 => Some("run0"),
+            Self::None => None,
+        }
+    }
+
+    /// Flag that makes the helper fail instead of asking for a password.
+    fn non_interactive_flag(self) -> Option<&'static str> {
+        match self {
+            Self::Sudo | Self::Doas => Some("-n"),
+            Self::Run0 => Some("--no-ask-password"),
+            Self::None => None,
+        }
+    }
+
+    /// Root needs nothing; otherwise the first of sudo, doas, run0 found in PATH.
+    pub fn detect() -> Self {
+        if is_root() {
+            return Self::None;
+        }
+        [Self::Sudo, Self::Doas, Self::Run0]
+            .into_iter()
+            .find(|e| e.program().is_some_and(in_path))
+            .unwrap_or(Self::Sudo)
+    }
+}
+
+static SELECTED: OnceLock<Escalation> = OnceLock::new();
+
+/// Pick the escalation method from config (`privilege.command`), or detect it.
+/// Call once at startup, before any command runs.
+pub fn init(configured: Option<&str>) -> Result<(), String> {
+    let esc = match configured.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) => Escalation::parse(s).ok_or_else(|| {
+            format!("invalid privilege.command {s:?} (expected sudo, doas, run0 or none)")
+        })?,
+        None => Escalation::detect(),
+    };
+    let _ = SELECTED.set(esc);
+    Ok(())
+}
+
+pub fn current() -> Escalation {
+    *SELECTED.get_or_init(Escalation::detect)
+}
+
+/// `program` run with elevated privileges, e.g. `sudo xbps-install`.
+pub fn command(program: &str) -> Command {
+    build(program, false)
+}
+
+/// Like `command`, but never prompts for a password (fails instead).
+pub fn command_noninteractive(program: &str) -> Command {
+    build(program, true)
+}
+
+fn build(program: &str, non_interactive: bool) -> Command {
+    let esc = current();
+    match esc.program() {
+        Some(helper) => {
+            let mut cmd = Command::new(helper);
+            if non_interactive && let Some(flag) = esc.non_interactive_flag() {
+                cmd.arg(flag);
+            }
+            cmd.arg(program);
+            cmd.args(rootdir::args(program));
+            cmd
+        }
+        None => rootdir::command(program),
+    }
+}
+
+/// Human-readable command line for logs: `label("xbps-install -u")` -> `sudo xbps-install -u`.
+pub fn label(cmdline: &str) -> String {
+    let cmdline = rootdir::label(cmdline);
+    match current().program() {
+        Some(helper) => format!("{helper} {cmdline}"),
+        None => cmdline,
+    }
+}
+
+pub fn is_root() -> bool {
+    // /proc/self is owned by the effective uid.
+    std::fs::metadata("/proc/self").is_ok_and(|m| m.uid() == 0)
+}
+
+pub fn in_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| is_executable(&dir.join(program)))
+    })
+}
+
+fn is_executable(p: &Path) -> bool {
+    std::fs::metadata(p).is_ok_and(|m| m.is_file() && m.mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Escalation;
+
+    #[test]
+    fn parses_configured_command() {
+        assert_eq!(Escalation::parse("doas"), Some(Escalation::Doas));
+        assert_eq!(Escalation::parse(" Run0 "), Some(Escalation::Run0));
+        assert_eq!(Escalation::parse("none"), Some(Escalation::None));
+        assert_eq!(Escalation::parse("su"), None);
+        assert_eq!(Escalation::None.program(), None);
+    }
+}
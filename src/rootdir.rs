@@ -0,0 +1,125 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+/// xbps tools that take `-r <rootdir>`.
+const XBPS_TOOLS: &[&str] = &[
+    "xbps-install",
+    "xbps-remove",
+    "xbps-query",
+    "xbps-pkgdb",
+    "xbps-reconfigure",
+    "xbps-alternatives",
+];
+
+/// xbps tools that take `--repository=<url>`.
+const REPO_TOOLS: &[&str] = &["xbps-install", "xbps-query"];
+
+static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+static REPOSITORIES: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Pick the target root: `--root`, else config `xbps.root`, else `/`.
+/// Call once at startup, before any command runs.
+pub fn init(cli: Option<&Path>, configured: Option<&Path>) -> Result<(), String> {
+    let dir = cli.or(configured).filter(|p| !p.as_os_str().is_empty());
+    let dir = match dir {
+        Some(p) if p == Path::new("/") => None,
+        Some(p) => {
+            if !p.is_dir() {
+                return Err(format!("root directory {} does not exist", p.display()));
+            }
+            Some(p.to_path_buf())
+        }
+        None => None,
+    };
+    let _ = ROOT.set(dir);
+    Ok(())
+}
+
+/// Repositories every xbps-install/xbps-query gets as `--repository`, ahead
+/// of xbps.d (the `xbps.mirror` ones). Call once at startup, after `init`.
+pub fn init_repositories(urls: Vec<String>) {
+    let _ = REPOSITORIES.set(urls);
+}
+
+pub fn repositories() -> &'static [String] {
+    REPOSITORIES.get_or_init(Vec::new)
+}
+
+/// Alternate root, if one is in use.
+pub fn current() -> Option<&'static Path> {
+    ROOT.get_or_init(|| None).as_deref()
+}
+
+fn takes_rootdir(program: &str) -> bool {
+    XBPS_TOOLS.contains(&program)
+}
+
+/// `-r <dir>` for `program` when an alternate root is in use, and the
+/// `xbps.mirror` repositories for the tools that take them.
+pub fn args(program: &str) -> Vec<OsString> {
+    let mut out: Vec<OsString> = match current() {
+        Some(dir) if takes_rootdir(program) => vec!["-r".into(), dir.as_os_str().to_os_string()],
+        _ => Vec::new(),
+    };
+    if REPO_TOOLS.contains(&program) {
+        out.extend(repositories().iter().map(|r| format!("--repository={r}").into()));
+    }
+    out
+}
+
+/// `program` run without privileges, already pointed at the target root.
+pub fn command(program: &str) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.args(args(program));
+    cmd
+}
+
+/// Host path inside the target root: `/etc/xbps.d` -> `/mnt/etc/xbps.d`.
+pub fn path(abs: &str) -> PathBuf {
+    match current() {
+        Some(dir) => dir.join(abs.trim_start_matches('/')),
+        None => PathBuf::from(abs),
+    }
+}
+
+/// `xbps-install -u` -> `xbps-install -r /mnt -u` for log labels.
+pub fn label(cmdline: &str) -> String {
+    label_for(current(), cmdline)
+}
+
+fn label_for(root: Option<&Path>, cmdline: &str) -> String {
+    let (program, rest) = cmdline.split_once(' ').unwrap_or((cmdline, ""));
+    match root {
+        Some(dir) if takes_rootdir(program) => {
+            let mut s = format!("{program} -r {}", dir.display());
+            if !rest.is_empty() {
+                s.push(' ');
+                s.push_str(rest);
+            }
+            s
+        }
+        _ => cmdline.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::label_for;
+    use std::path::Path;
+
+    #[test]
+    fn label_inserts_rootdir_for_xbps_tools_only() {
+        let root = Some(Path::new("/mnt"));
+        assert_eq!(label_for(root, "xbps-install -Su"), "xbps-install -r /mnt -Su");
+        assert_eq!(label_for(root, "xbps-query"), "xbps-query -r /mnt");
+        assert_eq!(label_for(root, "rm -f /etc/foo"), "rm -f /etc/foo");
+        assert_eq!(label_for(None, "xbps-remove -R foo"), "xbps-remove -R foo");
+    }
+}
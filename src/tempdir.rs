@@ -0,0 +1,77 @@
+// Author Dustin Pilgrim
+// License: MIT
+
+use std::{
+    env, fs, io,
+    os::unix::fs::DirBuilderExt,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A directory only the current user can enter, made fresh under $TMPDIR
+/// (never an existing one someone else could have put there) and removed
+/// with everything in it when dropped.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Result<Self, String> {
+        static SEQ: AtomicU32 = AtomicU32::new(0);
+        let base = env::temp_dir();
+
+        for _ in 0..16 {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .subsec_nanos();
+            let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+            let path = base.join(format!("{prefix}-{}-{nanos:08x}{seq}", process::id()));
+
+            match fs::DirBuilder::new().mode(0o700).create(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(format!("failed to create {}: {e}", path.display())),
+            }
+        }
+        Err(format!("failed to create a temporary directory in {}", base.display()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TempDir;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn private_and_removed_on_drop() {
+        let a = TempDir::new("vx-test").unwrap();
+        let b = TempDir::new("vx-test").unwrap();
+        assert_ne!(a.path(), b.path());
+
+        let mode = std::fs::metadata(a.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        std::fs::write(a.join("f"), "x").unwrap();
+        let path = a.path().to_path_buf();
+        drop(a);
+        assert!(!path.exists());
+    }
+}